@@ -1,4 +0,0 @@
-pub mod syntax;
-pub mod grammar;
-
-pub use grammar::*;
@@ -1,21 +1,89 @@
 use std::time::Instant;
-use std::{env};
+use std::path::Path;
+use std::io::{self, BufRead};
+use std::{env, fs, process};
 
 use itertools::join;
-extern crate nom;
 
-mod grammar;
-use grammar::parse_grammar_file;
+use prattle::grammar::{
+    parse_grammar_file, format_grammar, compile_grammar_file, generate_parser, to_antlr, to_ebnf,
+    to_tree_sitter, precedence_chart, railroad_diagram,
+};
+use prattle::corpus::Generator;
+use prattle::reference::{differences, supported_operators};
+use prattle::operator::OperatorTable;
+use prattle::lexer::Mode;
+use prattle::parser::Parser;
+use prattle::trace::{Indented, JsonLines};
+use prattle::tree_view::{dot, tree_form, Lines};
+use prattle::serialize::{to_json, to_sexpr};
+use prattle::ASTNode;
+
+const USAGE: &str = "\
+Usage:
+  grammar <file>                      Parse a grammar definition file and print its entries.
+  grammar fmt [--check | --write] <file>...
+                                      Print grammar definition files in canonical form. With
+                                      --write, rewrite the files in place. With --check, only
+                                      report the files that are not in canonical form.
+  grammar codegen <file> [-o <output>]
+                                      Generate a standalone Rust parser for the grammar and
+                                      write it to <output>, or print it.
+  grammar ebnf <file> [-o <output>]   Export the grammar as EBNF, stratified by precedence.
+  grammar antlr <file> [-o <output>]  Export the grammar as an ANTLR4 grammar.
+  grammar tree-sitter <file> [-o <output>]
+                                      Export the grammar as a tree-sitter grammar.js.
+  grammar railroad <file> <directory> Draw the syntax of each entry as an SVG railroad diagram,
+                                      written to <directory>/<name>.svg.
+  grammar chart <file> [-o <output>]  Write the operators as an HTML precedence chart.
+  grammar corpus <file> [--seed <n>] [--count <n>] [--depth <n>] [--near-misses | --differential]
+                                      Print random expressions, one per line, each followed by a
+                                      tab and its fully parenthesized form. With --near-misses,
+                                      print near-miss mutations of them that do not parse. With
+                                      --differential, parse the expressions and their near misses
+                                      with the reference precedence-climbing and shunting-yard
+                                      parsers too, using the operators they support, and print
+                                      the inputs the parsers disagree about.
+  grammar parse <file> [--trace | --trace=json] [--dump-ast=<format>] [--latex] [<expression>...]
+                                      Parse each expression, or else each line of standard input,
+                                      and print it in FullForm, e.g. Plus[a, Times[b, c]]. With
+                                      --trace, print the parser's steps to standard error, indented
+                                      by depth; with --trace=json, as JSON lines. With
+                                      --dump-ast=tree, print the tree drawn one node to a line;
+                                      with dot, as a Graphviz graph; with sexpr or json, as an
+                                      S-expression or JSON with spans. With --latex, read the
+                                      expressions as LaTeX math, e.g. with
+                                      resources/latex.grammar.";
 
 fn main() {
     let args: Vec<String> = env::args().collect();
 
     if args.len() < 2 {
         // ToDo: Read from STDIN in this case.
-        panic!("Filename required.")
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    }
+
+    match args[1].as_str() {
+        "fmt" => format_files(&args[2..]),
+        "codegen" => generate(&args[2..], generate_parser),
+        "ebnf" => generate(&args[2..], |table, filename| Ok(to_ebnf(table, filename))),
+        "antlr" => generate(&args[2..], |table, filename| {
+            Ok(to_antlr(table, &grammar_name(filename, true), filename))
+        }),
+        "tree-sitter" => generate(&args[2..], |table, filename| {
+            Ok(to_tree_sitter(table, &grammar_name(filename, false), filename))
+        }),
+        "railroad" => draw_diagrams(&args[2..]),
+        "chart" => generate(&args[2..], |table, filename| Ok(precedence_chart(table, filename))),
+        "corpus" => print_corpus(&args[2..]),
+        "parse" => parse_expressions(&args[2..]),
+        "-h" | "--help" => println!("{}", USAGE),
+        filename => parse_file(filename),
     }
+}
 
-    let filename = &args[1];
+fn parse_file(filename: &str) {
     println!("Parsing grammar definition file: {}", filename);
 
     // We track how long it takes to parse.
@@ -31,5 +99,289 @@ fn main() {
             => eprintln!("Failed to parse.\n{}", e),
     };
     println!("Elapsed time: {:?}", start.elapsed()); // note :?
+}
+
+/// The `fmt` subcommand. Exits with status 1 if a file could not be formatted or, with `--check`,
+/// is not in canonical form.
+fn format_files(args: &[String]) {
+    let check = args.iter().any(|a| a == "--check");
+    let write = args.iter().any(|a| a == "--write");
+    let filenames: Vec<&String> = args.iter().filter(|a| !a.starts_with("--")).collect();
+
+    if filenames.is_empty() || (check && write) {
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    }
+
+    let mut failed = false;
+    for filename in filenames {
+        let contents =
+            match fs::read_to_string(filename) {
+                Ok(contents) => contents,
+                Err(e) => {
+                    eprintln!("Could not read {}: {}", filename, e);
+                    failed = true;
+                    continue;
+                }
+            };
+        let formatted =
+            match format_grammar(&contents) {
+                Ok(formatted) => formatted,
+                Err(e) => {
+                    eprintln!("{}", e.in_file(filename));
+                    failed = true;
+                    continue;
+                }
+            };
+
+        if check {
+            if formatted != contents {
+                println!("{} is not formatted", filename);
+                failed = true;
+            }
+        } else if write {
+            if formatted != contents {
+                if let Err(e) = fs::write(filename, formatted) {
+                    eprintln!("Could not write {}: {}", filename, e);
+                    failed = true;
+                }
+            }
+        } else {
+            print!("{}", formatted);
+        }
+    }
+
+    if failed {
+        process::exit(1);
+    }
+}
+
+/// The `codegen`, `ebnf`, `antlr`, and `tree-sitter` subcommands, which compile a grammar file and
+/// write what `output` makes of it. Exits with status 1 if the grammar could not be compiled or the
+/// output could not be generated.
+fn generate(args: &[String], output: impl Fn(&OperatorTable, &str) -> Result<String, String>) {
+    let (filename, destination) =
+        match args {
+            [filename] => (filename, None),
+            [filename, flag, destination] if flag == "-o" => (filename, Some(destination)),
+            _ => {
+                eprintln!("{}", USAGE);
+                process::exit(2);
+            }
+        };
+
+    let generated =
+        compile_grammar_file(filename)
+            .map_err(|e| e.to_string())
+            .and_then(|table| output(&table, filename));
+
+    match (generated, destination) {
+        (Ok(source), Some(destination)) => {
+            if let Err(e) = fs::write(destination, source) {
+                eprintln!("Could not write {}: {}", destination, e);
+                process::exit(1);
+            }
+        }
+        (Ok(source), None) => print!("{}", source),
+        (Err(e), _) => {
+            eprintln!("Could not generate output for {}.\n{}", filename, e);
+            process::exit(1);
+        }
+    }
+}
+
+/// The `railroad` subcommand. Exits with status 1 if the grammar could not be parsed or a diagram
+/// could not be written.
+fn draw_diagrams(args: &[String]) {
+    let (filename, directory) =
+        match args {
+            [filename, directory] => (filename, Path::new(directory)),
+            _ => {
+                eprintln!("{}", USAGE);
+                process::exit(2);
+            }
+        };
+
+    let records =
+        match parse_grammar_file(filename) {
+            Ok(records) => records,
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        };
+    if let Err(e) = fs::create_dir_all(directory) {
+        eprintln!("Could not create {}: {}", directory.display(), e);
+        process::exit(1);
+    }
+
+    for record in records {
+        let output = directory.join(format!("{}.svg", record.name));
+        if let Err(e) = fs::write(&output, railroad_diagram(&record.syntax)) {
+            eprintln!("Could not write {}: {}", output.display(), e);
+            process::exit(1);
+        }
+    }
+}
 
+/// The `corpus` subcommand. Exits with status 1 if the grammar could not be compiled.
+fn print_corpus(args: &[String]) {
+    let mut filename = None;
+    let mut seed = 0;
+    let mut count = 100;
+    let mut depth = 4;
+    let mut near_misses = false;
+    let mut differential = false;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut number = || args.next().and_then(|n| n.parse::<u64>().ok());
+        let valid =
+            match arg.as_str() {
+                "--seed" => number().map(|n| seed = n).is_some(),
+                "--count" => number().map(|n| count = n as usize).is_some(),
+                "--depth" => number().map(|n| depth = n as usize).is_some(),
+                "--near-misses" => {
+                    near_misses = true;
+                    true
+                }
+                "--differential" => {
+                    differential = true;
+                    true
+                }
+                other if filename.is_none() && !other.starts_with("--") => {
+                    filename = Some(other.to_string());
+                    true
+                }
+                _ => false,
+            };
+        if !valid || (near_misses && differential) {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    }
+    let filename =
+        match filename {
+            Some(filename) => filename,
+            None => {
+                eprintln!("{}", USAGE);
+                process::exit(2);
+            }
+        };
+
+    let table =
+        match compile_grammar_file(&filename) {
+            Ok(table) => table,
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        };
+
+    if differential {
+        return compare_parsers(&supported_operators(&table), seed, count, depth);
+    }
+
+    let mut generator = Generator::new(&table, seed).with_max_depth(depth);
+    for sample in generator.corpus(count) {
+        if near_misses {
+            if let Some(input) = generator.near_miss(&sample) {
+                println!("{}", input);
+            }
+        } else {
+            println!("{}\t{}", sample.input, sample.parenthesized);
+        }
+    }
+}
+
+/// The `corpus --differential` mode. Exits with status 1 if the parsers disagree about an input.
+fn compare_parsers(table: &OperatorTable, seed: u64, count: usize, depth: usize) {
+    let mut generator = Generator::new(table, seed).with_max_depth(depth);
+    let mut inputs = Vec::new();
+    for sample in generator.corpus(count) {
+        inputs.extend(generator.near_miss(&sample));
+        inputs.push(sample.input);
+    }
+
+    // The table only has operators the reference parsers support.
+    let differences = differences(table, &inputs).unwrap();
+    for difference in &differences {
+        println!("{}", difference);
+    }
+    eprintln!("{} inputs, {} differences", inputs.len(), differences.len());
+    if !differences.is_empty() {
+        process::exit(1);
+    }
+}
+
+/// Exits with status 1 if an expression does not parse.
+fn parse_expressions(args: &[String]) {
+    let (options, mut operands): (Vec<&String>, Vec<&String>) =
+        args.iter().partition(|arg| arg.starts_with("--"));
+    if operands.is_empty() {
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    }
+    let filename = operands.remove(0);
+
+    let table =
+        match compile_grammar_file(filename) {
+            Ok(table) => table,
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        };
+    let mut parser = Parser::new(table);
+    let mut print: fn(&ASTNode) -> Result<String, String> = |tree| Ok(tree.to_string());
+    for option in options {
+        match option.as_str() {
+            "--trace" => parser.trace = Some(Box::new(Indented::stderr())),
+            "--trace=json" => parser.trace = Some(Box::new(JsonLines::new(io::stderr()))),
+            "--dump-ast=tree" => print = |tree| Ok(tree_form(tree, Lines::Box)),
+            "--dump-ast=dot" => print = |tree| Ok(dot(tree)),
+            // A tree nested deeper than `serialize::MAX_DEPTH` is not written.
+            "--dump-ast=sexpr" => print = to_sexpr,
+            "--dump-ast=json" => print = to_json,
+            "--latex" => parser.lexer_mode = Mode::Latex,
+            _ => {
+                eprintln!("{}", USAGE);
+                process::exit(2);
+            }
+        }
+    }
+
+    let expressions: Vec<String> =
+        match operands.is_empty() {
+            true => io::stdin().lock().lines().map_while(Result::ok).collect(),
+            false => operands.iter().map(|expression| expression.to_string()).collect(),
+        };
+    let mut failed = false;
+    for expression in expressions {
+        match parser.parse(&expression).map_err(|e| e.to_string()).and_then(|tree| print(&tree)) {
+            Ok(printed) => println!("{}", printed.trim_end()),
+            Err(e) => {
+                eprintln!("{}: {}", expression, e);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
+
+/// The name of the grammar in `filename` for ANTLR, which capitalizes it, or tree-sitter, which
+/// expects lower case: the file stem with anything but letters, digits, and `_` replaced by `_`.
+fn grammar_name(filename: &str, capitalize: bool) -> String {
+    let stem = Path::new(filename).file_stem().and_then(|s| s.to_str()).unwrap_or("expressions");
+    let mut name: String =
+        stem.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        name.insert(0, 'g');
+    }
+    match capitalize {
+        true => name[..1].to_ascii_uppercase() + &name[1..],
+        false => name.to_ascii_lowercase(),
+    }
 }
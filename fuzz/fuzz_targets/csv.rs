@@ -0,0 +1,17 @@
+//! Reads arbitrary text as a CSV operator table and, if it loads, parses a few expressions with it.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use prattle::operator::parse_operator_table;
+use prattle::parser::Parser;
+
+fuzz_target!(|text: &str| {
+  let table = match parse_operator_table(text) {
+    Ok(table) => table,
+    Err(_) => return
+  };
+  let mut parser = Parser::new(table);
+  for expression in ["a", "a + b * c", "-a!", "f[a, b]", "(a; b)", "a ? b : c"] {
+    let _ = parser.parse(expression);
+  }
+});
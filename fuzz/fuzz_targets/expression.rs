@@ -0,0 +1,39 @@
+//! Parses arbitrary text as an expression, once with the operators of `resources/operators.csv`
+//! and once with those of `resources/operators.grammar`, and writes each tree it parses in
+//! FullForm, as an S-expression, and as source text.
+#![no_main]
+
+use std::cell::RefCell;
+
+use libfuzzer_sys::fuzz_target;
+use prattle::grammar::{compile_records, parse_grammar};
+use prattle::operator::{parse_operator_table, OperatorTable};
+use prattle::parser::Parser;
+use prattle::printer::Printer;
+use prattle::serialize::to_sexpr;
+
+fn parser_and_printer(table: OperatorTable) -> (Parser, Printer) {
+  let printer = Printer::new(&table);
+  (Parser::new(table), printer)
+}
+
+thread_local! {
+  static PARSERS: RefCell<Vec<(Parser, Printer)>> = RefCell::new(vec![
+    parser_and_printer(parse_operator_table(include_str!("../../resources/operators.csv")).unwrap()),
+    parser_and_printer(
+      compile_records(&parse_grammar(include_str!("../../resources/operators.grammar")).unwrap()).unwrap()
+    ),
+  ]);
+}
+
+fuzz_target!(|text: &str| {
+  PARSERS.with(|parsers| {
+    for (parser, printer) in parsers.borrow_mut().iter_mut() {
+      if let Ok(tree) = parser.parse(text) {
+        let _ = tree.to_string();
+        let _ = to_sexpr(&tree);
+        let _ = printer.print(&tree);
+      }
+    }
+  });
+});
@@ -0,0 +1,26 @@
+//! Parses arbitrary text as a grammar definition and, if it compiles, parses a few expressions
+//! with the resulting operator table.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use prattle::grammar::{compile_records, parse_grammar};
+use prattle::parser::Parser;
+
+fuzz_target!(|text: &str| {
+  // An include directive reads a file relative to the current directory.
+  let includes = text.lines().map(|line| line.trim_start().to_lowercase()).any(|line| {
+    line.starts_with("include") || line.starts_with("import")
+  });
+  if includes {
+    return;
+  }
+
+  let table = match parse_grammar(text).map(|records| compile_records(&records)) {
+    Ok(Ok(table)) => table,
+    _ => return
+  };
+  let mut parser = Parser::new(table);
+  for expression in ["a", "a + b * c", "-a!", "f[a, b]", "(a; b)", "a ? b : c"] {
+    let _ = parser.parse(expression);
+  }
+});
@@ -0,0 +1,15 @@
+//! Tokenizes arbitrary text with the sigils of the operators in `resources/operators.csv`.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use prattle::lexer::Lexer;
+use prattle::operator::parse_operator_table;
+
+fuzz_target!(|text: &str| {
+  let table = parse_operator_table(include_str!("../../resources/operators.csv")).unwrap();
+  let mut sigils: Vec<String> = table.values().flat_map(|operator| operator.tokens()).collect();
+  sigils.sort();
+  sigils.dedup();
+
+  let _ = Lexer::new(text, sigils).tokenize();
+});
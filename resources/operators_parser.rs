@@ -0,0 +1,1140 @@
+//! A Pratt parser for the expression grammar in `resources/operators.grammar`, generated by `grammar codegen`. Do not
+//! edit it by hand; regenerate it from the grammar instead.
+//!
+//! `parse` reads an expression and builds each operator's `parse` template, and
+//! `parse_fullform` builds its `fullform` template instead.
+#![allow(dead_code, unused_mut, unused_variables, unreachable_code, clippy::all)]
+
+use std::fmt;
+
+/// An expression: an atom or a compound expression `head[arguments]`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    Symbol(String),
+    Number(f64),
+    String(String),
+    Apply(Box<Expr>, Vec<Expr>),
+}
+
+impl Expr {
+    /// The compound expression `name[arguments]`.
+    pub fn function(name: &str, arguments: Vec<Expr>) -> Expr {
+        Expr::Apply(Box::new(Expr::Symbol(name.to_string())), arguments)
+    }
+}
+
+/// Prints the expression in FullForm, e.g. `Plus[a, Times[2, b]]`, which `parse` reads back. An
+/// atom that plain text cannot spell is written between backquotes, e.g. `` `a b` `` or `` `-3` ``.
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Symbol(name) => write!(f, "{}", quote_symbol(name)),
+            // Zero and negative zero are the same number.
+            Expr::Number(value) if *value == 0.0 => write!(f, "0"),
+            Expr::Number(value) if value.is_nan() => write!(f, "`+nan.0`"),
+            Expr::Number(value) if value.is_infinite() => {
+                write!(f, "{}", if *value > 0.0 { "`+inf.0`" } else { "`-inf.0`" })
+            }
+            Expr::Number(value) if *value < 0.0 => write!(f, "`{}`", value),
+            Expr::Number(value) => write!(f, "{}", value),
+            Expr::String(text) => write!(f, "{}", quote(text)),
+            Expr::Apply(head, arguments) => {
+                write!(f, "{}[", head)?;
+                for (i, argument) in arguments.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", argument)?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+/// `text` as a string literal the lexer reads back as `text`.
+fn quote(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// `name` as a symbol the lexer reads back as `name`: as it is if it is an identifier, and
+/// otherwise between backquotes.
+fn quote_symbol(name: &str) -> String {
+    let mut chars = name.chars();
+    if chars.next().is_some_and(is_symbol_start) && chars.all(is_symbol_char) {
+        return name.to_string();
+    }
+    let mut quoted = String::from("`");
+    for (i, c) in name.chars().enumerate() {
+        // A name that is a number as written would read back as the number.
+        if c == '`' || c == '\\' || (i == 0 && quoted_number(name).is_some()) {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('`');
+    quoted
+}
+
+/// A range of byte offsets into the source text.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Span {
+        Span { start, end }
+    }
+}
+
+/// Errors encountered while lexing or parsing an expression.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum ParseError {
+    /// The lexer found text that is not the start of any token.
+    UnknownToken { text: String, span: Span },
+    UnterminatedString { span: Span },
+    /// A named character, e.g. `\[Alpha]`, whose name is not in the table of named characters.
+    UnknownNamedCharacter { name: String, span: Span },
+    /// The parser found a token other than the one the grammar requires.
+    UnexpectedToken { expected: String, found: String, span: Span },
+    /// The input ended where the grammar requires more.
+    UnexpectedEnd { expected: String, span: Span },
+    /// Whitespace precedes a token that an operator requires to follow the previous token directly.
+    UnexpectedSpace { before: String, span: Span },
+    /// No whitespace precedes a token that an operator requires to be separated by whitespace.
+    MissingSpace { before: String, span: Span },
+    /// An operator's template could not be instantiated with the operands it matched.
+    Template { operator: String, message: String, span: Span },
+    /// Expressions are nested more than `MAX_DEPTH` deep.
+    TooDeep { limit: usize, span: Span },
+}
+
+impl ParseError {
+    pub fn span(&self) -> Span {
+        match self {
+            ParseError::UnknownToken { span, .. }
+            | ParseError::UnterminatedString { span }
+            | ParseError::UnknownNamedCharacter { span, .. }
+            | ParseError::UnexpectedToken { span, .. }
+            | ParseError::UnexpectedEnd { span, .. }
+            | ParseError::UnexpectedSpace { span, .. }
+            | ParseError::MissingSpace { span, .. }
+            | ParseError::Template { span, .. }
+            | ParseError::TooDeep { span, .. } => *span,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::UnknownToken { text, span } => {
+                write!(f, "Unknown token {:?} at {}", text, span.start)
+            }
+            ParseError::UnterminatedString { span } => {
+                write!(f, "Unterminated string starting at {}", span.start)
+            }
+            ParseError::UnknownNamedCharacter { name, span } => {
+                write!(f, "Unknown named character \\[{}] at {}", name, span.start)
+            }
+            ParseError::UnexpectedToken { expected, found, span } => {
+                write!(f, "Expected {} but found {:?} at {}", expected, found, span.start)
+            }
+            ParseError::UnexpectedEnd { expected, .. } => {
+                write!(f, "Expected {} but reached the end of the input", expected)
+            }
+            ParseError::UnexpectedSpace { before, span } => {
+                write!(f, "Whitespace is not allowed before {:?} at {}", before, span.start)
+            }
+            ParseError::MissingSpace { before, span } => {
+                write!(f, "Whitespace is required before {:?} at {}", before, span.start)
+            }
+            ParseError::Template { operator, message, span } => {
+                write!(f, "Cannot build {} at {}: {}", operator, span.start, message)
+            }
+            ParseError::TooDeep { limit, span } => {
+                write!(f, "Expressions are nested more than {} deep at {}", limit, span.start)
+            }
+        }
+    }
+}
+
+/// Which of an operator's templates the parser instantiates. An operator without a `fullform`
+/// template uses its `parse` template for both.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum Form {
+    Parse,
+    FullForm,
+}
+
+/// Parses `text`, building each operator's `parse` template.
+pub fn parse(text: &str) -> Result<Expr, ParseError> {
+    parse_as(text, Form::Parse)
+}
+
+/// Parses `text`, building each operator's `fullform` template.
+pub fn parse_fullform(text: &str) -> Result<Expr, ParseError> {
+    parse_as(text, Form::FullForm)
+}
+
+pub fn parse_as(text: &str, form: Form) -> Result<Expr, ParseError> {
+    let mut parser = Parser {
+        tokens: tokenize(text)?,
+        cursor: 0,
+        last_end: 0,
+        form,
+        depth: 0,
+        lefts: 0,
+    };
+
+    let tree = parser.expr(0)?;
+
+    let token = parser.peek();
+    match token.kind {
+        TokenKind::EOF => Ok(tree),
+        _ => Err(unexpected("an operator or the end of the input", token)),
+    }
+}
+
+// The lexer
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum TokenKind {
+    Symbol,
+    Number,
+    String,
+    Operator,
+    EOF,
+}
+
+#[derive(Clone, Debug)]
+struct Token {
+    kind: TokenKind,
+    text: String,
+    span: Span,
+    space_before: bool,
+}
+
+/// Lexes the entire text. The last token is always an `EOF` token.
+fn tokenize(text: &str) -> Result<Vec<Token>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut cursor = 0;
+
+    loop {
+        let rest = &text[cursor..];
+        cursor += rest.len() - rest.trim_start().len();
+        let start = cursor;
+
+        check_named_char(text, cursor)?;
+        let (kind, contents) = match char_at(text, cursor) {
+            None => (TokenKind::EOF, String::new()),
+            Some((c, _)) if is_symbol_start(c) => {
+                let word = take_named_while(text, &mut cursor, is_symbol_char);
+                // A sigil may be a word, e.g. `and`.
+                match SIGILS.contains(&word.as_str()) {
+                    true => (TokenKind::Operator, word),
+                    false => (TokenKind::Symbol, word),
+                }
+            }
+            Some((c, _)) if c.is_ascii_digit() => (TokenKind::Number, number(text, &mut cursor)),
+            Some(('"', _)) => (TokenKind::String, string(text, &mut cursor)?),
+            Some(('`', _)) => quoted_atom(text, &mut cursor)?,
+            Some((c, length)) => match match_sigil(text, cursor) {
+                Some((sigil, sigil_length)) => {
+                    cursor += sigil_length;
+                    (TokenKind::Operator, sigil.to_string())
+                }
+                None => {
+                    return Err(ParseError::UnknownToken {
+                        text: c.to_string(),
+                        span: Span::new(start, start + length),
+                    });
+                }
+            },
+        };
+
+        let done = kind == TokenKind::EOF;
+        tokens.push(Token {
+            kind,
+            text: contents,
+            span: Span::new(start, cursor),
+            space_before: text[..start].ends_with(char::is_whitespace),
+        });
+        if done {
+            return Ok(tokens);
+        }
+    }
+}
+
+/// Reads a named character at the start of `text`, returning the name and the length of the
+/// escape in bytes.
+fn read_escape(text: &str) -> Option<(&str, usize)> {
+    let rest = text.strip_prefix("\\[")?;
+    let length = rest.find(|c: char| !c.is_ascii_alphanumeric()).unwrap_or(rest.len());
+    if length == 0 || !rest[length..].starts_with(']') {
+        return None;
+    }
+    Some((&rest[..length], length + 3))
+}
+
+/// The character at byte offset `position` and its length in the text. A named character is a
+/// single character whose length is the length of its escape.
+fn char_at(text: &str, position: usize) -> Option<(char, usize)> {
+    let rest = &text[position..];
+    if let Some((name, length)) = read_escape(rest) {
+        if let Some(c) = named_character(name) {
+            return Some((c, length));
+        }
+    }
+    rest.chars().next().map(|c| (c, c.len_utf8()))
+}
+
+/// Reports a named character at `position` whose name is not known.
+fn check_named_char(text: &str, position: usize) -> Result<(), ParseError> {
+    match read_escape(&text[position..]) {
+        Some((name, length)) if named_character(name).is_none() => {
+            Err(ParseError::UnknownNamedCharacter {
+                name: name.to_string(),
+                span: Span::new(position, position + length),
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+fn take_named_while<P: Fn(char) -> bool>(text: &str, cursor: &mut usize, predicate: P) -> String {
+    let mut taken = String::new();
+    while let Some((c, length)) = char_at(text, *cursor) {
+        if !predicate(c) {
+            break;
+        }
+        taken.push(c);
+        *cursor += length;
+    }
+    taken
+}
+
+/// The longest sigil at `position` and its length in the text.
+fn match_sigil(text: &str, position: usize) -> Option<(&'static str, usize)> {
+    SIGILS.iter().find_map(|sigil| {
+        let mut length = 0;
+        for expected in sigil.chars() {
+            match char_at(text, position + length) {
+                Some((c, char_length)) if c == expected => length += char_length,
+                _ => return None,
+            }
+        }
+        Some((*sigil, length))
+    })
+}
+
+/// Lexes `digits` or `digits.digits`.
+fn number(text: &str, cursor: &mut usize) -> String {
+    let start = *cursor;
+    let digits = |text: &str, position: usize| {
+        text[position..].find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len() - position)
+    };
+
+    *cursor += digits(text, *cursor);
+    let rest = &text[*cursor..];
+    if rest.starts_with('.') && rest[1..].starts_with(|c: char| c.is_ascii_digit()) {
+        *cursor += 1;
+        *cursor += digits(text, *cursor);
+    }
+    text[start..*cursor].to_string()
+}
+
+/// Lexes a number or symbol between backquotes with backslash escapes: a number if the text is one
+/// as written, e.g. `` `-3` `` or `` `+inf.0` ``, and otherwise a symbol, e.g. `` `a b` ``.
+fn quoted_atom(text: &str, cursor: &mut usize) -> Result<(TokenKind, String), ParseError> {
+    let start = *cursor;
+    *cursor += 1; // The opening backquote
+    let mut contents = String::new();
+    let mut escaped = false;
+
+    while let Some(c) = text[*cursor..].chars().next() {
+        *cursor += c.len_utf8();
+        match c {
+            '`' => {
+                return Ok(match quoted_number(&contents).filter(|_| !escaped) {
+                    Some(number) => (TokenKind::Number, number.to_string()),
+                    None => (TokenKind::Symbol, contents),
+                });
+            }
+            '\\' => {
+                escaped = true;
+                match text[*cursor..].chars().next() {
+                    Some(c) => {
+                        contents.push(c);
+                        *cursor += c.len_utf8();
+                    }
+                    None => break,
+                }
+            }
+            c => contents.push(c),
+        }
+    }
+    Err(ParseError::UnterminatedString { span: Span::new(start, *cursor) })
+}
+
+/// The number `text` between backquotes is, as text `f64::from_str` reads, if it is one.
+fn quoted_number(text: &str) -> Option<&str> {
+    match text {
+        "+inf.0" => Some("inf"),
+        "-inf.0" => Some("-inf"),
+        "+nan.0" => Some("NaN"),
+        _ => {
+            let unsigned = text.strip_prefix(['-', '+']).unwrap_or(text);
+            let (whole, fraction) = unsigned.split_once('.').unwrap_or((unsigned, "0"));
+            let digits = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+            (digits(whole) && digits(fraction)).then_some(text)
+        }
+    }
+}
+
+/// Lexes a double quoted string with backslash escapes, including named characters, returning
+/// its unescaped contents.
+fn string(text: &str, cursor: &mut usize) -> Result<String, ParseError> {
+    let start = *cursor;
+    *cursor += 1; // The opening quote
+    let mut contents = String::new();
+
+    loop {
+        check_named_char(text, *cursor)?;
+        match char_at(text, *cursor) {
+            Some(('"', _)) => {
+                *cursor += 1;
+                return Ok(contents);
+            }
+            Some(('\\', 1)) => {
+                *cursor += 1;
+                match text[*cursor..].chars().next() {
+                    Some(c) => {
+                        contents.push(match c {
+                            'n' => '\n',
+                            't' => '\t',
+                            c => c,
+                        });
+                        *cursor += c.len_utf8();
+                    }
+                    None => break,
+                }
+            }
+            Some((c, length)) => {
+                contents.push(c);
+                *cursor += length;
+            }
+            None => break,
+        }
+    }
+
+    *cursor = text.len();
+    Err(ParseError::UnterminatedString { span: Span::new(start, text.len()) })
+}
+
+fn is_symbol_start(c: char) -> bool {
+    c.is_alphabetic() || c == '$'
+}
+
+fn is_symbol_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '$'
+}
+
+/// Is `text` two or more repetitions of `token`?
+fn is_run_of(text: &str, token: &str) -> bool {
+    !token.is_empty() && text.len() > token.len() && text.trim_start_matches(token).is_empty()
+}
+
+// Templates
+
+/// The metavariables that bind operands.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum Meta {
+    Expr1,
+    Expr2,
+    Expr3,
+    Expr4,
+    Symbol,
+}
+
+/// A parse or fullform template. `Default` is the empty template, `name[operands]`. An optional
+/// part and a repetition group carry the metavariables they contain.
+enum Template {
+    Default,
+    Meta(Meta),
+    N,
+    NegN,
+    Number(i32),
+    Name,
+    Word(&'static str),
+    Literal(&'static str),
+    Cons(&'static Template, &'static [Template]),
+    Seq(&'static [Template]),
+    Alt(&'static [Template]),
+    Opt(&'static Template, &'static [Meta]),
+    Rep(&'static Template, &'static [Meta]),
+}
+
+enum Capture {
+    /// An operand matched by a metavariable.
+    Value(Meta, Expr),
+    /// The iterations of a repetition group.
+    Group(Vec<Bindings>),
+}
+
+/// The operands matched by an operator's syntax, in the order they were matched.
+#[derive(Default)]
+struct Bindings {
+    captures: Vec<Capture>,
+}
+
+impl Bindings {
+    fn bind(&mut self, metavariable: Meta, node: Expr) {
+        self.captures.push(Capture::Value(metavariable, node));
+    }
+
+    fn push_group(&mut self, iterations: Vec<Bindings>) {
+        self.captures.push(Capture::Group(iterations));
+    }
+
+    /// The operands bound directly to `metavariable`, not counting those bound within groups.
+    fn values_of(&self, metavariable: Meta) -> Vec<Expr> {
+        self.captures
+            .iter()
+            .filter_map(|c| match c {
+                Capture::Value(m, node) if *m == metavariable => Some(node.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The operands bound to `metavariable`, including those bound within groups.
+    fn all_values_of(&self, metavariable: Meta) -> Vec<Expr> {
+        let mut values = Vec::new();
+        for capture in &self.captures {
+            match capture {
+                Capture::Value(m, node) => {
+                    if *m == metavariable {
+                        values.push(node.clone());
+                    }
+                }
+                Capture::Group(iterations) => {
+                    for iteration in iterations {
+                        values.extend(iteration.all_values_of(metavariable));
+                    }
+                }
+            }
+        }
+        values
+    }
+
+    fn groups(&self) -> impl Iterator<Item = &Vec<Bindings>> {
+        self.captures.iter().filter_map(|c| match c {
+            Capture::Group(iterations) => Some(iterations),
+            _ => None,
+        })
+    }
+
+    /// Every matched operand in the order it was matched.
+    fn operands(&self) -> Vec<Expr> {
+        let mut operands = Vec::new();
+        for capture in &self.captures {
+            match capture {
+                Capture::Value(_, node) => operands.push(node.clone()),
+                Capture::Group(iterations) => {
+                    for iteration in iterations {
+                        operands.extend(iteration.operands());
+                    }
+                }
+            }
+        }
+        operands
+    }
+
+    /// The number of repetitions of the first repetition group, the value of `n`.
+    fn count(&self) -> Option<usize> {
+        self.groups().next().map(|iterations| iterations.len())
+    }
+}
+
+/// Instantiates `template` for the operator named `name`. The template must produce exactly one
+/// expression.
+fn instantiate(template: &Template, name: &str, bindings: &Bindings) -> Result<Expr, String> {
+    if let Template::Default = template {
+        return Ok(Expr::function(name, bindings.operands()));
+    }
+
+    let mut nodes = expand(template, name, &[bindings])?;
+    if nodes.len() != 1 {
+        return Err(format!("the template produced {} expressions instead of one", nodes.len()));
+    }
+    Ok(nodes.pop().unwrap())
+}
+
+/// Looks up a metavariable, innermost scope first. A metavariable bound only within repetition
+/// groups stands for all of its bindings.
+fn lookup(metavariable: Meta, scopes: &[&Bindings]) -> Vec<Expr> {
+    for scope in scopes.iter().rev() {
+        let values = scope.values_of(metavariable);
+        if !values.is_empty() {
+            return values;
+        }
+    }
+    match scopes.last() {
+        Some(scope) => scope.all_values_of(metavariable),
+        None => Vec::new(),
+    }
+}
+
+fn count(scopes: &[&Bindings]) -> usize {
+    scopes.iter().rev().find_map(|scope| scope.count()).unwrap_or(0)
+}
+
+/// Finds the repetition group that bound any of the metavariables of a template group.
+fn find_group<'b>(operands: &[Meta], scopes: &[&'b Bindings]) -> Option<&'b Vec<Bindings>> {
+    for scope in scopes.iter().rev() {
+        for iterations in scope.groups() {
+            let binds_operand = operands.is_empty()
+                || iterations.iter().any(|iteration| {
+                    operands.iter().any(|m| !iteration.all_values_of(*m).is_empty())
+                });
+            if binds_operand {
+                return Some(iterations);
+            }
+        }
+    }
+    None
+}
+
+/// Instantiates a part of a template, producing the list of expressions it stands for.
+fn expand(template: &Template, name: &str, scopes: &[&Bindings]) -> Result<Vec<Expr>, String> {
+    let nodes = match template {
+        Template::Default => Vec::new(),
+        Template::Meta(metavariable) => lookup(*metavariable, scopes),
+        Template::N => vec![Expr::Number(count(scopes) as f64)],
+        Template::NegN => vec![Expr::Number(-(count(scopes) as f64))],
+        Template::Number(value) => vec![Expr::Number(*value as f64)],
+        Template::Name => vec![Expr::Symbol(name.to_string())],
+        Template::Word(word) => vec![Expr::Symbol(word.to_string())],
+        Template::Literal(text) => vec![Expr::String(text.to_string())],
+        Template::Cons(head, arguments) => {
+            let mut heads = expand(head, name, scopes)?;
+            if heads.len() != 1 {
+                return Err(format!("the head produced {} expressions instead of one", heads.len()));
+            }
+            let mut children = Vec::new();
+            for argument in arguments.iter() {
+                children.extend(expand(argument, name, scopes)?);
+            }
+            vec![Expr::Apply(Box::new(heads.pop().unwrap()), children)]
+        }
+        Template::Seq(list) => {
+            let mut nodes = Vec::new();
+            for item in list.iter() {
+                nodes.extend(expand(item, name, scopes)?);
+            }
+            nodes
+        }
+        Template::Alt(list) => {
+            let mut nodes = Vec::new();
+            for item in list.iter() {
+                nodes = expand(item, name, scopes)?;
+                if !nodes.is_empty() {
+                    break;
+                }
+            }
+            nodes
+        }
+        Template::Opt(inner, operands) => {
+            let bound = operands.iter().all(|m| !lookup(*m, scopes).is_empty());
+            if bound {
+                expand(inner, name, scopes)?
+            } else {
+                Vec::new()
+            }
+        }
+        Template::Rep(inner, operands) => match find_group(operands, scopes) {
+            Some(iterations) => {
+                let mut nodes = Vec::new();
+                for iteration in iterations {
+                    let mut inner_scopes = scopes.to_vec();
+                    inner_scopes.push(iteration);
+                    nodes.extend(expand(inner, name, &inner_scopes)?);
+                }
+                nodes
+            }
+            None => expand(inner, name, scopes)?,
+        },
+    };
+    Ok(nodes)
+}
+
+// The parser
+
+struct Parser {
+    tokens: Vec<Token>,
+    cursor: usize,
+    // The end of the last consumed token.
+    last_end: usize,
+    form: Form,
+    // The number of calls of `expr` in progress.
+    depth: usize,
+    // The number of left operands being built by the calls of `expr` in progress.
+    lefts: usize,
+}
+
+/// Parses the remainder of an operator that takes the given expression as its left operand.
+type LeftDenotation = fn(&mut Parser, Expr) -> Result<Expr, ParseError>;
+
+impl Parser {
+    /// The next token, which is not consumed. The last token is always `EOF`.
+    fn peek(&self) -> &Token {
+        &self.tokens[self.cursor.min(self.tokens.len() - 1)]
+    }
+
+    /// The text of the next token if it is an operator token.
+    fn peek_operator(&self) -> Option<&str> {
+        let token = self.peek();
+        match token.kind {
+            TokenKind::Operator => Some(token.text.as_str()),
+            _ => None,
+        }
+    }
+
+    fn peek_is(&self, text: &str) -> bool {
+        self.peek_operator() == Some(text)
+    }
+
+    /// Fails if another level of nesting, a call of `expr` or a left operand, would exceed
+    /// `MAX_DEPTH`.
+    fn check_depth(&self) -> Result<(), ParseError> {
+        match self.depth + self.lefts >= MAX_DEPTH {
+            true => Err(ParseError::TooDeep { limit: MAX_DEPTH, span: self.peek().span }),
+            false => Ok(()),
+        }
+    }
+
+    fn consume(&mut self) -> Token {
+        let token = self.peek().clone();
+        if token.kind != TokenKind::EOF {
+            self.cursor += 1;
+            self.last_end = token.span.end;
+        }
+        token
+    }
+
+    fn expect(&mut self, text: &str) -> Result<Token, ParseError> {
+        if self.peek_is(text) {
+            Ok(self.consume())
+        } else {
+            Err(unexpected(&format!("{:?}", text), self.peek()))
+        }
+    }
+
+    /// Can the next token begin an expression?
+    fn can_start_expr(&self) -> bool {
+        let token = self.peek();
+        match token.kind {
+            TokenKind::Operator => is_null_token(&token.text),
+            TokenKind::EOF => false,
+            _ => true,
+        }
+    }
+
+    /// Parses a symbol, number, or string.
+    fn atom(&mut self) -> Expr {
+        let token = self.consume();
+        match token.kind {
+            TokenKind::Number => Expr::Number(token.text.parse::<f64>().unwrap_or(f64::NAN)),
+            TokenKind::String => Expr::String(token.text),
+            _ => Expr::Symbol(token.text),
+        }
+    }
+
+    fn symbol(&mut self) -> Result<Expr, ParseError> {
+        if self.peek().kind != TokenKind::Symbol {
+            return Err(unexpected("a symbol", self.peek()));
+        }
+        Ok(self.atom())
+    }
+
+    /// A run of a repeated token, e.g. `%%%` for `("%")+`, is lexed as a longer operator token if
+    /// the run or a part of it is itself a token. If the next token is a run of `text`, splits off
+    /// its first occurrence so that each occurrence counts as a repetition.
+    fn split_repeated(&mut self, text: &str) {
+        let token = self.peek().clone();
+        if token.kind != TokenKind::Operator || !is_run_of(&token.text, text) {
+            return;
+        }
+
+        let split = token.span.start + text.len();
+        let first = Token {
+            kind: TokenKind::Operator,
+            text: text.to_string(),
+            span: Span::new(token.span.start, split),
+            space_before: token.space_before,
+        };
+        let rest = Token {
+            kind: TokenKind::Operator,
+            text: token.text[text.len()..].to_string(),
+            span: Span::new(split, token.span.end),
+            space_before: false,
+        };
+        self.tokens.splice(self.cursor..self.cursor + 1, [first, rest]);
+    }
+
+    /// The error for a next token that breaks a whitespace constraint.
+    fn spacing_error(&self, space_required: bool) -> ParseError {
+        let token = self.peek();
+        let (before, span) = (token.text.clone(), token.span);
+        match space_required {
+            true => ParseError::MissingSpace { before, span },
+            false => ParseError::UnexpectedSpace { before, span },
+        }
+    }
+
+    fn make_node(
+        &self,
+        name: &str,
+        parse: &Template,
+        fullform: Option<&Template>,
+        bindings: &Bindings,
+        start: usize,
+    ) -> Result<Expr, ParseError> {
+        let template = match (self.form, fullform) {
+            (Form::FullForm, Some(fullform)) => fullform,
+            _ => parse,
+        };
+        instantiate(template, name, bindings).map_err(|message| ParseError::Template {
+            operator: name.to_string(),
+            message,
+            span: Span::new(start, self.last_end),
+        })
+    }
+}
+
+fn unexpected(expected: &str, token: &Token) -> ParseError {
+    match token.kind {
+        TokenKind::EOF => ParseError::UnexpectedEnd {
+            expected: expected.to_string(),
+            span: token.span,
+        },
+        _ => ParseError::UnexpectedToken {
+            expected: expected.to_string(),
+            found: token.text.clone(),
+            span: token.span,
+        },
+    }
+}
+
+// The grammar
+
+/// How deeply expressions may be nested, as in a `Parser` with the default `max_depth`.
+const MAX_DEPTH: usize = 256;
+
+/// The operator tokens, longest first.
+const SIGILS: &[&str] = &[
+    ":=",
+    "(",
+    ")",
+    "*",
+    "+",
+    ",",
+    "-",
+    "/",
+    "=",
+    "[",
+    "]",
+    "^",
+    "√",
+];
+
+/// The character named `\[name]`.
+fn named_character(name: &str) -> Option<char> {
+    match name {
+        "Alpha" => Some('α'),
+        "And" => Some('∧'),
+        "Beta" => Some('β'),
+        "CapitalDelta" => Some('Δ'),
+        "CapitalGamma" => Some('Γ'),
+        "CapitalLambda" => Some('Λ'),
+        "CapitalOmega" => Some('Ω'),
+        "CapitalPhi" => Some('Φ'),
+        "CapitalPi" => Some('Π'),
+        "CapitalPsi" => Some('Ψ'),
+        "CapitalSigma" => Some('Σ'),
+        "CapitalTheta" => Some('Θ'),
+        "CapitalXi" => Some('Ξ'),
+        "CenterDot" => Some('·'),
+        "Chi" => Some('χ'),
+        "CirclePlus" => Some('⊕'),
+        "CircleTimes" => Some('⊗'),
+        "Congruent" => Some('≡'),
+        "Cross" => Some('⨯'),
+        "Degree" => Some('°'),
+        "Del" => Some('∇'),
+        "Delta" => Some('δ'),
+        "Divide" => Some('÷'),
+        "DownArrow" => Some('↓'),
+        "Element" => Some('∈'),
+        "EmptySet" => Some('∅'),
+        "Epsilon" => Some('ε'),
+        "Equivalent" => Some('⟺'),
+        "Eta" => Some('η'),
+        "Exists" => Some('∃'),
+        "ForAll" => Some('∀'),
+        "Function" => Some('↦'),
+        "Gamma" => Some('γ'),
+        "GreaterEqual" => Some('≥'),
+        "Implies" => Some('⟹'),
+        "Infinity" => Some('∞'),
+        "Integral" => Some('∫'),
+        "Intersection" => Some('⋂'),
+        "Iota" => Some('ι'),
+        "Kappa" => Some('κ'),
+        "Lambda" => Some('λ'),
+        "LeftArrow" => Some('←'),
+        "LeftRightArrow" => Some('↔'),
+        "LessEqual" => Some('≤'),
+        "LongRightArrow" => Some('⟶'),
+        "MinusPlus" => Some('∓'),
+        "Mu" => Some('μ'),
+        "Not" => Some('¬'),
+        "NotElement" => Some('∉'),
+        "NotEqual" => Some('≠'),
+        "NotExists" => Some('∄'),
+        "Nu" => Some('ν'),
+        "Omega" => Some('ω'),
+        "Or" => Some('∨'),
+        "PartialD" => Some('∂'),
+        "Phi" => Some('φ'),
+        "Pi" => Some('π'),
+        "PlusMinus" => Some('±'),
+        "Product" => Some('∏'),
+        "Proportional" => Some('∝'),
+        "Psi" => Some('ψ'),
+        "Rho" => Some('ρ'),
+        "Rule" => Some('→'),
+        "RuleDelayed" => Some('⧴'),
+        "Sigma" => Some('σ'),
+        "Sqrt" => Some('√'),
+        "Subset" => Some('⊂'),
+        "SubsetEqual" => Some('⊆'),
+        "Sum" => Some('∑'),
+        "Superset" => Some('⊃'),
+        "SupersetEqual" => Some('⊇'),
+        "Tau" => Some('τ'),
+        "Theta" => Some('θ'),
+        "TildeTilde" => Some('≈'),
+        "Times" => Some('×'),
+        "Union" => Some('⋃'),
+        "UpArrow" => Some('↑'),
+        "Upsilon" => Some('υ'),
+        "Xi" => Some('ξ'),
+        "Zeta" => Some('ζ'),
+        _ => None,
+    }
+}
+
+/// Does `text` select an operator without a left operand?
+fn is_null_token(text: &str) -> bool {
+    match text {
+        "(" => true,
+        _ => is_run_of(text, "("),
+    }
+}
+
+const PARSE_BASE: Template = Template::Default;
+const PARSE_CONSTRUCT: Template = Template::Cons(&Template::Meta(Meta::Expr1), &[Template::Rep(&Template::Meta(Meta::Expr2), &[Meta::Expr2])]);
+const PARSE_DIVIDE: Template = Template::Default;
+const PARSE_MINUS: Template = Template::Default;
+const PARSE_PARENTHESES: Template = Template::Meta(Meta::Expr1);
+const PARSE_PLUS: Template = Template::Default;
+const PARSE_POWER: Template = Template::Default;
+const PARSE_SET: Template = Template::Default;
+const PARSE_SET_DELAYED: Template = Template::Default;
+const PARSE_TIMES: Template = Template::Default;
+
+impl Parser {
+    fn expr(&mut self, p: i32) -> Result<Expr, ParseError> {
+        self.check_depth()?;
+        self.depth += 1;
+        let mut tree = self.null_denotation()?;
+        let mut r = i32::MAX;
+        let mut lefts = 0;
+
+        loop {
+            let (lbp, nbp, led): (i32, i32, LeftDenotation) = match self.peek_operator() {
+                Some("*") => (9980, 9980, Parser::led_times),
+                Some("+") => (9970, 9970, Parser::led_plus),
+                Some("-") => (9970, 9970, Parser::led_minus),
+                Some("/") => (9980, 9980, Parser::led_divide),
+                Some(":=") => (9890, 9890, Parser::led_set_delayed),
+                Some("=") => (9890, 9890, Parser::led_set),
+                Some("[") => (9999, 9999, Parser::led_construct),
+                Some("^") => (9990, 9990, Parser::led_power),
+                Some("√") => (9995, 9994, Parser::led_base),
+                _ => break,
+            };
+            if p > lbp || lbp > r {
+                break;
+            }
+            // The tree so far becomes a left operand, one level deeper.
+            self.check_depth()?;
+            self.lefts += 1;
+            lefts += 1;
+            tree = led(self, tree)?;
+            r = nbp;
+        }
+
+        self.depth -= 1;
+        self.lefts -= lefts;
+        Ok(tree)
+    }
+
+    fn null_denotation(&mut self) -> Result<Expr, ParseError> {
+        match self.peek().kind {
+            TokenKind::Operator => {}
+            TokenKind::EOF => return Err(unexpected("an expression", self.peek())),
+            _ => return Ok(self.atom()),
+        }
+        match self.peek().text.as_str() {
+            "(" => self.nud_parentheses(),
+            text if is_run_of(text, "(") => self.nud_parentheses(),
+            _ => Err(unexpected("an expression", self.peek())),
+        }
+    }
+
+    fn led_base(&mut self, left: Expr) -> Result<Expr, ParseError> {
+        let start = self.peek().span.start;
+        let mut b0 = Bindings::default();
+        b0.bind(Meta::Expr1, left);
+        self.expect("√")?;
+        b0.bind(Meta::Expr2, self.expr(9996)?);
+        self.make_node("Base", &PARSE_BASE, None, &b0, start)
+    }
+
+    fn led_construct(&mut self, left: Expr) -> Result<Expr, ParseError> {
+        let start = self.peek().span.start;
+        let mut b0 = Bindings::default();
+        b0.bind(Meta::Expr1, left);
+        self.expect("[")?;
+        let mut iterations1 = Vec::new();
+        if self.can_start_expr() {
+            {
+                let mut b2 = Bindings::default();
+                b2.bind(Meta::Expr2, self.expr(0)?);
+                iterations1.push(b2);
+            }
+        }
+        while !iterations1.is_empty() && self.peek_is(",") {
+            let mut b3 = Bindings::default();
+            self.expect(",")?;
+            {
+                let mut b4 = Bindings::default();
+                b4.bind(Meta::Expr2, self.expr(0)?);
+                iterations1.push(b4);
+            }
+        }
+        b0.push_group(iterations1);
+        self.expect("]")?;
+        self.make_node("Construct", &PARSE_CONSTRUCT, None, &b0, start)
+    }
+
+    fn led_divide(&mut self, left: Expr) -> Result<Expr, ParseError> {
+        let start = self.peek().span.start;
+        let mut b0 = Bindings::default();
+        b0.bind(Meta::Expr1, left);
+        self.expect("/")?;
+        b0.bind(Meta::Expr2, self.expr(9981)?);
+        self.make_node("Divide", &PARSE_DIVIDE, None, &b0, start)
+    }
+
+    fn led_minus(&mut self, left: Expr) -> Result<Expr, ParseError> {
+        let start = self.peek().span.start;
+        let mut b0 = Bindings::default();
+        b0.bind(Meta::Expr1, left);
+        self.expect("-")?;
+        b0.bind(Meta::Expr2, self.expr(9971)?);
+        while self.peek_is("-") {
+            self.expect("-")?;
+            b0.bind(Meta::Expr2, self.expr(9971)?);
+        }
+        self.make_node("Minus", &PARSE_MINUS, None, &b0, start)
+    }
+
+    fn nud_parentheses(&mut self) -> Result<Expr, ParseError> {
+        let start = self.peek().span.start;
+        let mut b0 = Bindings::default();
+        self.expect("(")?;
+        b0.bind(Meta::Expr1, self.expr(0)?);
+        self.expect(")")?;
+        self.make_node("Parentheses", &PARSE_PARENTHESES, None, &b0, start)
+    }
+
+    fn led_plus(&mut self, left: Expr) -> Result<Expr, ParseError> {
+        let start = self.peek().span.start;
+        let mut b0 = Bindings::default();
+        b0.bind(Meta::Expr1, left);
+        self.expect("+")?;
+        b0.bind(Meta::Expr2, self.expr(9971)?);
+        while self.peek_is("+") {
+            self.expect("+")?;
+            b0.bind(Meta::Expr2, self.expr(9971)?);
+        }
+        self.make_node("Plus", &PARSE_PLUS, None, &b0, start)
+    }
+
+    fn led_power(&mut self, left: Expr) -> Result<Expr, ParseError> {
+        let start = self.peek().span.start;
+        let mut b0 = Bindings::default();
+        b0.bind(Meta::Expr1, left);
+        self.expect("^")?;
+        b0.bind(Meta::Expr2, self.expr(9990)?);
+        self.make_node("Power", &PARSE_POWER, None, &b0, start)
+    }
+
+    fn led_set(&mut self, left: Expr) -> Result<Expr, ParseError> {
+        let start = self.peek().span.start;
+        let mut b0 = Bindings::default();
+        b0.bind(Meta::Expr1, left);
+        self.expect("=")?;
+        b0.bind(Meta::Expr2, self.expr(9890)?);
+        self.make_node("Set", &PARSE_SET, None, &b0, start)
+    }
+
+    fn led_set_delayed(&mut self, left: Expr) -> Result<Expr, ParseError> {
+        let start = self.peek().span.start;
+        let mut b0 = Bindings::default();
+        b0.bind(Meta::Expr1, left);
+        self.expect(":=")?;
+        b0.bind(Meta::Expr2, self.expr(9890)?);
+        self.make_node("SetDelayed", &PARSE_SET_DELAYED, None, &b0, start)
+    }
+
+    fn led_times(&mut self, left: Expr) -> Result<Expr, ParseError> {
+        let start = self.peek().span.start;
+        let mut b0 = Bindings::default();
+        b0.bind(Meta::Expr1, left);
+        self.expect("*")?;
+        b0.bind(Meta::Expr2, self.expr(9981)?);
+        while self.peek_is("*") {
+            self.expect("*")?;
+            b0.bind(Meta::Expr2, self.expr(9981)?);
+        }
+        self.make_node("Times", &PARSE_TIMES, None, &b0, start)
+    }
+}
@@ -2,18 +2,16 @@
 
 A minimal abstract syntax tree (AST) implementation for a Pratt parser.
 
-For the sake of simplicity, the AST nodes serve triple duty:
+For the sake of simplicity, the AST nodes serve double duty:
 
-  1. Instead of having a separate `Token` type, the lexer produces AST nodes directly.
-  2. The parser assembles the AST nodes into a tree structure, the standard abstract syntax tree representing the
+  1. The parser assembles the AST nodes into a tree structure, the standard abstract syntax tree representing the
      source text.
-  3. An expression in our language is any subtree of the AST (any node with all of its descendants), including the
+  2. An expression in our language is any subtree of the AST (any node with all of its descendants), including the
      whole tree itself.
 
 Usually the expression will ultimately be represented by some tree structure. There are a variety of ways
-in which the expression tree could be elaborated relative to this implementation. For example, a node
-might also have a value, as in the case of a number literal, identifier, or string, or it might retain a
-`Token` or `Span` instance member representing the original source text and location that resulted in the
+in which the expression tree could be elaborated relative to this implementation. Our nodes have a value in the case
+of a number literal, identifier, or string, and they retain the `Span` of the source text that resulted in the
 creation of the node.
 
 */
@@ -22,53 +20,330 @@ creation of the node.
 use std::fmt::{Display, Formatter};
 use std::rc::Rc;
 
-use itertools::join;
-use crate::evaluator::Evaluator;
-use crate::operator::Operator;
+use crate::evaluator::{BuiltInFn, Evaluator};
+use crate::lexer::{Span, is_symbol_char, is_symbol_start, quoted_number};
 
-pub type RcASTNode<'a> = Rc<ASTNode<'a>>;
-pub type Children<'c> = Vec<RcASTNode<'c>>;
+pub type RcASTNode = Rc<ASTNode>;
+pub type Children = Vec<RcASTNode>;
 
 /// Our primary use for an `ASTNode` is as an expression. We could have called the struct `Expression`, but I wish to
 /// emphasize how building the expression tree is _syntax directed_.
 ///
+/// An expression is either an atom (a symbol, number, or string) or a compound expression `head[child1, child2, …]`.
+/// The head is usually a symbol, as in `Plus[1, 2]`, but may be any expression, as in `f[x][y]`. Note that the
+/// symbol `f` and the compound expression `f[]` are different expressions.
+///
 /// ASTNodes are immutable. As a consequence, we may share subexpressions between expressions.
-#[derive(Clone, Debug, Eq, PartialEq, Hash)]
-pub struct ASTNode<'a> {
+#[derive(Clone, Debug, PartialEq)]
+pub struct ASTNode {
+  /// For an atom, the atom's value. For a compound expression, the evaluator of its head. (The code for `f[x][y]` is
+  /// found through the symbol `f`.)
   evaluator: Evaluator,
-  children:  Children<'a>
+  /// `None` for atoms.
+  head:      Option<RcASTNode>,
+  children:  Children,
+  /// The source text the node was parsed from, if it was parsed.
+  span:      Option<Span>,
 }
 
 
-impl<'a> Display for ASTNode<'a>{
+/// Writes the node in FullForm, e.g. `Plus[1, Times[2, x]]`: an atom as itself, and a compound
+/// expression as its head followed by its children in brackets. FullForm is the canonical text of
+/// an expression: it does not depend on an operator table, and nodes that are the same expression,
+/// spans aside, have the same FullForm.
+///
+/// Strings are written in double quotes, with `"`, `\`, newlines, and tabs escaped as the lexer
+/// reads them. An atom the lexer would not read back from its plain text is written between
+/// backquotes, which the lexer reads as that atom: a symbol whose name is not an identifier,
+/// `` `a b` ``, and a number that is negative or not finite, `` `-3` ``, `` `+inf.0` ``, `` `-inf.0` ``,
+/// or `` `+nan.0` ``. A built-in function, which has no name, is written with its address,
+/// `BuiltIn["0x…"]`, and does not read back.
+///
+/// The tree is walked with an explicit stack, so that writing a deep tree cannot overflow the
+/// call stack.
+impl Display for ASTNode {
   fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    // What remains to be written, last first.
+    let mut pending: Vec<Result<&ASTNode, &'static str>> = vec![Ok(self)];
+    while let Some(next) = pending.pop() {
+      let node =
+        match next {
+          Ok(node) => node,
+          Err(text) => { f.write_str(text)?; continue; }
+        };
+
+      match (&node.head, &node.evaluator) {
+
+        (Some(head), _) => {
+          pending.push(Err("]"));
+          for (i, child) in node.children.iter().enumerate().rev() {
+            pending.push(Ok(child));
+            if i > 0 {
+              pending.push(Err(", "));
+            }
+          }
+          pending.push(Err("["));
+          pending.push(Ok(head));
+        }
+
+        (None, Evaluator::Symbol(name)) if is_symbol_name(name) => write!(f, "{}", name)?,
+
+        (None, Evaluator::Symbol(name)) => write!(f, "{}", quote_symbol(name))?,
+
+        (None, Evaluator::String(text)) => write!(f, "{}", quote(text))?,
+
+        // Zero and negative zero are the same number.
+        (None, Evaluator::Number(value)) if *value == 0.0 => write!(f, "0")?,
+
+        (None, Evaluator::Number(value)) if value.is_nan() => write!(f, "`+nan.0`")?,
 
-    match self {
+        (None, Evaluator::Number(value)) if value.is_infinite() => {
+          write!(f, "{}", if *value > 0.0 { "`+inf.0`" } else { "`-inf.0`" })?
+        }
+
+        (None, Evaluator::Number(value)) if *value < 0.0 => write!(f, "`{}`", value)?,
+
+        (None, Evaluator::Number(value)) => write!(f, "{}", value)?,
+
+        (None, Evaluator::BuiltIns(function)) => {
+          write!(f, "BuiltIn[{}]", quote(&format!("{:p}", *function as *const ())))?
+        }
 
-      ASTNode::Nonterminal{
-        operator,
-        children
       }
-        => {
-        let child_list = join(&*children, ",");
-        write!(f, "Nonterminal<{:?}>({})", operator.name, child_list)
+    }
+    Ok(())
+  }
+}
+
+pub(crate) fn is_symbol_name(name: &str) -> bool {
+  let mut chars = name.chars();
+  chars.next().is_some_and(is_symbol_start) && chars.all(is_symbol_char)
+}
+
+/// `text` as a string literal the lexer reads back as `text`.
+pub(crate) fn quote(text: &str) -> String {
+  let mut quoted = String::with_capacity(text.len() + 2);
+  quoted.push('"');
+  for c in text.chars() {
+    match c {
+      '"' => quoted.push_str("\\\""),
+      '\\' => quoted.push_str("\\\\"),
+      '\n' => quoted.push_str("\\n"),
+      '\t' => quoted.push_str("\\t"),
+      c => quoted.push(c)
+    }
+  }
+  quoted.push('"');
+  quoted
+}
+
+/// `name` between backquotes, which the lexer reads back as the symbol `name`. A name that is a
+/// number as written has its first character escaped, so that it does not read back as the number.
+fn quote_symbol(name: &str) -> String {
+  let mut quoted = String::with_capacity(name.len() + 2);
+  quoted.push('`');
+  for (i, c) in name.chars().enumerate() {
+    if c == '`' || c == '\\' || (i == 0 && quoted_number(name).is_some()) {
+      quoted.push('\\');
+    }
+    quoted.push(c);
+  }
+  quoted.push('`');
+  quoted
+}
+
+
+impl ASTNode{
+
+  pub fn symbol(name: &str) -> ASTNode {
+    ASTNode::atom(Evaluator::Symbol(name.to_string()))
+  }
+
+  pub fn number(value: f64) -> ASTNode {
+    ASTNode::atom(Evaluator::Number(value))
+  }
+
+  pub fn string(text: &str) -> ASTNode {
+    ASTNode::atom(Evaluator::String(text.to_string()))
+  }
+
+  pub fn builtin(function: BuiltInFn) -> ASTNode {
+    ASTNode::atom(Evaluator::BuiltIns(function))
+  }
+
+  fn atom(evaluator: Evaluator) -> ASTNode {
+    ASTNode{
+      evaluator,
+      head: None,
+      children: Children::new(),
+      span: None
+    }
+  }
+
+  /// Creates the compound expression `head[children]`.
+  pub fn compound(head: RcASTNode, children: Children) -> ASTNode {
+    ASTNode{
+      evaluator: head.evaluator.clone(),
+      head: Some(head),
+      children,
+      span: None
+    }
+  }
+
+  /// Creates the compound expression `name[children]`.
+  pub fn function(name: &str, children: Children) -> ASTNode {
+    ASTNode::compound(Rc::new(ASTNode::symbol(name)), children)
+  }
+
+  pub fn with_span(mut self, span: Span) -> ASTNode {
+    self.span = Some(span);
+    self
+  }
+
+  pub fn is_atom(&self) -> bool {
+    self.head.is_none()
+  }
+
+  pub fn evaluator(&self) -> &Evaluator {
+    &self.evaluator
+  }
+
+  pub fn head(&self) -> Option<&RcASTNode> {
+    self.head.as_ref()
+  }
+
+  pub fn children(&self) -> &Children {
+    &self.children
+  }
+
+  pub fn span(&self) -> Option<Span> {
+    self.span
+  }
+
+  /// The name of a symbol atom, or of the head of a compound expression with a symbol head.
+  pub fn name(&self) -> Option<&str> {
+    match &self.head {
+      Some(head) => if head.is_atom() { head.name() } else { None },
+      None => match &self.evaluator {
+        Evaluator::Symbol(name) => Some(name.as_str()),
+        _ => None
       }
+    }
+  }
 
-      ASTNode::Terminal(text) => {
-        write!(f, "Terminal(\"{}\")", text)
+  /// Is `other` the same expression, wherever the two were parsed from? Unlike `==`, this ignores
+  /// spans. Like `Display`, it walks the trees with an explicit stack.
+  pub fn same_expression(&self, other: &ASTNode) -> bool {
+    let mut pairs = vec![(self, other)];
+    while let Some((a, b)) = pairs.pop() {
+      if a.evaluator != b.evaluator || a.children.len() != b.children.len() {
+        return false;
+      }
+      match (&a.head, &b.head) {
+        (Some(a_head), Some(b_head)) => pairs.push((a_head, b_head)),
+        (None, None) => {}
+        _ => return false
       }
+      pairs.extend(a.children.iter().map(|c| &**c).zip(b.children.iter().map(|c| &**c)));
+    }
+    true
+  }
 
+  /// Because `ASTNode`s are immutable, `evaluate` creates a new node if it needs to.
+  pub fn evaluate(&self) -> RcASTNode {
+    match self.is_atom() {
+      true => Rc::new(self.clone()),
+      false => self.evaluator.evaluate(&self.children).unwrap_or_else(|| Rc::new(self.clone()))
     }
+  }
+
+}
 
+/// Dropping a node drops the subtrees no other node shares one at a time rather than recursively,
+/// so that dropping a deeply nested tree, such as the parser builds with `Stack::Explicit`, does
+/// not overflow the stack.
+impl Drop for ASTNode {
+  fn drop(&mut self) {
+    let mut orphans: Children = self.children.drain(..).chain(self.head.take()).collect();
+    while let Some(node) = orphans.pop() {
+      if let Ok(mut node) = Rc::try_unwrap(node) {
+        orphans.append(&mut node.children);
+        orphans.extend(node.head.take());
+      }
+    }
   }
 }
 
 
-impl<'a> ASTNode<'a>{
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::grammar::compile_grammar_file;
+  use crate::parser::Parser;
 
-  /// Because `ASTNode`s are immutable, `evaluate` creates a new node if it needs to.
-  pub fn evaluate(&self) -> RcASTNode<'a> {
-    self.evaluator.evaluate()
+  #[test]
+  fn full_form() {
+    let expression = ASTNode::function("Plus", vec![
+      Rc::new(ASTNode::number(1.0)),
+      Rc::new(ASTNode::function("Times", vec![Rc::new(ASTNode::number(2.5)), Rc::new(ASTNode::symbol("x"))]))
+    ]);
+    assert_eq!(expression.to_string(), "Plus[1, Times[2.5, x]]");
+
+    let f_x = Rc::new(ASTNode::function("f", vec![Rc::new(ASTNode::symbol("x"))]));
+    assert_eq!(ASTNode::compound(f_x, vec![]).to_string(), "f[x][]");
+    assert_eq!(ASTNode::string("say \"hi\"\\\n").to_string(), r#""say \"hi\"\\\n""#);
+    assert_eq!(ASTNode::symbol("$x1").to_string(), "$x1");
+    assert_eq!(ASTNode::symbol("a b").to_string(), "`a b`");
+    assert_eq!(ASTNode::symbol("1x").to_string(), "`1x`");
+    assert_eq!(ASTNode::symbol("-3").to_string(), "`\\-3`");
+    assert_eq!(ASTNode::number(-3.5).to_string(), "`-3.5`");
+    assert_eq!(ASTNode::number(f64::NEG_INFINITY).to_string(), "`-inf.0`");
+    assert_eq!(ASTNode::number(-0.0).to_string(), "0");
   }
 
+  #[test]
+  fn deep_full_form() {
+    let nested = |depth: usize| {
+      (0..depth).fold(ASTNode::symbol("x"), |node, _| ASTNode::function("f", vec![Rc::new(node)]))
+    };
+    let depth = 100_000;
+    let tree = nested(depth);
+    assert_eq!(tree.to_string(), format!("{}x{}", "f[".repeat(depth), "]".repeat(depth)));
+    assert!(tree.same_expression(&nested(depth)));
+    assert!(!tree.same_expression(&nested(depth - 1)));
+  }
+
+  #[test]
+  fn full_form_reads_back() {
+    // `f[x, …]` is a `Construct`, so this table reads FullForm.
+    let mut parser = Parser::new(compile_grammar_file("resources/operators.grammar").unwrap());
+
+    for text in ["a + b * c ^ d", "f[\"x\\\"y\", g[]][3.5]", "(a := b) = c - d"] {
+      let tree = parser.parse(text).unwrap();
+      let full_form = tree.to_string();
+      assert!(parser.parse(&full_form).unwrap().same_expression(&tree), "{} reads back differently", full_form);
+    }
+  }
+
+  #[test]
+  fn full_form_atoms_read_back() {
+    let mut parser = Parser::new(compile_grammar_file("resources/operators.grammar").unwrap());
+
+    let numbers = [-3.0, -0.25, -0.0, 1e300, 5e-324, f64::INFINITY, f64::NEG_INFINITY, f64::NAN];
+    let symbols = ["a b", "1x", "-3", "+nan.0", "a`b\\c", "Plus", ""];
+    let atoms =
+      numbers.into_iter().map(ASTNode::number)
+             .chain(symbols.into_iter().map(ASTNode::symbol))
+             .chain([ASTNode::string("\t\\[Alpha] \"`\"")]);
+    for atom in atoms {
+      let read = parser.parse(&atom.to_string()).unwrap();
+      let same =
+        match (atom.evaluator(), read.evaluator()) {
+          // NaN is not equal to itself.
+          (Evaluator::Number(a), Evaluator::Number(b)) if a.is_nan() => b.is_nan(),
+          _ => read.same_expression(&atom)
+        };
+      assert!(same, "{} reads back as {:?}", atom, read);
+    }
+  }
 }
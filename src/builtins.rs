@@ -15,30 +15,62 @@ case that went into keeping them nameless will have been wasted.
 
 */
 
-use std::intrinsics::unchecked_add;
+use std::rc::Rc;
+
+use crate::ast::ASTNode;
 use crate::errors::EvaluationError;
+use crate::evaluator::Evaluator;
 use crate::symbol_table::SymbolTable;
 
 /// Creates entries in the symbol table for each built in function. This function obviously needs to be kept in sync
 /// with whatever built-in functions exist.
 pub fn register_builtins(symbol_table: &mut SymbolTable){
-  symbol_table.register("add", )
+  symbol_table.register("add", Rc::new(ASTNode::builtin(apply_add)));
+  symbol_table.register("subtract", Rc::new(ASTNode::builtin(apply_subtract)));
 }
 
 
 pub fn add(a: f64, b: f64) -> Result<f64, EvaluationError>{
-  match checked_add(a, b) {
-    Some(value) => Ok(value),
-    None => Err(EvaluationError::Overflow)
-  }
+  checked(a + b)
 }
 
 
 pub fn subtract(a: f64, b: f64) -> Result<f64, EvaluationError>{
-  match checked_subtract(a, b) {
-    Some(value) => Ok(value),
-    None => Err(EvaluationError::Overflow)
+  checked(a - b)
+}
+
+/// Arithmetic on finite numbers overflows to an infinity.
+fn checked(value: f64) -> Result<f64, EvaluationError> {
+  match value.is_finite() {
+    true => Ok(value),
+    false => Err(EvaluationError::Overflow)
+  }
+}
+
+fn apply_add(arguments: &[ASTNode]) -> ASTNode {
+  apply_binary("add", arguments, add)
+}
+
+fn apply_subtract(arguments: &[ASTNode]) -> ASTNode {
+  apply_binary("subtract", arguments, subtract)
+}
+
+/// Applies `function` to two numbers. Any other arguments, or a failed computation, leave the expression unevaluated.
+fn apply_binary(name: &str, arguments: &[ASTNode], function: fn(f64, f64) -> Result<f64, EvaluationError>)
+  -> ASTNode
+{
+  let number = |node: &ASTNode| match node.evaluator() {
+    Evaluator::Number(value) if node.is_atom() => Some(*value),
+    _ => None
+  };
+  if let [a, b] = arguments {
+    if let (Some(a), Some(b)) = (number(a), number(b)) {
+      if let Ok(value) = function(a, b) {
+        return ASTNode::number(value);
+      }
+    }
   }
+  ASTNode::function(name, arguments.iter().cloned().map(Rc::new).collect())
 }
 
 
@@ -0,0 +1,435 @@
+/*!
+
+Generates a corpus of random expressions from an operator table, for testing the parser and any
+tool downstream of it.
+
+Each `Sample` is a well-formed expression together with its fully parenthesized form, in which
+every operator application is wrapped in parentheses: `a + b * c` comes with `(a + (b * c))`. The
+expressions need no parentheses of their own. Rather than parenthesize an operand that would
+otherwise be misparsed, the generator only puts an expression where the parser would read it
+back as written, so the corpus works for any table, even one without a grouping operator. It
+decides this the way the `printer` module decides where parentheses are needed, by comparing
+binding powers along the edge of the operand. Adjacent applications of a fully associative
+operator are a single application with more operands, as the parser reads them: `a + b + c` is
+`(a + b + c)`.
+
+The corpus covers the table: `Generator::corpus` begins with an expression for each operator the
+parser can select, so every affix and associativity in the table appears. Operators whose token is
+shadowed by another operator's are left out, as the parser never selects them.
+
+`Generator::near_miss` mutates a sample into an input that is almost, but not quite, well formed,
+by deleting, duplicating, or swapping tokens or cutting the input short. A mutation the parser
+accepts is discarded, so near misses are always errors, for testing error paths.
+
+Everything is determined by the seed, so a corpus can be reproduced from it.
+
+*/
+
+use std::rc::Rc;
+
+use crate::grammar::syntax::Syntax;
+use crate::operator::{Associativity, Operator, OperatorTable};
+use crate::parser::Parser;
+use crate::printer::{absorbs, ends_with_operand, fits, selectable_operators, takes_left_operand, Part, Position, Term};
+
+
+/// A small, fast pseudorandom number generator (SplitMix64). It is not suitable for cryptography,
+/// but the same seed always gives the same sequence on every platform.
+#[derive(Clone, Debug)]
+pub struct Rng {
+  state: u64,
+}
+
+impl Rng {
+  pub fn new(seed: u64) -> Rng {
+    Rng{ state: seed }
+  }
+
+  pub fn next_u64(&mut self) -> u64 {
+    self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = self.state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+  }
+
+  /// A number in `0..n`. `n` must be positive.
+  pub fn below(&mut self, n: usize) -> usize {
+    (self.next_u64() % n as u64) as usize
+  }
+
+  /// True with probability `1/n`.
+  pub fn one_in(&mut self, n: usize) -> bool {
+    self.below(n) == 0
+  }
+
+  pub fn choose<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+    &items[self.below(items.len())]
+  }
+}
+
+
+/// A generated expression and its fully parenthesized form.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Sample {
+  pub input        : String,
+  pub parenthesized: String,
+}
+
+
+
+/// Generates random expressions from an operator table.
+pub struct Generator {
+  operators: Vec<Rc<Operator>>,
+  atoms    : Vec<String>,
+  parser   : Parser,
+  rng      : Rng,
+  max_depth: usize,
+}
+
+impl Generator {
+  /// A generator of expressions up to depth 4, seeded with `seed`.
+  pub fn new(table: &OperatorTable, seed: u64) -> Generator {
+    let operators = selectable_operators(table);
+
+    // Symbols that are also tokens would not be read as symbols.
+    let tokens: Vec<String> = table.values().flat_map(|o| o.tokens()).collect();
+    let mut atoms: Vec<String> =
+      ["a", "b", "c", "x", "y", "z", "f", "g"]
+        .iter()
+        .map(|s| s.to_string())
+        .filter(|s| !tokens.contains(s))
+        .collect();
+    atoms.extend(["1", "2", "42", "3.5", "\"s\""].iter().map(|s| s.to_string()));
+
+    Generator{
+      operators,
+      atoms,
+      parser: Parser::new(table.clone()),
+      rng: Rng::new(seed),
+      max_depth: 4,
+    }
+  }
+
+  /// Limits the depth of generated expressions, the number of nested operator applications.
+  pub fn with_max_depth(self, max_depth: usize) -> Generator {
+    Generator{ max_depth, ..self }
+  }
+
+  /// The names of the operators the generator uses.
+  pub fn operator_names(&self) -> Vec<&str> {
+    self.operators.iter().map(|o| o.name.as_str()).collect()
+  }
+
+  /// A random expression.
+  pub fn sample(&mut self) -> Sample {
+    let term = self.term(self.max_depth);
+    sample(&term)
+  }
+
+  /// A random expression that applies `operator`, with random operands.
+  pub fn sample_of(&mut self, operator: &str) -> Option<Sample> {
+    let operator = self.operators.iter().find(|o| o.name == operator)?.clone();
+    let depth = self.max_depth.max(1);
+    Some(sample(&self.apply(&operator, depth)))
+  }
+
+  /// `count` expressions, beginning with one for each operator.
+  pub fn corpus(&mut self, count: usize) -> Vec<Sample> {
+    let names: Vec<String> = self.operator_names().iter().map(|s| s.to_string()).collect();
+    let mut samples: Vec<Sample> =
+      names.iter().filter_map(|name| self.sample_of(name)).take(count).collect();
+    while samples.len() < count {
+      samples.push(self.sample());
+    }
+    samples
+  }
+
+  /// A near-miss mutation of `sample` that the parser rejects, if one is found.
+  pub fn near_miss(&mut self, sample: &Sample) -> Option<String> {
+    let tokens = match self.parser.parse(&sample.input) {
+      Ok(_) => self.lex(&sample.input),
+      Err(_) => return None,
+    };
+    let operator_tokens: Vec<String> = self.operators.iter().flat_map(|o| o.tokens()).collect();
+
+    for _ in 0..20 {
+      let mut mutated = tokens.clone();
+      match self.rng.below(5) {
+        // Delete a token.
+        0 if !mutated.is_empty() => {
+          mutated.remove(self.rng.below(tokens.len()));
+        }
+        // Duplicate a token.
+        1 if !mutated.is_empty() => {
+          let i = self.rng.below(tokens.len());
+          mutated.insert(i, tokens[i].clone());
+        }
+        // Swap two adjacent tokens.
+        2 if mutated.len() > 1 => {
+          let i = self.rng.below(tokens.len() - 1);
+          mutated.swap(i, i + 1);
+        }
+        // Cut the input short.
+        3 if !mutated.is_empty() => {
+          mutated.truncate(self.rng.below(tokens.len()));
+        }
+        // Insert an operator token.
+        _ if !operator_tokens.is_empty() => {
+          let token = self.rng.choose(&operator_tokens).clone();
+          mutated.insert(self.rng.below(tokens.len() + 1), token);
+        }
+        _ => continue
+      }
+
+      let input = mutated.join(" ");
+      if self.parser.parse(&input).is_err() {
+        return Some(input);
+      }
+    }
+    None
+  }
+
+  /// The text of the tokens of `input`.
+  fn lex(&self, input: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    // The generator separates tokens with spaces, except where a syntax forbids it, so the tokens
+    // of a sample are its words, keeping words joined by `NoSpace` together.
+    for c in input.chars() {
+      match c {
+        '"' => { quoted = !quoted; current.push(c); }
+        ' ' if !quoted => {
+          if !current.is_empty() {
+            tokens.push(std::mem::take(&mut current));
+          }
+        }
+        c => current.push(c)
+      }
+    }
+    if !current.is_empty() {
+      tokens.push(current);
+    }
+    tokens
+  }
+
+  fn atom(&mut self) -> Term {
+    Term::Atom(self.rng.choose(&self.atoms).clone())
+  }
+
+  fn symbol(&mut self) -> Term {
+    let symbols: Vec<&String> =
+      self.atoms.iter().filter(|a| a.starts_with(|c: char| c.is_alphabetic())).collect();
+    match symbols.is_empty() {
+      true => Term::Atom("$s".to_string()),
+      false => Term::Atom(symbols[self.rng.below(symbols.len())].clone())
+    }
+  }
+
+  /// A random term of at most `depth` nested applications.
+  fn term(&mut self, depth: usize) -> Term {
+    if depth == 0 || self.operators.is_empty() || self.rng.one_in(4) {
+      return self.atom();
+    }
+    let operator = self.rng.choose(&self.operators).clone();
+    self.apply(&operator, depth)
+  }
+
+  /// A random operand of `operator` at `position` that the parser reads back as such.
+  fn operand(&mut self, operator: &Operator, position: Position, depth: usize) -> Term {
+    for _ in 0..8 {
+      let term = self.term(depth);
+      if fits(operator, &term, position) {
+        return term;
+      }
+    }
+    self.atom()
+  }
+
+  /// An application of `operator` with random operands of at most `depth - 1` applications.
+  fn apply(&mut self, operator: &Rc<Operator>, depth: usize) -> Term {
+    let elements = operator.syntax.elements();
+    let last = elements.iter().rposition(|e| e.consumes_input());
+    let mut parts = Vec::new();
+
+    for (i, element) in elements.iter().enumerate() {
+      let position =
+        match i {
+          0 if takes_left_operand(operator) => Position::Left,
+          _ if Some(i) == last && ends_with_operand(&operator.syntax) => Position::Trailing,
+          _ => Position::Closed
+        };
+      self.element(operator, element, position, depth, &mut parts);
+    }
+
+    // A fully associative operator may take more operands, repeating its last token and operand.
+    if operator.associativity == Associativity::Full && takes_left_operand(operator) {
+      let repeated: Vec<Part> = parts[1..].to_vec();
+      while self.rng.one_in(3) {
+        // The operand before the repeated token must not take it in.
+        if let Some(Part::Operand(last)) = parts.last() {
+          if absorbs(last, operator) {
+            break;
+          }
+        }
+        let mut more = repeated.clone();
+        if let Some(Part::Operand(last)) = more.last_mut() {
+          *last = self.operand(operator, Position::Trailing, depth.saturating_sub(1));
+        }
+        parts.extend(more);
+      }
+    }
+
+    Term::Apply{ operator: operator.clone(), parts }
+  }
+
+  fn element(&mut self, operator: &Rc<Operator>, element: &Syntax, position: Position, depth: usize,
+             parts: &mut Vec<Part>)
+  {
+    match element {
+
+      | Syntax::Expr1
+      | Syntax::Expr2
+      | Syntax::Expr3
+      | Syntax::Expr4 => {
+        let term = self.operand(operator, position, depth.saturating_sub(1));
+        parts.push(Part::Operand(term));
+      }
+
+      Syntax::Symbol => {
+        let term = self.symbol();
+        parts.push(Part::Operand(term));
+      }
+
+      | Syntax::N
+      | Syntax::NegN => parts.push(Part::Operand(Term::Atom(self.rng.below(10).to_string()))),
+
+      Syntax::Number(n) => parts.push(Part::Token(n.to_string())),
+
+      Syntax::NoSpace => parts.push(Part::NoSpace),
+
+      | Syntax::Space
+      | Syntax::Newline
+      | Syntax::Empty => {}
+
+      // A trailing optional operand is always present, so that what follows cannot be taken for
+      // it.
+      Syntax::Optional(inner) => {
+        if position == Position::Trailing || !self.rng.one_in(2) {
+          self.element(operator, inner, position, depth, parts);
+        }
+      }
+
+      | Syntax::OnePlus(inner)
+      | Syntax::ZeroPlus(inner)
+      | Syntax::OnePlusDelim(inner, _)
+      | Syntax::ZeroPlusDelim(inner, _) => {
+        let minimum = match element { Syntax::OnePlus(_) | Syntax::OnePlusDelim(..) => 1, _ => 0 };
+        let count = minimum + self.rng.below(3);
+        for i in 0..count {
+          if let (true, Syntax::OnePlusDelim(_, delimiter) | Syntax::ZeroPlusDelim(_, delimiter)) = (i > 0, element) {
+            self.element(operator, delimiter, Position::Closed, depth, parts);
+          }
+          self.element(operator, inner, Position::Closed, depth, parts);
+        }
+      }
+
+      | Syntax::Sequence(list)
+      | Syntax::SequenceDelim(list) => {
+        for item in list {
+          self.element(operator, item, Position::Closed, depth, parts);
+        }
+      }
+
+      Syntax::Alternative(list) if !list.is_empty() => {
+        let choice = self.rng.choose(list).clone();
+        self.element(operator, &choice, position, depth, parts);
+      }
+
+      other => {
+        if let Some(text) = other.token_text() {
+          parts.push(Part::Token(text));
+        }
+      }
+
+    }
+  }
+}
+
+fn sample(term: &Term) -> Sample {
+  let mut input = String::new();
+  let mut parenthesized = String::new();
+  term.write(&mut input, false);
+  term.write(&mut parenthesized, true);
+  Sample{ input, parenthesized }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::grammar::compile_grammar_file;
+
+  #[test]
+  fn samples_parse_as_parenthesized() {
+    // The grammar's parentheses are transparent, so both forms parse to the same tree.
+    let table = compile_grammar_file("resources/operators.grammar").unwrap();
+    let mut parser = Parser::new(table.clone());
+    let mut generator = Generator::new(&table, 7);
+
+    for sample in generator.corpus(300) {
+      let tree = parser.parse(&sample.input)
+                       .unwrap_or_else(|e| panic!("{}: {}", sample.input, e));
+      let expected = parser.parse(&sample.parenthesized).unwrap();
+      assert!(tree.same_expression(&expected), "{} is not {}", sample.input, sample.parenthesized);
+    }
+  }
+
+  #[test]
+  fn corpus_covers_the_table() {
+    let table = compile_grammar_file("resources/operators.grammar").unwrap();
+    let mut generator = Generator::new(&table, 1);
+    let names: Vec<String> = generator.operator_names().iter().map(|s| s.to_string()).collect();
+    let corpus = generator.corpus(names.len());
+
+    assert_eq!(names.len(), table.len());
+    assert!(corpus[names.iter().position(|n| n == "Construct").unwrap()].input.contains('['));
+    assert!(corpus.iter().all(|s| s.parenthesized.starts_with('(')));
+  }
+
+  #[test]
+  fn seeds_are_reproducible() {
+    let table = compile_grammar_file("resources/operators.grammar").unwrap();
+    let first = Generator::new(&table, 99).corpus(50);
+
+    assert_eq!(first, Generator::new(&table, 99).corpus(50));
+    assert_ne!(first, Generator::new(&table, 100).corpus(50));
+  }
+
+  #[test]
+  fn full_associativity_and_depth() {
+    let table = compile_grammar_file("resources/operators.grammar").unwrap();
+    let mut generator = Generator::new(&table, 3).with_max_depth(1);
+    let samples: Vec<Sample> = (0..200).filter_map(|_| generator.sample_of("Plus")).collect();
+
+    assert!(samples.iter().any(|s| s.input.matches('+').count() > 1));
+    assert!(samples.iter().all(|s| s.parenthesized.matches('(').count() == 1));
+  }
+
+  #[test]
+  fn near_misses_are_rejected() {
+    let table = compile_grammar_file("resources/operators.grammar").unwrap();
+    let mut parser = Parser::new(table.clone());
+    let mut generator = Generator::new(&table, 11);
+
+    let mut mutated = 0;
+    for sample in generator.corpus(100) {
+      if let Some(input) = generator.near_miss(&sample) {
+        assert!(parser.parse(&input).is_err(), "{} parses", input);
+        mutated += 1;
+      }
+    }
+    assert!(mutated > 50);
+  }
+}
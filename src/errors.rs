@@ -6,6 +6,8 @@ Error types.
 
 use std::fmt::{Display, Formatter};
 
+use crate::lexer::Span;
+
 /// These are just examples of what you might have.
 pub enum EvaluationError {
   DivisionByZero,
@@ -29,6 +31,89 @@ impl Display for EvaluationError {
 }
 
 
+/// Errors encountered while lexing or parsing an expression.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum ParseError {
+  /// The lexer found text that is not the start of any token.
+  UnknownToken{ text: String, span: Span },
+  UnterminatedString{ span: Span },
+  /// A named character, e.g. `\[Alpha]`, whose name is not in the table of named characters.
+  UnknownNamedCharacter{ name: String, span: Span },
+  /// The parser found a token other than the one the grammar requires.
+  UnexpectedToken{ expected: String, found: String, span: Span },
+  /// The input ended where the grammar requires more.
+  UnexpectedEnd{ expected: String, span: Span },
+  /// Whitespace precedes a token that an operator requires to follow the previous token directly.
+  UnexpectedSpace{ before: String, span: Span },
+  /// No whitespace precedes a token that an operator requires to be separated by whitespace.
+  MissingSpace{ before: String, span: Span },
+  /// An operator's parse template could not be instantiated with the operands it matched.
+  Template{ operator: String, message: String, span: Span },
+  /// Expressions are nested more deeply than the parser's `max_depth` allows.
+  TooDeep{ limit: usize, span: Span },
+}
+
+impl ParseError {
+  pub fn span(&self) -> Span {
+    match self {
+      | ParseError::UnknownToken{ span, .. }
+      | ParseError::UnterminatedString{ span }
+      | ParseError::UnknownNamedCharacter{ span, .. }
+      | ParseError::UnexpectedToken{ span, .. }
+      | ParseError::UnexpectedEnd{ span, .. }
+      | ParseError::UnexpectedSpace{ span, .. }
+      | ParseError::MissingSpace{ span, .. }
+      | ParseError::Template{ span, .. }
+      | ParseError::TooDeep{ span, .. } => *span
+    }
+  }
+}
+
+impl Display for ParseError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+
+      ParseError::UnknownToken{ text, span } => {
+        write!(f, "Unknown token {:?} at {}", text, span.start)
+      }
+
+      ParseError::UnterminatedString{ span } => {
+        write!(f, "Unterminated string starting at {}", span.start)
+      }
+
+      ParseError::UnknownNamedCharacter{ name, span } => {
+        write!(f, "Unknown named character \\[{}] at {}", name, span.start)
+      }
+
+      ParseError::UnexpectedToken{ expected, found, span } => {
+        write!(f, "Expected {} but found {:?} at {}", expected, found, span.start)
+      }
+
+      ParseError::UnexpectedEnd{ expected, .. } => {
+        write!(f, "Expected {} but reached the end of the input", expected)
+      }
+
+      ParseError::UnexpectedSpace{ before, span } => {
+        write!(f, "Whitespace is not allowed before {:?} at {}", before, span.start)
+      }
+
+      ParseError::MissingSpace{ before, span } => {
+        write!(f, "Whitespace is required before {:?} at {}", before, span.start)
+      }
+
+      ParseError::Template{ operator, message, span } => {
+        write!(f, "Cannot build {} at {}: {}", operator, span.start, message)
+      }
+
+      ParseError::TooDeep{ limit, span } => {
+        write!(f, "Expressions are nested more than {} deep at {}", limit, span.start)
+      }
+
+    }
+  }
+}
+
+
 
 #[cfg(test)]
 mod tests {
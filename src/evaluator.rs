@@ -18,44 +18,63 @@ evaluate to themselves. Other evaluators need to be able to access the raw value
 // }
 
 
+use std::rc::Rc;
+
 use crate::ast::{
   ASTNode,
   RcASTNode,
   Children
 };
 
-pub type BuiltInFn = fn(args: &Vec<ASTNode>) -> ASTNode;
+pub type BuiltInFn = fn(args: &[ASTNode]) -> ASTNode;
 
-#[derive(Clone, PartialEq, Eq, Hash)]
+#[derive(Clone, Debug)]
 pub enum Evaluator {
   /// The evaluator might need to look up another piece of code bound to a symbol (a "symbolic expression"). We do
   /// not resolve it until evaluation, as the thing it is bound to can change dynamically.
   Symbol(String),
   Number(f64),
+  String(String),
   BuiltIns(BuiltInFn),
 
   // Other possibilities:
-  // String(String),
   // Integer(i64), // or other numeric types
   // FFI(…) // Foreign function. Can us `BuiltIn` for this purpose, perhaps.
 
 }
 
+// Numbers compare by value, and built-ins compare by the address of their function.
+impl PartialEq for Evaluator {
+  fn eq(&self, other: &Evaluator) -> bool {
+    match (self, other) {
+      (Evaluator::Symbol(a), Evaluator::Symbol(b))     => a == b,
+      (Evaluator::Number(a), Evaluator::Number(b))     => a == b,
+      (Evaluator::String(a), Evaluator::String(b))     => a == b,
+      (Evaluator::BuiltIns(a), Evaluator::BuiltIns(b)) => std::ptr::fn_addr_eq(*a, *b),
+      _ => false
+    }
+  }
+}
+
 impl Evaluator{
-  pub fn evaluate<'a>(&self, children: &Children) -> RcASTNode<'a> {
+  /// Computes the value of an expression with this evaluator from the expression's children, or
+  /// returns `None` if there is nothing to compute and the expression is its own value. Atoms
+  /// evaluate to themselves, and so, until symbols can be bound, do compound expressions with a
+  /// symbol head.
+  pub fn evaluate(&self, children: &Children) -> Option<RcASTNode> {
     match self {
 
-      Evaluator::Symbol(String) => {
-        // Look up the expression referenced by the symbol.
-
-      }
-
-      Evaluator::Number(_) => {
-
+      Evaluator::Symbol(_) => {
+        // ToDo: Look up the expression referenced by the symbol.
+        None
       }
 
-      Evaluator::BuiltIns(_) => {
+      Evaluator::Number(_)
+      | Evaluator::String(_) => None,
 
+      Evaluator::BuiltIns(function) => {
+        let arguments: Vec<ASTNode> = children.iter().map(|child| (**child).clone()).collect();
+        Some(Rc::new(function(&arguments)))
       }
 
     }
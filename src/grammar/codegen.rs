@@ -0,0 +1,1574 @@
+//! This module generates a standalone Rust parser from an operator table. The generated module
+//! is the parser of the `parser` module specialised to one grammar: the command tables become a
+//! `match` on the next token with the binding powers of each operator inlined as constants, and
+//! each operator's syntax becomes a function that matches it element by element. The generated
+//! module depends on nothing but `std`, so it can be dropped into any crate:
+//!
+//! ```text
+//! grammar codegen resources/operators.grammar -o src/operators_parser.rs
+//! ```
+//!
+//! The generated parser reads the same language and builds the same trees as a `Parser` given
+//! the same operator table, except that its expressions are the `Expr` enum it defines rather than
+//! `ASTNode`s. Because operators are fixed when the module is generated, it has no equivalent of
+//! `Parser::add_operator`. An element that only makes sense in a template, like `n`, is reported
+//! when the module is generated rather than when an expression is parsed.
+
+use std::collections::{BTreeMap, HashSet};
+
+use crate::grammar::format::format_syntax;
+use crate::grammar::syntax::Syntax;
+use crate::named_characters::NamedCharacters;
+use crate::operator::{Associativity, Operator, OperatorTable};
+use crate::parser::{left_elements, normalize_operator, DEFAULT_MAX_DEPTH};
+
+/// The part of the generated module that is the same for every grammar: expressions, errors, the
+/// lexer, templates, and the parts of the parser that do not depend on the operators.
+const PRELUDE: &str = r##"use std::fmt;
+
+/// An expression: an atom or a compound expression `head[arguments]`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    Symbol(String),
+    Number(f64),
+    String(String),
+    Apply(Box<Expr>, Vec<Expr>),
+}
+
+impl Expr {
+    /// The compound expression `name[arguments]`.
+    pub fn function(name: &str, arguments: Vec<Expr>) -> Expr {
+        Expr::Apply(Box::new(Expr::Symbol(name.to_string())), arguments)
+    }
+}
+
+/// Prints the expression in FullForm, e.g. `Plus[a, Times[2, b]]`, which `parse` reads back. An
+/// atom that plain text cannot spell is written between backquotes, e.g. `` `a b` `` or `` `-3` ``.
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Symbol(name) => write!(f, "{}", quote_symbol(name)),
+            // Zero and negative zero are the same number.
+            Expr::Number(value) if *value == 0.0 => write!(f, "0"),
+            Expr::Number(value) if value.is_nan() => write!(f, "`+nan.0`"),
+            Expr::Number(value) if value.is_infinite() => {
+                write!(f, "{}", if *value > 0.0 { "`+inf.0`" } else { "`-inf.0`" })
+            }
+            Expr::Number(value) if *value < 0.0 => write!(f, "`{}`", value),
+            Expr::Number(value) => write!(f, "{}", value),
+            Expr::String(text) => write!(f, "{}", quote(text)),
+            Expr::Apply(head, arguments) => {
+                write!(f, "{}[", head)?;
+                for (i, argument) in arguments.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", argument)?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+/// `text` as a string literal the lexer reads back as `text`.
+fn quote(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// `name` as a symbol the lexer reads back as `name`: as it is if it is an identifier, and
+/// otherwise between backquotes.
+fn quote_symbol(name: &str) -> String {
+    let mut chars = name.chars();
+    if chars.next().is_some_and(is_symbol_start) && chars.all(is_symbol_char) {
+        return name.to_string();
+    }
+    let mut quoted = String::from("`");
+    for (i, c) in name.chars().enumerate() {
+        // A name that is a number as written would read back as the number.
+        if c == '`' || c == '\\' || (i == 0 && quoted_number(name).is_some()) {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('`');
+    quoted
+}
+
+/// A range of byte offsets into the source text.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Span {
+        Span { start, end }
+    }
+}
+
+/// Errors encountered while lexing or parsing an expression.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum ParseError {
+    /// The lexer found text that is not the start of any token.
+    UnknownToken { text: String, span: Span },
+    UnterminatedString { span: Span },
+    /// A named character, e.g. `\[Alpha]`, whose name is not in the table of named characters.
+    UnknownNamedCharacter { name: String, span: Span },
+    /// The parser found a token other than the one the grammar requires.
+    UnexpectedToken { expected: String, found: String, span: Span },
+    /// The input ended where the grammar requires more.
+    UnexpectedEnd { expected: String, span: Span },
+    /// Whitespace precedes a token that an operator requires to follow the previous token directly.
+    UnexpectedSpace { before: String, span: Span },
+    /// No whitespace precedes a token that an operator requires to be separated by whitespace.
+    MissingSpace { before: String, span: Span },
+    /// An operator's template could not be instantiated with the operands it matched.
+    Template { operator: String, message: String, span: Span },
+    /// Expressions are nested more than `MAX_DEPTH` deep.
+    TooDeep { limit: usize, span: Span },
+}
+
+impl ParseError {
+    pub fn span(&self) -> Span {
+        match self {
+            ParseError::UnknownToken { span, .. }
+            | ParseError::UnterminatedString { span }
+            | ParseError::UnknownNamedCharacter { span, .. }
+            | ParseError::UnexpectedToken { span, .. }
+            | ParseError::UnexpectedEnd { span, .. }
+            | ParseError::UnexpectedSpace { span, .. }
+            | ParseError::MissingSpace { span, .. }
+            | ParseError::Template { span, .. }
+            | ParseError::TooDeep { span, .. } => *span,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::UnknownToken { text, span } => {
+                write!(f, "Unknown token {:?} at {}", text, span.start)
+            }
+            ParseError::UnterminatedString { span } => {
+                write!(f, "Unterminated string starting at {}", span.start)
+            }
+            ParseError::UnknownNamedCharacter { name, span } => {
+                write!(f, "Unknown named character \\[{}] at {}", name, span.start)
+            }
+            ParseError::UnexpectedToken { expected, found, span } => {
+                write!(f, "Expected {} but found {:?} at {}", expected, found, span.start)
+            }
+            ParseError::UnexpectedEnd { expected, .. } => {
+                write!(f, "Expected {} but reached the end of the input", expected)
+            }
+            ParseError::UnexpectedSpace { before, span } => {
+                write!(f, "Whitespace is not allowed before {:?} at {}", before, span.start)
+            }
+            ParseError::MissingSpace { before, span } => {
+                write!(f, "Whitespace is required before {:?} at {}", before, span.start)
+            }
+            ParseError::Template { operator, message, span } => {
+                write!(f, "Cannot build {} at {}: {}", operator, span.start, message)
+            }
+            ParseError::TooDeep { limit, span } => {
+                write!(f, "Expressions are nested more than {} deep at {}", limit, span.start)
+            }
+        }
+    }
+}
+
+/// Which of an operator's templates the parser instantiates. An operator without a `fullform`
+/// template uses its `parse` template for both.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum Form {
+    Parse,
+    FullForm,
+}
+
+/// Parses `text`, building each operator's `parse` template.
+pub fn parse(text: &str) -> Result<Expr, ParseError> {
+    parse_as(text, Form::Parse)
+}
+
+/// Parses `text`, building each operator's `fullform` template.
+pub fn parse_fullform(text: &str) -> Result<Expr, ParseError> {
+    parse_as(text, Form::FullForm)
+}
+
+pub fn parse_as(text: &str, form: Form) -> Result<Expr, ParseError> {
+    let mut parser = Parser {
+        tokens: tokenize(text)?,
+        cursor: 0,
+        last_end: 0,
+        form,
+        depth: 0,
+        lefts: 0,
+    };
+
+    let tree = parser.expr(0)?;
+
+    let token = parser.peek();
+    match token.kind {
+        TokenKind::EOF => Ok(tree),
+        _ => Err(unexpected("an operator or the end of the input", token)),
+    }
+}
+
+// The lexer
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum TokenKind {
+    Symbol,
+    Number,
+    String,
+    Operator,
+    EOF,
+}
+
+#[derive(Clone, Debug)]
+struct Token {
+    kind: TokenKind,
+    text: String,
+    span: Span,
+    space_before: bool,
+}
+
+/// Lexes the entire text. The last token is always an `EOF` token.
+fn tokenize(text: &str) -> Result<Vec<Token>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut cursor = 0;
+
+    loop {
+        let rest = &text[cursor..];
+        cursor += rest.len() - rest.trim_start().len();
+        let start = cursor;
+
+        check_named_char(text, cursor)?;
+        let (kind, contents) = match char_at(text, cursor) {
+            None => (TokenKind::EOF, String::new()),
+            Some((c, _)) if is_symbol_start(c) => {
+                let word = take_named_while(text, &mut cursor, is_symbol_char);
+                // A sigil may be a word, e.g. `and`.
+                match SIGILS.contains(&word.as_str()) {
+                    true => (TokenKind::Operator, word),
+                    false => (TokenKind::Symbol, word),
+                }
+            }
+            Some((c, _)) if c.is_ascii_digit() => (TokenKind::Number, number(text, &mut cursor)),
+            Some(('"', _)) => (TokenKind::String, string(text, &mut cursor)?),
+            Some(('`', _)) => quoted_atom(text, &mut cursor)?,
+            Some((c, length)) => match match_sigil(text, cursor) {
+                Some((sigil, sigil_length)) => {
+                    cursor += sigil_length;
+                    (TokenKind::Operator, sigil.to_string())
+                }
+                None => {
+                    return Err(ParseError::UnknownToken {
+                        text: c.to_string(),
+                        span: Span::new(start, start + length),
+                    });
+                }
+            },
+        };
+
+        let done = kind == TokenKind::EOF;
+        tokens.push(Token {
+            kind,
+            text: contents,
+            span: Span::new(start, cursor),
+            space_before: text[..start].ends_with(char::is_whitespace),
+        });
+        if done {
+            return Ok(tokens);
+        }
+    }
+}
+
+/// Reads a named character at the start of `text`, returning the name and the length of the
+/// escape in bytes.
+fn read_escape(text: &str) -> Option<(&str, usize)> {
+    let rest = text.strip_prefix("\\[")?;
+    let length = rest.find(|c: char| !c.is_ascii_alphanumeric()).unwrap_or(rest.len());
+    if length == 0 || !rest[length..].starts_with(']') {
+        return None;
+    }
+    Some((&rest[..length], length + 3))
+}
+
+/// The character at byte offset `position` and its length in the text. A named character is a
+/// single character whose length is the length of its escape.
+fn char_at(text: &str, position: usize) -> Option<(char, usize)> {
+    let rest = &text[position..];
+    if let Some((name, length)) = read_escape(rest) {
+        if let Some(c) = named_character(name) {
+            return Some((c, length));
+        }
+    }
+    rest.chars().next().map(|c| (c, c.len_utf8()))
+}
+
+/// Reports a named character at `position` whose name is not known.
+fn check_named_char(text: &str, position: usize) -> Result<(), ParseError> {
+    match read_escape(&text[position..]) {
+        Some((name, length)) if named_character(name).is_none() => {
+            Err(ParseError::UnknownNamedCharacter {
+                name: name.to_string(),
+                span: Span::new(position, position + length),
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+fn take_named_while<P: Fn(char) -> bool>(text: &str, cursor: &mut usize, predicate: P) -> String {
+    let mut taken = String::new();
+    while let Some((c, length)) = char_at(text, *cursor) {
+        if !predicate(c) {
+            break;
+        }
+        taken.push(c);
+        *cursor += length;
+    }
+    taken
+}
+
+/// The longest sigil at `position` and its length in the text.
+fn match_sigil(text: &str, position: usize) -> Option<(&'static str, usize)> {
+    SIGILS.iter().find_map(|sigil| {
+        let mut length = 0;
+        for expected in sigil.chars() {
+            match char_at(text, position + length) {
+                Some((c, char_length)) if c == expected => length += char_length,
+                _ => return None,
+            }
+        }
+        Some((*sigil, length))
+    })
+}
+
+/// Lexes `digits` or `digits.digits`.
+fn number(text: &str, cursor: &mut usize) -> String {
+    let start = *cursor;
+    let digits = |text: &str, position: usize| {
+        text[position..].find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len() - position)
+    };
+
+    *cursor += digits(text, *cursor);
+    let rest = &text[*cursor..];
+    if rest.starts_with('.') && rest[1..].starts_with(|c: char| c.is_ascii_digit()) {
+        *cursor += 1;
+        *cursor += digits(text, *cursor);
+    }
+    text[start..*cursor].to_string()
+}
+
+/// Lexes a number or symbol between backquotes with backslash escapes: a number if the text is one
+/// as written, e.g. `` `-3` `` or `` `+inf.0` ``, and otherwise a symbol, e.g. `` `a b` ``.
+fn quoted_atom(text: &str, cursor: &mut usize) -> Result<(TokenKind, String), ParseError> {
+    let start = *cursor;
+    *cursor += 1; // The opening backquote
+    let mut contents = String::new();
+    let mut escaped = false;
+
+    while let Some(c) = text[*cursor..].chars().next() {
+        *cursor += c.len_utf8();
+        match c {
+            '`' => {
+                return Ok(match quoted_number(&contents).filter(|_| !escaped) {
+                    Some(number) => (TokenKind::Number, number.to_string()),
+                    None => (TokenKind::Symbol, contents),
+                });
+            }
+            '\\' => {
+                escaped = true;
+                match text[*cursor..].chars().next() {
+                    Some(c) => {
+                        contents.push(c);
+                        *cursor += c.len_utf8();
+                    }
+                    None => break,
+                }
+            }
+            c => contents.push(c),
+        }
+    }
+    Err(ParseError::UnterminatedString { span: Span::new(start, *cursor) })
+}
+
+/// The number `text` between backquotes is, as text `f64::from_str` reads, if it is one.
+fn quoted_number(text: &str) -> Option<&str> {
+    match text {
+        "+inf.0" => Some("inf"),
+        "-inf.0" => Some("-inf"),
+        "+nan.0" => Some("NaN"),
+        _ => {
+            let unsigned = text.strip_prefix(['-', '+']).unwrap_or(text);
+            let (whole, fraction) = unsigned.split_once('.').unwrap_or((unsigned, "0"));
+            let digits = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+            (digits(whole) && digits(fraction)).then_some(text)
+        }
+    }
+}
+
+/// Lexes a double quoted string with backslash escapes, including named characters, returning
+/// its unescaped contents.
+fn string(text: &str, cursor: &mut usize) -> Result<String, ParseError> {
+    let start = *cursor;
+    *cursor += 1; // The opening quote
+    let mut contents = String::new();
+
+    loop {
+        check_named_char(text, *cursor)?;
+        match char_at(text, *cursor) {
+            Some(('"', _)) => {
+                *cursor += 1;
+                return Ok(contents);
+            }
+            Some(('\\', 1)) => {
+                *cursor += 1;
+                match text[*cursor..].chars().next() {
+                    Some(c) => {
+                        contents.push(match c {
+                            'n' => '\n',
+                            't' => '\t',
+                            c => c,
+                        });
+                        *cursor += c.len_utf8();
+                    }
+                    None => break,
+                }
+            }
+            Some((c, length)) => {
+                contents.push(c);
+                *cursor += length;
+            }
+            None => break,
+        }
+    }
+
+    *cursor = text.len();
+    Err(ParseError::UnterminatedString { span: Span::new(start, text.len()) })
+}
+
+fn is_symbol_start(c: char) -> bool {
+    c.is_alphabetic() || c == '$'
+}
+
+fn is_symbol_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '$'
+}
+
+/// Is `text` two or more repetitions of `token`?
+fn is_run_of(text: &str, token: &str) -> bool {
+    !token.is_empty() && text.len() > token.len() && text.trim_start_matches(token).is_empty()
+}
+
+// Templates
+
+/// The metavariables that bind operands.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum Meta {
+    Expr1,
+    Expr2,
+    Expr3,
+    Expr4,
+    Symbol,
+}
+
+/// A parse or fullform template. `Default` is the empty template, `name[operands]`. An optional
+/// part and a repetition group carry the metavariables they contain.
+enum Template {
+    Default,
+    Meta(Meta),
+    N,
+    NegN,
+    Number(i32),
+    Name,
+    Word(&'static str),
+    Literal(&'static str),
+    Cons(&'static Template, &'static [Template]),
+    Seq(&'static [Template]),
+    Alt(&'static [Template]),
+    Opt(&'static Template, &'static [Meta]),
+    Rep(&'static Template, &'static [Meta]),
+}
+
+enum Capture {
+    /// An operand matched by a metavariable.
+    Value(Meta, Expr),
+    /// The iterations of a repetition group.
+    Group(Vec<Bindings>),
+}
+
+/// The operands matched by an operator's syntax, in the order they were matched.
+#[derive(Default)]
+struct Bindings {
+    captures: Vec<Capture>,
+}
+
+impl Bindings {
+    fn bind(&mut self, metavariable: Meta, node: Expr) {
+        self.captures.push(Capture::Value(metavariable, node));
+    }
+
+    fn push_group(&mut self, iterations: Vec<Bindings>) {
+        self.captures.push(Capture::Group(iterations));
+    }
+
+    /// The operands bound directly to `metavariable`, not counting those bound within groups.
+    fn values_of(&self, metavariable: Meta) -> Vec<Expr> {
+        self.captures
+            .iter()
+            .filter_map(|c| match c {
+                Capture::Value(m, node) if *m == metavariable => Some(node.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The operands bound to `metavariable`, including those bound within groups.
+    fn all_values_of(&self, metavariable: Meta) -> Vec<Expr> {
+        let mut values = Vec::new();
+        for capture in &self.captures {
+            match capture {
+                Capture::Value(m, node) => {
+                    if *m == metavariable {
+                        values.push(node.clone());
+                    }
+                }
+                Capture::Group(iterations) => {
+                    for iteration in iterations {
+                        values.extend(iteration.all_values_of(metavariable));
+                    }
+                }
+            }
+        }
+        values
+    }
+
+    fn groups(&self) -> impl Iterator<Item = &Vec<Bindings>> {
+        self.captures.iter().filter_map(|c| match c {
+            Capture::Group(iterations) => Some(iterations),
+            _ => None,
+        })
+    }
+
+    /// Every matched operand in the order it was matched.
+    fn operands(&self) -> Vec<Expr> {
+        let mut operands = Vec::new();
+        for capture in &self.captures {
+            match capture {
+                Capture::Value(_, node) => operands.push(node.clone()),
+                Capture::Group(iterations) => {
+                    for iteration in iterations {
+                        operands.extend(iteration.operands());
+                    }
+                }
+            }
+        }
+        operands
+    }
+
+    /// The number of repetitions of the first repetition group, the value of `n`.
+    fn count(&self) -> Option<usize> {
+        self.groups().next().map(|iterations| iterations.len())
+    }
+}
+
+/// Instantiates `template` for the operator named `name`. The template must produce exactly one
+/// expression.
+fn instantiate(template: &Template, name: &str, bindings: &Bindings) -> Result<Expr, String> {
+    if let Template::Default = template {
+        return Ok(Expr::function(name, bindings.operands()));
+    }
+
+    let mut nodes = expand(template, name, &[bindings])?;
+    if nodes.len() != 1 {
+        return Err(format!("the template produced {} expressions instead of one", nodes.len()));
+    }
+    Ok(nodes.pop().unwrap())
+}
+
+/// Looks up a metavariable, innermost scope first. A metavariable bound only within repetition
+/// groups stands for all of its bindings.
+fn lookup(metavariable: Meta, scopes: &[&Bindings]) -> Vec<Expr> {
+    for scope in scopes.iter().rev() {
+        let values = scope.values_of(metavariable);
+        if !values.is_empty() {
+            return values;
+        }
+    }
+    match scopes.last() {
+        Some(scope) => scope.all_values_of(metavariable),
+        None => Vec::new(),
+    }
+}
+
+fn count(scopes: &[&Bindings]) -> usize {
+    scopes.iter().rev().find_map(|scope| scope.count()).unwrap_or(0)
+}
+
+/// Finds the repetition group that bound any of the metavariables of a template group.
+fn find_group<'b>(operands: &[Meta], scopes: &[&'b Bindings]) -> Option<&'b Vec<Bindings>> {
+    for scope in scopes.iter().rev() {
+        for iterations in scope.groups() {
+            let binds_operand = operands.is_empty()
+                || iterations.iter().any(|iteration| {
+                    operands.iter().any(|m| !iteration.all_values_of(*m).is_empty())
+                });
+            if binds_operand {
+                return Some(iterations);
+            }
+        }
+    }
+    None
+}
+
+/// Instantiates a part of a template, producing the list of expressions it stands for.
+fn expand(template: &Template, name: &str, scopes: &[&Bindings]) -> Result<Vec<Expr>, String> {
+    let nodes = match template {
+        Template::Default => Vec::new(),
+        Template::Meta(metavariable) => lookup(*metavariable, scopes),
+        Template::N => vec![Expr::Number(count(scopes) as f64)],
+        Template::NegN => vec![Expr::Number(-(count(scopes) as f64))],
+        Template::Number(value) => vec![Expr::Number(*value as f64)],
+        Template::Name => vec![Expr::Symbol(name.to_string())],
+        Template::Word(word) => vec![Expr::Symbol(word.to_string())],
+        Template::Literal(text) => vec![Expr::String(text.to_string())],
+        Template::Cons(head, arguments) => {
+            let mut heads = expand(head, name, scopes)?;
+            if heads.len() != 1 {
+                return Err(format!("the head produced {} expressions instead of one", heads.len()));
+            }
+            let mut children = Vec::new();
+            for argument in arguments.iter() {
+                children.extend(expand(argument, name, scopes)?);
+            }
+            vec![Expr::Apply(Box::new(heads.pop().unwrap()), children)]
+        }
+        Template::Seq(list) => {
+            let mut nodes = Vec::new();
+            for item in list.iter() {
+                nodes.extend(expand(item, name, scopes)?);
+            }
+            nodes
+        }
+        Template::Alt(list) => {
+            let mut nodes = Vec::new();
+            for item in list.iter() {
+                nodes = expand(item, name, scopes)?;
+                if !nodes.is_empty() {
+                    break;
+                }
+            }
+            nodes
+        }
+        Template::Opt(inner, operands) => {
+            let bound = operands.iter().all(|m| !lookup(*m, scopes).is_empty());
+            if bound {
+                expand(inner, name, scopes)?
+            } else {
+                Vec::new()
+            }
+        }
+        Template::Rep(inner, operands) => match find_group(operands, scopes) {
+            Some(iterations) => {
+                let mut nodes = Vec::new();
+                for iteration in iterations {
+                    let mut inner_scopes = scopes.to_vec();
+                    inner_scopes.push(iteration);
+                    nodes.extend(expand(inner, name, &inner_scopes)?);
+                }
+                nodes
+            }
+            None => expand(inner, name, scopes)?,
+        },
+    };
+    Ok(nodes)
+}
+
+// The parser
+
+struct Parser {
+    tokens: Vec<Token>,
+    cursor: usize,
+    // The end of the last consumed token.
+    last_end: usize,
+    form: Form,
+    // The number of calls of `expr` in progress.
+    depth: usize,
+    // The number of left operands being built by the calls of `expr` in progress.
+    lefts: usize,
+}
+
+/// Parses the remainder of an operator that takes the given expression as its left operand.
+type LeftDenotation = fn(&mut Parser, Expr) -> Result<Expr, ParseError>;
+
+impl Parser {
+    /// The next token, which is not consumed. The last token is always `EOF`.
+    fn peek(&self) -> &Token {
+        &self.tokens[self.cursor.min(self.tokens.len() - 1)]
+    }
+
+    /// The text of the next token if it is an operator token.
+    fn peek_operator(&self) -> Option<&str> {
+        let token = self.peek();
+        match token.kind {
+            TokenKind::Operator => Some(token.text.as_str()),
+            _ => None,
+        }
+    }
+
+    fn peek_is(&self, text: &str) -> bool {
+        self.peek_operator() == Some(text)
+    }
+
+    /// Fails if another level of nesting, a call of `expr` or a left operand, would exceed
+    /// `MAX_DEPTH`.
+    fn check_depth(&self) -> Result<(), ParseError> {
+        match self.depth + self.lefts >= MAX_DEPTH {
+            true => Err(ParseError::TooDeep { limit: MAX_DEPTH, span: self.peek().span }),
+            false => Ok(()),
+        }
+    }
+
+    fn consume(&mut self) -> Token {
+        let token = self.peek().clone();
+        if token.kind != TokenKind::EOF {
+            self.cursor += 1;
+            self.last_end = token.span.end;
+        }
+        token
+    }
+
+    fn expect(&mut self, text: &str) -> Result<Token, ParseError> {
+        if self.peek_is(text) {
+            Ok(self.consume())
+        } else {
+            Err(unexpected(&format!("{:?}", text), self.peek()))
+        }
+    }
+
+    /// Can the next token begin an expression?
+    fn can_start_expr(&self) -> bool {
+        let token = self.peek();
+        match token.kind {
+            TokenKind::Operator => is_null_token(&token.text),
+            TokenKind::EOF => false,
+            _ => true,
+        }
+    }
+
+    /// Parses a symbol, number, or string.
+    fn atom(&mut self) -> Expr {
+        let token = self.consume();
+        match token.kind {
+            TokenKind::Number => Expr::Number(token.text.parse::<f64>().unwrap_or(f64::NAN)),
+            TokenKind::String => Expr::String(token.text),
+            _ => Expr::Symbol(token.text),
+        }
+    }
+
+    fn symbol(&mut self) -> Result<Expr, ParseError> {
+        if self.peek().kind != TokenKind::Symbol {
+            return Err(unexpected("a symbol", self.peek()));
+        }
+        Ok(self.atom())
+    }
+
+    /// A run of a repeated token, e.g. `%%%` for `("%")+`, is lexed as a longer operator token if
+    /// the run or a part of it is itself a token. If the next token is a run of `text`, splits off
+    /// its first occurrence so that each occurrence counts as a repetition.
+    fn split_repeated(&mut self, text: &str) {
+        let token = self.peek().clone();
+        if token.kind != TokenKind::Operator || !is_run_of(&token.text, text) {
+            return;
+        }
+
+        let split = token.span.start + text.len();
+        let first = Token {
+            kind: TokenKind::Operator,
+            text: text.to_string(),
+            span: Span::new(token.span.start, split),
+            space_before: token.space_before,
+        };
+        let rest = Token {
+            kind: TokenKind::Operator,
+            text: token.text[text.len()..].to_string(),
+            span: Span::new(split, token.span.end),
+            space_before: false,
+        };
+        self.tokens.splice(self.cursor..self.cursor + 1, [first, rest]);
+    }
+
+    /// The error for a next token that breaks a whitespace constraint.
+    fn spacing_error(&self, space_required: bool) -> ParseError {
+        let token = self.peek();
+        let (before, span) = (token.text.clone(), token.span);
+        match space_required {
+            true => ParseError::MissingSpace { before, span },
+            false => ParseError::UnexpectedSpace { before, span },
+        }
+    }
+
+    fn make_node(
+        &self,
+        name: &str,
+        parse: &Template,
+        fullform: Option<&Template>,
+        bindings: &Bindings,
+        start: usize,
+    ) -> Result<Expr, ParseError> {
+        let template = match (self.form, fullform) {
+            (Form::FullForm, Some(fullform)) => fullform,
+            _ => parse,
+        };
+        instantiate(template, name, bindings).map_err(|message| ParseError::Template {
+            operator: name.to_string(),
+            message,
+            span: Span::new(start, self.last_end),
+        })
+    }
+}
+
+fn unexpected(expected: &str, token: &Token) -> ParseError {
+    match token.kind {
+        TokenKind::EOF => ParseError::UnexpectedEnd {
+            expected: expected.to_string(),
+            span: token.span,
+        },
+        _ => ParseError::UnexpectedToken {
+            expected: expected.to_string(),
+            found: token.text.clone(),
+            span: token.span,
+        },
+    }
+}
+"##;
+
+/// Generates the source of a Rust module that parses the grammar described by `table`. `source`
+/// names the grammar in the module's documentation, usually the grammar definition file.
+pub fn generate_parser(table: &OperatorTable, source: &str) -> Result<String, String> {
+    let named_chars = NamedCharacters::builtin();
+
+    // Operators are visited in name order, so for a token shared by two operators the later name
+    // wins, just as in `Parser::build_command_tables`.
+    let mut names: Vec<&String> = table.keys().collect();
+    names.sort();
+    let operators: Vec<Operator> =
+        names.iter().map(|name| normalize_operator(&table[*name], named_chars)).collect();
+
+    let mut left_commands: BTreeMap<&str, usize> = BTreeMap::new();
+    let mut null_commands: BTreeMap<&str, usize> = BTreeMap::new();
+    let mut sigils: Vec<String> = Vec::new();
+    for (index, operator) in operators.iter().enumerate() {
+        if let Some(token) = &operator.l_token {
+            left_commands.insert(token, index);
+        }
+        if let Some(token) = &operator.n_token {
+            null_commands.insert(token, index);
+        }
+        for token in operator.tokens() {
+            if !token.is_empty() && !sigils.contains(&token) {
+                sigils.push(token);
+            }
+        }
+    }
+    // Longest first, so that the first sigil that matches is the longest, as in the `Lexer`.
+    sigils.sort_by(|a, b| b.chars().count().cmp(&a.chars().count()).then(a.cmp(b)));
+
+    let identifiers = identifiers(&operators, &[]);
+    let mut generated: Vec<usize> = left_commands.values().chain(null_commands.values()).copied().collect();
+    generated.sort();
+    generated.dedup();
+
+    let mut out = String::new();
+    out.push_str(&format!(
+        "//! A Pratt parser for the expression grammar in `{}`, generated by `grammar codegen`. Do not\n\
+         //! edit it by hand; regenerate it from the grammar instead.\n\
+         //!\n\
+         //! `parse` reads an expression and builds each operator's `parse` template, and\n\
+         //! `parse_fullform` builds its `fullform` template instead.\n\
+         #![allow(dead_code, unused_mut, unused_variables, unreachable_code, clippy::all)]\n\n",
+        source
+    ));
+    out.push_str(PRELUDE);
+
+    out.push_str("\n// The grammar\n\n");
+    out.push_str(&format!(
+        "/// How deeply expressions may be nested, as in a `Parser` with the default `max_depth`.\n\
+         const MAX_DEPTH: usize = {};\n\n",
+        DEFAULT_MAX_DEPTH
+    ));
+    out.push_str("/// The operator tokens, longest first.\nconst SIGILS: &[&str] = &[\n");
+    for sigil in &sigils {
+        out.push_str(&format!("    {:?},\n", sigil));
+    }
+    out.push_str("];\n\n");
+
+    out.push_str("/// The character named `\\[name]`.\nfn named_character(name: &str) -> Option<char> {\n    match name {\n");
+    for (name, c) in named_chars.iter() {
+        out.push_str(&format!("        {:?} => Some({:?}),\n", name, c));
+    }
+    out.push_str("        _ => None,\n    }\n}\n\n");
+
+    out.push_str("/// Does `text` select an operator without a left operand?\nfn is_null_token(text: &str) -> bool {\n");
+    if null_commands.is_empty() {
+        out.push_str("    false\n}\n\n");
+    } else {
+        let tokens: Vec<String> = null_commands.keys().map(|t| format!("{:?}", t)).collect();
+        let runs: Vec<String> = null_commands.keys().map(|t| format!("is_run_of(text, {:?})", t)).collect();
+        out.push_str(&format!(
+            "    match text {{\n        {} => true,\n        _ => {},\n    }}\n}}\n\n",
+            tokens.join(" | "),
+            runs.join(" || ")
+        ));
+    }
+
+    for &index in &generated {
+        let operator = &operators[index];
+        let constant = identifiers[index].to_ascii_uppercase();
+        out.push_str(&format!(
+            "const PARSE_{}: Template = {};\n",
+            constant,
+            template(&operator.parse, true)
+        ));
+        if operator.fullform != Syntax::Empty {
+            out.push_str(&format!(
+                "const FULLFORM_{}: Template = {};\n",
+                constant,
+                template(&operator.fullform, true)
+            ));
+        }
+    }
+
+    let mut emitter = Emitter::new();
+    emitter.line("");
+    emitter.open("impl Parser {");
+    emitter.expr(&operators, &identifiers, &left_commands);
+    emitter.line("");
+    emitter.null_denotation(&identifiers, &null_commands);
+    for &index in &generated {
+        emitter.line("");
+        emitter.denotation(&operators[index], &identifiers[index])?;
+    }
+    emitter.close("}");
+    out.push_str(&emitter.out);
+
+    Ok(out)
+}
+
+/// A snake case identifier for each operator, unique among the operators and different from the
+/// `reserved` identifiers.
+pub(crate) fn identifiers(operators: &[Operator], reserved: &[&str]) -> Vec<String> {
+    let mut taken: HashSet<String> = reserved.iter().map(|r| r.to_string()).collect();
+    let mut identifiers = Vec::new();
+
+    for operator in operators {
+        let mut identifier = String::new();
+        let mut after_lowercase = false;
+        for c in operator.name.chars() {
+            if c.is_ascii_alphanumeric() {
+                if c.is_ascii_uppercase() && after_lowercase {
+                    identifier.push('_');
+                }
+                identifier.push(c.to_ascii_lowercase());
+                after_lowercase = !c.is_ascii_uppercase();
+            } else {
+                if !identifier.ends_with('_') {
+                    identifier.push('_');
+                }
+                after_lowercase = false;
+            }
+        }
+        if identifier.is_empty() || identifier.starts_with(|c: char| c.is_ascii_digit()) {
+            identifier.insert_str(0, "op_");
+        }
+
+        let mut unique = identifier.clone();
+        let mut suffix = 2;
+        while taken.contains(&unique) {
+            unique = format!("{}_{}", identifier, suffix);
+            suffix += 1;
+        }
+        taken.insert(unique.clone());
+        identifiers.push(unique);
+    }
+
+    identifiers
+}
+
+/// The metavariable a syntax element binds, if it binds one.
+fn metavariable(syntax: &Syntax) -> Option<&'static str> {
+    match syntax {
+        Syntax::Expr1 => Some("Meta::Expr1"),
+        Syntax::Expr2 => Some("Meta::Expr2"),
+        Syntax::Expr3 => Some("Meta::Expr3"),
+        Syntax::Expr4 => Some("Meta::Expr4"),
+        Syntax::Symbol => Some("Meta::Symbol"),
+        Syntax::Optional(inner) => metavariable(inner),
+        _ => None,
+    }
+}
+
+/// The Rust expression for a template. Only the whole template can be the default template.
+fn template(syntax: &Syntax, whole: bool) -> String {
+    let list = |items: &[Syntax]| -> String {
+        items.iter().map(|item| template(item, false)).collect::<Vec<_>>().join(", ")
+    };
+    let metavariables = |inner: &Syntax| -> String {
+        inner.operands().iter().filter_map(metavariable).collect::<Vec<_>>().join(", ")
+    };
+
+    match syntax {
+        Syntax::Empty if whole => "Template::Default".to_string(),
+        Syntax::Expr1
+        | Syntax::Expr2
+        | Syntax::Expr3
+        | Syntax::Expr4
+        | Syntax::Symbol => format!("Template::Meta({})", metavariable(syntax).unwrap()),
+        Syntax::N => "Template::N".to_string(),
+        Syntax::NegN => "Template::NegN".to_string(),
+        Syntax::Number(value) => format!("Template::Number({})", value),
+        Syntax::Word(word) if word == "name" => "Template::Name".to_string(),
+        Syntax::Word(word) => format!("Template::Word({:?})", word),
+        Syntax::Literal(text) => format!("Template::Literal({:?})", text),
+        Syntax::NamedChar(name) => format!("Template::Literal({:?})", format!("\\[{}]", name)),
+        Syntax::Cons(head, arguments) => {
+            format!("Template::Cons(&{}, &[{}])", template(head, false), list(arguments))
+        }
+        Syntax::Sequence(items)
+        | Syntax::SequenceDelim(items) => format!("Template::Seq(&[{}])", list(items)),
+        Syntax::Alternative(items) => format!("Template::Alt(&[{}])", list(items)),
+        Syntax::Optional(inner) => {
+            format!("Template::Opt(&{}, &[{}])", template(inner, false), metavariables(inner))
+        }
+        Syntax::OnePlus(inner)
+        | Syntax::ZeroPlus(inner)
+        | Syntax::OnePlusDelim(inner, _)
+        | Syntax::ZeroPlusDelim(inner, _) => {
+            format!("Template::Rep(&{}, &[{}])", template(inner, false), metavariables(inner))
+        }
+        Syntax::Comma
+        | Syntax::NoSpace
+        | Syntax::Space
+        | Syntax::Newline
+        | Syntax::Empty => "Template::Seq(&[])".to_string(),
+    }
+}
+
+/// The Rust condition that the next token can begin the given part of a syntax.
+fn can_start(syntax: &Syntax) -> String {
+    match syntax {
+        Syntax::Expr1
+        | Syntax::Expr2
+        | Syntax::Expr3
+        | Syntax::Expr4 => "self.can_start_expr()".to_string(),
+        Syntax::Symbol => "self.peek().kind == TokenKind::Symbol".to_string(),
+        Syntax::Optional(inner)
+        | Syntax::OnePlus(inner)
+        | Syntax::ZeroPlus(inner)
+        | Syntax::OnePlusDelim(inner, _)
+        | Syntax::ZeroPlusDelim(inner, _) => can_start(inner),
+        Syntax::Sequence(list)
+        | Syntax::SequenceDelim(list) => {
+            match list.iter().find(|e| e.consumes_input()) {
+                Some(first) => can_start(first),
+                None => "false".to_string(),
+            }
+        }
+        Syntax::Alternative(list) if !list.is_empty() => {
+            format!("({})", list.iter().map(can_start).collect::<Vec<_>>().join(" || "))
+        }
+        other => match other.token_text() {
+            Some(text) => format!("self.peek_is({:?})", text),
+            None => "false".to_string(),
+        },
+    }
+}
+
+/// The Rust condition that the whitespace before the next token satisfies `constraint`.
+fn spacing_allows(constraint: &Syntax) -> &'static str {
+    match constraint {
+        Syntax::Space => "self.peek().space_before",
+        _ => "!self.peek().space_before",
+    }
+}
+
+/// Writes the methods of the generated parser. Each operator's syntax is unrolled into code that
+/// does what the `Parser`'s steps do when they follow it.
+struct Emitter {
+    out: String,
+    indent: usize,
+    // Numbers the variables holding bindings and repetition groups.
+    next_variable: usize,
+}
+
+impl Emitter {
+    fn new() -> Emitter {
+        Emitter { out: String::new(), indent: 0, next_variable: 0 }
+    }
+
+    fn line(&mut self, text: &str) {
+        if !text.is_empty() {
+            self.out.push_str(&"    ".repeat(self.indent));
+            self.out.push_str(text);
+        }
+        self.out.push('\n');
+    }
+
+    /// Writes a line that opens a block.
+    fn open(&mut self, text: &str) {
+        self.line(text);
+        self.indent += 1;
+    }
+
+    /// Writes a line that closes a block.
+    fn close(&mut self, text: &str) {
+        self.indent -= 1;
+        self.line(text);
+    }
+
+    fn variable(&mut self) -> usize {
+        self.next_variable += 1;
+        self.next_variable
+    }
+
+    /// The Pratt loop. The left command table becomes a `match` on the next token that yields the
+    /// binding powers and the left denotation of the operator it selects.
+    fn expr(&mut self, operators: &[Operator], identifiers: &[String], left_commands: &BTreeMap<&str, usize>) {
+        self.open("fn expr(&mut self, p: i32) -> Result<Expr, ParseError> {");
+        self.line("self.check_depth()?;");
+        self.line("self.depth += 1;");
+        self.line("let mut tree = self.null_denotation()?;");
+        self.line("let mut r = i32::MAX;");
+        self.line("let mut lefts = 0;");
+        self.line("");
+        self.open("loop {");
+        self.open("let (lbp, nbp, led): (i32, i32, LeftDenotation) = match self.peek_operator() {");
+        for (token, &index) in left_commands {
+            let operator = &operators[index];
+            // An L token that breaks the operator's whitespace constraint does not select it.
+            let guard = match left_elements(operator).get(1) {
+                Some(constraint) if constraint.is_spacing() => format!(" if {}", spacing_allows(constraint)),
+                _ => String::new(),
+            };
+            self.line(&format!(
+                "Some({:?}){} => ({}, {}, Parser::led_{}),",
+                token, guard, operator.lbp(), operator.nbp(), identifiers[index]
+            ));
+        }
+        self.line("_ => break,");
+        self.close("};");
+        self.open("if p > lbp || lbp > r {");
+        self.line("break;");
+        self.close("}");
+        self.line("// The tree so far becomes a left operand, one level deeper.");
+        self.line("self.check_depth()?;");
+        self.line("self.lefts += 1;");
+        self.line("lefts += 1;");
+        self.line("tree = led(self, tree)?;");
+        self.line("r = nbp;");
+        self.close("}");
+        self.line("");
+        self.line("self.depth -= 1;");
+        self.line("self.lefts -= lefts;");
+        self.line("Ok(tree)");
+        self.close("}");
+    }
+
+    /// Parses an atom or an operator that begins with an N token. A run of a repeated N token
+    /// selects the operator of the repeated token.
+    fn null_denotation(&mut self, identifiers: &[String], null_commands: &BTreeMap<&str, usize>) {
+        self.open("fn null_denotation(&mut self) -> Result<Expr, ParseError> {");
+        self.open("match self.peek().kind {");
+        self.line("TokenKind::Operator => {}");
+        self.line("TokenKind::EOF => return Err(unexpected(\"an expression\", self.peek())),");
+        self.line("_ => return Ok(self.atom()),");
+        self.close("}");
+        self.open("match self.peek().text.as_str() {");
+        for (token, &index) in null_commands {
+            self.line(&format!("{:?} => self.nud_{}(),", token, identifiers[index]));
+        }
+        // A run of several N tokens selects the operator of the longest, as in `Parser`.
+        let mut runs: Vec<(&&str, &usize)> = null_commands.iter().collect();
+        runs.sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then(a.cmp(b)));
+        for (token, &index) in runs {
+            self.line(&format!("text if is_run_of(text, {:?}) => self.nud_{}(),", token, identifiers[index]));
+        }
+        self.line("_ => Err(unexpected(\"an expression\", self.peek())),");
+        self.close("}");
+        self.close("}");
+    }
+
+    /// The null or left denotation of an operator.
+    fn denotation(&mut self, operator: &Operator, identifier: &str) -> Result<(), String> {
+        self.next_variable = 0;
+        let left_operand = operator.l_token.is_some();
+
+        if left_operand {
+            self.open(&format!("fn led_{}(&mut self, left: Expr) -> Result<Expr, ParseError> {{", identifier));
+        } else {
+            self.open(&format!("fn nud_{}(&mut self) -> Result<Expr, ParseError> {{", identifier));
+        }
+        self.line("let start = self.peek().span.start;");
+        self.line("let mut b0 = Bindings::default();");
+
+        if left_operand {
+            let elements = left_elements(operator);
+            match elements.first() {
+                Some(Syntax::Symbol) => {
+                    self.open("if !matches!(left, Expr::Symbol(_)) {");
+                    self.open("return Err(ParseError::UnexpectedToken {");
+                    self.line(&format!(
+                        "expected: {:?}.to_string(),",
+                        format!("a symbol before {}", operator.l_token.clone().unwrap_or_default())
+                    ));
+                    self.line("found: \"an expression\".to_string(),");
+                    self.line("span: self.peek().span,");
+                    self.close("});");
+                    self.close("}");
+                    self.line("b0.bind(Meta::Symbol, left);");
+                }
+                Some(first) => {
+                    if let Some(metavariable) = metavariable(first) {
+                        self.line(&format!("b0.bind({}, left);", metavariable));
+                    }
+                }
+                None => {}
+            }
+            self.sequence(operator, &elements[1..], "b0", true)?;
+
+            // Adjacent fully associative operators collapse into a single node. Each repetition
+            // binds the operator's right operand again.
+            if let (Associativity::Full, Some(token)) = (operator.associativity, &operator.l_token) {
+                self.open(&format!("while self.peek_is({:?}) {{", token));
+                self.sequence(operator, &elements[1..], "b0", true)?;
+                self.close("}");
+            }
+        } else {
+            self.sequence(operator, &operator.syntax.elements(), "b0", true)?;
+        }
+
+        let constant = identifier.to_ascii_uppercase();
+        let fullform = match operator.fullform {
+            Syntax::Empty => "None".to_string(),
+            _ => format!("Some(&FULLFORM_{})", constant),
+        };
+        self.line(&format!(
+            "self.make_node({:?}, &PARSE_{}, {}, &b0, start)",
+            operator.name, constant, fullform
+        ));
+        self.close("}");
+        Ok(())
+    }
+
+    /// Matches each element of a sequence. See `Step::Sequence` in the `parser` module.
+    fn sequence(&mut self, operator: &Operator, elements: &[&Syntax], bindings: &str, trailing: bool)
+        -> Result<(), String>
+    {
+        let last = elements.iter().rposition(|e| e.consumes_input());
+        let mut constraint: Option<&Syntax> = None;
+
+        for (i, element) in elements.iter().enumerate() {
+            let trailing = trailing && Some(i) == last;
+            if element.is_spacing() {
+                constraint = Some(element);
+                continue;
+            }
+            match constraint.take() {
+                // An optional element that breaks the constraint is absent.
+                Some(constraint)
+                    if matches!(element, Syntax::Optional(_) | Syntax::ZeroPlus(_) | Syntax::ZeroPlusDelim(..)) =>
+                {
+                    self.open(&format!("if {} {{", spacing_allows(constraint)));
+                    self.element(operator, element, bindings, trailing)?;
+                    self.close("}");
+                }
+                Some(constraint) => {
+                    self.open(&format!("if !({}) {{", spacing_allows(constraint)));
+                    self.line(&format!(
+                        "return Err(self.spacing_error({}));",
+                        *constraint == Syntax::Space
+                    ));
+                    self.close("}");
+                    self.element(operator, element, bindings, trailing)?;
+                }
+                None => self.element(operator, element, bindings, trailing)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Matches one element of a syntax. See `Step::Match` in the `parser` module.
+    fn element(&mut self, operator: &Operator, syntax: &Syntax, bindings: &str, trailing: bool)
+        -> Result<(), String>
+    {
+        match syntax {
+            Syntax::Expr1
+            | Syntax::Expr2
+            | Syntax::Expr3
+            | Syntax::Expr4 => {
+                // Operands that are not trailing are closed by a token, so they may be any
+                // expression.
+                let bp = if trailing { operator.rbp() } else { 0 };
+                self.line(&format!(
+                    "{}.bind({}, self.expr({})?);",
+                    bindings, metavariable(syntax).unwrap(), bp
+                ));
+            }
+
+            Syntax::Symbol => {
+                self.line(&format!("{}.bind(Meta::Symbol, self.symbol()?);", bindings));
+            }
+
+            Syntax::Optional(inner) => {
+                self.open(&format!("if {} {{", can_start(inner)));
+                self.element(operator, inner, bindings, trailing)?;
+                self.close("}");
+            }
+
+            Syntax::OnePlus(inner)
+            | Syntax::ZeroPlus(inner) => {
+                let iterations = format!("iterations{}", self.variable());
+                let split = inner.token_text().filter(|text| !text.is_empty());
+                self.line(&format!("let mut {} = Vec::new();", iterations));
+                if let Syntax::OnePlus(_) = syntax {
+                    if let Some(text) = &split {
+                        self.line(&format!("self.split_repeated({:?});", text));
+                    }
+                    self.iteration(operator, inner, &iterations, trailing)?;
+                }
+                self.open("loop {");
+                if let Some(text) = &split {
+                    self.line(&format!("self.split_repeated({:?});", text));
+                }
+                self.open(&format!("if !({}) {{", can_start(inner)));
+                self.line("break;");
+                self.close("}");
+                self.line("let cursor = self.cursor;");
+                self.iteration(operator, inner, &iterations, trailing)?;
+                self.open("if self.cursor == cursor {");
+                self.line("break;");
+                self.close("}");
+                self.close("}");
+                self.line(&format!("{}.push_group({});", bindings, iterations));
+            }
+
+            Syntax::OnePlusDelim(inner, delimiter)
+            | Syntax::ZeroPlusDelim(inner, delimiter) => {
+                let iterations = format!("iterations{}", self.variable());
+                self.line(&format!("let mut {} = Vec::new();", iterations));
+                if let Syntax::OnePlusDelim(..) = syntax {
+                    self.iteration(operator, inner, &iterations, trailing)?;
+                } else {
+                    self.open(&format!("if {} {{", can_start(inner)));
+                    self.iteration(operator, inner, &iterations, trailing)?;
+                    self.close("}");
+                }
+                self.open(&format!("while !{}.is_empty() && {} {{", iterations, can_start(delimiter)));
+                let discarded = format!("b{}", self.variable());
+                self.line(&format!("let mut {} = Bindings::default();", discarded));
+                self.element(operator, delimiter, &discarded, false)?;
+                self.iteration(operator, inner, &iterations, trailing)?;
+                self.close("}");
+                self.line(&format!("{}.push_group({});", bindings, iterations));
+            }
+
+            Syntax::Sequence(list)
+            | Syntax::SequenceDelim(list) => {
+                let elements: Vec<&Syntax> = list.iter().collect();
+                self.sequence(operator, &elements, bindings, trailing)?;
+            }
+
+            Syntax::Alternative(list) => {
+                for (i, alternative) in list.iter().enumerate() {
+                    let keyword = if i == 0 { "if" } else { "} else if" };
+                    if i > 0 {
+                        self.indent -= 1;
+                    }
+                    self.open(&format!("{} {} {{", keyword, can_start(alternative)));
+                    self.element(operator, alternative, bindings, trailing)?;
+                }
+                let expected = list.iter().map(|a| a.to_string()).collect::<Vec<_>>().join(" or ");
+                if !list.is_empty() {
+                    self.indent -= 1;
+                    self.open("} else {");
+                    self.line(&format!("return Err(unexpected({:?}, self.peek()));", expected));
+                    self.close("}");
+                } else {
+                    self.line(&format!("return Err(unexpected({:?}, self.peek()));", expected));
+                }
+            }
+
+            Syntax::NoSpace
+            | Syntax::Space
+            | Syntax::Newline
+            | Syntax::Empty => {}
+
+            other => match other.token_text() {
+                Some(text) => self.line(&format!("self.expect({:?})?;", text)),
+                // Metavariables like `n` and constructions like `f[x]` only make sense in
+                // templates.
+                None => {
+                    return Err(format!(
+                        "{} cannot appear in the syntax of {}",
+                        format_syntax(other),
+                        operator.name
+                    ));
+                }
+            },
+        }
+        Ok(())
+    }
+
+    /// Matches one iteration of a repetition group into its own bindings.
+    fn iteration(&mut self, operator: &Operator, inner: &Syntax, iterations: &str, trailing: bool)
+        -> Result<(), String>
+    {
+        let bindings = format!("b{}", self.variable());
+        self.open("{");
+        self.line(&format!("let mut {} = Bindings::default();", bindings));
+        self.element(operator, inner, &bindings, trailing)?;
+        self.line(&format!("{}.push({});", iterations, bindings));
+        self.close("}");
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+#[path = "../../resources/operators_parser.rs"]
+mod operators_parser;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::ASTNode;
+    use crate::evaluator::Evaluator;
+    use crate::grammar::{compile_records, parse_grammar};
+    use crate::parser::Parser;
+
+    use super::operators_parser::{self, Expr};
+
+    const OPERATORS_GRAMMAR: &str = include_str!("../../resources/operators.grammar");
+    const OPERATORS_PARSER: &str = include_str!("../../resources/operators_parser.rs");
+
+    fn operators() -> OperatorTable {
+        compile_records(&parse_grammar(OPERATORS_GRAMMAR).unwrap()).unwrap()
+    }
+
+    fn to_expr(node: &ASTNode) -> Expr {
+        match node.head() {
+            Some(head) => Expr::Apply(
+                Box::new(to_expr(head)),
+                node.children().iter().map(|child| to_expr(child)).collect(),
+            ),
+            None => match node.evaluator() {
+                Evaluator::Symbol(name) => Expr::Symbol(name.clone()),
+                Evaluator::Number(value) => Expr::Number(*value),
+                Evaluator::String(text) => Expr::String(text.clone()),
+                other => panic!("unexpected atom {:?}", other),
+            },
+        }
+    }
+
+    #[test]
+    fn generated_parser_is_up_to_date() {
+        // Regenerate with `grammar codegen resources/operators.grammar -o resources/operators_parser.rs`.
+        let generated = generate_parser(&operators(), "resources/operators.grammar").unwrap();
+        assert!(generated == OPERATORS_PARSER, "resources/operators_parser.rs is out of date");
+    }
+
+    /// A chain of `terms` divisions inside `depth` parentheses.
+    fn nested_chain(depth: usize, terms: usize) -> String {
+        format!("{}{}{}", "(".repeat(depth), vec!["a"; terms].join("/"), ")".repeat(depth))
+    }
+
+    #[test]
+    fn generated_parser_agrees_with_parser() {
+        let mut parser = Parser::new(operators());
+
+        for text in [
+            "a + b * c ^ d ^ e",
+            "1 + 2 + 3 - 4 - 5",
+            "a * b / c * d",
+            "(a + b) * c",
+            "f[x, y + 1][z]",
+            "f[]",
+            "x = y := z + 1",
+            "2 √ 3",
+            "2 √ 3 √ 4",
+            "\\[Alpha] + \"a string\\n\"",
+            "3.25 * x",
+            "a +",
+            "a + * b",
+            "f[x,",
+            "(a",
+            "a b",
+            "a # b",
+            "\"open",
+            "\\[NoSuchName]",
+            "`a b` - `-3` * `+inf.0` + `\\-3`",
+            "`open",
+            &"(".repeat(100_000),
+            &vec!["a"; 100_000].join("/"),
+            &vec!["a"; DEFAULT_MAX_DEPTH - 1].join("/"),
+            &nested_chain(DEFAULT_MAX_DEPTH / 2, DEFAULT_MAX_DEPTH / 2 - 1),
+            &nested_chain(DEFAULT_MAX_DEPTH / 2, DEFAULT_MAX_DEPTH / 2),
+            &format!("{}a{}", "(".repeat(DEFAULT_MAX_DEPTH - 1), ")".repeat(DEFAULT_MAX_DEPTH - 1)),
+        ] {
+            let expected = parser.parse(text).map(|node| to_expr(&node)).map_err(|e| e.to_string());
+            let actual = operators_parser::parse(text).map_err(|e| e.to_string());
+            assert_eq!(actual, expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn generated_parser_prints_fullform() {
+        let tree = operators_parser::parse("f[a + b, \"c\"] ^ 2").unwrap();
+        assert_eq!(tree.to_string(), "Power[f[Plus[a, b], \"c\"], 2]");
+
+        let atoms = "f[`a b`, `\\-3`, `-3`, `+nan.0`, \"t\\tab\\\\\"]";
+        assert_eq!(operators_parser::parse(atoms).unwrap().to_string(), atoms);
+    }
+
+    #[test]
+    fn generate_unrolled_syntax() {
+        let records = parse_grammar(
+            "name: Blank\nprecedence: 5\nsyntax: symb nospace \"_\" (symb)?\n\n\
+             name: Out\nprecedence: 5\nsyntax: (\"%\")+\nparse: Out[-n]\n\n\
+             name: Times\nassociativity: full\nprecedence: 20\nsyntax: expr1 \"*\" expr2\n",
+        )
+        .unwrap();
+        let generated = generate_parser(&compile_records(&records).unwrap(), "test").unwrap();
+
+        assert!(generated.contains("Some(\"*\") => (9980, 9980, Parser::led_times),"));
+        assert!(generated.contains("Some(\"_\") if !self.peek().space_before => (9995, 9995, Parser::led_blank),"));
+        assert!(generated.contains("text if is_run_of(text, \"%\") => self.nud_out(),"));
+        assert!(generated.contains("self.split_repeated(\"%\");"));
+        assert!(generated.contains("while self.peek_is(\"*\") {"));
+        assert!(generated.contains("const PARSE_OUT: Template = Template::Cons(&Template::Word(\"Out\"), &[Template::NegN]);"));
+    }
+
+    #[test]
+    fn generate_errors() {
+        let records = parse_grammar("name: Count\nprecedence: 5\nsyntax: \"#\" n\n").unwrap();
+        let error = generate_parser(&compile_records(&records).unwrap(), "test").unwrap_err();
+        assert_eq!(error, "n cannot appear in the syntax of Count");
+    }
+}
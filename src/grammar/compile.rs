@@ -0,0 +1,312 @@
+//! This module compiles the `OpRecord`s of a grammar description into the `Operator`s used by
+//! the parser. The LToken, NToken, OToken, affix, and arity of an operator are not written in
+//! the grammar description. They are inferred from the shape of the operator's syntax:
+//!
+//! | Syntax shape               | Affix    | Tokens                            |
+//! |:---------------------------|:---------|:----------------------------------|
+//! | `expr1 "+" expr2`          | Infix    | L = `+`                           |
+//! | `expr1 "!"`                | Postfix  | L = `!`                           |
+//! | `"-" expr1`                | Prefix   | N = `-`                           |
+//! | `"(" expr1 ")"`            | Matchfix | N = `(`, O = `)`                  |
+//! | `expr1 "?" expr2 ":" expr3`| Infix    | L = `?`, O = `:`                  |
+//! | `("%")+`                   | Null     | N = `%`                           |
+//!
+//! The arity is the number of distinct operand metavariables (`expr1`..`expr4` and `symb`)
+//! appearing anywhere in the syntax. Repetition does not change the arity.
+
+use crate::grammar::syntax::{self, Syntax, OpRecord};
+use crate::grammar::grammar::parse_grammar_file;
+use crate::grammar::error::GrammarError;
+use crate::operator::{Operator, OperatorTable, Affix, Associativity, MAX_PRECEDENCE};
+
+/// Converts the grammar DSL's associativity into the parser's associativity.
+fn compile_associativity(associativity: &syntax::Associativity) -> Associativity {
+    match associativity {
+        syntax::Associativity::Left => Associativity::Left,
+        syntax::Associativity::Right => Associativity::Right,
+        syntax::Associativity::Non => Associativity::Non,
+        syntax::Associativity::Full => Associativity::Full,
+    }
+}
+
+/// Returns the elements of a syntax that consume input.
+fn elements(syntax: &Syntax) -> Vec<&Syntax> {
+    syntax.elements().into_iter().filter(|e| e.consumes_input()).collect()
+}
+
+/// The first token an element must begin with, looking inside of groups.
+fn first_token(syntax: &Syntax) -> Option<String> {
+    match syntax {
+        Syntax::OnePlus(inner)
+        | Syntax::OnePlusDelim(inner, _) => first_token(inner),
+        Syntax::Sequence(list)
+        | Syntax::SequenceDelim(list) => {
+            list.iter().find(|e| e.consumes_input()).and_then(first_token)
+        }
+        other => other.token_text(),
+    }
+}
+
+/// Infers the affix, arity, and tokens of the operator described by `record` and returns the
+/// corresponding `Operator`.
+pub fn compile_record(record: &OpRecord) -> Result<Operator, String> {
+    if record.precedence > MAX_PRECEDENCE {
+        return Err(format!(
+            "the precedence of `{}` is {}, but precedences may not exceed {}",
+            record.name, record.precedence, MAX_PRECEDENCE
+        ));
+    }
+
+    let elements = elements(&record.syntax);
+
+    let (first, rest) = match elements.split_first() {
+        Some(split) => split,
+        None => return Err(format!("the operator `{}` has no syntax", record.name)),
+    };
+
+    let has_left_operand = first.is_operand();
+    let has_right_operand = rest.last().is_some_and(|e| e.is_operand());
+
+    let mut l_token = None;
+    let mut n_token = None;
+
+    if has_left_operand {
+        // The operator is selected by the token immediately following its left operand.
+        l_token = rest.first().and_then(|e| first_token(e));
+        if l_token.is_none() {
+            return Err(format!(
+                "cannot infer the LToken of `{}`: its left operand must be followed by a token",
+                record.name
+            ));
+        }
+    } else {
+        n_token = first_token(first);
+        if n_token.is_none() {
+            return Err(format!(
+                "cannot infer the NToken of `{}`: its syntax must begin with an operand or a token",
+                record.name
+            ));
+        }
+    }
+
+    // The O token is the first token after the leading token that is not inside a group, as in
+    // the closing `)` of `"(" expr1 ")"`. Delimiters of repetitions are not O tokens.
+    let o_token =
+        rest.iter()
+            .skip(if has_left_operand { 1 } else { 0 })
+            .filter_map(|e| e.token_text())
+            .next();
+
+    let operands = record.syntax.operands();
+
+    let affix = match (has_left_operand, has_right_operand) {
+        (true, true) => Affix::Infix,
+        (true, false) => Affix::Postfix,
+        (false, true) => Affix::Prefix,
+        (false, false) if operands.is_empty() => Affix::Null,
+        (false, false) => Affix::Matchfix,
+    };
+
+    Ok(Operator {
+        name: record.name.clone(),
+        precedence: record.precedence,
+        l_token,
+        n_token,
+        o_token,
+        associativity: compile_associativity(&record.associativity),
+        affix,
+        arity: operands.len() as u32,
+        syntax: record.syntax.clone(),
+        parse: record.parse.clone(),
+        fullform: record.fullform.clone(),
+        latex: record.latex.clone(),
+        mathml: record.mathml.clone(),
+    })
+}
+
+/// Compiles every record into an `OperatorTable`. It is an error for two records to have the
+/// same name.
+pub fn compile_records(records: &[OpRecord]) -> Result<OperatorTable, GrammarError> {
+    let mut operator_table = OperatorTable::new();
+
+    for record in records {
+        // Records read from a grammar definition know where they were defined.
+        let error = |message: String| {
+            let mut error = GrammarError::new(&message).in_entry(&record.name);
+            if let Some(provenance) = &record.provenance {
+                error.message = format!("{} (defined at {})", message, provenance);
+                error.file = provenance.file.clone();
+            }
+            error
+        };
+
+        let operator = compile_record(record).map_err(error)?;
+        if operator_table.contains_key(&operator.name) {
+            return Err(error(format!("the operator `{}` is defined more than once", operator.name)));
+        }
+        operator_table.insert(operator.name.clone(), operator);
+    }
+
+    Ok(operator_table)
+}
+
+/// Reads a grammar definition file and compiles it into an `OperatorTable`.
+pub fn compile_grammar_file(filename: &str) -> Result<OperatorTable, GrammarError> {
+    let records = parse_grammar_file(filename)?;
+    compile_records(&records)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(name: &str, associativity: syntax::Associativity, syntax: Syntax) -> OpRecord {
+        OpRecord {
+            name: name.to_string(),
+            associativity,
+            precedence: 10,
+            meaningful: true,
+            syntax,
+            parse: Syntax::Empty,
+            fullform: Syntax::Empty,
+            latex: Syntax::Empty,
+            mathml: Syntax::Empty,
+            provenance: None,
+        }
+    }
+
+    fn literal(text: &str) -> Syntax {
+        Syntax::Literal(text.to_string())
+    }
+
+    #[test]
+    fn compile_infix_test() {
+        let plus = record(
+            "Plus",
+            syntax::Associativity::Full,
+            Syntax::Sequence(vec![Syntax::Expr1, literal("+"), Syntax::Expr2])
+        );
+        let op = compile_record(&plus).unwrap();
+
+        assert_eq!(op.affix, Affix::Infix);
+        assert_eq!(op.associativity, Associativity::Full);
+        assert_eq!(op.arity, 2);
+        assert_eq!(op.l_token, Some("+".to_string()));
+        assert_eq!(op.n_token, None);
+        assert_eq!(op.o_token, None);
+    }
+
+    #[test]
+    fn compile_mixfix_test() {
+        // expr1 "\^" expr2 "\%" expr3
+        let subsuperscript = record(
+            "SubsuperscriptBox",
+            syntax::Associativity::Right,
+            Syntax::Sequence(vec![
+                Syntax::Expr1, literal("\\^"), Syntax::Expr2, literal("\\%"), Syntax::Expr3
+            ])
+        );
+        let op = compile_record(&subsuperscript).unwrap();
+
+        assert_eq!(op.affix, Affix::Infix);
+        assert_eq!(op.arity, 3);
+        assert_eq!(op.l_token, Some("\\^".to_string()));
+        assert_eq!(op.o_token, Some("\\%".to_string()));
+    }
+
+    #[test]
+    fn compile_postfix_repetition_test() {
+        // expr1 "[[" (expr2, )* "]]"
+        let part = record(
+            "Part",
+            syntax::Associativity::Left,
+            Syntax::Sequence(vec![
+                Syntax::Expr1,
+                literal("[["),
+                Syntax::ZeroPlusDelim(Box::new(Syntax::Expr2), Box::new(Syntax::Comma)),
+                literal("]]")
+            ])
+        );
+        let op = compile_record(&part).unwrap();
+
+        assert_eq!(op.affix, Affix::Postfix);
+        assert_eq!(op.arity, 2);
+        assert_eq!(op.l_token, Some("[[".to_string()));
+        assert_eq!(op.o_token, Some("]]".to_string()));
+    }
+
+    #[test]
+    fn compile_null_and_matchfix_test() {
+        // ("%")+
+        let out = record(
+            "Out",
+            syntax::Associativity::Non,
+            Syntax::OnePlus(Box::new(literal("%")))
+        );
+        let op = compile_record(&out).unwrap();
+
+        assert_eq!(op.affix, Affix::Null);
+        assert_eq!(op.arity, 0);
+        assert_eq!(op.n_token, Some("%".to_string()));
+
+        // "(" expr1 ")"
+        let parentheses = record(
+            "Parentheses",
+            syntax::Associativity::Non,
+            Syntax::Sequence(vec![literal("("), Syntax::Expr1, literal(")")])
+        );
+        let op = compile_record(&parentheses).unwrap();
+
+        assert_eq!(op.affix, Affix::Matchfix);
+        assert_eq!(op.arity, 1);
+        assert_eq!(op.n_token, Some("(".to_string()));
+        assert_eq!(op.o_token, Some(")".to_string()));
+    }
+
+    #[test]
+    fn compile_nospace_optional_test() {
+        // symb NoSpace "___" NoSpace (expr1)?
+        let blank = record(
+            "NamedBlankNullSequence",
+            syntax::Associativity::Left,
+            Syntax::Sequence(vec![
+                Syntax::Symbol,
+                Syntax::NoSpace,
+                literal("___"),
+                Syntax::NoSpace,
+                Syntax::Optional(Box::new(Syntax::Expr1))
+            ])
+        );
+        let op = compile_record(&blank).unwrap();
+
+        assert_eq!(op.affix, Affix::Infix);
+        assert_eq!(op.arity, 2);
+        assert_eq!(op.l_token, Some("___".to_string()));
+    }
+
+    #[test]
+    fn compile_errors_test() {
+        let empty = record("Nothing", syntax::Associativity::Left, Syntax::Empty);
+        assert!(compile_record(&empty).is_err());
+
+        let juxtaposition = record(
+            "Times",
+            syntax::Associativity::Full,
+            Syntax::Sequence(vec![Syntax::Expr1, Syntax::Expr2])
+        );
+        assert!(compile_record(&juxtaposition).is_err());
+
+        let plus = record(
+            "Plus",
+            syntax::Associativity::Full,
+            Syntax::Sequence(vec![Syntax::Expr1, literal("+"), Syntax::Expr2])
+        );
+        let plus_again = record(
+            "Plus",
+            syntax::Associativity::Full,
+            Syntax::Sequence(vec![Syntax::Expr1, literal("+"), Syntax::Expr2])
+        );
+        assert!(compile_records(&[plus, plus_again]).is_err());
+    }
+}
@@ -0,0 +1,411 @@
+//! Renders grammars for documentation, offline and without dependencies:
+//!
+//!   * `railroad_diagram` draws the syntax of an entry as an SVG railroad diagram. Tokens are drawn
+//!     in rounded boxes and operands in square boxes. An optional element is a branch that may be
+//!     skipped, a repetition is a loop back under the repeated element, with its delimiter, if any,
+//!     on the way back, and whitespace constraints are notes on the line.
+//!   * `precedence_chart` writes an operator table as an HTML page with a table of the operators
+//!     grouped by precedence, tightest first, with their syntax, associativity, and affix.
+//!
+//! Both are self-contained documents with their styles inline, so they can be opened directly or
+//! embedded in other pages.
+
+use std::collections::BTreeMap;
+
+use crate::grammar::format::format_syntax;
+use crate::grammar::syntax::Syntax;
+use crate::operator::{Operator, OperatorTable};
+
+/// The width of a character of a box's label.
+const CHAR_WIDTH: i32 = 8;
+/// The width of a character of a note on the line.
+const NOTE_CHAR_WIDTH: i32 = 7;
+/// Half the height of a box.
+const HALF_BOX: i32 = 11;
+/// The length of the line between the elements of a sequence.
+const GAP: i32 = 10;
+/// The radius of the arcs of branches and loops.
+const ARC: i32 = 10;
+/// The space between the branches of a choice.
+const VERTICAL_GAP: i32 = 8;
+/// The space around a diagram.
+const MARGIN: i32 = 20;
+
+const STYLE: &str = "\
+    path { fill: none; stroke: #333; stroke-width: 2; }\
+    rect { fill: #f4f4ff; stroke: #333; stroke-width: 2; }\
+    rect.token { fill: #fffbe6; }\
+    text { font: 14px monospace; text-anchor: middle; dominant-baseline: central; }\
+    text.note { font: italic 12px sans-serif; fill: #666; }";
+
+/// A diagram element. Every element is drawn with its entry on the left and its exit on the right,
+/// on the same horizontal line, and takes up `up` above that line and `down` below it.
+#[derive(Debug)]
+enum Node {
+    Token(String),
+    Operand(String),
+    Note(String),
+    Skip,
+    Sequence(Vec<Node>),
+    /// The first branch is on the line, and the others below it.
+    Choice(Vec<Node>),
+    /// An element and what is passed on the way back to repeat it.
+    Loop(Box<Node>, Box<Node>),
+}
+
+impl Node {
+    fn from_syntax(syntax: &Syntax) -> Node {
+        let sequence = |list: &Vec<Syntax>| {
+            let nodes: Vec<Node> =
+                list.iter().map(Node::from_syntax).filter(|n| !matches!(n, Node::Skip)).collect();
+            match nodes.len() {
+                0 => Node::Skip,
+                1 => nodes.into_iter().next().unwrap(),
+                _ => Node::Sequence(nodes),
+            }
+        };
+
+        match syntax {
+            Syntax::Expr1
+            | Syntax::Expr2
+            | Syntax::Expr3
+            | Syntax::Expr4
+            | Syntax::N
+            | Syntax::NegN
+            | Syntax::Symbol => Node::Operand(format_syntax(syntax)),
+            Syntax::Number(n) => Node::Token(n.to_string()),
+            Syntax::NoSpace => Node::Note("no space".to_string()),
+            Syntax::Space => Node::Note("space".to_string()),
+            Syntax::Newline
+            | Syntax::Empty => Node::Skip,
+            Syntax::OnePlus(item) => Node::Loop(Box::new(Node::from_syntax(item)), Box::new(Node::Skip)),
+            Syntax::OnePlusDelim(item, delimiter) => {
+                Node::Loop(Box::new(Node::from_syntax(item)), Box::new(Node::from_syntax(delimiter)))
+            }
+            Syntax::ZeroPlus(item) => Node::Choice(vec![
+                Node::Skip,
+                Node::Loop(Box::new(Node::from_syntax(item)), Box::new(Node::Skip)),
+            ]),
+            Syntax::ZeroPlusDelim(item, delimiter) => Node::Choice(vec![
+                Node::Skip,
+                Node::Loop(Box::new(Node::from_syntax(item)), Box::new(Node::from_syntax(delimiter))),
+            ]),
+            Syntax::Optional(item) => Node::Choice(vec![Node::Skip, Node::from_syntax(item)]),
+            Syntax::Cons(head, tail) => {
+                let mut nodes = vec![Node::from_syntax(head), Node::Token("[".to_string())];
+                for (i, item) in tail.iter().enumerate() {
+                    if i > 0 {
+                        nodes.push(Node::Token(",".to_string()));
+                    }
+                    nodes.push(Node::from_syntax(item));
+                }
+                nodes.push(Node::Token("]".to_string()));
+                Node::Sequence(nodes)
+            }
+            Syntax::Sequence(list)
+            | Syntax::SequenceDelim(list) => sequence(list),
+            Syntax::Alternative(list) => Node::Choice(list.iter().map(Node::from_syntax).collect()),
+            token => Node::Token(token.token_text().unwrap_or_default()),
+        }
+    }
+
+    fn width(&self) -> i32 {
+        match self {
+            Node::Token(text)
+            | Node::Operand(text) => text.chars().count() as i32 * CHAR_WIDTH + 2 * GAP,
+            Node::Note(text) => text.chars().count() as i32 * NOTE_CHAR_WIDTH + GAP,
+            Node::Skip => 0,
+            Node::Sequence(nodes) => {
+                nodes.iter().map(Node::width).sum::<i32>() + GAP * (nodes.len() as i32 - 1)
+            }
+            Node::Choice(nodes) => nodes.iter().map(Node::width).max().unwrap_or(0) + 4 * ARC,
+            Node::Loop(item, back) => item.width().max(back.width()) + 2 * ARC,
+        }
+    }
+
+    fn up(&self) -> i32 {
+        match self {
+            Node::Token(_)
+            | Node::Operand(_) => HALF_BOX,
+            Node::Note(_) => 2 * HALF_BOX,
+            Node::Skip => 0,
+            Node::Sequence(nodes) => nodes.iter().map(Node::up).max().unwrap_or(0),
+            Node::Choice(nodes) => nodes.first().map_or(0, Node::up),
+            Node::Loop(item, _) => item.up(),
+        }
+    }
+
+    fn down(&self) -> i32 {
+        match self {
+            Node::Token(_)
+            | Node::Operand(_) => HALF_BOX,
+            Node::Note(_)
+            | Node::Skip => 0,
+            Node::Sequence(nodes) => nodes.iter().map(Node::down).max().unwrap_or(0),
+            Node::Choice(nodes) => {
+                let offsets = branch_offsets(nodes);
+                match (offsets.last(), nodes.last()) {
+                    (Some(offset), Some(last)) => offset + last.down(),
+                    _ => 0,
+                }
+            }
+            Node::Loop(item, back) => loop_offset(item, back) + back.down(),
+        }
+    }
+
+    /// Draws the element with its entry at `(x, y)`.
+    fn draw(&self, x: i32, y: i32, out: &mut String) {
+        match self {
+            Node::Token(text)
+            | Node::Operand(text) => {
+                // Tokens have rounded corners.
+                let class = match self {
+                    Node::Token(_) => " class=\"token\" rx=\"10\"",
+                    _ => "",
+                };
+                out.push_str(&format!(
+                    "<rect{} x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"/>\n\
+                     <text x=\"{}\" y=\"{}\">{}</text>\n",
+                    class, x, y - HALF_BOX, self.width(), 2 * HALF_BOX,
+                    x + self.width() / 2, y, escape(text)
+                ));
+            }
+            Node::Note(text) => {
+                line(out, x, y, self.width());
+                out.push_str(&format!(
+                    "<text class=\"note\" x=\"{}\" y=\"{}\">{}</text>\n",
+                    x + self.width() / 2, y - HALF_BOX, escape(text)
+                ));
+            }
+            Node::Skip => {}
+            Node::Sequence(nodes) => {
+                let mut x = x;
+                for (i, node) in nodes.iter().enumerate() {
+                    if i > 0 {
+                        line(out, x, y, GAP);
+                        x += GAP;
+                    }
+                    node.draw(x, y, out);
+                    x += node.width();
+                }
+            }
+            Node::Choice(nodes) => {
+                let width = self.width();
+                for (node, offset) in nodes.iter().zip(branch_offsets(nodes)) {
+                    let branch_y = y + offset;
+                    if offset == 0 {
+                        line(out, x, y, 2 * ARC);
+                    } else {
+                        out.push_str(&format!(
+                            "<path d=\"M{} {} a{r} {r} 0 0 1 {r} {r} v{} a{r} {r} 0 0 0 {r} {r}\"/>\n",
+                            x, y, offset - 2 * ARC, r = ARC
+                        ));
+                        out.push_str(&format!(
+                            "<path d=\"M{} {} a{r} {r} 0 0 0 {r} -{r} v-{} a{r} {r} 0 0 1 {r} -{r}\"/>\n",
+                            x + width - 2 * ARC, branch_y, offset - 2 * ARC, r = ARC
+                        ));
+                    }
+                    node.draw(x + 2 * ARC, branch_y, out);
+                    let end = x + 2 * ARC + node.width();
+                    line(out, end, branch_y, x + width - 2 * ARC - end);
+                    if offset == 0 {
+                        line(out, x + width - 2 * ARC, y, 2 * ARC);
+                    }
+                }
+            }
+            Node::Loop(item, back) => {
+                let inner = item.width().max(back.width());
+                let offset = loop_offset(item, back);
+                let back_y = y + offset;
+
+                line(out, x, y, ARC);
+                item.draw(x + ARC, y, out);
+                line(out, x + ARC + item.width(), y, inner - item.width() + ARC);
+
+                // The way back, from right to left.
+                out.push_str(&format!(
+                    "<path d=\"M{} {} a{r} {r} 0 0 1 {r} {r} v{} a{r} {r} 0 0 1 -{r} {r}\"/>\n",
+                    x + ARC + inner, y, offset - 2 * ARC, r = ARC
+                ));
+                let back_x = x + ARC + (inner - back.width()) / 2;
+                line(out, x + ARC, back_y, back_x - x - ARC);
+                back.draw(back_x, back_y, out);
+                line(out, back_x + back.width(), back_y, x + ARC + inner - back_x - back.width());
+                out.push_str(&format!(
+                    "<path d=\"M{} {} a{r} {r} 0 0 1 -{r} -{r} v-{} a{r} {r} 0 0 1 {r} -{r}\"/>\n",
+                    x + ARC, back_y, offset - 2 * ARC, r = ARC
+                ));
+            }
+        }
+    }
+}
+
+/// The vertical offset of each branch of a choice from the line.
+fn branch_offsets(nodes: &[Node]) -> Vec<i32> {
+    let mut offsets = Vec::with_capacity(nodes.len());
+    let mut offset = 0;
+    for (i, node) in nodes.iter().enumerate() {
+        if i > 0 {
+            offset = (offset + nodes[i - 1].down() + VERTICAL_GAP + node.up()).max(offset + 2 * ARC);
+        }
+        offsets.push(offset);
+    }
+    offsets
+}
+
+/// The vertical offset of the way back of a loop from the line.
+fn loop_offset(item: &Node, back: &Node) -> i32 {
+    (item.down() + VERTICAL_GAP + back.up()).max(2 * ARC)
+}
+
+fn line(out: &mut String, x: i32, y: i32, length: i32) {
+    if length > 0 {
+        out.push_str(&format!("<path d=\"M{} {} h{}\"/>\n", x, y, length));
+    }
+}
+
+/// Escapes text for XML and HTML.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Draws `syntax` as a standalone SVG railroad diagram.
+pub fn railroad_diagram(syntax: &Syntax) -> String {
+    let node = Node::from_syntax(syntax);
+    let width = node.width() + 2 * MARGIN;
+    let height = node.up() + node.down() + 2 * MARGIN;
+    let y = MARGIN + node.up();
+
+    let mut out = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n\
+         <style>{}</style>\n",
+        STYLE, w = width, h = height
+    );
+    // The line begins and ends with a short bar.
+    out.push_str(&format!(
+        "<path d=\"M{} {} v{} m0 -{} h{}\"/>\n",
+        MARGIN / 2, y - HALF_BOX / 2, HALF_BOX / 2 * 2, HALF_BOX / 2, MARGIN / 2
+    ));
+    node.draw(MARGIN, y, &mut out);
+    out.push_str(&format!(
+        "<path d=\"M{} {} h{} m0 -{} v{}\"/>\n",
+        width - MARGIN, y, MARGIN / 2, HALF_BOX / 2, HALF_BOX / 2 * 2
+    ));
+    out.push_str("</svg>\n");
+    out
+}
+
+/// Writes the operator table as an HTML precedence chart. `source` names the grammar in the title.
+pub fn precedence_chart(table: &OperatorTable, source: &str) -> String {
+    let mut levels: BTreeMap<u32, Vec<&Operator>> = BTreeMap::new();
+    for operator in table.values() {
+        levels.entry(operator.precedence).or_default().push(operator);
+    }
+
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Operators of {title}</title>\n\
+         <style>\n\
+         table {{ border-collapse: collapse; font-family: sans-serif; }}\n\
+         th, td {{ border: 1px solid #999; padding: 4px 10px; text-align: left; vertical-align: top; }}\n\
+         tbody:nth-child(even) {{ background: #f4f4f4; }}\n\
+         code {{ white-space: pre; }}\n\
+         </style>\n</head>\n<body>\n<h1>Operators of {title}</h1>\n\
+         <p>Operators are listed from the tightest binding to the loosest. A smaller precedence binds \
+         tighter.</p>\n<table>\n<thead>\n<tr><th>Precedence</th><th>Operator</th><th>Syntax</th>\
+         <th>Associativity</th><th>Affix</th></tr>\n</thead>\n",
+        title = escape(source)
+    );
+
+    for (precedence, mut operators) in levels {
+        operators.sort_by(|a, b| a.name.cmp(&b.name));
+        out.push_str("<tbody>\n");
+        for (i, operator) in operators.iter().enumerate() {
+            out.push_str("<tr>");
+            if i == 0 {
+                out.push_str(&format!("<td rowspan=\"{}\">{}</td>", operators.len(), precedence));
+            }
+            out.push_str(&format!(
+                "<td>{}</td><td><code>{}</code></td><td>{}</td><td>{}</td></tr>\n",
+                escape(&operator.name),
+                escape(&format_syntax(&operator.syntax)),
+                format!("{:?}", operator.associativity).to_lowercase(),
+                format!("{:?}", operator.affix).to_lowercase()
+            ));
+        }
+        out.push_str("</tbody>\n");
+    }
+
+    out.push_str("</table>\n</body>\n</html>\n");
+    out
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::{compile_records, parse_grammar};
+
+    fn syntax(syntax: &str) -> Syntax {
+        parse_grammar(&format!("name: Test\nsyntax: {}\n", syntax)).unwrap()[0].syntax.clone()
+    }
+
+    fn node(source: &str) -> Node {
+        Node::from_syntax(&syntax(source))
+    }
+
+    #[test]
+    fn diagram_layout() {
+        // A box, the gap after it, and a box with the arcs of its branch.
+        let optional = node("expr1 (expr2)?");
+        assert_eq!(optional.width(), 60 + GAP + (60 + 4 * ARC));
+        assert_eq!((optional.up(), optional.down()), (HALF_BOX, 2 * ARC + HALF_BOX));
+
+        // The delimiter is drawn on the way back.
+        let repeated = node("(expr2,)+");
+        match &repeated {
+            Node::Loop(item, back) => {
+                assert!(matches!(**item, Node::Operand(ref text) if text == "expr2"));
+                assert!(matches!(**back, Node::Token(ref text) if text == ","));
+            }
+            other => panic!("Expected a loop, found {:?}", other),
+        }
+        assert_eq!(repeated.down(), HALF_BOX + VERTICAL_GAP + HALF_BOX + HALF_BOX);
+    }
+
+    #[test]
+    fn railroad_svg() {
+        let svg = railroad_diagram(&syntax(r#"expr1 nospace "<" ("a"|"b") expr2"#));
+
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.ends_with("</svg>\n"));
+        assert!(svg.contains(">&lt;</text>"));
+        assert!(svg.contains("<text class=\"note\""));
+        assert_eq!(svg.matches("<rect").count(), 5);
+    }
+
+    #[test]
+    fn chart_groups_by_precedence() {
+        let table = compile_records(&parse_grammar(
+            "name: Times\nprecedence: 20\nsyntax: expr1 \"*\" expr2\n\n\
+             name: Divide\nprecedence: 20\nsyntax: expr1 \"/\" expr2\n\n\
+             name: Less\nassociativity: non\nprecedence: 40\nsyntax: expr1 \"<\" expr2\n"
+        ).unwrap()).unwrap();
+        let chart = precedence_chart(&table, "arithmetic.grammar");
+
+        assert_eq!(chart.matches("<tbody>").count(), 2);
+        assert!(chart.contains("<tr><td rowspan=\"2\">20</td><td>Divide</td>"));
+        assert!(chart.contains("<tr><td>Times</td>"));
+        assert!(chart.contains("<td>Less</td><td><code>expr1 &quot;&lt;&quot; expr2</code></td><td>non</td><td>infix</td>"));
+        assert!(chart.find("Divide") < chart.find("Less"));
+    }
+}
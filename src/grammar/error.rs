@@ -0,0 +1,125 @@
+//! Errors in grammar definition files. A `GrammarError` says where the problem is (the file, line,
+//! and column, and the entry and field being parsed) and what was expected there, and it displays
+//! itself with an excerpt of the offending line:
+//!
+//! ```text
+//! error: expected `true` or `false`
+//!   --> operators.grammar:12:13
+//!    |
+//! 12 | meaningful: maybe
+//!    |             ^
+//!    = in the `meaningful` field of the entry `Plus`
+//! ```
+
+use std::fmt;
+
+use crate::grammar::syntax::{FieldType, enum_to_field};
+
+/// A position in the text of a grammar definition.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Location {
+    /// The line number, starting at 1.
+    pub line: usize,
+    /// The column, counted in characters and starting at 1.
+    pub column: usize,
+    /// The text of the line, without its line ending.
+    pub source_line: String,
+}
+
+impl Location {
+    /// The location of the byte offset `offset` in `contents`.
+    pub fn of(contents: &str, offset: usize) -> Location {
+        let offset = offset.min(contents.len());
+        let line_start = contents[..offset].rfind('\n').map_or(0, |i| i + 1);
+
+        Location {
+            line: contents[..offset].matches('\n').count() + 1,
+            column: contents[line_start..offset].chars().count() + 1,
+            source_line: contents[line_start..].lines().next().unwrap_or("").to_string(),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct GrammarError {
+    /// The grammar definition file, if the grammar was read from one.
+    pub file: Option<String>,
+    /// Where in the file the error is, if it is at a particular place.
+    pub location: Option<Location>,
+    /// The name of the entry being parsed, if any. Errors in a `defaults:` block have none.
+    pub entry: Option<String>,
+    /// The field being parsed, if any.
+    pub field: Option<FieldType>,
+    /// What went wrong, usually what was expected, e.g. "expected `true` or `false`".
+    pub message: String,
+}
+
+impl GrammarError {
+    pub fn new(message: &str) -> GrammarError {
+        GrammarError {
+            file: None,
+            location: None,
+            entry: None,
+            field: None,
+            message: message.to_string(),
+        }
+    }
+
+    pub fn at(mut self, location: Location) -> GrammarError {
+        self.location = Some(location);
+        self
+    }
+
+    pub fn in_file(mut self, file: &str) -> GrammarError {
+        self.file = Some(file.to_string());
+        self
+    }
+
+    pub fn in_entry(mut self, entry: &str) -> GrammarError {
+        self.entry = Some(entry.to_string());
+        self
+    }
+
+    pub fn in_field(mut self, field: FieldType) -> GrammarError {
+        self.field = Some(field);
+        self
+    }
+}
+
+impl fmt::Display for GrammarError {
+    fn fmt(&self, dest: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(dest, "error: {}", self.message)?;
+
+        let file = self.file.as_deref().unwrap_or("<grammar>");
+        match &self.location {
+            Some(location) => {
+                let number = location.line.to_string();
+                let gutter = " ".repeat(number.len());
+                let indent: String =
+                    location.source_line
+                            .chars()
+                            .take(location.column - 1)
+                            .map(|c| if c == '\t' { '\t' } else { ' ' })
+                            .collect();
+
+                writeln!(dest, "{}--> {}:{}:{}", gutter, file, location.line, location.column)?;
+                writeln!(dest, "{} |", gutter)?;
+                writeln!(dest, "{} | {}", number, location.source_line)?;
+                write!(dest, "{} | {}^", gutter, indent)?;
+            }
+            None => {
+                write!(dest, "  --> {}", file)?;
+            }
+        }
+
+        let gutter = " ".repeat(self.location.as_ref().map_or(1, |l| l.line.to_string().len()));
+        match (&self.field, &self.entry) {
+            (Some(field), Some(entry)) => write!(
+                dest, "\n{} = in the `{}` field of the entry `{}`", gutter, enum_to_field(*field), entry
+            ),
+            (Some(field), None) => write!(dest, "\n{} = in the `{}` field", gutter, enum_to_field(*field)),
+            (None, Some(entry)) => write!(dest, "\n{} = in the entry `{}`", gutter, entry),
+            (None, None) => Ok(()),
+        }
+    }
+}
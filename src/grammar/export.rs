@@ -0,0 +1,677 @@
+//! This module exports an operator table to standard grammar notations, for documentation and for
+//! other parsing tools:
+//!
+//!   * `to_ebnf` writes ISO 14977 EBNF stratified by precedence: one rule for each precedence,
+//!     from the loosest to the tightest, each of which is built from the next tighter one.
+//!   * `to_antlr` writes an ANTLR4 grammar whose `expr` rule lists the operators from the tightest
+//!     to the loosest, the order in which ANTLR climbs precedence in a left recursive rule.
+//!   * `to_tree_sitter` writes a tree-sitter `grammar.js` with a rule for each operator, wrapped in
+//!     `prec.left` or `prec.right` with the operator's binding power.
+//!
+//! An operator that is closed by a token and takes no left operand, like `"(" expr1 ")"`, binds
+//! like an atom whatever its precedence. ANTLR and tree-sitter have no non-associative operators,
+//! so these are exported as left associative, as are fully associative operators. Whitespace
+//! constraints are dropped, except that tree-sitter's `token.immediate` expresses a `nospace`
+//! before a token.
+
+use crate::grammar::codegen::identifiers;
+use crate::grammar::syntax::Syntax;
+use crate::named_characters::NamedCharacters;
+use crate::operator::{Associativity, Operator, OperatorTable, MAX_PRECEDENCE};
+use crate::parser::normalize_operator;
+
+/// How an operator fits into a grammar stratified by precedence.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+enum Shape {
+    /// Takes a left operand that may be any tighter expression, e.g. `expr1 "+" expr2`. The
+    /// elements of the entry do not include the left operand.
+    Left,
+    /// Ends with an operand but takes no left operand, e.g. `"-" expr1`.
+    Prefix,
+    /// Closed by a token, e.g. `"(" expr1 ")"`.
+    Closed,
+}
+
+struct Entry {
+    operator: Operator,
+    identifier: String,
+    shape: Shape,
+    elements: Vec<Syntax>,
+}
+
+impl Entry {
+    fn elements(&self) -> Vec<&Syntax> {
+        self.elements.iter().collect()
+    }
+
+    /// Does the entry end with an operand? The trailing operand binds according to the operator's
+    /// associativity.
+    fn has_trailing_operand(&self) -> bool {
+        ends_with_operand(&self.elements)
+    }
+
+    /// The binding power, in which larger binds tighter, as tree-sitter expects.
+    fn binding_power(&self) -> u32 {
+        MAX_PRECEDENCE - self.operator.precedence.min(MAX_PRECEDENCE)
+    }
+}
+
+fn ends_with_operand(elements: &[Syntax]) -> bool {
+    match elements.iter().rev().find(|e| e.consumes_input()) {
+        Some(Syntax::Optional(inner)) => ends_with_operand(std::slice::from_ref(inner)),
+        Some(last) => matches!(last, Syntax::Expr1 | Syntax::Expr2 | Syntax::Expr3 | Syntax::Expr4),
+        None => false,
+    }
+}
+
+/// The operators of the table in order of precedence, tightest first, with named characters in
+/// their tokens replaced by the characters they name.
+fn entries(table: &OperatorTable, reserved: &[&str]) -> Vec<Entry> {
+    let named_chars = NamedCharacters::builtin();
+    let mut operators: Vec<Operator> =
+        table.values().map(|operator| normalize_operator(operator, named_chars)).collect();
+    operators.sort_by(|a, b| a.precedence.cmp(&b.precedence).then(a.name.cmp(&b.name)));
+
+    let identifiers = identifiers(&operators, reserved);
+    operators
+        .into_iter()
+        .zip(identifiers)
+        .map(|(operator, identifier)| {
+            let elements: Vec<Syntax> =
+                operator.syntax
+                        .elements()
+                        .into_iter()
+                        .filter(|e| e.consumes_input() || e.is_spacing())
+                        .cloned()
+                        .collect();
+            let left_operand =
+                operator.l_token.is_some() && elements.first().is_some_and(|e| e.is_operand() && *e != Syntax::Symbol);
+
+            let (shape, elements) =
+                if left_operand {
+                    (Shape::Left, elements[1..].to_vec())
+                } else if ends_with_operand(&elements) {
+                    (Shape::Prefix, elements)
+                } else {
+                    (Shape::Closed, elements)
+                };
+            Entry { operator, identifier, shape, elements }
+        })
+        .collect()
+}
+
+/// The constructions of a grammar notation.
+trait Notation {
+    fn literal(&self, text: &str, immediate: bool) -> String;
+    fn symbol(&self) -> String;
+    fn sequence(&self, items: Vec<String>) -> String;
+    fn optional(&self, item: String) -> String;
+    fn zero_plus(&self, item: String) -> String;
+    fn one_plus(&self, item: String) -> String;
+    fn alternative(&self, items: Vec<String>) -> String;
+}
+
+/// Renders a sequence of syntax elements. The last operand stands for `trailing` and any other
+/// operand for `inner`.
+fn render(notation: &dyn Notation, elements: &[&Syntax], trailing: &str, inner: &str) -> String {
+    let last = elements.iter().rposition(|e| e.consumes_input());
+    let mut items = Vec::new();
+    let mut immediate = false;
+
+    for (i, element) in elements.iter().enumerate() {
+        if element.is_spacing() {
+            immediate = **element == Syntax::NoSpace;
+            continue;
+        }
+        let operand = if Some(i) == last { trailing } else { inner };
+        items.extend(render_element(notation, element, operand, inner, immediate));
+        immediate = false;
+    }
+
+    notation.sequence(items)
+}
+
+fn render_element(notation: &dyn Notation, syntax: &Syntax, operand: &str, inner: &str, immediate: bool)
+    -> Option<String>
+{
+    let item = match syntax {
+        Syntax::Expr1
+        | Syntax::Expr2
+        | Syntax::Expr3
+        | Syntax::Expr4 => operand.to_string(),
+        Syntax::Symbol => notation.symbol(),
+        Syntax::Optional(item) => {
+            notation.optional(render_element(notation, item, operand, inner, immediate)?)
+        }
+        Syntax::OnePlus(item) => notation.one_plus(render_element(notation, item, operand, inner, false)?),
+        Syntax::ZeroPlus(item) => notation.zero_plus(render_element(notation, item, operand, inner, false)?),
+        Syntax::OnePlusDelim(item, delimiter)
+        | Syntax::ZeroPlusDelim(item, delimiter) => {
+            let item = render_element(notation, item, operand, inner, false)?;
+            let delimited = match render_element(notation, delimiter, inner, inner, false) {
+                Some(delimiter) => notation.sequence(vec![delimiter, item.clone()]),
+                None => item.clone(),
+            };
+            let list = notation.sequence(vec![item, notation.zero_plus(delimited)]);
+            match syntax {
+                Syntax::ZeroPlusDelim(..) => notation.optional(list),
+                _ => list,
+            }
+        }
+        Syntax::Sequence(list)
+        | Syntax::SequenceDelim(list) => {
+            let elements: Vec<&Syntax> = list.iter().collect();
+            render(notation, &elements, operand, inner)
+        }
+        Syntax::Alternative(list) => notation.alternative(
+            list.iter().filter_map(|a| render_element(notation, a, operand, inner, immediate)).collect()
+        ),
+        other => notation.literal(&other.token_text()?, immediate),
+    };
+    Some(item)
+}
+
+/// Wraps an item in parentheses unless it is a single word or already parenthesized.
+fn group(item: String) -> String {
+    let parenthesized = item.starts_with('(') && item.ends_with(')') && balanced(&item[1..item.len() - 1]);
+    if item.contains(' ') && !parenthesized {
+        format!("({})", item)
+    } else {
+        item
+    }
+}
+
+/// Do the parentheses in `text` balance, ignoring those in quoted literals?
+fn balanced(text: &str) -> bool {
+    let mut depth = 0;
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    for c in text.chars() {
+        match quote {
+            Some(q) => {
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' {
+                    escaped = true;
+                } else if c == q {
+                    quote = None;
+                }
+            }
+            None => match c {
+                '\'' | '"' => quote = Some(c),
+                '(' => depth += 1,
+                ')' if depth == 0 => return false,
+                ')' => depth -= 1,
+                _ => {}
+            },
+        }
+    }
+    depth == 0
+}
+
+/// `text` as a single quoted literal, with backslash escapes as in ANTLR and JavaScript.
+fn single_quoted(text: &str) -> String {
+    let mut quoted = String::from("'");
+    for c in text.chars() {
+        match c {
+            '\'' => quoted.push_str("\\'"),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('\'');
+    quoted
+}
+
+// EBNF
+
+struct Ebnf;
+
+impl Notation for Ebnf {
+    fn literal(&self, text: &str, _immediate: bool) -> String {
+        // EBNF has no escapes, so a literal containing `"` is quoted with `'`, and one containing
+        // both `"` and `'` is written as a sequence of literals that each contain only one of them.
+        if !text.contains('"') {
+            return format!("\"{}\"", text);
+        }
+        if !text.contains('\'') {
+            return format!("'{}'", text);
+        }
+        let mut parts = Vec::new();
+        let mut rest = text;
+        while !rest.is_empty() {
+            let length =
+                match rest.starts_with('"') {
+                    true => rest.find('\'').unwrap_or(rest.len()),
+                    false => rest.find('"').unwrap_or(rest.len()),
+                };
+            parts.push(self.literal(&rest[..length], false));
+            rest = &rest[length..];
+        }
+        self.sequence(parts)
+    }
+
+    fn symbol(&self) -> String {
+        "symbol".to_string()
+    }
+
+    fn sequence(&self, items: Vec<String>) -> String {
+        items.join(" , ")
+    }
+
+    fn optional(&self, item: String) -> String {
+        format!("[ {} ]", item)
+    }
+
+    fn zero_plus(&self, item: String) -> String {
+        format!("{{ {} }}", item)
+    }
+
+    fn one_plus(&self, item: String) -> String {
+        format!("{} , {{ {} }}", item, item)
+    }
+
+    fn alternative(&self, items: Vec<String>) -> String {
+        match items.len() {
+            1 => items.join(""),
+            _ => format!("( {} )", items.join(" | ")),
+        }
+    }
+}
+
+/// Writes the grammar as EBNF. `source` names the grammar in the header comment.
+pub fn to_ebnf(table: &OperatorTable, source: &str) -> String {
+    let entries = entries(table, &[]);
+
+    let mut precedences: Vec<u32> =
+        entries.iter().filter(|e| e.shape != Shape::Closed).map(|e| e.operator.precedence).collect();
+    precedences.dedup();
+    // From the loosest to the tightest.
+    precedences.reverse();
+
+    let level = |precedence: u32| format!("expr_{}", precedence);
+    let mut out = format!(
+        "(* Generated by `grammar ebnf` from {}.\n   \
+         There is one rule for each precedence, from the loosest to the tightest. *)\n\n",
+        source
+    );
+    out.push_str(&format!(
+        "expr = {} ;\n",
+        precedences.first().map_or("primary".to_string(), |p| level(*p))
+    ));
+
+    for (i, precedence) in precedences.iter().enumerate() {
+        let this = level(*precedence);
+        let next = precedences.get(i + 1).map_or("primary".to_string(), |p| level(*p));
+        let at_level: Vec<&Entry> =
+            entries.iter().filter(|e| e.shape != Shape::Closed && e.operator.precedence == *precedence).collect();
+
+        let mut alternatives = Vec::new();
+        let mut repeated_tails = Vec::new();
+        let mut optional_tails = Vec::new();
+        for entry in &at_level {
+            match entry.shape {
+                // A prefix operator's operand may begin with another prefix operator of the same
+                // precedence.
+                Shape::Prefix => alternatives.push(render(&Ebnf, &entry.elements(), &this, "expr")),
+                _ if !entry.has_trailing_operand() || entry.operator.associativity != Associativity::Non
+                    && entry.operator.associativity != Associativity::Right => {
+                    repeated_tails.push(render(&Ebnf, &entry.elements(), &next, "expr"));
+                }
+                _ if entry.operator.associativity == Associativity::Right => {
+                    optional_tails.push(render(&Ebnf, &entry.elements(), &this, "expr"));
+                }
+                _ => optional_tails.push(render(&Ebnf, &entry.elements(), &next, "expr")),
+            }
+        }
+
+        let mut left = next.clone();
+        if !repeated_tails.is_empty() {
+            left.push_str(&format!(" , {{ {} }}", repeated_tails.join(" | ")));
+        }
+        if !optional_tails.is_empty() {
+            left.push_str(&format!(" , [ {} ]", optional_tails.join(" | ")));
+        }
+        alternatives.push(left);
+
+        let names: Vec<&str> = at_level.iter().map(|e| e.operator.name.as_str()).collect();
+        out.push_str(&format!("\n(* {} *)\n", names.join(", ")));
+        out.push_str(&format!("{} = {} ;\n", this, alternatives.join("\n    | ")));
+    }
+
+    let mut primaries = vec!["symbol".to_string(), "number".to_string(), "string".to_string()];
+    for entry in entries.iter().filter(|e| e.shape == Shape::Closed) {
+        primaries.push(render(&Ebnf, &entry.elements(), "expr", "expr"));
+    }
+    out.push_str(&format!("\nprimary = {} ;\n", primaries.join("\n    | ")));
+
+    out.push_str(
+        "\nsymbol = ? a letter or \"$\" followed by letters, digits, and \"$\" ? ;\n\
+         number = digit , { digit } , [ \".\" , digit , { digit } ] ;\n\
+         digit = \"0\" | \"1\" | \"2\" | \"3\" | \"4\" | \"5\" | \"6\" | \"7\" | \"8\" | \"9\" ;\n\
+         string = ? a double quoted string with backslash escapes ? ;\n"
+    );
+    out
+}
+
+// ANTLR
+
+struct Antlr;
+
+impl Notation for Antlr {
+    fn literal(&self, text: &str, _immediate: bool) -> String {
+        single_quoted(text)
+    }
+
+    fn symbol(&self) -> String {
+        "SYMBOL".to_string()
+    }
+
+    fn sequence(&self, items: Vec<String>) -> String {
+        items.join(" ")
+    }
+
+    fn optional(&self, item: String) -> String {
+        format!("{}?", group(item))
+    }
+
+    fn zero_plus(&self, item: String) -> String {
+        format!("{}*", group(item))
+    }
+
+    fn one_plus(&self, item: String) -> String {
+        format!("{}+", group(item))
+    }
+
+    fn alternative(&self, items: Vec<String>) -> String {
+        match items.len() {
+            1 => items.join(""),
+            _ => format!("({})", items.join(" | ")),
+        }
+    }
+}
+
+/// The label of an alternative: the identifier in upper camel case.
+fn label(identifier: &str) -> String {
+    identifier
+        .split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+/// Writes the grammar as an ANTLR4 grammar named `name`. `source` names the grammar in the header
+/// comment.
+pub fn to_antlr(table: &OperatorTable, name: &str, source: &str) -> String {
+    let mut entries = entries(table, &["expr", "start", "symbol_atom", "number_atom", "string_atom"]);
+    // Closed operators come last, with the atoms, whatever their precedence.
+    entries.sort_by_key(|e| e.shape == Shape::Closed);
+
+    // Operators of the same precedence and shape share an alternative, so that ANTLR gives them the
+    // same precedence. Closed operators have alternatives of their own.
+    let mut alternatives: Vec<(String, String)> = Vec::new();
+    let mut i = 0;
+    while i < entries.len() {
+        let entry = &entries[i];
+        let key = |e: &Entry| {
+            (e.operator.precedence, e.shape, e.has_trailing_operand(), e.operator.associativity == Associativity::Right)
+        };
+        let mut group_end = i + 1;
+        if entry.shape != Shape::Closed {
+            while group_end < entries.len() && key(&entries[group_end]) == key(entry) {
+                group_end += 1;
+            }
+        }
+        let grouped = &entries[i..group_end];
+        i = group_end;
+
+        let tails: Vec<String> =
+            grouped.iter().map(|e| render(&Antlr, &e.elements(), "expr", "expr")).collect();
+        let body = match tails.len() {
+            1 => tails.join(""),
+            _ => format!("({})", tails.join(" | ")),
+        };
+        let assoc =
+            match entry.operator.associativity == Associativity::Right && entry.has_trailing_operand() {
+                true => "<assoc=right> ",
+                false => "",
+            };
+        let alternative = match entry.shape {
+            Shape::Left => format!("{}expr {}", assoc, body),
+            _ => format!("{}{}", assoc, body),
+        };
+        let label: String = grouped.iter().map(|e| label(&e.identifier)).collect();
+        alternatives.push((alternative, label));
+    }
+    for atom in ["SYMBOL", "NUMBER", "STRING"] {
+        alternatives.push((atom.to_string(), label(&format!("{}_atom", atom.to_ascii_lowercase()))));
+    }
+
+    let width = alternatives.iter().map(|(a, _)| a.chars().count()).max().unwrap_or(0);
+    let mut out = format!(
+        "// Generated by `grammar antlr` from {}.\n\
+         // The alternatives of `expr` are listed from the tightest binding to the loosest.\n\
+         grammar {};\n\n\
+         start\n    : expr EOF\n    ;\n\nexpr\n",
+        source, name
+    );
+    for (i, (alternative, label)) in alternatives.iter().enumerate() {
+        let padding = " ".repeat(width - alternative.chars().count());
+        out.push_str(&format!(
+            "    {} {}{}  # {}\n",
+            if i == 0 { ':' } else { '|' }, alternative, padding, label
+        ));
+    }
+    out.push_str(
+        "    ;\n\n\
+         SYMBOL : [\\p{L}$] [\\p{L}\\p{N}$]* ;\n\
+         NUMBER : [0-9]+ ('.' [0-9]+)? ;\n\
+         STRING : '\"' ('\\\\' . | ~[\"\\\\])* '\"' ;\n\
+         WS     : [ \\t\\r\\n]+ -> skip ;\n"
+    );
+    out
+}
+
+// tree-sitter
+
+struct TreeSitter;
+
+impl Notation for TreeSitter {
+    fn literal(&self, text: &str, immediate: bool) -> String {
+        match immediate {
+            true => format!("token.immediate({})", single_quoted(text)),
+            false => single_quoted(text),
+        }
+    }
+
+    fn symbol(&self) -> String {
+        "$.symbol".to_string()
+    }
+
+    fn sequence(&self, items: Vec<String>) -> String {
+        match items.len() {
+            0 => "blank()".to_string(),
+            1 => items.join(""),
+            _ => format!("seq({})", items.join(", ")),
+        }
+    }
+
+    fn optional(&self, item: String) -> String {
+        format!("optional({})", item)
+    }
+
+    fn zero_plus(&self, item: String) -> String {
+        format!("repeat({})", item)
+    }
+
+    fn one_plus(&self, item: String) -> String {
+        format!("repeat1({})", item)
+    }
+
+    fn alternative(&self, items: Vec<String>) -> String {
+        match items.len() {
+            1 => items.join(""),
+            _ => format!("choice({})", items.join(", ")),
+        }
+    }
+}
+
+/// Writes the grammar as a tree-sitter `grammar.js` for the language `name`. `source` names the
+/// grammar in the header comment.
+pub fn to_tree_sitter(table: &OperatorTable, name: &str, source: &str) -> String {
+    let entries = entries(table, &["source_file", "symbol", "number", "string"]);
+    let expression = "$._expression";
+
+    let mut out = format!(
+        "// Generated by `grammar tree-sitter` from {}.\n\
+         // Precedences are binding powers, so the larger binds tighter.\n\
+         module.exports = grammar({{\n  name: {},\n\n  extras: $ => [/\\s/],\n\n  rules: {{\n    \
+         source_file: $ => $._expression,\n\n    _expression: $ => choice(\n      $.symbol,\n      $.number,\n      $.string,\n",
+        source, single_quoted(name)
+    );
+    for entry in &entries {
+        out.push_str(&format!("      $.{},\n", entry.identifier));
+    }
+    out.push_str("    ),\n\n");
+
+    for entry in &entries {
+        let mut elements = entry.elements();
+        if entry.shape == Shape::Left {
+            elements.insert(0, &Syntax::Expr1);
+        }
+        let body = render(&TreeSitter, &elements, expression, expression);
+        let rule = match entry.shape {
+            Shape::Closed => body,
+            Shape::Prefix => format!("prec.right({}, {})", entry.binding_power(), body),
+            Shape::Left if entry.operator.associativity == Associativity::Right && entry.has_trailing_operand() => {
+                format!("prec.right({}, {})", entry.binding_power(), body)
+            }
+            Shape::Left => format!("prec.left({}, {})", entry.binding_power(), body),
+        };
+        out.push_str(&format!("    {}: $ => {},\n\n", entry.identifier, rule));
+    }
+
+    out.push_str(
+        "    symbol: $ => /[\\p{L}$][\\p{L}\\p{N}$]*/,\n\n    \
+         number: $ => /\\d+(\\.\\d+)?/,\n\n    \
+         string: $ => /\"([^\"\\\\]|\\\\.)*\"/,\n  }\n});\n"
+    );
+    out
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::{compile_records, parse_grammar};
+
+    fn table(grammar: &str) -> OperatorTable {
+        compile_records(&parse_grammar(grammar).unwrap()).unwrap()
+    }
+
+    fn arithmetic() -> OperatorTable {
+        table(
+            "defaults:\nassociativity: left\n\n\
+             name: Power\nassociativity: right\nprecedence: 10\nsyntax: expr1 \"^\" expr2\n\n\
+             name: Negate\nprecedence: 15\nsyntax: \"-\" expr1\n\n\
+             name: Times\nassociativity: full\nprecedence: 20\nsyntax: expr1 \"*\" expr2\n\n\
+             name: Divide\nprecedence: 20\nsyntax: expr1 \"/\" expr2\n\n\
+             name: Less\nassociativity: non\nprecedence: 40\nsyntax: expr1 \"<\" expr2\n\n\
+             name: Factorial\nprecedence: 5\nsyntax: expr1 \"!\"\n\n\
+             name: Parentheses\nprecedence: 100\nsyntax: \"(\" expr1 \")\"\n\n\
+             name: Construct\nprecedence: 1\nsyntax: expr1 \"[\" (expr2,)* \"]\"\n",
+        )
+    }
+
+    #[test]
+    fn export_ebnf() {
+        let ebnf = to_ebnf(&arithmetic(), "arithmetic.grammar");
+        let rules: Vec<&str> = ebnf.lines().filter(|l| l.contains(" = ") || l.starts_with("    |")).collect();
+
+        assert_eq!(
+            rules[..10],
+            [
+                "expr = expr_40 ;",
+                "expr_40 = expr_20 , [ \"<\" , expr_20 ] ;",
+                "expr_20 = expr_15 , { \"/\" , expr_15 | \"*\" , expr_15 } ;",
+                "expr_15 = \"-\" , expr_15",
+                "    | expr_10 ;",
+                "expr_10 = expr_5 , [ \"^\" , expr_10 ] ;",
+                "expr_5 = expr_1 , { \"!\" } ;",
+                "expr_1 = primary , { \"[\" , [ expr , { \",\" , expr } ] , \"]\" } ;",
+                "primary = symbol",
+                "    | number",
+            ]
+        );
+        assert!(ebnf.contains("    | \"(\" , expr , \")\" ;"));
+    }
+
+    #[test]
+    fn export_antlr() {
+        let antlr = to_antlr(&arithmetic(), "Arithmetic", "arithmetic.grammar");
+        let alternatives: Vec<String> =
+            antlr.lines()
+                 .skip_while(|l| *l != "expr")
+                 .filter(|l| l.starts_with("    :") || l.starts_with("    |"))
+                 .map(|l| l.split_whitespace().collect::<Vec<_>>().join(" "))
+                 .collect();
+
+        assert!(antlr.contains("grammar Arithmetic;"));
+        assert_eq!(
+            alternatives,
+            [
+                ": expr '[' (expr (',' expr)*)? ']' # Construct",
+                "| expr '!' # Factorial",
+                "| <assoc=right> expr '^' expr # Power",
+                "| '-' expr # Negate",
+                "| expr ('/' expr | '*' expr) # DivideTimes",
+                "| expr '<' expr # Less",
+                "| '(' expr ')' # Parentheses",
+                "| SYMBOL # SymbolAtom",
+                "| NUMBER # NumberAtom",
+                "| STRING # StringAtom",
+            ]
+        );
+    }
+
+    #[test]
+    fn export_tree_sitter() {
+        let grammar = to_tree_sitter(&arithmetic(), "arithmetic", "arithmetic.grammar");
+
+        assert!(grammar.contains("  name: 'arithmetic',"));
+        assert!(grammar.contains("    power: $ => prec.right(9990, seq($._expression, '^', $._expression)),"));
+        assert!(grammar.contains("    times: $ => prec.left(9980, seq($._expression, '*', $._expression)),"));
+        assert!(grammar.contains("    negate: $ => prec.right(9985, seq('-', $._expression)),"));
+        assert!(grammar.contains("    parentheses: $ => seq('(', $._expression, ')'),"));
+        assert!(grammar.contains(
+            "    construct: $ => prec.left(9999, seq($._expression, '[', optional(seq($._expression, repeat(seq(',', $._expression)))), ']')),"
+        ));
+    }
+
+    #[test]
+    fn export_whitespace_and_symbols() {
+        let table = table(
+            "name: Blank\nprecedence: 5\nsyntax: symb nospace \"_\" (symb)?\n\n\
+             name: Quote\nprecedence: 5\nsyntax: \"'\" expr1\n",
+        );
+
+        assert!(to_tree_sitter(&table, "t", "t")
+            .contains("    blank: $ => seq($.symbol, token.immediate('_'), optional($.symbol)),"));
+        assert!(to_antlr(&table, "T", "t").contains("'\\'' expr"));
+        assert!(to_ebnf(&table, "t").contains("    | symbol , \"_\" , [ symbol ] ;"));
+    }
+
+    #[test]
+    fn ebnf_quotes() {
+        assert_eq!(Ebnf.literal("+", false), "\"+\"");
+        assert_eq!(Ebnf.literal("\"", false), "'\"'");
+        assert_eq!(Ebnf.literal("a'b\"\"c'", false), "\"a'b\" , '\"\"c' , \"'\"");
+    }
+}
@@ -0,0 +1,388 @@
+//! Prints grammar definitions as canonical grammar source, the inverse of parsing them.
+//!
+//! `format_syntax` and `format_record` print a `Syntax` or an `OpRecord` as it would be written in a
+//! grammar file. `format_grammar` reformats a whole grammar file, keeping its comments:
+//!
+//!   * Entries, `defaults:` blocks, `include` directives, and comment blocks that stand on their own
+//!     are separated by exactly one blank line.
+//!   * The fields of an entry are printed in a fixed order: `name`, `associativity`, `precedence`,
+//!     `meaningful`, `syntax`, `parse`, `fullform`, `latex`, `mathml`. Comment lines move with the
+//!     field they precede, and a comment at the end of a field's line stays there.
+//!   * Values are printed in canonical form, e.g. `associativity: none` becomes `associativity: non`
+//!     and `(expr2, )*` becomes `(expr2,)*`. Fields are neither added nor removed.
+//!
+//! Parsing the output of any of these gives back what was printed: equal `Syntax`es, and records
+//! equal in everything but their provenance.
+
+use crate::grammar::syntax::{Syntax, OpRecord, FieldType, enum_to_field, enum_to_assoc};
+use crate::grammar::grammar::{Failure, parse_op_name, parse_field_name, parse_field, include_directive,
+                              defaults_header, grammar_error};
+use crate::grammar::error::GrammarError;
+
+/// Prints a syntax or template as grammar source.
+pub fn format_syntax(syntax: &Syntax) -> String {
+    let join = |list: &Vec<Syntax>, separator: &str| {
+        list.iter()
+            .map(format_syntax)
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<String>>()
+            .join(separator)
+    };
+
+    match syntax {
+        Syntax::Expr1 => "expr1".to_string(),
+        Syntax::Expr2 => "expr2".to_string(),
+        Syntax::Expr3 => "expr3".to_string(),
+        Syntax::Expr4 => "expr4".to_string(),
+        Syntax::N => "n".to_string(),
+        Syntax::NegN => "-n".to_string(),
+        Syntax::Symbol => "symb".to_string(),
+        Syntax::Comma => ",".to_string(),
+        Syntax::NoSpace => "NoSpace".to_string(),
+        Syntax::Space => "Space".to_string(),
+        Syntax::Word(word) => word.clone(),
+        Syntax::NamedChar(name) => format!("\\[{}]", name),
+        Syntax::Number(n) => n.to_string(),
+        Syntax::Literal(text) => format!("\"{}\"", text),
+        Syntax::OnePlus(inner) => format!("({})+", format_syntax(inner)),
+        Syntax::ZeroPlus(inner) => format!("({})*", format_syntax(inner)),
+        Syntax::Optional(inner) => format!("({})?", format_syntax(inner)),
+        Syntax::OnePlusDelim(inner, delimiter) => {
+            format!("({}{})+", format_syntax(inner), format_delimiter(delimiter))
+        }
+        Syntax::ZeroPlusDelim(inner, delimiter) => {
+            format!("({}{})*", format_syntax(inner), format_delimiter(delimiter))
+        }
+        Syntax::Cons(head, tail) => format!("{}[{}]", format_syntax(head), join(tail, ", ")),
+        Syntax::Sequence(list)
+        | Syntax::SequenceDelim(list) => join(list, " "),
+        Syntax::Alternative(list) => format!("({})", join(list, "|")),
+        Syntax::Newline
+        | Syntax::Empty => String::new(),
+    }
+}
+
+/// A comma hugs the repeated element, `(expr2,)*`. Any other delimiter is separated by a space.
+fn format_delimiter(delimiter: &Syntax) -> String {
+    match delimiter {
+        Syntax::Comma => ",".to_string(),
+        other => format!(" {}", format_syntax(other)),
+    }
+}
+
+/// Prints the value of a field of `record`.
+fn format_value(record: &OpRecord, field: FieldType) -> String {
+    match field {
+        FieldType::Name => record.name.clone(),
+        FieldType::Associativity => enum_to_assoc(&record.associativity).to_lowercase(),
+        FieldType::Precedence => record.precedence.to_string(),
+        FieldType::Meaningful => record.meaningful.to_string(),
+        FieldType::Syntax => format_syntax(&record.syntax),
+        FieldType::Parse => format_syntax(&record.parse),
+        FieldType::Fullform => format_syntax(&record.fullform),
+        FieldType::Latex => format_syntax(&record.latex),
+        FieldType::Mathml => format_syntax(&record.mathml),
+        FieldType::Defaults
+        | FieldType::Error => String::new(),
+    }
+}
+
+/// Prints a record as a complete grammar entry. Every field is given, except that empty templates,
+/// which stand for the default templates, are left out.
+pub fn format_record(record: &OpRecord) -> String {
+    let mut fields = vec![
+        FieldType::Name,
+        FieldType::Associativity,
+        FieldType::Precedence,
+        FieldType::Meaningful,
+        FieldType::Syntax,
+    ];
+    if record.parse != Syntax::Empty {
+        fields.push(FieldType::Parse);
+    }
+    if record.fullform != Syntax::Empty {
+        fields.push(FieldType::Fullform);
+    }
+    if record.latex != Syntax::Empty {
+        fields.push(FieldType::Latex);
+    }
+    if record.mathml != Syntax::Empty {
+        fields.push(FieldType::Mathml);
+    }
+
+    fields.into_iter()
+          .map(|field| format!("{}: {}\n", enum_to_field(field), format_value(record, field)))
+          .collect()
+}
+
+/// The position of a field in the canonical field order.
+fn field_rank(field: FieldType) -> usize {
+    match field {
+        FieldType::Name => 0,
+        FieldType::Associativity => 1,
+        FieldType::Precedence => 2,
+        FieldType::Meaningful => 3,
+        FieldType::Syntax => 4,
+        FieldType::Parse => 5,
+        FieldType::Fullform => 6,
+        FieldType::Latex => 7,
+        FieldType::Mathml => 8,
+        FieldType::Defaults
+        | FieldType::Error => 9,
+    }
+}
+
+/// The comment at the end of a line, if any. A `#` within a quoted literal does not begin one.
+fn trailing_comment(line: &str) -> Option<&str> {
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return Some(line[i..].trim_end()),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// A line of output together with the comment lines above it and the comment at its end.
+struct Line {
+    comments: Vec<String>,
+    text: String,
+    trailing: Option<String>,
+}
+
+impl Line {
+    fn print(&self, out: &mut String) {
+        for comment in &self.comments {
+            out.push_str(comment);
+            out.push('\n');
+        }
+        out.push_str(&self.text);
+        if let Some(comment) = &self.trailing {
+            out.push_str("  ");
+            out.push_str(comment);
+        }
+        out.push('\n');
+    }
+}
+
+/// An entry, `defaults:` block, `include` directive, or comment block standing on its own.
+struct Item {
+    /// The `name:` line, `defaults:` line, or `include` line. A comment block has none.
+    header: Option<Line>,
+    /// The item's fields with their ranks in the canonical order.
+    fields: Vec<(usize, Line)>,
+    /// The lines of a comment block.
+    comments: Vec<String>,
+}
+
+impl Item {
+    fn new(header: Line) -> Item {
+        Item { header: Some(header), fields: Vec::new(), comments: Vec::new() }
+    }
+
+    fn print(&mut self, out: &mut String) {
+        for comment in &self.comments {
+            out.push_str(comment);
+            out.push('\n');
+        }
+        if let Some(header) = &self.header {
+            header.print(out);
+        }
+        // A stable sort keeps repeated fields in the order they were given.
+        self.fields.sort_by_key(|(rank, _)| *rank);
+        for (_, line) in &self.fields {
+            line.print(out);
+        }
+    }
+}
+
+/// Reformats the grammar definition `contents` in canonical form. See the module documentation.
+/// Included files are not read.
+pub fn format_grammar(contents: &str) -> Result<String, GrammarError> {
+    let error = |failure: Failure| grammar_error(contents, failure);
+
+    let mut items: Vec<Item> = Vec::new();
+    // Comment lines not yet attached to anything, and whether a blank line followed them.
+    let mut comments: Vec<String> = Vec::new();
+    let mut comments_closed = false;
+    // Is the last item an entry or defaults block that fields may still be added to?
+    let mut open = false;
+
+    let mut offset = 0;
+    for raw_line in contents.split_inclusive('\n') {
+        let line = &contents[offset..offset + raw_line.len()];
+        offset += raw_line.len();
+        let trimmed = line.trim();
+
+        if trimmed.is_empty() {
+            comments_closed = !comments.is_empty();
+            continue;
+        }
+        if trimmed.starts_with('#') {
+            if comments_closed {
+                items.push(Item { header: None, fields: Vec::new(), comments: comments.split_off(0) });
+                comments_closed = false;
+            }
+            comments.push(trimmed.to_string());
+            continue;
+        }
+
+        let trailing = trailing_comment(line).map(String::from);
+        let mut begin_item = |text: String, items: &mut Vec<Item>, comments: &mut Vec<String>| {
+            if comments_closed {
+                items.push(Item { header: None, fields: Vec::new(), comments: comments.split_off(0) });
+            }
+            items.push(Item::new(Line { comments: comments.split_off(0), text, trailing: trailing.clone() }));
+            comments_closed = false;
+        };
+
+        if let Ok((_, file)) = include_directive(line) {
+            begin_item(format!("include \"{}\"", file), &mut items, &mut comments);
+            open = false;
+            continue;
+        }
+        if defaults_header(line).is_ok() {
+            begin_item("defaults:".to_string(), &mut items, &mut comments);
+            open = true;
+            continue;
+        }
+
+        let (value, field) = match parse_field_name(line) {
+            Ok(parsed) => parsed,
+            Err(_) => {
+                return Err(error(Failure::new(
+                    line.trim_start(), None, "expected a field name followed by `:`"
+                )));
+            }
+        };
+
+        if field == FieldType::Name {
+            let (_, name) = parse_op_name(line).map_err(|e| error(Failure::from_nom(
+                e, line, Some(FieldType::Name),
+                "expected an entry name, which is a word, followed by the end of the line"
+            )))?;
+            begin_item(format!("name: {}", name), &mut items, &mut comments);
+            open = true;
+            continue;
+        }
+        if !open {
+            return Err(error(Failure::new(
+                line.trim_start(), None, "expected `name:` or `defaults:` to begin an entry"
+            )));
+        }
+
+        let mut record = OpRecord::default();
+        parse_field(value, line, field, &mut record).map_err(error)?;
+        let text = format!("{}: {}", enum_to_field(field), format_value(&record, field));
+        comments_closed = false;
+        let line = Line { comments: comments.split_off(0), text, trailing };
+        items.last_mut().unwrap().fields.push((field_rank(field), line));
+    }
+
+    if !comments.is_empty() {
+        items.push(Item { header: None, fields: Vec::new(), comments });
+    }
+
+    let mut out = String::new();
+    for (i, item) in items.iter_mut().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        item.print(&mut out);
+    }
+    Ok(out)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::grammar::{parse_entry, parse_grammar};
+
+    /// Records equal in everything but provenance.
+    fn without_provenance(mut records: Vec<OpRecord>) -> Vec<OpRecord> {
+        for record in records.iter_mut() {
+            record.provenance = None;
+        }
+        records
+    }
+
+    fn assert_round_trips(contents: &str) {
+        let formatted = format_grammar(contents).unwrap();
+
+        assert_eq!(
+            without_provenance(parse_grammar(&formatted).unwrap()),
+            without_provenance(parse_grammar(contents).unwrap())
+        );
+        assert_eq!(format_grammar(&formatted).unwrap(), formatted);
+    }
+
+    #[test]
+    fn format_grammar_test() {
+        let contents = r#"# Arithmetic
+# operators
+
+  name: Plus   # Addition
+syntax: expr1 "+" expr2
+    associativity:  FULL
+# The precedence of addition
+precedence: 030
+defaults:
+meaningful: TRUE
+name: Part
+parse: Part[expr1, (expr2, )*]
+syntax: expr1 "[[" (expr2 , )* "]]"
+# The end
+"#;
+        let expected = r#"# Arithmetic
+# operators
+
+name: Plus  # Addition
+associativity: full
+# The precedence of addition
+precedence: 30
+syntax: expr1 "+" expr2
+
+defaults:
+meaningful: true
+
+name: Part
+syntax: expr1 "[[" (expr2,)* "]]"
+parse: Part[expr1, (expr2,)*]
+
+# The end
+"#;
+
+        assert_eq!(format_grammar(contents).unwrap(), expected);
+        assert_round_trips(contents);
+    }
+
+    #[test]
+    fn format_files_round_trip_test() {
+        assert_round_trips(include_str!("../../examples/Grammar.txt"));
+        assert_round_trips(include_str!("../../resources/operators.grammar"));
+    }
+
+    #[test]
+    fn format_record_round_trip_test() {
+        let contents = include_str!("../../examples/Grammar.txt");
+
+        for record in without_provenance(parse_grammar(contents).unwrap()) {
+            let formatted = format_record(&record);
+            let (rest, parsed) = parse_entry(&formatted).unwrap();
+            assert_eq!(rest, "");
+            assert_eq!(parsed, record, "{}", formatted);
+        }
+    }
+
+    #[test]
+    fn format_errors_test() {
+        let error = format_grammar("name: Plus\nprecedence: high\n").unwrap_err();
+
+        assert_eq!(error.field, Some(FieldType::Precedence));
+        assert_eq!(error.location.unwrap().column, 13);
+        assert!(format_grammar("syntax: expr1 \"+\" expr2\n").is_err());
+    }
+}
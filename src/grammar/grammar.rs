@@ -0,0 +1,1020 @@
+#![allow(dead_code)]
+/// This module defines the grammar of a grammar description DSL.
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+extern crate nom;
+use nom::{
+    bytes::complete::{tag, tag_no_case, is_not},
+    character::complete::{alpha1, alphanumeric0, digit1, multispace1, space0, space1,
+                          line_ending, not_line_ending, char as character},
+    combinator::{map, map_res, opt, recognize},
+    sequence::{preceded, terminated, pair, tuple, delimited},
+    IResult,
+    Err,
+    error::ErrorKind,
+    branch::alt,
+    number::complete::float,
+    multi::{separated_nonempty_list, separated_list, many1, many0_count},
+};
+
+use crate::grammar::syntax::*;
+use crate::grammar::error::{GrammarError, Location};
+
+// region: Nesting Limit
+
+/// The deepest nesting of groups and constructions, like `((expr1)?)*` or `f[g[x]]`, that a syntax
+/// may have. Each level costs several stack frames, so without a limit a long enough run of `(`
+/// would overflow the stack.
+const MAX_NESTING: usize = 64;
+
+thread_local! {
+    /// How deeply nested the syntax element being parsed is.
+    static NESTING: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Is `error` the failure to parse a syntax nested more deeply than `MAX_NESTING`?
+fn too_deep(error: &Err<(&str, ErrorKind)>) -> bool {
+    matches!(error, Err::Failure((_, ErrorKind::TooLarge)))
+}
+// endregion: Nesting Limit
+
+/// The same as `parse_field`, but expects a name field: `"name: value"`.
+pub(crate) fn parse_op_name(input: &str) -> IResult<&str, &str> {
+    let parse_field_name =
+        delimited(
+            many0_count(alt((multispace1, comment))),
+            tag_no_case("name:"),
+            space0
+        );
+
+    delimited(
+        parse_field_name,
+        alpha1,
+        end_of_line
+    )(input)
+}
+pub(crate) fn parse_field_name(input: &str) -> IResult<&str, FieldType> {
+    map(
+        delimited(
+            many0_count(alt((multispace1, comment))),
+            alpha1,
+            tag(":")
+        ),
+    field_to_enum
+    )(input)
+}
+fn parse_field_value(input: &str) -> IResult<&str, &str> {
+    delimited(
+        space0,
+        alpha1,
+        end_of_line
+    )(input)
+}
+fn parse_bool_option(input: &str) -> IResult<&str, bool> {
+    let parse_field_value =
+        delimited(
+            space0,
+            alt((
+                tag_no_case("true"),
+                tag_no_case("false")
+            )),
+            end_of_line
+        );
+
+    map(
+        parse_field_value,
+        |instr: &str| match &instr.to_ascii_lowercase()[..] {
+            "true"=> true,
+            "false"=>false,
+            _ => false
+        }
+    )(input)
+}
+fn parse_associativity(input: &str) -> IResult<&str, Associativity> {
+    let parse_field_value =
+        delimited(
+            space0,
+            alt((
+                tag_no_case("left"),
+                tag_no_case("right"),
+                tag_no_case("full"),
+                tag_no_case("non"),
+                tag_no_case("none"),
+            )),
+            end_of_line
+        );
+
+    map(
+        parse_field_value,
+        assoc_to_enum
+    )(input)
+}
+fn parse_number(input: &str) -> IResult<&str, u32>{
+    delimited(
+        space0,
+        map_res(digit1, |digits: &str| digits.parse::<u32>()),
+        end_of_line
+    )(input)
+}
+
+// Custom combinators, not used on their own.
+fn eoff(inp: &str) -> IResult<&str, &str> {
+    let result = many0_count(alt((multispace1, comment)))(inp);
+    match result {
+        Ok((i, _)) => {
+            if i.is_empty() {
+                Ok((i, ""))
+            } else {
+                Err(Err::Error((inp, ErrorKind::Eof)))
+            }
+        },
+        Err(Err::Error((i, _))) => {
+            // For some reason, eof errors manifest as Many0 errors.
+            if i.is_empty() {
+                Ok((i, ""))
+            } else {
+                Err(Err::Error((inp, ErrorKind::Eof)))
+            }
+        }
+//        Err(Err::Error((i, ErrorKind::Eof))) // Shouldn't happen.
+//            => Ok((i, &""[..])),
+        Err(x) => Err(x) // Some other error, not eof
+    }
+}
+fn end_of_line(inp: &str) -> IResult<&str, Syntax> {
+    map(
+        tuple((
+            space0,
+            opt(comment),
+            alt((line_ending, eoff))
+        )),
+        |_| Syntax::Newline
+    )(inp)
+}
+fn comment(inp: &str) -> IResult<&str, &str> {
+    map(
+        terminated(
+            character('#'),
+            not_line_ending
+        ),
+        |_| "" // Must be thrown away.
+    )(inp)
+}
+
+fn parse_syntax_token(input: &str) -> IResult<&str, Syntax> {
+    fn string_literal(inp: &str) -> IResult<&str, Syntax> {
+        map(
+            delimited(
+                tag("\""),
+                is_not("\""),
+                tag("\""),
+            ),
+            |inner: &str| {
+                Syntax::Literal(inner.to_string())
+            }
+        )(inp)
+    }
+    fn metavariable(inp: &str) -> IResult<&str, Syntax> {
+        // Metavariables are matched as whole words so that, e.g., `name` is not read as `n`
+        // followed by `ame`.
+        map(
+            alt((
+                tag_no_case("-n"),
+                tag(","),
+                recognize(pair(alpha1, alphanumeric0))
+            )),
+            syntax_to_enum
+        )(inp)
+    }
+    fn cons(inp: &str) -> IResult<&str, Syntax> {
+        map(
+            pair(
+                metavariable,
+                delimited(
+                    character('['),
+                    separated_list(terminated(character(','), space0), parse_syntax_token),
+                    character(']')
+                )
+            ),
+            | (head, tail) | {
+                Syntax::Cons(Box::from(head), tail)
+            }
+        )(inp)
+    }
+    fn number(inp: &str) -> IResult<&str, Syntax> {
+        map(float,
+            |n| {Syntax::Number(n as i32)}
+        )(inp)
+    }
+    /// A parenthesized group: alternatives, `("a"|"b" expr1)`, or elements followed by `?`, `+`,
+    /// or `*`. A repeated group of exactly two elements, like `(expr2 ",")*`, repeats the first
+    /// delimited by the second.
+    fn group(inp: &str) -> IResult<&str, Syntax> {
+        let (rest, (mut alternatives, suffix)) =
+            pair(
+                delimited(
+                    character('('),
+                    separated_nonempty_list(
+                        preceded(space0, character('|')),
+                        many1(parse_syntax_token)
+                    ),
+                    preceded(space0, character(')'))
+                ),
+                opt(alt((character('?'), character('+'), character('*'))))
+            )(inp)?;
+
+        let sequence = |mut list: Vec<Syntax>| {
+            match list.len() {
+                1 => list.pop().unwrap(),
+                _ => Syntax::Sequence(list)
+            }
+        };
+        let repeat = |suffix: char, inner: Syntax| {
+            let inner = Box::new(inner);
+            match suffix {
+                '?' => Syntax::Optional(inner),
+                '+' => Syntax::OnePlus(inner),
+                _ => Syntax::ZeroPlus(inner)
+            }
+        };
+
+        let syntax =
+            match (alternatives.len(), suffix) {
+                (1, None) if alternatives[0].len() == 1 => Syntax::Alternative(alternatives.pop().unwrap()),
+                // Elements are only grouped to be repeated or made optional.
+                (1, None) => return Err(Err::Error((inp, ErrorKind::Alt))),
+                (1, Some(suffix)) if suffix != '?' && alternatives[0].len() == 2 => {
+                    let mut elements = alternatives.pop().unwrap();
+                    let delimiter = Box::new(elements.pop().unwrap());
+                    let inner = Box::new(elements.pop().unwrap());
+                    match suffix {
+                        '+' => Syntax::OnePlusDelim(inner, delimiter),
+                        _ => Syntax::ZeroPlusDelim(inner, delimiter)
+                    }
+                }
+                (1, Some(suffix)) => repeat(suffix, sequence(alternatives.pop().unwrap())),
+                (_, None) => Syntax::Alternative(alternatives.into_iter().map(sequence).collect()),
+                (_, Some(suffix)) => {
+                    repeat(suffix, Syntax::Alternative(alternatives.into_iter().map(sequence).collect()))
+                }
+            };
+        Ok((rest, syntax))
+    }
+    fn named_char(inp: &str) -> IResult<&str, Syntax>{
+        map(
+            delimited(
+                tag("\\["),
+                alpha1,
+                character(']')
+            ),
+            |inner| Syntax::NamedChar(String::from(inner))
+        )(inp)
+    }
+
+    // Things we skip:
+    fn skip(inp: &str) -> IResult<&str, Syntax> {
+        preceded(
+            space1,
+            parse_syntax_token
+        )(inp)
+    }
+
+    let depth = NESTING.with(|n| n.replace(n.get() + 1));
+    let result =
+        match depth < MAX_NESTING {
+            true => alt((
+                cons,
+                metavariable,
+                string_literal,
+                named_char,
+                number,
+                group,
+                skip,
+            ))(input),
+            false => Err(Err::Failure((input, ErrorKind::TooLarge)))
+        };
+    NESTING.with(|n| n.set(depth));
+    result
+}
+
+fn parse_syntax(input: &str) -> IResult<&str, Syntax> {
+    map(
+        terminated(
+            many1(parse_syntax_token),
+            end_of_line
+        ),
+        |mut inner| {
+            if inner.len() == 1{
+                inner.pop().unwrap()
+            } else {
+                Syntax::Sequence(inner)
+            }
+        }
+    )(input)
+}
+
+/// Why and where parsing an entry or a `defaults:` block stopped. `parse_grammar` turns it into a
+/// `GrammarError` once it knows where `input` lies in the file.
+#[derive(Debug, PartialEq)]
+pub struct Failure<'a> {
+    /// The input at the point of failure.
+    pub input: &'a str,
+    pub entry: Option<String>,
+    pub field: Option<FieldType>,
+    pub message: String,
+}
+
+impl<'a> Failure<'a> {
+    pub(crate) fn new(input: &'a str, field: Option<FieldType>, message: &str) -> Failure<'a> {
+        Failure {
+            input,
+            entry: None,
+            field,
+            message: message.to_string(),
+        }
+    }
+
+    /// Converts a nom error, which records the input at which it occurred.
+    pub(crate) fn from_nom(error: Err<(&'a str, ErrorKind)>, fallback: &'a str,
+                           field: Option<FieldType>, message: &str) -> Failure<'a>
+    {
+        let input = match error {
+            Err::Error((input, _)) | Err::Failure((input, _)) => input,
+            Err::Incomplete(_) => fallback,
+        };
+        Failure::new(input, field, message)
+    }
+}
+
+/// Skips blank lines and comments.
+fn skip_blank(input: &str) -> &str {
+    match many0_count(alt((multispace1, comment)))(input) {
+        Ok((rest, _)) => rest,
+        Err(_) => input,
+    }
+}
+
+const SYNTAX_EXPECTED: &str = "expected a syntax element or the end of the line";
+
+/// Parses the fields following a `name:` or `defaults:` line into `op_record`, stopping at the
+/// end of the input or at the next `name:` or `defaults:` line.
+fn parse_fields(input: &str, mut op_record: OpRecord) -> Result<(&str, OpRecord), Failure<'_>>{
+    let mut prior_rest: &str = input; // Remaining input of prior iteration over fields
+    let mut rest: &str = input; // Remaining input
+    let mut field: FieldType;
+
+    // Keep fetching fields until we either hit eof, a name field, or an error.
+    loop {
+        if eoff(rest).is_ok() || include_directive(rest).is_ok() {
+            return Ok((rest, op_record));
+        }
+        match parse_field_name(rest) {
+            Ok((r, f)) => {
+                rest = r;
+                field = f;
+            },
+            Err(_) => {
+                return Err(Failure::new(
+                    skip_blank(rest), None, "expected a field name followed by `:`"
+                ));
+            }
+        }
+
+        match field {
+            FieldType::Name
+            | FieldType::Defaults => {
+                // Put the name back on the input
+                return Ok((prior_rest, op_record));
+            }
+            _ => rest = parse_field(rest, prior_rest, field, &mut op_record)?,
+        }
+        prior_rest = rest;
+    }
+    // Unreachable.
+}
+
+/// Parses the value of `field` from `input`, which follows the field name, through the end of the
+/// line and stores it in `op_record`. The field name begins at `field_start`. Name and defaults
+/// fields have no value of this kind and are not handled here.
+pub(crate) fn parse_field<'a>(input: &'a str, field_start: &'a str, field: FieldType,
+                              op_record: &mut OpRecord) -> Result<&'a str, Failure<'a>>
+{
+    let parsed =
+        match field {
+            FieldType::Associativity => {
+                parse_associativity(input)
+                    .map(|(r, a)| { op_record.associativity = a; r })
+                    .map_err(|e| (e, "expected `left`, `right`, `full`, or `non`"))
+            }
+            FieldType::Precedence => {
+                parse_number(input)
+                    .map(|(r, a)| { op_record.precedence = a; r })
+                    .map_err(|e| (e, "expected a precedence, which is a whole number"))
+            }
+            FieldType::Meaningful => {
+                parse_bool_option(input)
+                    .map(|(r, a)| { op_record.meaningful = a; r })
+                    .map_err(|e| (e, "expected `true` or `false`"))
+            }
+            FieldType::Syntax => {
+                parse_syntax(input)
+                    .map(|(r, a)| { op_record.syntax = a; r })
+                    .map_err(|e| (e, SYNTAX_EXPECTED))
+            }
+            FieldType::Parse => {
+                parse_syntax(input)
+                    .map(|(r, a)| { op_record.parse = a; r })
+                    .map_err(|e| (e, SYNTAX_EXPECTED))
+            }
+            FieldType::Fullform => {
+                parse_syntax(input)
+                    .map(|(r, a)| { op_record.fullform = a; r })
+                    .map_err(|e| (e, SYNTAX_EXPECTED))
+            }
+            FieldType::Latex => {
+                parse_syntax(input)
+                    .map(|(r, a)| { op_record.latex = a; r })
+                    .map_err(|e| (e, SYNTAX_EXPECTED))
+            }
+            FieldType::Mathml => {
+                parse_syntax(input)
+                    .map(|(r, a)| { op_record.mathml = a; r })
+                    .map_err(|e| (e, SYNTAX_EXPECTED))
+            }
+            FieldType::Name
+            | FieldType::Defaults
+            | FieldType::Error => {
+                let name_start = skip_blank(field_start);
+                let name = name_start.split(':').next().unwrap_or("");
+                return Err(Failure::new(
+                    name_start,
+                    None,
+                    &format!("unknown field `{}`, expected one of `name`, `defaults`, \
+                              `associativity`, `precedence`, `meaningful`, `syntax`, `parse`, \
+                              `fullform`, `latex`, or `mathml`", name)
+                ));
+            }
+        };
+
+    parsed.map_err(|(e, message)| {
+        let message = if too_deep(&e) { "the syntax is nested too deeply" } else { message };
+        let mut failure = Failure::from_nom(e, input, Some(field), message);
+        // A bad single-word value, e.g. `lefty`, is wrong as a whole, so point at its start
+        // rather than at the first character that failed to match.
+        if !matches!(field, FieldType::Syntax | FieldType::Parse | FieldType::Fullform | FieldType::Latex | FieldType::Mathml) {
+            failure.input = input.trim_start_matches([' ', '\t']);
+        }
+        failure
+    })
+}
+
+/// Parses an entry, filling in the fields it does not give with the built-in defaults.
+pub fn parse_entry(input: &str) -> Result<(&str, OpRecord), Failure<'_>>{
+    parse_entry_with_defaults(input, &OpRecord::default())
+}
+
+/// Parses an entry, filling in the fields it does not give from `defaults`.
+pub fn parse_entry_with_defaults<'a>(input: &'a str, defaults: &OpRecord)
+    -> Result<(&'a str, OpRecord), Failure<'a>>
+{
+    let (rest, name) =
+        parse_op_name(input).map_err(|e| {
+            let message =
+                if parse_field_name(input).is_ok_and(|(_, f)| f == FieldType::Name) {
+                    "expected an entry name, which is a word, followed by the end of the line"
+                } else {
+                    "expected `name:` or `defaults:` to begin an entry"
+                };
+            Failure::from_nom(e, input, Some(FieldType::Name), message)
+        })?;
+    let op_record = OpRecord{
+        name: String::from(name),
+        ..defaults.clone()
+    };
+
+    let (rest, mut op_record) =
+        parse_fields(rest, op_record).map_err(|mut failure| {
+            failure.entry = Some(name.to_string());
+            failure
+        })?;
+    op_record.syntax = op_record.syntax.with_op(name);
+    Ok((rest, op_record))
+}
+
+/// Parses a `defaults:` block, the fields of which override the corresponding fields of
+/// `defaults` for every entry that follows it in the file. A `name:` field is not allowed.
+pub fn parse_defaults<'a>(input: &'a str, defaults: &OpRecord)
+    -> Result<(&'a str, OpRecord), Failure<'a>>
+{
+    let (rest, _) =
+        defaults_header(input).map_err(|e| {
+            Failure::from_nom(e, input, Some(FieldType::Defaults), "expected `defaults:` alone on a line")
+        })?;
+    parse_fields(rest, defaults.clone())
+}
+
+pub(crate) fn defaults_header(input: &str) -> IResult<&str, Syntax> {
+    preceded(
+        delimited(
+            many0_count(alt((multispace1, comment))),
+            tag_no_case("defaults:"),
+            space0
+        ),
+        end_of_line
+    )(input)
+}
+
+/// Converts a `Failure` within `contents` into a `GrammarError`.
+pub(crate) fn grammar_error(contents: &str, failure: Failure) -> GrammarError {
+    let offset = contents.len() - failure.input.len();
+    let mut error = GrammarError::new(&failure.message).at(Location::of(contents, offset));
+    error.entry = failure.entry;
+    error.field = failure.field;
+    error
+}
+
+/// Parses an `include "file"` (or `import "file"`) directive, returning the file name.
+pub(crate) fn include_directive(input: &str) -> IResult<&str, &str> {
+    delimited(
+        tuple((
+            many0_count(alt((multispace1, comment))),
+            alt((tag_no_case("include"), tag_no_case("import"))),
+            space1
+        )),
+        delimited(tag("\""), is_not("\"\n"), tag("\"")),
+        end_of_line
+    )(input)
+}
+
+/// Reads grammar definitions, following `include` directives, into one list of records.
+///
+/// An included file is read as if its entries appeared in place of the directive, except that
+/// `defaults:` blocks only apply to the file they appear in. A file is read only once, however
+/// many times it is included, and a file that includes itself, directly or not, is an error.
+///
+/// An entry with the same name as an entry from another file overrides it: the new record takes
+/// the place of the old one. Two entries with the same name in the same file are an error.
+#[derive(Default)]
+struct Loader {
+    /// The files being read, innermost last.
+    stack: Vec<PathBuf>,
+    /// Every file read so far.
+    included: HashSet<PathBuf>,
+    records: Vec<OpRecord>,
+}
+
+impl Loader {
+    fn load_file(&mut self, filename: &str) -> Result<(), GrammarError> {
+        let contents: String =
+            fs::read_to_string(filename).map_err(|e| {
+                GrammarError::new(&format!("could not read the file: {}", e)).in_file(filename)
+            })?;
+        let path = fs::canonicalize(filename).unwrap_or_else(|_| PathBuf::from(filename));
+
+        self.included.insert(path.clone());
+        self.stack.push(path);
+        let result = self.load(&contents, Some(filename));
+        self.stack.pop();
+
+        result.map_err(|e| if e.file.is_none() { e.in_file(filename) } else { e })
+    }
+
+    /// Reads the grammar definition `contents`, which was read from `file`, if any.
+    fn load(&mut self, contents: &str, file: Option<&str>) -> Result<(), GrammarError> {
+        let mut defaults = OpRecord::default();
+        let mut rest = contents;
+
+        while eoff(rest).is_err() {
+            let start = skip_blank(rest);
+            let location = || Location::of(contents, contents.len() - start.len());
+
+            if let Ok((r, included)) = include_directive(rest) {
+                self.include(included, file).map_err(|e| {
+                    if e.file.is_none() { e.at(location()) } else { e }
+                })?;
+                rest = r;
+                continue;
+            }
+
+            if defaults_header(rest).is_ok() {
+                let (r, d) = parse_defaults(rest, &defaults).map_err(|f| grammar_error(contents, f))?;
+                rest = r;
+                defaults = d;
+                continue;
+            }
+
+            let (r, mut entry) =
+                parse_entry_with_defaults(rest, &defaults).map_err(|f| grammar_error(contents, f))?;
+            entry.provenance = Some(Provenance {
+                file: file.map(String::from),
+                line: location().line,
+            });
+            self.define(entry).map_err(|e| e.at(location()))?;
+            rest = r;
+        }
+
+        Ok(())
+    }
+
+    /// Reads the file named by an `include` directive in `file`. A relative name is relative to
+    /// the directory of `file`.
+    fn include(&mut self, included: &str, file: Option<&str>) -> Result<(), GrammarError> {
+        let directory = file.and_then(|f| Path::new(f).parent()).unwrap_or(Path::new(""));
+        let included_path = directory.join(included);
+        let filename = included_path.to_string_lossy().to_string();
+        let path = fs::canonicalize(&included_path).unwrap_or_else(|_| included_path.clone());
+
+        if self.stack.contains(&path) {
+            let cycle: Vec<String> =
+                self.stack
+                    .iter()
+                    .skip_while(|p| **p != path)
+                    .chain(std::iter::once(&path))
+                    .map(|p| p.to_string_lossy().to_string())
+                    .collect();
+            return Err(GrammarError::new(
+                &format!("the file includes itself: {}", cycle.join(" includes "))
+            ));
+        }
+        if self.included.contains(&path) {
+            return Ok(());
+        }
+        if let Err(e) = fs::metadata(&included_path) {
+            return Err(GrammarError::new(&format!("could not read the included file {}: {}", filename, e)));
+        }
+
+        self.load_file(&filename)
+    }
+
+    /// Adds a record, overriding any record of the same name from another file.
+    fn define(&mut self, record: OpRecord) -> Result<(), GrammarError> {
+        match self.records.iter_mut().find(|r| r.name == record.name) {
+            Some(previous) => {
+                let file = |r: &OpRecord| r.provenance.as_ref().and_then(|p| p.file.clone());
+                if file(previous) == file(&record) {
+                    let first = previous.provenance.as_ref().map_or(0, |p| p.line);
+                    return Err(
+                        GrammarError::new(&format!(
+                            "the entry `{}` is already defined in this file on line {}",
+                            record.name, first
+                        ))
+                        .in_entry(&record.name)
+                        .in_field(FieldType::Name)
+                    );
+                }
+                *previous = record;
+            }
+            None => self.records.push(record),
+        }
+        Ok(())
+    }
+
+    /// The records read, if there are any.
+    fn finish(self) -> Result<Vec<OpRecord>, GrammarError> {
+        if self.records.is_empty() {
+            return Err(GrammarError::new("the grammar definition has no entries"));
+        }
+        Ok(self.records)
+    }
+}
+
+/// Parses the contents of a grammar definition. Every record is complete: any field an entry
+/// does not give is taken from the `defaults:` blocks preceding it or else the built-in defaults,
+/// and every entry must end up with a syntax. Included files are relative to the current
+/// directory.
+pub fn parse_grammar(contents: &str) -> Result<Vec<OpRecord>, GrammarError>{
+    let mut loader = Loader::default();
+    loader.load(contents, None)?;
+    loader.finish()
+}
+
+/// Reads and parses a grammar definition file and every file it includes, merging their entries.
+/// Errors name the file they occur in, and each record's provenance names the file and line it
+/// was defined at.
+pub fn parse_grammar_file(filename: &str) -> Result<Vec<OpRecord>, GrammarError>{
+    let mut loader = Loader::default();
+    loader.load_file(filename)?;
+    loader.finish().map_err(|e| e.in_file(filename))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operator::MAX_PRECEDENCE;
+
+    #[test]
+    fn parse_field_name_test() {
+        // Test parse_field:
+        let result = parse_field_name("name:\tMultiplyMatrix\n");
+
+        assert_eq!(result, Ok(("\tMultiplyMatrix\n", FieldType::Name) ));
+    }
+
+    #[test]
+    fn parse_op_name_test1() {
+        // Test parse_field:
+        let result = parse_op_name("name:\tMultiplyMatrix\n");
+
+        assert_eq!(result, Ok(("", "MultiplyMatrix") ));
+    }
+
+    #[test]
+    fn parse_op_name_test2() {
+        // Test parse_op_name:
+        let result = parse_op_name("NAME: Divide\n");
+
+        assert_eq!(result, Ok(("", "Divide") ));
+    }
+
+    #[test]
+    fn parse_syntax_test1() {
+        // Test parse_syntax:
+        let test_input = "expr1\"[[\"(expr2\",\")*\"]]\"\n";
+        let (rest, _) = parse_syntax(test_input).unwrap();
+        assert_eq!(rest, "");
+    }
+
+    #[test]
+    fn parse_syntax_test2() {
+        // Test parse_syntax:
+        let test_input = "SubsuperscriptBox[expr1,expr2,expr3]\n";
+        let (rest, _) = parse_syntax(test_input).unwrap();
+        assert_eq!(rest, "");
+    }
+
+    #[test]
+    fn parse_syntax_test3() {
+        // Test parse_syntax:
+        let test_input = " expr1 \"\\^\" expr2 \"\\%\" expr3";
+        let (rest, _) = parse_syntax(test_input).unwrap();
+        assert_eq!(rest, "");
+    }
+
+    #[test]
+    fn parse_repetition_test() {
+        let (_, syntax) = parse_syntax("(\"%\")+\n").unwrap();
+        assert_eq!(syntax, Syntax::OnePlus(Box::new(Syntax::Literal("%".to_string()))));
+
+        // `n` is a metavariable only as a whole word.
+        let (_, template) = parse_syntax("Out[Minus[n], name]\n").unwrap();
+        assert_eq!(
+            template,
+            Syntax::Cons(
+                Box::new(Syntax::Word("Out".to_string())),
+                vec![
+                    Syntax::Cons(Box::new(Syntax::Word("Minus".to_string())), vec![Syntax::N]),
+                    Syntax::Word("name".to_string())
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn parse_group_test() {
+        let literal = |text: &str| Syntax::Literal(text.to_string());
+
+        let (_, syntax) = parse_syntax("expr1 (\"[\" expr2 \"]\")?\n").unwrap();
+        assert_eq!(
+            syntax,
+            Syntax::Sequence(vec![
+                Syntax::Expr1,
+                Syntax::Optional(Box::new(Syntax::Sequence(vec![literal("["), Syntax::Expr2, literal("]")])))
+            ])
+        );
+
+        let (_, syntax) = parse_syntax("(\"a\" expr1|\"b\")\n").unwrap();
+        assert_eq!(
+            syntax,
+            Syntax::Alternative(vec![Syntax::Sequence(vec![literal("a"), Syntax::Expr1]), literal("b")])
+        );
+
+        let (_, syntax) = parse_syntax("(expr2 \";\")*\n").unwrap();
+        assert_eq!(syntax, Syntax::ZeroPlusDelim(Box::new(Syntax::Expr2), Box::new(literal(";"))));
+    }
+
+    #[test]
+    fn parse_nesting_limit_test() {
+        let nested = |depth: usize| format!("{}expr1{}\n", "(".repeat(depth), ")?".repeat(depth));
+        assert!(parse_syntax(&nested(MAX_NESTING - 1)).is_ok());
+
+        let error = parse_grammar(&format!("name: Deep\nsyntax: {}", nested(100_000))).unwrap_err();
+        assert_eq!(error.message, "the syntax is nested too deeply");
+    }
+
+    #[test]
+    fn parse_named_char_test() {
+        let (rest, syntax) = parse_syntax("expr1 \\[Element] expr2\n").unwrap();
+
+        assert_eq!(rest, "");
+        assert_eq!(
+            syntax,
+            Syntax::Sequence(vec![
+                Syntax::Expr1,
+                Syntax::NamedChar("Element".to_string()),
+                Syntax::Expr2
+            ])
+        );
+    }
+
+    #[test]
+    fn parse_entry_test() {
+        let test_input = r#"name: SubsuperscriptBox
+            associativity: right
+            meaningful: true
+            syntax: expr1 "\^" expr2 "\%" expr3
+            parse: SubsuperscriptBox[expr1, expr2, expr3]"#;
+        let (rest, _) = parse_entry(test_input).unwrap();
+        assert_eq!(rest, "");
+    }
+
+    #[test]
+    fn parse_precedence_and_full_test() {
+        let test_input = "name: Plus\nassociativity: full\nprecedence: 30\nsyntax: expr1 \"+\" expr2\n";
+        let (rest, record) = parse_entry(test_input).unwrap();
+
+        assert_eq!(rest, "");
+        assert_eq!(record.precedence, 30);
+        assert_eq!(record.associativity, Associativity::Full);
+    }
+
+    #[test]
+    fn parse_defaults_test() {
+        let test_input = r#"
+            # Fields not given by an entry come from the defaults.
+            name: Times
+            syntax: expr1 "*" expr2
+
+            defaults:
+            associativity: right
+            precedence: 10
+            meaningful: true
+
+            name: Power
+            syntax: expr1 "^" expr2
+
+            name: Set
+            precedence: 110
+            syntax: expr1 "=" expr2
+            parse: Set[expr1, expr2]
+            "#;
+        let records = parse_grammar(test_input).unwrap();
+
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].associativity, Associativity::Left);
+        assert_eq!(records[0].precedence, MAX_PRECEDENCE);
+        assert!(!records[0].meaningful);
+        assert_eq!(records[1].associativity, Associativity::Right);
+        assert_eq!(records[1].precedence, 10);
+        assert!(records[1].meaningful);
+        assert_eq!(records[1].parse, Syntax::Empty);
+        assert_eq!(records[2].precedence, 110);
+        assert_eq!(records[2].associativity, Associativity::Right);
+    }
+
+    #[test]
+    fn grammar_error_test() {
+        let test_input = "name: Plus\nsyntax: expr1 \"+\" expr2\n\nname: Times\nmeaningful: maybe\n";
+        let error = parse_grammar(test_input).unwrap_err();
+
+        assert_eq!(error.entry, Some("Times".to_string()));
+        assert_eq!(error.field, Some(FieldType::Meaningful));
+        assert_eq!(
+            error.to_string(),
+            "error: expected `true` or `false`\n \
+             --> <grammar>:5:13\n  \
+             |\n\
+             5 | meaningful: maybe\n  \
+             |             ^\n  \
+             = in the `meaningful` field of the entry `Times`"
+        );
+
+        let test_input = "defaults:\nassociativity: left\ncolour: blue\n";
+        let error = parse_grammar(test_input).unwrap_err();
+        let location = error.location.unwrap();
+
+        assert_eq!(error.entry, None);
+        assert!(error.message.starts_with("unknown field `colour`"));
+        assert_eq!((location.line, location.column), (3, 1));
+
+        let test_input = "name: Plus\n  syntax: expr1 \"+ expr2\n";
+        let error = parse_grammar(test_input).unwrap_err();
+        let location = error.location.unwrap();
+
+        assert_eq!(error.field, Some(FieldType::Syntax));
+        assert_eq!((location.line, location.column), (2, 17));
+        assert_eq!(location.source_line, "  syntax: expr1 \"+ expr2");
+    }
+
+    #[test]
+    fn grammar_file_error_test() {
+        let error = parse_grammar_file("no/such/file.grammar").unwrap_err();
+
+        assert_eq!(error.file, Some("no/such/file.grammar".to_string()));
+        assert!(error.message.starts_with("could not read the file"));
+    }
+
+    /// A directory of grammar files written by a test, removed when the test is done with it.
+    struct GrammarFiles(PathBuf);
+
+    impl GrammarFiles {
+        fn join(&self, name: &str) -> PathBuf {
+            self.0.join(name)
+        }
+    }
+
+    impl Drop for GrammarFiles {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// Writes `files` into a fresh directory, returning the directory.
+    fn write_grammar_files(test: &str, files: &[(&str, &str)]) -> GrammarFiles {
+        let directory = std::env::temp_dir().join(format!("prattle-{}-{}", test, std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let directory = GrammarFiles(directory);
+        for (name, contents) in files {
+            fs::write(directory.join(name), contents).unwrap();
+        }
+        directory
+    }
+
+    #[test]
+    fn include_test() {
+        let directory = write_grammar_files("include", &[
+            ("core.grammar", "name: Plus\nsyntax: expr1 \"+\" expr2\n\nname: Times\nsyntax: expr1 \"*\" expr2\n"),
+            ("main.grammar", r#"
+                include "core.grammar"
+                import "core.grammar" # Already read, so this does nothing.
+
+                # Overrides Times from core.grammar
+                name: Times
+                precedence: 20
+                syntax: expr1 "*" expr2
+
+                name: Power
+                syntax: expr1 "^" expr2
+                "#),
+        ]);
+        let main = directory.join("main.grammar");
+        let records = parse_grammar_file(main.to_str().unwrap()).unwrap();
+
+        let names: Vec<&str> = records.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["Plus", "Times", "Power"]);
+        assert_eq!(records[1].precedence, 20);
+
+        let provenance = records[0].provenance.clone().unwrap();
+        assert!(provenance.file.unwrap().ends_with("core.grammar"));
+        assert_eq!(provenance.line, 1);
+        let provenance = records[1].provenance.clone().unwrap();
+        assert!(provenance.file.unwrap().ends_with("main.grammar"));
+        assert_eq!(provenance.line, 6);
+    }
+
+    #[test]
+    fn include_errors_test() {
+        let directory = write_grammar_files("include-errors", &[
+            ("a.grammar", "include \"b.grammar\"\nname: A\nsyntax: \"a\"\n"),
+            ("b.grammar", "name: B\nsyntax: \"b\"\ninclude \"a.grammar\"\n"),
+            ("missing.grammar", "name: M\nsyntax: \"m\"\n\ninclude \"nowhere.grammar\"\n"),
+            ("twice.grammar", "name: T\nsyntax: \"t\"\nname: T\nsyntax: \"u\"\n"),
+        ]);
+        let path = |name: &str| directory.join(name).to_str().unwrap().to_string();
+
+        let error = parse_grammar_file(&path("a.grammar")).unwrap_err();
+        assert!(error.message.starts_with("the file includes itself"), "{}", error);
+        assert!(error.file.unwrap().ends_with("b.grammar"));
+        assert_eq!(error.location.unwrap().line, 3);
+
+        let error = parse_grammar_file(&path("missing.grammar")).unwrap_err();
+        assert!(error.message.starts_with("could not read the included file"), "{}", error);
+        assert!(error.file.unwrap().ends_with("missing.grammar"));
+        assert_eq!(error.location.unwrap().line, 4);
+
+        let error = parse_grammar_file(&path("twice.grammar")).unwrap_err();
+        assert_eq!(error.message, "the entry `T` is already defined in this file on line 1");
+        assert_eq!(error.location.unwrap().line, 3);
+    }
+
+    #[test]
+    fn parse_missing_syntax_test() {
+        let test_input = "name: Divides\nassociativity: left\n";
+        let records = parse_grammar(test_input).unwrap();
+
+        assert_eq!(
+            records[0].syntax,
+            Syntax::Sequence(vec![Syntax::Expr1, Syntax::Word("Divides".to_string()), Syntax::Expr2])
+        );
+        assert_eq!(records[0].precedence, MAX_PRECEDENCE);
+
+        let test_input = "defaults:\nsyntax: op expr1\n\nname: Not\n\nname: Tilde\nsyntax: expr1 \"~\" expr2\n";
+        let records = parse_grammar(test_input).unwrap();
+
+        assert_eq!(records[0].syntax, Syntax::Sequence(vec![Syntax::Word("Not".to_string()), Syntax::Expr1]));
+        assert_eq!(records[1].name, "Tilde");
+        assert_eq!(records[1].syntax.tokens(), vec!["~".to_string()]);
+    }
+}
@@ -0,0 +1,17 @@
+pub mod syntax;
+#[allow(clippy::module_inception)]
+pub mod grammar;
+pub mod compile;
+pub mod error;
+pub mod format;
+pub mod codegen;
+pub mod export;
+pub mod diagram;
+
+pub use grammar::*;
+pub use compile::*;
+pub use error::GrammarError;
+pub use format::{format_grammar, format_record, format_syntax};
+pub use codegen::generate_parser;
+pub use export::{to_antlr, to_ebnf, to_tree_sitter};
+pub use diagram::{precedence_chart, railroad_diagram};
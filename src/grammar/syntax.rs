@@ -0,0 +1,396 @@
+#![allow(dead_code)]
+//! This module defines types for the grammar description DSL.
+
+use itertools::{join};
+use std::fmt;
+
+use crate::operator::MAX_PRECEDENCE;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum FieldType {
+    Name,
+    Defaults,
+    Associativity,
+    Precedence,
+    Meaningful,
+    Syntax,
+    Parse,
+    Fullform,
+    Latex,
+    Mathml,
+    Error,
+}
+
+/// Given a field name as a FieldName enum value, returns its &str representation.
+pub fn enum_to_field(name: FieldType) -> &'static str{
+    match name {
+        FieldType::Name => "name",
+        FieldType::Defaults => "defaults",
+        FieldType::Associativity => "associativity",
+        FieldType::Precedence => "precedence",
+        FieldType::Meaningful => "meaningful",
+        FieldType::Syntax => "syntax",
+        FieldType::Parse => "parse",
+        FieldType::Fullform => "fullform",
+        FieldType::Latex => "latex",
+        FieldType::Mathml => "mathml",
+        _ => "Error"
+    }
+}
+/// Given a field name as a &str string, returns a FieldName.
+pub fn field_to_enum(name: &str) -> FieldType {
+    match &name.to_ascii_lowercase()[..] {
+        "name" => FieldType::Name,
+        "defaults" => FieldType::Defaults,
+        "associativity" => FieldType::Associativity,
+        "precedence" => FieldType::Precedence,
+        "meaningful" => FieldType::Meaningful,
+        "syntax" => FieldType::Syntax,
+        "parse" => FieldType::Parse,
+        "fullform" => FieldType::Fullform,
+        "latex" => FieldType::Latex,
+        "mathml" => FieldType::Mathml,
+        _ => FieldType::Error,
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Associativity{
+    Left,
+    Right,
+    Non,
+    Full
+}
+
+pub fn assoc_to_enum(name: &str) ->Associativity{
+    match &name.to_ascii_lowercase()[..] {
+        "left" => Associativity::Left,
+        "right" => Associativity::Right,
+        "full" => Associativity::Full,
+        "non" => Associativity::Non,
+        _ => Associativity::Non,
+    }
+}
+
+pub fn enum_to_assoc(name: &Associativity) -> &str{
+    match name {
+        Associativity::Left => "Left" ,
+        Associativity::Right => "Right",
+        Associativity::Full => "Full",
+        Associativity::Non => "Non",
+    }
+}
+
+pub enum TokenDenotation{
+    Left,
+    Null,
+    Other
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub enum Syntax {
+    Expr1,
+    Expr2,
+    Expr3,
+    Expr4,
+    N,
+    NegN,
+    Symbol,
+    Comma,
+    NoSpace, // No whitespace may separate the neighboring elements.
+    Space,   // Whitespace must separate the neighboring elements.
+    Newline,
+    Word(String),
+    NamedChar(String),
+    Number(i32),
+    OnePlus(Box<Syntax>),
+    OnePlusDelim(Box<Syntax>, Box<Syntax>), // OnePlusDelim(inner, delim)
+    ZeroPlus(Box<Syntax>),
+    ZeroPlusDelim(Box<Syntax>, Box<Syntax>), // ZeroPlusDelim(inner, delim)
+    Optional(Box<Syntax>),
+    Literal(String),
+    Cons(Box<Syntax>, Vec<Syntax>),
+    SequenceDelim(Vec<Syntax>),
+    Sequence(Vec<Syntax>),
+    Alternative(Vec<Syntax>),
+    Empty // The absense of a grammar value.
+}
+
+impl Syntax {
+    /// Is this a metavariable standing for an operand (`expr1`..`expr4` or `symb`), possibly
+    /// optional?
+    pub fn is_operand(&self) -> bool {
+        match self {
+            Syntax::Expr1
+            | Syntax::Expr2
+            | Syntax::Expr3
+            | Syntax::Expr4
+            | Syntax::Symbol => true,
+            Syntax::Optional(inner) => inner.is_operand(),
+            _ => false,
+        }
+    }
+
+    /// Does this element of a syntax consume input? `NoSpace`, `Space`, `Newline`, and `Empty` do
+    /// not.
+    pub fn consumes_input(&self) -> bool {
+        !matches!(self, Syntax::NoSpace | Syntax::Space | Syntax::Newline | Syntax::Empty)
+    }
+
+    /// Is this a whitespace constraint on the neighboring elements (`NoSpace` or `Space`)?
+    pub fn is_spacing(&self) -> bool {
+        matches!(self, Syntax::NoSpace | Syntax::Space)
+    }
+
+    /// The text of the token this element matches, if it is a token. An unquoted word in a
+    /// syntax is a keyword token.
+    pub fn token_text(&self) -> Option<String> {
+        match self {
+            Syntax::Literal(text)
+            | Syntax::Word(text) => Some(text.clone()),
+            Syntax::NamedChar(name) => Some(format!("\\[{}]", name)),
+            Syntax::Comma => Some(",".to_string()),
+            _ => None,
+        }
+    }
+
+    /// The elements of a sequence. Any other syntax is a sequence of one element.
+    pub fn elements(&self) -> Vec<&Syntax> {
+        match self {
+            Syntax::Sequence(list)
+            | Syntax::SequenceDelim(list) => list.iter().collect(),
+            other => vec![other],
+        }
+    }
+
+    /// Calls `f` on this syntax and on every syntax nested within it, outermost first.
+    pub fn visit<F: FnMut(&Syntax)>(&self, f: &mut F) {
+        f(self);
+        match self {
+            Syntax::OnePlus(inner)
+            | Syntax::ZeroPlus(inner)
+            | Syntax::Optional(inner) => inner.visit(f),
+            Syntax::OnePlusDelim(inner, delim)
+            | Syntax::ZeroPlusDelim(inner, delim) => {
+                inner.visit(f);
+                delim.visit(f);
+            }
+            Syntax::Cons(head, tail) => {
+                head.visit(f);
+                for item in tail {
+                    item.visit(f);
+                }
+            }
+            Syntax::Sequence(list)
+            | Syntax::SequenceDelim(list)
+            | Syntax::Alternative(list) => {
+                for item in list {
+                    item.visit(f);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// The text of every token appearing anywhere in the syntax, without duplicates.
+    pub fn tokens(&self) -> Vec<String> {
+        let mut tokens: Vec<String> = Vec::new();
+        self.visit(&mut |s| {
+            if let Some(text) = s.token_text() {
+                if !tokens.contains(&text) {
+                    tokens.push(text);
+                }
+            }
+        });
+        tokens
+    }
+
+    /// The syntax with every unquoted word `op` replaced by the keyword token `token`. In a syntax,
+    /// `op` stands for the entry's name, as `name` does in a template.
+    pub fn with_op(&self, token: &str) -> Syntax {
+        let with_op = |list: &Vec<Syntax>| list.iter().map(|item| item.with_op(token)).collect();
+        match self {
+            Syntax::Word(word) if word == "op" => Syntax::Word(token.to_string()),
+            Syntax::OnePlus(inner) => Syntax::OnePlus(Box::new(inner.with_op(token))),
+            Syntax::ZeroPlus(inner) => Syntax::ZeroPlus(Box::new(inner.with_op(token))),
+            Syntax::Optional(inner) => Syntax::Optional(Box::new(inner.with_op(token))),
+            Syntax::OnePlusDelim(inner, delim) => {
+                Syntax::OnePlusDelim(Box::new(inner.with_op(token)), Box::new(delim.with_op(token)))
+            }
+            Syntax::ZeroPlusDelim(inner, delim) => {
+                Syntax::ZeroPlusDelim(Box::new(inner.with_op(token)), Box::new(delim.with_op(token)))
+            }
+            Syntax::Cons(head, tail) => Syntax::Cons(Box::new(head.with_op(token)), with_op(tail)),
+            Syntax::Sequence(list) => Syntax::Sequence(with_op(list)),
+            Syntax::SequenceDelim(list) => Syntax::SequenceDelim(with_op(list)),
+            Syntax::Alternative(list) => Syntax::Alternative(with_op(list)),
+            other => other.clone(),
+        }
+    }
+
+    /// The operand metavariables appearing anywhere in the syntax, without duplicates.
+    pub fn operands(&self) -> Vec<Syntax> {
+        let mut operands: Vec<Syntax> = Vec::new();
+        self.visit(&mut |s| {
+            if s.is_operand() && !matches!(s, Syntax::Optional(_)) && !operands.contains(s) {
+                operands.push(s.clone());
+            }
+        });
+        operands
+    }
+}
+
+// Display functions
+impl fmt::Display for Syntax{
+    fn fmt(&self, dest: &mut fmt::Formatter) -> fmt::Result {
+        let out = match self{
+            Syntax::Expr1 => "Expr1: expr1".to_string(),
+            Syntax::Expr2 => "Expr2: expr2".to_string(),
+            Syntax::Expr3 => "Expr3: expr3".to_string(),
+            Syntax::Expr4 => "Expr4: expr4".to_string(),
+            Syntax::N => "N: n".to_string(),
+            Syntax::NegN => "NegN: -n".to_string(),
+            Syntax::Symbol => "Symbol: symb".to_string(),
+            Syntax::Word(word) => format!("Word: {}", &word),
+            Syntax::Comma => ",".to_string(),
+            Syntax::NoSpace => "NoSpace: nospace".to_string(),
+            Syntax::Space => "Space: space".to_string(),
+            Syntax::Newline => "\n\tNewline\n".to_string(),
+            Syntax::Number(n) => format!("Number: {}",  &n),
+            Syntax::OnePlus(expr)
+                => format!("Plus: ({})+", &expr),
+            Syntax::OnePlusDelim(expr, delim)
+                => format!("Plus Delim: ({} {})+", &expr, &delim),
+            Syntax::ZeroPlus(expr)
+                => format!("Star: ({})*", &expr),
+            Syntax::ZeroPlusDelim(expr, delim)
+                => format!("Star Delim: ({} {})*", &expr, &delim),
+            Syntax::Optional(expr)
+                => format!("Opt: ({})?", &expr),
+            Syntax::Literal(expr)
+                => format!("Literal: \"{}\"", &expr),
+            Syntax::Cons(head, tail)
+                => format!("Cons: {}[{}]", head, join(tail, ", ")),
+            Syntax::SequenceDelim(list)
+                => format!("Sequence Delim: {}", join(list, ", ")),
+            Syntax::Sequence(list)
+                => format!("Sequence: {}", join(list, " ")),
+            Syntax::Alternative(list)
+                => format!("Alt: ({})", join(list, " | ")),
+            Syntax::NamedChar(name)
+                =>  format!("NamedChar: {}", name),
+            Syntax::Empty => "Empty".to_string(),
+        };
+        write!(dest, "<{} >", out)
+    }
+}
+impl fmt::Debug for Syntax{
+    fn fmt(&self, dest: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(dest, "{}", self)
+    }
+}
+
+pub fn syntax_to_enum(name: &str) -> Syntax{
+    match &name.to_lowercase()[..]{
+        "expr1" => Syntax::Expr1,
+        "expr2" => Syntax::Expr2,
+        "expr3" => Syntax::Expr3,
+        "expr4" => Syntax::Expr4,
+        "n" => Syntax::N,
+        "-n" => Syntax::NegN,
+        "symb" => Syntax::Symbol,
+        "," => Syntax::Comma,
+        "nospace" => Syntax::NoSpace,
+        "space" => Syntax::Space,
+        _ => Syntax::Word(String::from(name)),
+    }
+}
+
+/// Where an entry was defined.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Provenance {
+    /// The grammar definition file, or `None` for a grammar that was not read from a file.
+    pub file: Option<String>,
+    /// The line of the entry's `name:` field, starting at 1.
+    pub line: usize,
+}
+
+impl fmt::Display for Provenance {
+    fn fmt(&self, dest: &mut fmt::Formatter) -> fmt::Result {
+        write!(dest, "{}:{}", self.file.as_deref().unwrap_or("<grammar>"), self.line)
+    }
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct OpRecord {
+    pub name: String,
+    pub associativity: Associativity,
+    pub precedence: u32,
+    pub meaningful: bool,
+    pub syntax: Syntax,
+    pub parse: Syntax,
+    pub fullform: Syntax,
+    /// The typeset forms of the operator. Empty templates stand for the defaults `typeset` derives
+    /// from the syntax.
+    pub latex: Syntax,
+    pub mathml: Syntax,
+    /// Where the entry was defined, if it was read from a grammar definition.
+    pub provenance: Option<Provenance>
+}
+
+/// The built-in defaults, which a `defaults:` block in a grammar file overrides. The default
+/// precedence, `MAX_PRECEDENCE`, binds loosest, and the default syntax is the infix operator
+/// `expr1 op expr2`, whose token is the entry's name. An empty `parse` template means
+/// `name[operands]`, an empty `fullform` template means the same as `parse`, and empty `latex` and
+/// `mathml` templates mean the default typeset forms.
+impl Default for OpRecord {
+    fn default() -> OpRecord {
+        OpRecord {
+            name: String::new(),
+            associativity: Associativity::Left,
+            precedence: MAX_PRECEDENCE,
+            meaningful: false,
+            syntax: Syntax::Sequence(vec![Syntax::Expr1, Syntax::Word("op".to_string()), Syntax::Expr2]),
+            parse: Syntax::Empty,
+            fullform: Syntax::Empty,
+            latex: Syntax::Empty,
+            mathml: Syntax::Empty,
+            provenance: None
+        }
+    }
+}
+
+impl fmt::Display for OpRecord {
+    fn fmt(&self, dest: &mut fmt::Formatter) -> fmt::Result {
+        let mut out = String::from("OpRecord{");
+        out.push_str("\n\tname: ");
+        out.push_str(&self.name);
+        out.push_str("\n\tassociativity: ");
+        out.push_str(enum_to_assoc(&self.associativity));
+        out.push_str("\n\tprecedence: ");
+        out.push_str(&format!("{}", self.precedence));
+        out.push_str("\n\tmeaningful: ");
+        out.push_str(&format!("{}", self.meaningful));
+        out.push_str("\n\tgrammar: ");
+        out.push_str(&format!("{}", &self.syntax));
+        out.push_str("\n\tparse: ");
+        out.push_str(&format!("{}", &self.parse));
+        out.push_str("\n\tfullform: ");
+        out.push_str(&format!("{}", &self.fullform));
+        out.push_str("\n\tlatex: ");
+        out.push_str(&format!("{}", &self.latex));
+        out.push_str("\n\tmathml: ");
+        out.push_str(&format!("{}", &self.mathml));
+        if let Some(provenance) = &self.provenance {
+            out.push_str("\n\tdefined at: ");
+            out.push_str(&provenance.to_string());
+        }
+        out.push_str("\n}");
+        write!(dest, "<{} >", out)
+    }
+}
+impl fmt::Debug for OpRecord{
+    fn fmt(&self, dest: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(dest, "{}", self)
+    }
+}
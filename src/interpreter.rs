@@ -5,7 +5,6 @@ This contains the internal infrastructure needed to interpret code, including a
  */
 
 
-use std::collections::HashMap;
 use crate::ASTNode;
 use crate::symbol_table::SymbolTable;
 
@@ -14,9 +13,9 @@ pub trait Evaluatable {
   fn evaluate() -> ASTNode;
 }
 
-pub struct RuntimeContext<'a> {
-  symbols: SymbolTable<'a>,
-  root: ASTNode<'a>
+pub struct RuntimeContext {
+  pub symbols: SymbolTable,
+  pub root: ASTNode
 }
 
 
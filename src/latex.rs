@@ -0,0 +1,86 @@
+/*!
+
+Parses LaTeX math, e.g. `\frac{a + b}{2} \cdot x^{2}`, into the same trees as the native syntax.
+
+There is no separate LaTeX parser. The preset operator table in `resources/latex.grammar` describes
+LaTeX's notation with ordinary operators, mixfix for `\frac{..}{..}`, `\sqrt[..]{..}`, and
+`\sum_{i=..}^{..}`, and matchfix for `{..}`, `\left( .. \right)`, and `\left| .. \right|`; and the
+lexer, in `Mode::Latex`, reads control words, spacing commands, and Greek letters. The operators
+that `resources/operators.grammar` also has, like `Power` and `Times`, keep its names and
+precedences, and the LaTeX spellings of them, like `\cdot` and `\frac`, build the same nodes:
+
+```text
+\frac{a}{b} \cdot x^{2}    Times[Divide[a, b], Power[x, 2]]
+\sqrt[3]{x}                Sqrt[x, 3]
+\sum_{i=1}^{n} i^2         Sum[Power[i, 2], List[i, 1, n]]
+```
+
+Only explicit operators are understood. LaTeX writes products by juxtaposition, `2x` or `ab`, and
+applies functions without brackets, `\sin x`; neither parses, and a run of letters is a single
+symbol, as in the native syntax.
+
+*/
+
+use crate::grammar::{compile_records, parse_grammar};
+use crate::lexer::Mode;
+use crate::operator::OperatorTable;
+use crate::parser::Parser;
+
+const LATEX_GRAMMAR: &str = include_str!("../resources/latex.grammar");
+
+/// The preset operator table for LaTeX math.
+pub fn latex_table() -> OperatorTable {
+  compile_records(&parse_grammar(LATEX_GRAMMAR).unwrap()).unwrap()
+}
+
+/// A parser for LaTeX math: the preset table with the lexer in `Mode::Latex`.
+pub fn latex_parser() -> Parser {
+  let mut parser = Parser::new(latex_table());
+  parser.lexer_mode = Mode::Latex;
+  parser
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::grammar::compile_grammar_file;
+
+  fn parse_latex(text: &str) -> String {
+    latex_parser().parse(text).unwrap().to_string()
+  }
+
+  #[test]
+  fn same_trees_as_native_syntax() {
+    let mut native = Parser::new(compile_grammar_file("resources/operators.grammar").unwrap());
+    let mut latex = latex_parser();
+
+    for (latex_text, native_text) in [
+      ("\\frac{a + b}{2} \\cdot x^{2}", "(a + b)/2 * x^2"),
+      ("x^{y^z} - \\left( a \\times b \\times c \\right)", "x^y^z - (a * b * c)"),
+      ("y = \\frac{1}{x} \\div \\left[ 2 + z \\right]", "y = 1/x / (2 + z)"),
+      ("{a+b}^2", "(a + b)^2"),
+    ] {
+      assert_eq!(
+        latex.parse(latex_text).unwrap().to_string(),
+        native.parse(native_text).unwrap().to_string(),
+        "{}", latex_text
+      );
+    }
+  }
+
+  #[test]
+  fn latex_forms() {
+    assert_eq!(parse_latex("\\sqrt{x + 1}"), "Sqrt[Plus[x, 1]]");
+    assert_eq!(parse_latex("\\sqrt[3]{x}"), "Sqrt[x, 3]");
+    assert_eq!(
+      parse_latex("\\sum_{i=1}^{n} i^2 + 1"),
+      "Plus[Sum[Power[i, 2], List[i, 1, n]], 1]"
+    );
+    assert_eq!(
+      parse_latex("\\left| x_{1} - x_2 \\right|"),
+      "Abs[Minus[Subscript[x, 1], Subscript[x, 2]]]"
+    );
+    assert_eq!(parse_latex("\\alpha\\,\\cdot\\quad\\Gamma^\\infty"), "Times[α, Power[Γ, Infinity]]");
+  }
+}
@@ -2,4 +2,636 @@
 
 A minimal lexer that holds the state of the parsing process.
 
+The lexer knows nothing about the grammar except the set of operator tokens (sigils) it should
+recognize, which the parser collects from its operator table. Everything else is one of a handful of
+atoms: symbols (identifiers), numbers, and strings. Operator tokens are matched greedily, so if
+both `[` and `[[` are tokens, `[[` is lexed as a single token.
+
+A named character like `\[Alpha]` reads exactly as the character it names, `α`, wherever it
+appears: in symbols, operator tokens, and strings. See the `named_characters` module.
+
+Text between backquotes is an atom that the other forms cannot spell, which is how FullForm writes
+one: a number if the text is one as written, `` `-3` ``, `` `+inf.0` ``, `` `-inf.0` ``, or
+`` `+nan.0` ``, and otherwise a symbol with the text as its name, `` `a b` ``. A backslash escapes the
+next character, so `` `\-3` `` is the symbol `-3`.
+
+Whitespace separates tokens and is otherwise discarded, but each token records whether whitespace
+precedes and follows it so that operators can require or forbid whitespace between their parts,
+e.g. `x___` versus `x ___`.
+
+In `Mode::Latex` the lexer reads LaTeX math instead. A control word like `\frac` is a single token,
+and `\left` and `\right` take the delimiter that follows them, so `\left(` is one token. A control
+word naming a Greek letter, e.g. `\alpha` or `\Gamma`, is the symbol `α` or `Γ`, and `\infty` is
+the symbol `Infinity`. The spacing commands `\,`, `\:`, `\;`, `\!`, `\ `, `\quad`, and `\qquad`
+are whitespace. As in TeX, a number right after `^` or `_` is a single digit, so `x^23` is `x^{2}3`.
+
 */
+#![allow(dead_code)]
+
+use crate::errors::ParseError;
+use crate::named_characters::NamedCharacters;
+
+/// A range of byte offsets into the source text.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct Span {
+  pub start: usize,
+  pub end  : usize,
+}
+
+impl Span {
+  pub fn new(start: usize, end: usize) -> Span {
+    Span{ start, end }
+  }
+
+  /// The smallest span containing both spans.
+  pub fn merge(&self, other: &Span) -> Span {
+    Span{
+      start: self.start.min(other.start),
+      end  : self.end.max(other.end)
+    }
+  }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum TokenKind {
+  Symbol,   // An identifier, e.g. `x` or `Plus`
+  Number,   // E.g. `3.14`
+  String,   // E.g. `"hello"`. The text of the token holds the contents without quotes.
+  Operator, // One of the tokens of an operator, e.g. `+`, `[[`, or `)`
+  EOF
+}
+
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct Token {
+  pub kind        : TokenKind,
+  pub text        : String,
+  pub span        : Span,
+  /// Is the token preceded by whitespace? The first token is preceded by whitespace only if the
+  /// text begins with whitespace.
+  pub space_before: bool,
+  /// Is the token followed by whitespace? Only known once the following token has been lexed, so
+  /// this is set by `Lexer::tokenize`. The `EOF` token is never followed by whitespace.
+  pub space_after : bool,
+}
+
+/// What kind of text the lexer reads.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub enum Mode {
+  #[default]
+  Native,
+  /// LaTeX math, e.g. `\frac{a}{b}`.
+  Latex,
+}
+
+/// LaTeX control words read as whitespace.
+const LATEX_SPACES: &[&str] = &["\\qquad", "\\quad", "\\,", "\\:", "\\;", "\\!", "\\ "];
+
+/// LaTeX control words read as symbols other than Greek letters.
+const LATEX_SYMBOLS: &[(&str, &str)] = &[("infty", "Infinity")];
+
+impl Token {
+  /// Is this the operator token `text`?
+  pub fn is_operator(&self, text: &str) -> bool {
+    self.kind == TokenKind::Operator && self.text == text
+  }
+}
+
+pub struct Lexer<'s> {
+  text       : &'s str,
+  cursor     : usize,
+  /// Operator tokens, longest first so that the first match is the longest match.
+  sigils     : Vec<String>,
+  named_chars: &'s NamedCharacters,
+  mode       : Mode,
+  /// Was the last token `^` or `_`? In LaTeX a number there is a single digit.
+  after_script: bool,
+}
+
+impl<'s> Lexer<'s> {
+
+  /// A lexer that knows the built-in named characters.
+  pub fn new(text: &'s str, sigils: Vec<String>) -> Lexer<'s> {
+    Lexer::with_named_characters(text, sigils, NamedCharacters::builtin())
+  }
+
+  /// A lexer that knows the named characters in `named_chars`. Named characters in `sigils` must
+  /// already be replaced with the characters they name.
+  pub fn with_named_characters(text: &'s str, sigils: Vec<String>, named_chars: &'s NamedCharacters)
+    -> Lexer<'s>
+  {
+    let mut sigils: Vec<String> = sigils.into_iter().filter(|s| !s.is_empty()).collect();
+    sigils.sort_by(|a, b| b.chars().count().cmp(&a.chars().count()).then(a.cmp(b)));
+    sigils.dedup();
+
+    Lexer{
+      text,
+      cursor: 0,
+      sigils,
+      named_chars,
+      mode: Mode::Native,
+      after_script: false,
+    }
+  }
+
+  /// The lexer reading text of the kind `mode`.
+  pub fn with_mode(mut self, mode: Mode) -> Lexer<'s> {
+    self.mode = mode;
+    self
+  }
+
+  /// Lexes the entire text. The last token is always an `EOF` token.
+  pub fn tokenize(mut self) -> Result<Vec<Token>, ParseError> {
+    let mut tokens: Vec<Token> = Vec::new();
+
+    loop {
+      let token = self.next_token()?;
+      let done = token.kind == TokenKind::EOF;
+      if let Some(previous) = tokens.last_mut() {
+        previous.space_after = token.space_before;
+      }
+      tokens.push(token);
+      if done {
+        return Ok(tokens);
+      }
+    }
+  }
+
+  fn rest(&self) -> &'s str {
+    &self.text[self.cursor..]
+  }
+
+  fn peek_char(&self) -> Option<char> {
+    self.rest().chars().next()
+  }
+
+  fn skip_whitespace(&mut self) {
+    loop {
+      let rest = self.rest();
+      self.cursor += rest.len() - rest.trim_start().len();
+      if self.mode != Mode::Latex {
+        return;
+      }
+      match LATEX_SPACES.iter().find(|space| self.rest().starts_with(*space)) {
+        // `\quad` is spacing but `\quadrant` is not.
+        Some(space) if space.ends_with(|c: char| c.is_ascii_alphabetic())
+          && self.rest()[space.len()..].starts_with(|c: char| c.is_ascii_alphabetic()) => return,
+        Some(space) => self.cursor += space.len(),
+        None => return,
+      }
+    }
+  }
+
+  /// The character at byte offset `position` and its length in the text. A named character is a
+  /// single character whose length is the length of its escape.
+  fn char_at(&self, position: usize) -> Option<(char, usize)> {
+    let rest = &self.text[position..];
+    if let Some((name, length)) = NamedCharacters::read_escape(rest) {
+      if let Some(c) = self.named_chars.get(name) {
+        return Some((c, length));
+      }
+    }
+    rest.chars().next().map(|c| (c, c.len_utf8()))
+  }
+
+  /// The next character, reading a named character as the character it names.
+  fn peek_named_char(&self) -> Option<(char, usize)> {
+    self.char_at(self.cursor)
+  }
+
+  /// Reports a named character at the cursor whose name is not in the table.
+  fn check_named_char(&self) -> Result<(), ParseError> {
+    match NamedCharacters::read_escape(self.rest()) {
+      Some((name, length)) if self.named_chars.get(name).is_none() => {
+        Err(ParseError::UnknownNamedCharacter{
+          name: name.to_string(),
+          span: Span::new(self.cursor, self.cursor + length)
+        })
+      }
+      _ => Ok(())
+    }
+  }
+
+  /// Advances the cursor over characters, named or not, satisfying `predicate` and returns them.
+  fn take_named_while<P: Fn(char) -> bool>(&mut self, predicate: P) -> String {
+    let mut text = String::new();
+    while let Some((c, length)) = self.peek_named_char() {
+      if !predicate(c) {
+        break;
+      }
+      text.push(c);
+      self.cursor += length;
+    }
+    text
+  }
+
+  /// The longest sigil at the cursor and its length in the text.
+  fn match_sigil(&self) -> Option<(&String, usize)> {
+    self.sigils.iter().find_map(|sigil| {
+      let mut length = 0;
+      for expected in sigil.chars() {
+        match self.char_at(self.cursor + length) {
+          Some((c, char_length)) if c == expected => length += char_length,
+          _ => return None
+        }
+      }
+      Some((sigil, length))
+    })
+  }
+
+  /// Advances the cursor over characters satisfying `predicate` and returns them.
+  fn take_while<P: Fn(char) -> bool>(&mut self, predicate: P) -> &'s str {
+    let rest = self.rest();
+    let length = rest.find(|c: char| !predicate(c)).unwrap_or(rest.len());
+    self.cursor += length;
+    &rest[..length]
+  }
+
+  fn make_token(&self, kind: TokenKind, text: &str, start: usize) -> Token {
+    Token{
+      kind,
+      text        : text.to_string(),
+      span        : Span::new(start, self.cursor),
+      space_before: self.text[..start].ends_with(char::is_whitespace),
+      space_after : false
+    }
+  }
+
+  pub fn next_token(&mut self) -> Result<Token, ParseError> {
+    let token = self.read_token()?;
+    self.after_script =
+      self.mode == Mode::Latex && (token.is_operator("^") || token.is_operator("_"));
+    Ok(token)
+  }
+
+  fn read_token(&mut self) -> Result<Token, ParseError> {
+    self.skip_whitespace();
+    let start = self.cursor;
+
+    self.check_named_char()?;
+    let (c, length) = match self.peek_named_char() {
+      Some(next) => next,
+      None => {
+        return Ok(self.make_token(TokenKind::EOF, "", start));
+      }
+    };
+
+    if is_symbol_start(c) {
+      let text = self.take_named_while(is_symbol_char);
+      // A sigil may be a word, e.g. `and`.
+      let kind =
+        if self.sigils.contains(&text) { TokenKind::Operator } else { TokenKind::Symbol };
+      return Ok(self.make_token(kind, &text, start));
+    }
+
+    if c.is_ascii_digit() {
+      if self.after_script {
+        self.cursor += 1;
+        return Ok(self.make_token(TokenKind::Number, &c.to_string(), start));
+      }
+      return Ok(self.number(start));
+    }
+
+    if self.mode == Mode::Latex && c == '\\'
+      && self.rest()[1..].starts_with(|c: char| c.is_ascii_alphabetic())
+    {
+      return self.control_word(start);
+    }
+
+    if c == '"' {
+      return self.string(start);
+    }
+
+    if c == '`' {
+      return self.quoted_atom(start);
+    }
+
+    match self.match_sigil() {
+      Some((sigil, sigil_length)) => {
+        let sigil = sigil.clone();
+        self.cursor += sigil_length;
+        Ok(self.make_token(TokenKind::Operator, &sigil, start))
+      }
+      None => {
+        self.cursor += length;
+        Err(ParseError::UnknownToken{
+          text: c.to_string(),
+          span: Span::new(start, self.cursor)
+        })
+      }
+    }
+  }
+
+  /// Lexes a LaTeX control word: an operator token if it is a sigil, or a symbol if it names a
+  /// character.
+  fn control_word(&mut self, start: usize) -> Result<Token, ParseError> {
+    self.cursor += 1;
+    self.take_while(|c| c.is_ascii_alphabetic());
+    let mut text = self.text[start..self.cursor].to_string();
+
+    if text == "\\left" || text == "\\right" {
+      self.skip_whitespace();
+      // The delimiter is a character, e.g. `(`, or a control symbol, e.g. `\{`.
+      let rest = self.rest();
+      let mut delimiter = rest.chars().take(if rest.starts_with('\\') { 2 } else { 1 });
+      if let Some(c) = delimiter.next() {
+        text.push(c);
+        self.cursor += c.len_utf8();
+      }
+      if let Some(c) = delimiter.next() {
+        text.push(c);
+        self.cursor += c.len_utf8();
+      }
+    }
+
+    if self.sigils.contains(&text) {
+      return Ok(self.make_token(TokenKind::Operator, &text, start));
+    }
+    let word = &text[1..];
+    if let Some((_, symbol)) = LATEX_SYMBOLS.iter().find(|(control_word, _)| *control_word == word) {
+      return Ok(self.make_token(TokenKind::Symbol, symbol, start));
+    }
+    let name =
+      if word.starts_with(|c: char| c.is_ascii_uppercase()) {
+        format!("Capital{}", word)
+      } else {
+        word[..1].to_ascii_uppercase() + &word[1..]
+      };
+    match self.named_chars.get(&name) {
+      Some(c) if c.is_alphabetic() => Ok(self.make_token(TokenKind::Symbol, &c.to_string(), start)),
+      _ => Err(ParseError::UnknownToken{ text, span: Span::new(start, self.cursor) }),
+    }
+  }
+
+  /// Lexes `digits` or `digits.digits`.
+  fn number(&mut self, start: usize) -> Token {
+    self.take_while(|c| c.is_ascii_digit());
+
+    let rest = self.rest();
+    if rest.starts_with('.') && rest[1..].starts_with(|c: char| c.is_ascii_digit()) {
+      self.cursor += 1;
+      self.take_while(|c| c.is_ascii_digit());
+    }
+
+    let text = &self.text[start..self.cursor];
+    self.make_token(TokenKind::Number, text, start)
+  }
+
+  /// Lexes a double quoted string with backslash escapes, including named characters. The token's
+  /// text is the unescaped contents of the string.
+  fn string(&mut self, start: usize) -> Result<Token, ParseError> {
+    self.cursor += 1; // The opening quote
+    let mut contents = String::new();
+
+    loop {
+      self.check_named_char()?;
+      match self.peek_named_char() {
+
+        Some(('"', _)) => {
+          self.cursor += 1;
+          return Ok(self.make_token(TokenKind::String, &contents, start));
+        }
+
+        Some(('\\', 1)) => {
+          self.cursor += 1;
+          match self.peek_char() {
+            Some(c) => {
+              contents.push(match c { 'n' => '\n', 't' => '\t', c => c });
+              self.cursor += c.len_utf8();
+            }
+            None => break,
+          }
+        }
+
+        Some((c, length)) => {
+          contents.push(c);
+          self.cursor += length;
+        }
+
+        None => break,
+
+      }
+    }
+
+    self.cursor = self.text.len();
+    Err(ParseError::UnterminatedString{ span: Span::new(start, self.cursor) })
+  }
+
+  /// Lexes a number or symbol between backquotes with backslash escapes.
+  fn quoted_atom(&mut self, start: usize) -> Result<Token, ParseError> {
+    self.cursor += 1; // The opening backquote
+    let mut contents = String::new();
+    let mut escaped = false;
+
+    loop {
+      match self.peek_char() {
+        Some('`') => {
+          self.cursor += 1;
+          let token =
+            match quoted_number(&contents).filter(|_| !escaped) {
+              Some(number) => self.make_token(TokenKind::Number, number, start),
+              None => self.make_token(TokenKind::Symbol, &contents, start)
+            };
+          return Ok(token);
+        }
+        Some('\\') => {
+          self.cursor += 1;
+          escaped = true;
+          match self.peek_char() {
+            Some(c) => {
+              contents.push(c);
+              self.cursor += c.len_utf8();
+            }
+            None => break,
+          }
+        }
+        Some(c) => {
+          contents.push(c);
+          self.cursor += c.len_utf8();
+        }
+        None => break,
+      }
+    }
+
+    Err(ParseError::UnterminatedString{ span: Span::new(start, self.cursor) })
+  }
+
+}
+
+/// The number `text` between backquotes is, as text `f64::from_str` reads, if it is one.
+pub(crate) fn quoted_number(text: &str) -> Option<&str> {
+  match text {
+    "+inf.0" => Some("inf"),
+    "-inf.0" => Some("-inf"),
+    "+nan.0" => Some("NaN"),
+    _ => {
+      let unsigned = text.strip_prefix(['-', '+']).unwrap_or(text);
+      let (whole, fraction) = unsigned.split_once('.').unwrap_or((unsigned, "0"));
+      let digits = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+      (digits(whole) && digits(fraction)).then_some(text)
+    }
+  }
+}
+
+pub(crate) fn is_symbol_start(c: char) -> bool {
+  c.is_alphabetic() || c == '$'
+}
+
+pub(crate) fn is_symbol_char(c: char) -> bool {
+  c.is_alphanumeric() || c == '$'
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn kinds_and_texts(text: &str, sigils: &[&str]) -> Vec<(TokenKind, String)> {
+    let sigils = sigils.iter().map(|s| s.to_string()).collect();
+    Lexer::new(text, sigils)
+      .tokenize()
+      .unwrap()
+      .into_iter()
+      .map(|t| (t.kind, t.text))
+      .collect()
+  }
+
+  #[test]
+  fn lex_atoms_and_operators() {
+    let tokens = kinds_and_texts("f[[x1, 2.5]] + \"a\\\"b\"", &["[", "[[", "]]", ",", "+"]);
+
+    assert_eq!(
+      tokens,
+      vec![
+        (TokenKind::Symbol, "f".to_string()),
+        (TokenKind::Operator, "[[".to_string()),
+        (TokenKind::Symbol, "x1".to_string()),
+        (TokenKind::Operator, ",".to_string()),
+        (TokenKind::Number, "2.5".to_string()),
+        (TokenKind::Operator, "]]".to_string()),
+        (TokenKind::Operator, "+".to_string()),
+        (TokenKind::String, "a\"b".to_string()),
+        (TokenKind::EOF, "".to_string()),
+      ]
+    );
+  }
+
+  #[test]
+  fn lex_word_sigil() {
+    let tokens = kinds_and_texts("a and band", &["and"]);
+
+    assert_eq!(tokens[1], (TokenKind::Operator, "and".to_string()));
+    assert_eq!(tokens[2], (TokenKind::Symbol, "band".to_string()));
+  }
+
+  #[test]
+  fn lex_whitespace() {
+    let tokens = Lexer::new(" x___ y", vec!["___".to_string()]).tokenize().unwrap();
+    let spacing: Vec<(bool, bool)> = tokens.iter().map(|t| (t.space_before, t.space_after)).collect();
+
+    assert_eq!(spacing, vec![(true, false), (false, true), (true, false), (false, false)]);
+  }
+
+  #[test]
+  fn lex_named_characters() {
+    let tokens = kinds_and_texts(
+      "\\[Alpha]x \\[Element] αx ∈ \"\\[Rule]\\\\\"", &["∈"]
+    );
+
+    assert_eq!(
+      tokens,
+      vec![
+        (TokenKind::Symbol, "αx".to_string()),
+        (TokenKind::Operator, "∈".to_string()),
+        (TokenKind::Symbol, "αx".to_string()),
+        (TokenKind::Operator, "∈".to_string()),
+        (TokenKind::String, "→\\".to_string()),
+        (TokenKind::EOF, "".to_string()),
+      ]
+    );
+
+    let mut named_chars = NamedCharacters::default();
+    named_chars.insert("Heart", '♥');
+    let tokens = Lexer::with_named_characters("\\[Heart]", vec!["♥".to_string()], &named_chars)
+      .tokenize()
+      .unwrap();
+    assert_eq!(tokens[0].text, "♥");
+    assert_eq!(tokens[0].span, Span::new(0, 8));
+
+    assert_eq!(
+      Lexer::new("a + \\[Heart]", vec!["+".to_string()]).tokenize(),
+      Err(ParseError::UnknownNamedCharacter{ name: "Heart".to_string(), span: Span::new(4, 12) })
+    );
+  }
+
+  #[test]
+  fn lex_latex() {
+    let sigils = ["^", "\\frac", "{", "}", "\\left(", "\\right)"].iter().map(|s| s.to_string());
+    let tokens: Vec<(TokenKind, String)> =
+      Lexer::new("\\frac{\\alpha}{x^23}\\,\\left (\\Omega\\quad\\right)", sigils.collect())
+        .with_mode(Mode::Latex)
+        .tokenize()
+        .unwrap()
+        .into_iter()
+        .map(|t| (t.kind, t.text))
+        .collect();
+
+    assert_eq!(
+      tokens,
+      vec![
+        (TokenKind::Operator, "\\frac".to_string()),
+        (TokenKind::Operator, "{".to_string()),
+        (TokenKind::Symbol, "α".to_string()),
+        (TokenKind::Operator, "}".to_string()),
+        (TokenKind::Operator, "{".to_string()),
+        (TokenKind::Symbol, "x".to_string()),
+        (TokenKind::Operator, "^".to_string()),
+        (TokenKind::Number, "2".to_string()),
+        (TokenKind::Number, "3".to_string()),
+        (TokenKind::Operator, "}".to_string()),
+        (TokenKind::Operator, "\\left(".to_string()),
+        (TokenKind::Symbol, "Ω".to_string()),
+        (TokenKind::Operator, "\\right)".to_string()),
+        (TokenKind::EOF, "".to_string()),
+      ]
+    );
+
+    let result = Lexer::new("\\quadrant", vec![]).with_mode(Mode::Latex).tokenize();
+    assert_eq!(
+      result,
+      Err(ParseError::UnknownToken{ text: "\\quadrant".to_string(), span: Span::new(0, 9) })
+    );
+  }
+
+  #[test]
+  fn lex_quoted_atoms() {
+    let tokens = kinds_and_texts("`a b` `-3` `+inf.0` `\\-3` `a\\`b` `1x`", &[]);
+
+    assert_eq!(
+      tokens,
+      vec![
+        (TokenKind::Symbol, "a b".to_string()),
+        (TokenKind::Number, "-3".to_string()),
+        (TokenKind::Number, "inf".to_string()),
+        (TokenKind::Symbol, "-3".to_string()),
+        (TokenKind::Symbol, "a`b".to_string()),
+        (TokenKind::Symbol, "1x".to_string()),
+        (TokenKind::EOF, "".to_string()),
+      ]
+    );
+
+    let result = Lexer::new("`a", vec![]).tokenize();
+    assert_eq!(result, Err(ParseError::UnterminatedString{ span: Span::new(0, 2) }));
+  }
+
+  #[test]
+  fn lex_errors() {
+    let result = Lexer::new("1 ? 2", vec!["+".to_string()]).tokenize();
+    assert_eq!(
+      result,
+      Err(ParseError::UnknownToken{ text: "?".to_string(), span: Span::new(2, 3) })
+    );
+
+    let result = Lexer::new("\"abc", vec![]).tokenize();
+    assert_eq!(result, Err(ParseError::UnterminatedString{ span: Span::new(0, 4) }));
+  }
+}
@@ -0,0 +1,35 @@
+/*!
+
+Prattle is a Pratt parser whose expression grammar lives in an operator database rather than in
+code. The database can be read from a CSV file (`operator::get_operator_table`) or compiled from
+a grammar description written in the grammar DSL (`grammar::compile_grammar_file`), or written
+inline in Rust with the `operators!` macro.
+
+*/
+
+pub mod operator;
+pub mod ast;
+pub mod parser;
+pub mod template;
+pub mod lexer;
+pub mod named_characters;
+pub mod interpreter;
+pub mod builtins;
+pub mod errors;
+pub mod symbol_table;
+pub mod evaluator;
+pub mod grammar;
+pub mod printer;
+pub mod corpus;
+pub mod reference;
+pub mod trace;
+pub mod tree_view;
+pub mod serialize;
+pub mod typeset;
+pub mod latex;
+mod macros;
+#[cfg(test)]
+mod property_tests;
+
+pub use crate::ast::ASTNode;
+pub use crate::operator::{Operator, OperatorTable};
@@ -0,0 +1,223 @@
+/*!
+
+The `operators!` macro defines an operator table inline in Rust. Each row describes an operator the
+way a row of `operators.csv` does, but a malformed row is a compile error rather than a panic when
+the database is loaded:
+
+```ignore
+let table = prattle::operators! {
+  Power      : 10,  "^",      infix,    right;
+  Times      : 20,  "*",      infix,    full;
+  Plus       : 30,  "+",      infix,    full;
+  Negate     : 15,  "-",      prefix,   right;
+  Factorial  : 5,   "!",      postfix,  left;
+  Parentheses: 100, "(" ")",  matchfix, non;
+  Construct  : 1,   "[" "]",  infix,    left;
+};
+```
+
+A row is `Name: precedence, tokens, affix, associativity;`. The affix is one of `infix`, `prefix`,
+`postfix`, `matchfix`, and `null`, and the associativity is one of `left`, `right`, `non`, `full`,
+and `null`. Infix and postfix operators take an L token, prefix and null operators an N token, and
+matchfix operators an N token and an O token. Any of them but matchfix may have an O token after
+its first token, like `Construct` above. The arity follows from the affix, and the syntax is built
+from the affix and tokens just as `get_operator_table` builds it for a row of the CSV file.
+
+The table is checked while it is compiled:
+
+  * An unknown affix or associativity is rejected by the macro, pointing at the row, as is the
+    wrong number of tokens for the affix.
+  * A precedence must be a `u32` no greater than `MAX_PRECEDENCE`.
+  * Tokens must be nonempty strings.
+  * Two rows may not have the same name.
+
+```compile_fail
+let table = prattle::operators! {
+  Plus: 30, "+", infix, full;
+  Plus: 40, "-", infix, left; // the name `Plus` is defined multiple times
+};
+```
+
+```compile_fail
+let table = prattle::operators! {
+  Plus: 30000, "+", infix, full; // the precedence of Plus exceeds MAX_PRECEDENCE
+};
+```
+
+```compile_fail
+let table = prattle::operators! {
+  Plus: 30, "+", infx, full; // no rules expected the token `infx`
+};
+```
+
+The macro builds a new `OperatorTable` each time it is evaluated. Begin the table with
+`static NAME;` to define a static instead, which is built the first time it is used:
+
+```ignore
+prattle::operators! {
+  static ARITHMETIC;
+  Times: 20, "*", infix, full;
+  Plus : 30, "+", infix, full;
+}
+
+let parser = Parser::new(ARITHMETIC.clone());
+```
+
+*/
+
+#[macro_export]
+macro_rules! operators {
+
+  (@tokens infix $l:literal)              => { (Some($l), None, None) };
+  (@tokens infix $l:literal $o:literal)   => { (Some($l), None, Some($o)) };
+  (@tokens postfix $l:literal)            => { (Some($l), None, None) };
+  (@tokens postfix $l:literal $o:literal) => { (Some($l), None, Some($o)) };
+  (@tokens prefix $n:literal)             => { (None, Some($n), None) };
+  (@tokens prefix $n:literal $o:literal)  => { (None, Some($n), Some($o)) };
+  (@tokens null $n:literal)               => { (None, Some($n), None) };
+  (@tokens null $n:literal $o:literal)    => { (None, Some($n), Some($o)) };
+  (@tokens matchfix $n:literal $o:literal) => { (None, Some($n), Some($o)) };
+  (@tokens infix $($token:literal)*) => {
+    compile_error!("an infix operator takes an L token and an optional O token")
+  };
+  (@tokens postfix $($token:literal)*) => {
+    compile_error!("a postfix operator takes an L token and an optional O token")
+  };
+  (@tokens prefix $($token:literal)*) => {
+    compile_error!("a prefix operator takes an N token and an optional O token")
+  };
+  (@tokens null $($token:literal)*) => {
+    compile_error!("a null operator takes an N token and an optional O token")
+  };
+  (@tokens matchfix $($token:literal)*) => {
+    compile_error!("a matchfix operator takes an N token and an O token")
+  };
+
+  (@affix infix)    => { $crate::operator::Affix::Infix };
+  (@affix prefix)   => { $crate::operator::Affix::Prefix };
+  (@affix postfix)  => { $crate::operator::Affix::Postfix };
+  (@affix matchfix) => { $crate::operator::Affix::Matchfix };
+  (@affix null)     => { $crate::operator::Affix::Null };
+
+  (@arity infix)    => { 2 };
+  (@arity prefix)   => { 1 };
+  (@arity postfix)  => { 1 };
+  (@arity matchfix) => { 1 };
+  (@arity null)     => { 0 };
+
+  (@associativity left)  => { $crate::operator::Associativity::Left };
+  (@associativity right) => { $crate::operator::Associativity::Right };
+  (@associativity non)   => { $crate::operator::Associativity::Non };
+  (@associativity full)  => { $crate::operator::Associativity::Full };
+  (@associativity null)  => { $crate::operator::Associativity::Null };
+
+  (static $table:ident; $($rows:tt)*) => {
+    static $table: std::sync::LazyLock<$crate::operator::OperatorTable> =
+      std::sync::LazyLock::new(|| $crate::operators!{ $($rows)* });
+  };
+
+  ($($name:ident : $precedence:literal, $($token:literal)+, $affix:ident, $associativity:ident);* $(;)?) => {{
+    // Two rows with the same name would declare the same variant twice.
+    #[allow(non_camel_case_types, dead_code)]
+    enum OperatorNames { $($name),* }
+
+    #[allow(unused_mut)]
+    let mut table = $crate::operator::OperatorTable::new();
+    $(
+      const _: () = {
+        let precedence: u32 = $precedence;
+        assert!(
+          precedence <= $crate::operator::MAX_PRECEDENCE,
+          concat!("the precedence of ", stringify!($name), " exceeds MAX_PRECEDENCE")
+        );
+      };
+      $(
+        const _: () = assert!(
+          !$token.is_empty(),
+          concat!("the tokens of ", stringify!($name), " may not be empty")
+        );
+      )+
+
+      let (l_token, n_token, o_token): (Option<&str>, Option<&str>, Option<&str>) =
+        $crate::operators!(@tokens $affix $($token)+);
+      let operator = $crate::operator::Operator{
+        name         : stringify!($name).to_string(),
+        precedence   : $precedence,
+        l_token      : l_token.map(str::to_string),
+        n_token      : n_token.map(str::to_string),
+        o_token      : o_token.map(str::to_string),
+        associativity: $crate::operators!(@associativity $associativity),
+        affix        : $crate::operators!(@affix $affix),
+        arity        : $crate::operators!(@arity $affix),
+        syntax       : $crate::grammar::syntax::Syntax::Empty,
+        parse        : $crate::grammar::syntax::Syntax::Empty,
+        fullform     : $crate::grammar::syntax::Syntax::Empty,
+        latex        : $crate::grammar::syntax::Syntax::Empty,
+        mathml       : $crate::grammar::syntax::Syntax::Empty,
+      };
+      let operator = $crate::operator::Operator{
+        syntax: $crate::operator::default_syntax(&operator),
+        ..operator
+      };
+      table.insert(operator.name.clone(), operator);
+    )*
+    table
+  }};
+
+}
+
+
+#[cfg(test)]
+mod tests {
+  use crate::operator::{get_operator_table, Affix};
+  use crate::parser::Parser;
+
+  crate::operators! {
+    static ARITHMETIC;
+    Times: 20, "*", infix, full;
+    Plus : 30, "+", infix, full;
+  }
+
+  #[test]
+  fn same_table_as_the_csv_loader() {
+    let table = crate::operators! {
+      Base       : 5,   "√",     infix,  non;
+      Power      : 10,  "^",     infix,  right;
+      Times      : 20,  "*",     infix,  full;
+      Divide     : 20,  "/",     infix,  left;
+      Plus       : 30,  "+",     infix,  full;
+      Minus      : 30,  "-",     infix,  full;
+      Parentheses: 100, "(" ")", prefix, non;
+      Construct  : 100, "[" "]", infix,  left;
+      Set        : 110, "=",     infix,  right;
+      SetDelayed : 110, ":=",    infix,  right;
+    };
+
+    assert_eq!(table, get_operator_table());
+  }
+
+  #[test]
+  fn affixes_and_tokens() {
+    let table = crate::operators! {
+      Factorial: 5,   "!",      postfix,  left;
+      List     : 100, "{" "}",  matchfix, non;
+      Out      : 0,   "%",      null,     null;
+    };
+
+    assert_eq!(table["Factorial"].l_token.as_deref(), Some("!"));
+    assert_eq!(table["Factorial"].arity, 1);
+    assert_eq!(table["List"].affix, Affix::Matchfix);
+    assert_eq!((table["List"].n_token.as_deref(), table["List"].o_token.as_deref()), (Some("{"), Some("}")));
+    assert_eq!(table["Out"].arity, 0);
+  }
+
+  #[test]
+  fn static_table() {
+    let mut parser = Parser::new(ARITHMETIC.clone());
+    let tree = parser.parse("a + b * c").unwrap();
+
+    assert_eq!(tree.name(), Some("Plus"));
+    assert_eq!(tree.children()[1].name(), Some("Times"));
+    assert_eq!(tree.children()[0].name(), Some("a"));
+  }
+}
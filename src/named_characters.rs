@@ -0,0 +1,209 @@
+/*!
+
+Named characters are written `\[Name]`, e.g. `\[Alpha]` or `\[Element]`, and stand for a single
+character, `α` or `∈`. They let expressions and grammar files use characters that are hard to type.
+
+A named character and the character it names are interchangeable: the lexer reads `\[Alpha]x` and
+`αx` as the same symbol, and an operator whose syntax contains `\[Element]` or `"\[Element]"` is
+selected by both `\[Element]` and `∈` in source text. Tokens always hold the character itself.
+
+The built-in table is `NamedCharacters::builtin()`. To add names of your own, clone it with
+`NamedCharacters::default()` and `insert` them.
+
+*/
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::grammar::syntax::Syntax;
+
+/// The built-in named characters. Each character has exactly one name.
+const BUILTIN_NAMED_CHARACTERS: &[(&str, char)] = &[
+  // Greek letters
+  ("Alpha", 'α'), ("Beta", 'β'), ("Gamma", 'γ'), ("Delta", 'δ'), ("Epsilon", 'ε'), ("Zeta", 'ζ'),
+  ("Eta", 'η'), ("Theta", 'θ'), ("Iota", 'ι'), ("Kappa", 'κ'), ("Lambda", 'λ'), ("Mu", 'μ'),
+  ("Nu", 'ν'), ("Xi", 'ξ'), ("Pi", 'π'), ("Rho", 'ρ'), ("Sigma", 'σ'), ("Tau", 'τ'),
+  ("Upsilon", 'υ'), ("Phi", 'φ'), ("Chi", 'χ'), ("Psi", 'ψ'), ("Omega", 'ω'),
+  ("CapitalGamma", 'Γ'), ("CapitalDelta", 'Δ'), ("CapitalTheta", 'Θ'), ("CapitalLambda", 'Λ'),
+  ("CapitalXi", 'Ξ'), ("CapitalPi", 'Π'), ("CapitalSigma", 'Σ'), ("CapitalPhi", 'Φ'),
+  ("CapitalPsi", 'Ψ'), ("CapitalOmega", 'Ω'),
+  // Arithmetic and calculus
+  ("Times", '×'), ("Divide", '÷'), ("PlusMinus", '±'), ("MinusPlus", '∓'), ("CenterDot", '·'),
+  ("Cross", '⨯'), ("Sqrt", '√'), ("Infinity", '∞'), ("Degree", '°'), ("PartialD", '∂'),
+  ("Del", '∇'), ("Integral", '∫'), ("Sum", '∑'), ("Product", '∏'),
+  ("CirclePlus", '⊕'), ("CircleTimes", '⊗'),
+  // Relations
+  ("NotEqual", '≠'), ("LessEqual", '≤'), ("GreaterEqual", '≥'), ("Congruent", '≡'),
+  ("TildeTilde", '≈'), ("Proportional", '∝'),
+  // Logic and sets
+  ("And", '∧'), ("Or", '∨'), ("Not", '¬'), ("Implies", '⟹'), ("Equivalent", '⟺'),
+  ("ForAll", '∀'), ("Exists", '∃'), ("NotExists", '∄'), ("Element", '∈'), ("NotElement", '∉'),
+  ("Subset", '⊂'), ("Superset", '⊃'), ("SubsetEqual", '⊆'), ("SupersetEqual", '⊇'),
+  ("Union", '⋃'), ("Intersection", '⋂'), ("EmptySet", '∅'),
+  // Arrows
+  ("Rule", '→'), ("RuleDelayed", '⧴'), ("LeftArrow", '←'), ("UpArrow", '↑'), ("DownArrow", '↓'),
+  ("LeftRightArrow", '↔'), ("LongRightArrow", '⟶'), ("Function", '↦'),
+];
+
+/// A table of named characters in both directions.
+#[derive(Clone, Debug)]
+pub struct NamedCharacters {
+  characters: HashMap<String, char>,
+  names     : HashMap<char, String>,
+}
+
+impl NamedCharacters {
+
+  /// A table with no names at all.
+  pub fn new() -> NamedCharacters {
+    NamedCharacters{
+      characters: HashMap::new(),
+      names     : HashMap::new(),
+    }
+  }
+
+  /// The built-in table, shared by every lexer that is not given a table of its own.
+  pub fn builtin() -> &'static NamedCharacters {
+    static BUILTIN: OnceLock<NamedCharacters> = OnceLock::new();
+    BUILTIN.get_or_init(|| {
+      let mut table = NamedCharacters::new();
+      for (name, c) in BUILTIN_NAMED_CHARACTERS {
+        table.insert(name, *c);
+      }
+      table
+    })
+  }
+
+  /// Adds or replaces the name `name` for `c`. If `c` already has a name, `name` becomes the name
+  /// `name_of` reports, but the old name still reads as `c`.
+  pub fn insert(&mut self, name: &str, c: char) {
+    self.characters.insert(name.to_string(), c);
+    self.names.insert(c, name.to_string());
+  }
+
+  /// The character named `name`, given without the surrounding `\[` and `]`.
+  pub fn get(&self, name: &str) -> Option<char> {
+    self.characters.get(name).copied()
+  }
+
+  pub fn name_of(&self, c: char) -> Option<&str> {
+    self.names.get(&c).map(String::as_str)
+  }
+
+  /// Every name and the character it names, in name order.
+  pub fn iter(&self) -> impl Iterator<Item=(&str, char)> {
+    let mut names: Vec<(&str, char)> =
+      self.characters.iter().map(|(name, c)| (name.as_str(), *c)).collect();
+    names.sort();
+    names.into_iter()
+  }
+
+  /// Reads a named character at the start of `text`, returning the name and the length of the
+  /// escape in bytes. The name need not be in the table.
+  pub fn read_escape(text: &str) -> Option<(&str, usize)> {
+    let rest = text.strip_prefix("\\[")?;
+    let length = rest.find(|c: char| !c.is_ascii_alphanumeric()).unwrap_or(rest.len());
+    if length == 0 || !rest[length..].starts_with(']') {
+      return None;
+    }
+    Some((&rest[..length], length + 3))
+  }
+
+  /// Replaces every known named character in `text` with the character it names. Unknown names
+  /// are left as they are.
+  pub fn normalize(&self, text: &str) -> String {
+    let mut normalized = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find("\\[") {
+      normalized.push_str(&rest[..start]);
+      rest = &rest[start..];
+      match NamedCharacters::read_escape(rest) {
+        Some((name, length)) if self.get(name).is_some() => {
+          normalized.push(self.get(name).unwrap());
+          rest = &rest[length..];
+        }
+        _ => {
+          normalized.push_str("\\[");
+          rest = &rest[2..];
+        }
+      }
+    }
+    normalized.push_str(rest);
+
+    normalized
+  }
+
+  /// Replaces the named characters in the tokens of a syntax with the characters they name, so
+  /// that `\[Element]` and `"\[Element]"` both become `"∈"`.
+  pub fn normalize_syntax(&self, syntax: &Syntax) -> Syntax {
+    let boxed = |inner: &Syntax| Box::new(self.normalize_syntax(inner));
+    let list = |items: &Vec<Syntax>| items.iter().map(|s| self.normalize_syntax(s)).collect();
+
+    match syntax {
+      Syntax::NamedChar(name) => {
+        match self.get(name) {
+          Some(c) => Syntax::Literal(c.to_string()),
+          None => syntax.clone()
+        }
+      }
+      Syntax::Literal(text) => Syntax::Literal(self.normalize(text)),
+      Syntax::OnePlus(inner) => Syntax::OnePlus(boxed(inner)),
+      Syntax::ZeroPlus(inner) => Syntax::ZeroPlus(boxed(inner)),
+      Syntax::Optional(inner) => Syntax::Optional(boxed(inner)),
+      Syntax::OnePlusDelim(inner, delimiter) => Syntax::OnePlusDelim(boxed(inner), boxed(delimiter)),
+      Syntax::ZeroPlusDelim(inner, delimiter) => Syntax::ZeroPlusDelim(boxed(inner), boxed(delimiter)),
+      Syntax::Cons(head, tail) => Syntax::Cons(boxed(head), list(tail)),
+      Syntax::Sequence(items) => Syntax::Sequence(list(items)),
+      Syntax::SequenceDelim(items) => Syntax::SequenceDelim(list(items)),
+      Syntax::Alternative(items) => Syntax::Alternative(list(items)),
+      other => other.clone()
+    }
+  }
+
+}
+
+impl Default for NamedCharacters {
+  /// A copy of the built-in table.
+  fn default() -> NamedCharacters {
+    NamedCharacters::builtin().clone()
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn builtin_names_are_unique() {
+    let table = NamedCharacters::builtin();
+
+    for (name, c) in BUILTIN_NAMED_CHARACTERS {
+      assert_eq!(table.get(name), Some(*c));
+      assert_eq!(table.name_of(*c), Some(*name));
+    }
+  }
+
+  #[test]
+  fn normalize_text_and_syntax() {
+    let mut table = NamedCharacters::default();
+    table.insert("Heart", '♥');
+
+    assert_eq!(table.normalize("a\\[Element]\\[Heart] \\[Nonsense] \\["), "a∈♥ \\[Nonsense] \\[");
+    assert_eq!(NamedCharacters::builtin().get("Heart"), None);
+    assert_eq!(
+      table.normalize_syntax(&Syntax::Sequence(vec![
+        Syntax::Expr1,
+        Syntax::NamedChar("Element".to_string()),
+        Syntax::Literal("\\[Rule]".to_string())
+      ])),
+      Syntax::Sequence(vec![
+        Syntax::Expr1,
+        Syntax::Literal("∈".to_string()),
+        Syntax::Literal("→".to_string())
+      ])
+    );
+  }
+}
@@ -8,19 +8,27 @@ A table of operators will hold the operator database for all the operators in th
 grammar. The parsing algorithm will look up a given operator using the operator's token (sigil).
 Thus, the operator table is a `HashMap` from `String` to `Operator`.
 
+Precedence follows the convention of the operator database: the smaller the precedence, the
+tighter the operator binds, so `Power` (10) binds tighter than `Times` (20). Binding powers follow
+Pratt's convention, in which the larger binding power binds tighter, so the binding power of an
+operator is `MAX_PRECEDENCE - precedence`.
+
 */
 #![allow(dead_code)]
 
 use std::collections::HashMap;
-use std::fs::File;
-use std::io::{BufReader, BufRead};
-use crate::ASTNode;
-use crate::interpreter::RuntimeContext;
+use std::fs;
+use std::str::FromStr;
+
+use crate::grammar::syntax::Syntax;
 
 const OPERATOR_DB_FILE: &str = "resources/operators.csv"; // Used in `get_operator_table()`
 
 pub type OperatorTable = HashMap<String, Operator>;
 
+/// Precedences range from 0 (binds tightest) to `MAX_PRECEDENCE` (binds loosest).
+pub const MAX_PRECEDENCE: u32 = 10_000;
+
 #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
 pub enum Associativity {
   Null,  // Things like constants or identifiers that have no affix or associativity. Also,
@@ -32,21 +40,24 @@ pub enum Associativity {
          // e.g. 1 + 2 + 3 + 4 == Plus(1, 2, 3, 4)
 }
 
-impl Associativity{
-  pub fn from_str(s: &str) -> Associativity {
+/// Reads the associativity column of the CSV operator database.
+impl FromStr for Associativity {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Associativity, String> {
     match s{
 
-      "R" => Associativity::Right,
+      "R" => Ok(Associativity::Right),
 
-      "L" => Associativity::Left,
+      "L" => Ok(Associativity::Left),
 
-      "F" => Associativity::Full,
+      "F" => Ok(Associativity::Full),
 
-      "N" => Associativity::Non,
+      "N" => Ok(Associativity::Non),
 
-      "" => Associativity::Null,
+      "" => Ok(Associativity::Null),
 
-      _ => {unreachable!()}
+      _ => Err(format!("{:?} is not an associativity", s))
     }
   }
 }
@@ -64,23 +75,24 @@ pub enum Affix {
 }
 
 
-impl Affix {
-  pub fn from_str(s: &str) -> Affix {
+/// Reads the affix column of the CSV operator database.
+impl FromStr for Affix {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Affix, String> {
     match s {
 
-      "N" => Affix::Null,
+      "N" => Ok(Affix::Null),
 
-      "P" => Affix::Prefix,
+      "P" => Ok(Affix::Prefix),
 
-      "S" => Affix::Postfix,
+      "S" => Ok(Affix::Postfix),
 
-      "I" => Affix::Infix,
+      "I" => Ok(Affix::Infix),
 
-      "M" => Affix::Matchfix,
+      "M" => Ok(Affix::Matchfix),
 
-      _   => {
-        panic!("Unknown affix encountered: {}", s)
-      }
+      _   => Err(format!("{:?} is not an affix", s))
 
     }
   }
@@ -97,59 +109,92 @@ pub struct Operator {                // Example Value
   pub associativity: Associativity,  // "L"
   pub affix        : Affix,          // "I"
   pub arity        : u32,            // 2 An alternative is to use an enum or newtype.
+  pub syntax       : Syntax,         // expr1 "*" expr2
+  pub parse        : Syntax,         // Times[expr1, expr2] `Empty` means the default template.
+  pub fullform     : Syntax,         // <Empty>             `Empty` means the same as `parse`.
+  pub latex        : Syntax,         // <Empty>             `Empty` means the default typeset form.
+  pub mathml       : Syntax,         // <Empty>
 }
 
 
 impl Operator {
 
-  pub fn lbp(&self) -> u32 {
+  /// The binding power corresponding to the operator's precedence. See the module documentation.
+  fn binding_power(&self) -> i32 {
+    (MAX_PRECEDENCE - self.precedence.min(MAX_PRECEDENCE)) as i32
+  }
+
+  /// The left binding power. Only operators that take a left operand have one.
+  pub fn lbp(&self) -> i32 {
     match self.affix {
 
       | Affix::Infix
-      | Affix::Postfix => self.precedence,
+      | Affix::Postfix => self.binding_power(),
 
       _ => -1
 
     }
   }
 
-  pub fn rbp(&self) -> u32 {
-    match self.associativity {
+  /// The right binding power, the binding power with which the operator's last operand is parsed
+  /// when it is not closed by a token.
+  pub fn rbp(&self) -> i32 {
+    match self.affix {
 
-      | Associativity::Left
-      | Associativity::Non => self.lbp() + 1,
+      Affix::Infix => {
+        match self.associativity {
 
-      Associativity::Right => self.lbp(),
+          Associativity::Right => self.binding_power(),
 
-      Associativity::Full  => self.lbp() - 1,
+          // Full associativity gathers the operands of adjacent operators itself, so, like left
+          // associativity, its right operand stops at the next operator of the same precedence.
+          _ => self.binding_power() + 1,
 
-      Associativity::Null  => -1 // Technically, Matchfix is N/A.
+        }
+      }
 
-    }
+      Affix::Prefix => self.binding_power(),
 
-  }
+      // Operands of matchfix operators are delimited, and postfix and null operators have no
+      // right operand.
+      _ => 0
 
-  pub fn nbp(&self) -> u32 {
-    match self.associativity {
+    }
+  }
 
-      | Associativity::Left
-      | Associativity::Right => self.lbp(),
+  /// NBP stands for the "next binding power". It gives the highest binding power of the operator
+  /// that this operator can be a left operand of.
+  pub fn nbp(&self) -> i32 {
+    match self.affix {
 
-      | Associativity::Non
-      | Associativity::Full  => self.lbp() - 1,
+      Affix::Infix => {
+        match self.associativity {
 
-      _  => {
+          Associativity::Non => self.binding_power() - 1,
 
-        match self.affix {
+          _ => self.binding_power(),
 
-          Affix::Prefix => self.precedence,
-          Affix::Matchfix => 0,
-          Affix::Postfix => -1,
-          _ => { unreachable!() }
         }
       }
 
+      Affix::Postfix => self.binding_power(),
+
+      // The result of an operator without a left operand can be the left operand of anything.
+      _ => i32::MAX
+
+    }
+  }
+
+  /// The tokens of the operator, including the delimiters and other tokens of its syntax. The
+  /// lexer needs these to recognize the operator's tokens in the source text.
+  pub fn tokens(&self) -> Vec<String> {
+    let mut tokens = self.syntax.tokens();
+    for token in [&self.l_token, &self.n_token, &self.o_token].into_iter().flatten() {
+      if !tokens.contains(token) {
+        tokens.push(token.clone());
+      }
     }
+    tokens
   }
 
   // The parse-time functionality of `Operator` lives in the `impl Parser`.
@@ -158,42 +203,171 @@ impl Operator {
 
 
 
+/// The CSV operator database does not describe the syntax of its operators, so we build the
+/// syntax from the affix, tokens, and arity: `expr1 L expr2 O` for a binary operator like
+/// `f[x]`, `expr1 L expr2 O expr3` for a ternary one like `a ? b : c`, `N expr1 O`, and so on.
+pub fn default_syntax(operator: &Operator) -> Syntax {
+  let mut sequence = Vec::new();
+  let token = |t: &Option<String>| t.as_ref().map(|t| Syntax::Literal(t.clone()));
+
+  match operator.affix {
+
+    | Affix::Infix
+    | Affix::Postfix => {
+      sequence.push(Syntax::Expr1);
+      sequence.extend(token(&operator.l_token));
+      if operator.affix == Affix::Infix {
+        sequence.push(Syntax::Expr2);
+      }
+    }
+
+    | Affix::Prefix
+    | Affix::Matchfix => {
+      sequence.extend(token(&operator.n_token));
+      sequence.push(Syntax::Expr1);
+    }
+
+    Affix::Null => {
+      sequence.extend(token(&operator.n_token));
+    }
+
+  }
+  sequence.extend(token(&operator.o_token));
+  if operator.affix == Affix::Infix && operator.o_token.is_some() && operator.arity > 2 {
+    sequence.push(Syntax::Expr3);
+  }
+
+  Syntax::Sequence(sequence)
+}
+
+
+/// Reads the operator table from `resources/operators.csv`. Panics if the file cannot be read or
+/// is malformed.
 pub fn get_operator_table() -> OperatorTable {
-  let f = File::open(OPERATOR_DB_FILE)
-      .expect(format!("Could not read from {}", OPERATOR_DB_FILE).as_str());
-  let reader = BufReader::new(f);
+  let text = fs::read_to_string(OPERATOR_DB_FILE)
+      .unwrap_or_else(|e| panic!("Could not read from {}: {}", OPERATOR_DB_FILE, e));
+
+  parse_operator_table(&text).unwrap_or_else(|e| panic!("{}: {}", OPERATOR_DB_FILE, e))
+}
+
+/// Reads an operator table in the CSV format of `resources/operators.csv`: a line of column
+/// headers, then one operator per line with the columns
+///
+///   name, precedence, L token, N token, O token, associativity, affix, arity
+///
+/// Blank lines are skipped. An error names the line of the first malformed row.
+pub fn parse_operator_table(text: &str) -> Result<OperatorTable, String> {
   let mut operator_table = OperatorTable::new();
-  let mut lines = reader.lines();
-  lines.next(); // Eat the column headers
 
-  for line in lines {
-    let line = line.unwrap();
-    let mut fields = line.split(',');
+  // The first line holds the column headers.
+  for (index, line) in text.lines().enumerate().skip(1) {
+    if line.trim().is_empty() {
+      continue;
+    }
+    let error = |message: String| format!("line {}: {}", index + 1, message);
+    let mut fields = line.split(',').map(str::trim);
+    let mut field = |column: &str| fields.next().ok_or_else(|| error(format!("missing the {} column", column)));
+    let token = |s: &str| if s.is_empty() { None } else { Some(s.to_string()) };
+
+    let name = field("name")?;
+    if name.is_empty() {
+      return Err(error("the operator has no name".to_string()));
+    }
+    let precedence = field("precedence")?;
+    let precedence =
+      precedence.parse::<u32>().map_err(|_| error(format!("{:?} is not a precedence", precedence)))?;
+    let (l_token, n_token, o_token) = (token(field("L token")?), token(field("N token")?), token(field("O token")?));
+    let associativity = field("associativity")?.parse::<Associativity>().map_err(error)?;
+    let affix = field("affix")?.parse::<Affix>().map_err(error)?;
+    let arity = field("arity")?;
+    let arity = arity.parse::<u32>().map_err(|_| error(format!("{:?} is not an arity", arity)))?;
+
+    // The syntax built from the affix begins with a left operand exactly when the operator has an
+    // L token, so an operator with the other kind of token could never match it.
+    match (affix, &l_token, &n_token) {
+      (Affix::Infix | Affix::Postfix, _, Some(_)) => {
+        return Err(error(format!("the {:?} operator {} cannot have an N token", affix, name)));
+      }
+      (Affix::Prefix | Affix::Matchfix | Affix::Null, Some(_), _) => {
+        return Err(error(format!("the {:?} operator {} cannot have an L token", affix, name)));
+      }
+      _ => {}
+    }
 
     let new_op = Operator{
-      // Fields filled according to csv column order which need not be declaration order.
-      name      : fields.next().unwrap().to_string(),
-      precedence: fields.next().unwrap().parse::<u32>().unwrap(),
-      l_token   : fields.next().map_or(
-                    None,
-                    |s| if s != "" { Some(s.to_string()) } else { None }
-                  ),
-      n_token   : fields.next().map_or(
-                    None,
-                    |s| if s != "" { Some(s.to_string()) } else { None }
-                  ),
-      o_token   : fields.next().map_or(
-                    None,
-                    |s| if s != "" { Some(s.to_string()) } else { None }
-                  ),
-      associativity : Associativity::from_str(fields.next().unwrap()),
-      affix     : Affix::from_str(fields.next().unwrap()),
-      arity     : fields.next().unwrap().parse::<u32>().unwrap(),
+      name: name.to_string(),
+      precedence,
+      l_token,
+      n_token,
+      o_token,
+      associativity,
+      affix,
+      arity,
+      syntax    : Syntax::Empty,
+      parse     : Syntax::Empty,
+      fullform  : Syntax::Empty,
+      latex     : Syntax::Empty,
+      mathml    : Syntax::Empty,
     };
+    let new_op = Operator{ syntax: default_syntax(&new_op), ..new_op };
 
-    println!("{:?}", &new_op);
     operator_table.insert(new_op.name.clone(), new_op);
   }
 
-  operator_table
+  Ok(operator_table)
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parser::Parser;
+
+  const HEADER: &str = "NAME_STRING, PRECEDENCE, L_TOKEN, N_TOKEN, O_TOKEN, ASSOCIATIVITY, AFFIX, ARITY\n";
+
+  #[test]
+  fn parse_csv_rows() {
+    let table = parse_operator_table(&format!("{}Plus, 30, +, , , F, I, 2\n\nParentheses, 0, , (, ), N, M, 1\n", HEADER)).unwrap();
+
+    assert_eq!(table.len(), 2);
+    assert_eq!(table["Plus"].l_token, Some("+".to_string()));
+    assert_eq!(table["Plus"].associativity, Associativity::Full);
+    assert_eq!(table["Parentheses"].affix, Affix::Matchfix);
+    assert_eq!(table["Parentheses"].o_token, Some(")".to_string()));
+  }
+
+  #[test]
+  fn default_syntax_of_o_tokens() {
+    let table = parse_operator_table(&format!(
+      "{}Construct, 1, [, , ], L, I, 2
+Conditional, 120, ?, , :, R, I, 3
+", HEADER
+    )).unwrap();
+    let literal = |text: &str| Syntax::Literal(text.to_string());
+
+    assert_eq!(
+      table["Construct"].syntax,
+      Syntax::Sequence(vec![Syntax::Expr1, literal("["), Syntax::Expr2, literal("]")])
+    );
+    assert_eq!(
+      table["Conditional"].syntax,
+      Syntax::Sequence(vec![Syntax::Expr1, literal("?"), Syntax::Expr2, literal(":"), Syntax::Expr3])
+    );
+    let tree = Parser::new(table).parse("a ? b : c ? d : e").unwrap();
+    assert_eq!(tree.to_string(), "Conditional[a, b, Conditional[c, d, e]]");
+  }
+
+  #[test]
+  fn malformed_csv_rows() {
+    for (row, message) in [
+      ("Plus, 30, +, , , F, I", "line 2: missing the arity column"),
+      ("Plus, -1, +, , , F, I, 2", "line 2: \"-1\" is not a precedence"),
+      ("Plus, 30, +, , , X, I, 2", "line 2: \"X\" is not an associativity"),
+      ("Plus, 30, +, , , F, Q, 2", "line 2: \"Q\" is not an affix"),
+      (", 30, +, , , F, I, 2", "line 2: the operator has no name"),
+      ("Plus, 30, +, +, , F, I, 2", "line 2: the Infix operator Plus cannot have an N token"),
+    ] {
+      assert_eq!(parse_operator_table(&format!("{}{}\n", HEADER, row)), Err(message.to_string()));
+    }
+  }
 }
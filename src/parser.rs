@@ -33,105 +33,1288 @@ The present design differs in several ways:
      with the rest of the parsing code. This not only consolidates the parsing code but also
      separates the concerns of describing data about an operator from the parsing algorithm.
 
+  4. Instead of hand written parselets, every operator carries its syntax, e.g.
+     `expr1 "[[" (expr2, )* "]]"`, which the parser follows to match the operator's remaining tokens
+     and operands, and a parse template, e.g. `Part[expr1, (expr2,)*]`, from which it builds the
+     node. See the `template` module.
+  5. A syntax may forbid (`NoSpace`) or require (`Space`) whitespace between two of its elements.
+     An optional element that fails the constraint is treated as absent, and an L token that fails
+     it does not continue the expression, so `x___` and `x ___` can parse differently.
+  6. Named characters in an operator's tokens, e.g. `\[Element]`, are replaced with the characters
+     they name, so the operator is selected by both `\[Element]` and `∈` in the source text.
+  7. Nesting is limited to `max_depth` levels, counting both calls of `E` and the left operands of
+     a chain like `a/b/c`, which nest the tree as deeply, beyond which parsing fails with
+     `ParseError::TooDeep` rather than overflowing the stack. `E` matches operators' syntax with a
+     stack of steps on the heap, and with `Stack::Explicit` it parses operands with those steps as
+     well instead of recursing, so the limit can be raised as far as memory allows.
+
 There are other incidental differences:
-  * Our node objects are `Atoms`.
-  * The lexer is trivial.
+  * Our nodes are `ASTNode`s, expressions in the style of Mathematica: an atom, or a head applied
+    to children, `Plus[a, b]`, built from the operator's parse template.
+  * The lexer knows nothing of the grammar beyond the tokens of the operator table. See the `lexer`
+    module.
 
 */
 #![allow(dead_code)]
 
-use crate::operator::OperatorTable;
-use crate::ast::ASTNode;
+use std::collections::HashMap;
+use std::rc::Rc;
 
+use crate::operator::{Associativity, Operator, OperatorTable};
+use crate::ast::{ASTNode, RcASTNode};
+use crate::lexer::{Lexer, Mode, Span, Token, TokenKind};
+use crate::named_characters::NamedCharacters;
+use crate::errors::ParseError;
+use crate::grammar::syntax::Syntax;
+use crate::template::{Bindings, instantiate};
+use crate::trace::{Event, Reason, Sink};
 
-const INF: u32 = u32::MAX;
 
-pub struct Parser<'a, 's> {
-  pub op_table : OperatorTable,
-  pub root_node: Option<ASTNode<'a>>,
-  text         : &'s str,
-  cursor       : u32,
+const INF: i32 = i32::MAX;
+
+/// The default `max_depth`. Unoptimized, the recursive parser uses about 2.5 KiB of stack for each
+/// call of `E`, so this keeps it within 640 KiB, less than a third of the 2 MiB stack of a spawned
+/// thread.
+pub const DEFAULT_MAX_DEPTH: usize = 256;
+
+/// Which of an operator's templates the parser instantiates. An operator without a `fullform`
+/// template uses its `parse` template for both.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum Form {
+  Parse,
+  FullForm,
 }
 
-impl<'a, 's> Parser<'a, 's> {
+/// Where the parser keeps the state of the nested calls of `E`.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum Stack {
+  /// `E` recurses on the thread's stack to parse each operand.
+  Native,
+  /// `E` parses operands with steps on its stack on the heap rather than by recursing. See `Step`.
+  Explicit,
+}
+
+pub struct Parser {
+  pub op_table : OperatorTable,
+  pub form     : Form,
+  pub stack    : Stack,
+  /// The number of levels of nesting, calls of `E` and left operands together, beyond which
+  /// parsing fails with `ParseError::TooDeep`.
+  pub max_depth: usize,
+  /// Receives the steps of each parse, if given. See the `trace` module.
+  pub trace    : Option<Box<dyn Sink>>,
+  /// What kind of text the lexer reads, e.g. LaTeX math. See the `latex` module.
+  pub lexer_mode: Mode,
+  // The command tables map tokens to the operators they select.
+  left_commands: HashMap<String, Rc<Operator>>,
+  null_commands: HashMap<String, Rc<Operator>>,
+  // The N tokens, longest first, which `null_command_lookup` tries against runs of a token.
+  null_tokens  : Vec<String>,
+  sigils       : Vec<String>,
+  named_chars  : NamedCharacters,
+  tokens       : Vec<Token>,
+  cursor       : usize,
+  // The end of the last consumed token, used to compute the spans of nodes.
+  last_end     : usize,
+  depth        : usize,
+  // The number of left operands being built by the calls of `E` in progress.
+  lefts        : usize,
+  // The position of the token the last traced null or left command was selected by, which the
+  // trace does not report again when the operator's syntax matches it.
+  selected     : Option<usize>,
+}
 
-  pub fn new(text: &str) -> Parser {
-    // Load operator database
-    let op_table = OperatorTable::new();
+impl Parser {
 
-    Parser{
+  pub fn new(op_table: OperatorTable) -> Parser {
+    let mut parser = Parser{
       op_table,
-      root_node: None,
-      text,
-      cursor: 0
+      form         : Form::Parse,
+      stack        : Stack::Native,
+      max_depth    : DEFAULT_MAX_DEPTH,
+      trace        : None,
+      lexer_mode   : Mode::Native,
+      left_commands: HashMap::new(),
+      null_commands: HashMap::new(),
+      null_tokens  : Vec::new(),
+      sigils       : Vec::new(),
+      named_chars  : NamedCharacters::default(),
+      tokens       : Vec::new(),
+      cursor       : 0,
+      last_end     : 0,
+      depth        : 0,
+      lefts        : 0,
+      selected     : None,
+    };
+    parser.build_command_tables();
+    parser
+  }
+
+  /// Adds or replaces an operator.
+  pub fn add_operator(&mut self, operator: Operator) {
+    self.op_table.insert(operator.name.clone(), operator);
+    self.build_command_tables();
+  }
+
+  /// Adds or replaces the named character `\[name]`.
+  pub fn add_named_character(&mut self, name: &str, c: char) {
+    self.named_chars.insert(name, c);
+    self.build_command_tables();
+  }
+
+  /// Builds the command tables and the list of sigils from the operator table. Operators are
+  /// visited in name order so that a conflict between two operators is resolved the same way every
+  /// time.
+  fn build_command_tables(&mut self) {
+    self.left_commands.clear();
+    self.null_commands.clear();
+    self.sigils.clear();
+
+    let mut names: Vec<&String> = self.op_table.keys().collect();
+    names.sort();
+
+    for name in names {
+      let operator = Rc::new(normalize_operator(&self.op_table[name], &self.named_chars));
+
+      if let Some(token) = &operator.l_token {
+        self.left_commands.insert(token.clone(), operator.clone());
+      }
+      if let Some(token) = &operator.n_token {
+        self.null_commands.insert(token.clone(), operator.clone());
+      }
+      for token in operator.tokens() {
+        if !self.sigils.contains(&token) {
+          self.sigils.push(token);
+        }
+      }
     }
+
+    self.null_tokens = self.null_commands.keys().cloned().collect();
+    self.null_tokens.sort_by(|a, b| b.len().cmp(&a.len()).then(a.cmp(b)));
   }
 
+  pub fn parse(&mut self, text: &str) -> Result<RcASTNode, ParseError> {
+    self.tokens   =
+      Lexer::with_named_characters(text, self.sigils.clone(), &self.named_chars)
+        .with_mode(self.lexer_mode)
+        .tokenize()?;
+    self.cursor   = 0;
+    self.last_end = 0;
+    self.depth    = 0;
+    self.lefts    = 0;
+    self.selected = None;
 
-  pub fn parse(text: &str) -> Option<ASTNode>{
-    // Bootstrap parsing algorithm...
-    None
+    let tree = self.E(0)?;
+
+    let token = self.peek();
+    match token.kind {
+      TokenKind::EOF => Ok(tree),
+      _ => Err(unexpected("an operator or the end of the input", token))
+    }
   }
 
+  /// Parses an expression whose operators bind at least as tightly as `p` by running its `Step`s.
+  /// With `Stack::Native`, each operand of an operator is parsed by a recursive call of `E`, so
+  /// the steps of each call are those of one level of nesting. With `Stack::Explicit`, operands
+  /// are parsed by steps on the same stack, so the first call parses the whole expression.
   #[allow(non_snake_case)]
-  fn E(&self, p: u32){
-    let mut token = self.consume();
+  fn E(&mut self, p: i32) -> Result<RcASTNode, ParseError> {
+    let mut machine = Machine{ steps: vec![Step::Expression(p)], ..Machine::default() };
+    while let Some(step) = machine.steps.pop() {
+      self.step(step, &mut machine)?;
+    }
+    Ok(machine.result.expect("the last step leaves the tree"))
+  }
+
+  /// Counts a call of `E`, failing if it is nested too deeply. An error ends the parse, so only
+  /// calls that return a tree need to uncount themselves.
+  fn enter(&mut self, p: i32) -> Result<(), ParseError> {
+    self.check_depth()?;
+    self.depth += 1;
+    self.emit(|parser, token| Event::Enter{ depth: parser.depth, p, token: token.text.clone(), span: token.span });
+    Ok(())
+  }
+
+  /// Fails if another level of nesting would exceed `max_depth`.
+  fn check_depth(&self) -> Result<(), ParseError> {
+    match self.depth + self.lefts >= self.max_depth {
+      true => Err(ParseError::TooDeep{ limit: self.max_depth, span: self.peek().span }),
+      false => Ok(())
+    }
+  }
+
+  /// The operator selected by the next token if it continues an expression begun with binding
+  /// power `p` whose last operator had next binding power `r`.
+  fn next_left_command(&mut self, p: i32, r: i32) -> Option<Rc<Operator>> {
+    let c = self.left_command_lookup(self.peek());
+    let reason =
+      match &c {
+        None => Some(Reason::NoLeftCommand),
+        Some(c) if p > c.lbp() => Some(Reason::Looser{ operator: c.name.clone(), lbp: c.lbp() }),
+        Some(c) if c.lbp() > r => Some(Reason::Tighter{ operator: c.name.clone(), lbp: c.lbp() }),
+        Some(c) => {
+          // An L token that breaks the operator's whitespace constraint does not select the
+          // operator.
+          let constraint = left_elements(c).get(1).copied().filter(|e| e.is_spacing());
+          match constraint.is_some_and(|constraint| !self.spacing_allows(constraint)) {
+            true => Some(Reason::Spacing{ operator: c.name.clone() }),
+            false => None
+          }
+        }
+      };
+
+    match reason {
+      Some(reason) => {
+        self.emit(|parser, token| Event::Break{
+          depth: parser.depth, token: token.text.clone(), p, r, reason, span: token.span
+        });
+        None
+      }
+      None => {
+        let c = c.unwrap();
+        self.emit(|parser, token| Event::Left{
+          depth   : parser.depth,
+          token   : token.text.clone(),
+          operator: c.name.clone(),
+          p, r,
+          lbp     : c.lbp(),
+          nbp     : c.nbp(),
+          span    : token.span
+        });
+        self.selected = Some(self.cursor);
+        Some(c)
+      }
+    }
+  }
+
+  /// Reports the next token, which an operator's syntax matches, to the trace unless it is the
+  /// token that selected the operator.
+  fn emit_token(&mut self) {
+    if self.selected != Some(self.cursor) {
+      self.emit(|parser, token| Event::Token{ depth: parser.depth, token: token.text.clone(), span: token.span });
+    }
+  }
+
+  /// Reports an event about the next token to the trace, if there is one.
+  fn emit<F>(&mut self, event: F) where F: FnOnce(&Parser, &Token) -> Event {
+    if self.trace.is_some() {
+      let event = event(self, self.peek());
+      if let Some(sink) = self.trace.as_mut() {
+        sink.event(&event);
+      }
+    }
+  }
+
+  /// Consumes an atom, or looks up the operator whose N token is next, which remains to be matched
+  /// from the start of the operator's syntax.
+  fn begin_null(&mut self) -> Result<Null, ParseError> {
+    let token = self.peek().clone();
+
+    let node =
+      match token.kind {
+
+        TokenKind::Symbol => ASTNode::symbol(&token.text),
+
+        TokenKind::String => ASTNode::string(&token.text),
+
+        TokenKind::Number => {
+          // The lexer only produces numbers of the form `digits` or `digits.digits`.
+          ASTNode::number(token.text.parse::<f64>().unwrap_or(f64::NAN))
+        }
+
+        TokenKind::Operator => {
+          let c = match self.null_command_lookup(&token) {
+            Some(c) => c,
+            None => return Err(unexpected("an expression", &token))
+          };
+          self.emit(|parser, token| Event::Null{
+            depth: parser.depth, token: token.text.clone(), operator: Some(c.name.clone()), span: token.span
+          });
+          self.selected = Some(self.cursor);
+          return Ok(Null::Operator(c, token.span.start));
+        }
+
+        TokenKind::EOF => return Err(unexpected("an expression", &token))
+
+      };
+
+    self.emit(|parser, token| Event::Null{
+      depth: parser.depth, token: token.text.clone(), operator: None, span: token.span
+    });
+    self.consume();
+    Ok(Null::Atom(Rc::new(node.with_span(token.span))))
+  }
+
+  /// Binds `left` as the left operand of `c`, returning the start of the node and the bindings.
+  fn begin_left(&self, c: &Operator, left: RcASTNode) -> Result<(usize, Bindings), ParseError> {
+    let start = left.span().map_or(self.peek().span.start, |span| span.start);
+    let mut bindings = Bindings::new();
+    match left_elements(c).first() {
+
+      Some(Syntax::Symbol) if left.name().is_none() || !left.is_atom() => {
+        return Err(ParseError::UnexpectedToken{
+          expected: format!("a symbol before {}", c.l_token.clone().unwrap_or_default()),
+          found   : "an expression".to_string(),
+          span    : left.span().unwrap_or_default()
+        });
+      }
+
+      Some(metavariable) => bindings.bind(metavariable, left),
+
+      None => {}
+
+    }
+    Ok((start, bindings))
+  }
+
+  /// Does the fully associative operator `c` continue with another of its L tokens?
+  fn repeats(&self, c: &Operator) -> bool {
+    c.associativity == Associativity::Full
+      && c.l_token.as_ref().is_some_and(|token| self.peek().is_operator(token))
+  }
+
+  fn make_node(&self, c: &Operator, bindings: &Bindings, start: usize)
+    -> Result<RcASTNode, ParseError>
+  {
+    let span = Span::new(start, self.last_end);
+    let template =
+      match (self.form, &c.fullform) {
+        (Form::FullForm, fullform) if *fullform != Syntax::Empty => fullform,
+        _ => &c.parse
+      };
+
+    match instantiate(template, &c.name, bindings) {
+      Ok(node) => Ok(Rc::new(node.with_span(span))),
+      Err(message) => Err(ParseError::Template{ operator: c.name.clone(), message, span })
+    }
+  }
+
+  /// Checks the whitespace constraint preceding `element`, returning whether to match the element.
+  /// An optional element that fails the constraint is treated as absent.
+  fn satisfies(&self, constraint: Option<&Syntax>, element: &Syntax) -> Result<bool, ParseError> {
+    match constraint {
+      Some(constraint) if !self.spacing_allows(constraint) => {
+        match element {
+          Syntax::Optional(_) | Syntax::ZeroPlus(_) | Syntax::ZeroPlusDelim(..) => Ok(false),
+          _ => Err(self.spacing_error(constraint))
+        }
+      }
+      _ => Ok(true)
+    }
+  }
+
+  /// Does the whitespace before the next token satisfy `constraint`?
+  fn spacing_allows(&self, constraint: &Syntax) -> bool {
+    let space_before = self.peek().space_before;
+    match constraint {
+      Syntax::NoSpace => !space_before,
+      Syntax::Space => space_before,
+      _ => true
+    }
+  }
+
+  fn spacing_error(&self, constraint: &Syntax) -> ParseError {
+    let token = self.peek();
+    match constraint {
+      Syntax::Space => ParseError::MissingSpace{ before: token.text.clone(), span: token.span },
+      _ => ParseError::UnexpectedSpace{ before: token.text.clone(), span: token.span },
+    }
+  }
+
+  fn match_symbol(&mut self, syntax: &Syntax, bindings: &mut Bindings) -> Result<(), ParseError> {
+    let token = self.peek().clone();
+    if token.kind != TokenKind::Symbol {
+      return Err(unexpected("a symbol", &token));
+    }
+    self.emit_token();
+    self.consume();
+    bindings.bind(syntax, Rc::new(ASTNode::symbol(&token.text).with_span(token.span)));
+    Ok(())
+  }
+
+  /// The first alternative the next token can begin.
+  fn choose_alternative<'s>(&self, list: &'s [Syntax]) -> Result<&'s Syntax, ParseError> {
+    match list.iter().find(|alternative| self.can_start(alternative)) {
+      Some(alternative) => Ok(alternative),
+      None => {
+        let expected = list.iter().map(|a| a.to_string()).collect::<Vec<_>>().join(" or ");
+        Err(unexpected(&expected, self.peek()))
+      }
+    }
+  }
+
+  /// Matches an element of a syntax that is a token.
+  fn match_token(&mut self, c: &Operator, syntax: &Syntax) -> Result<(), ParseError> {
+    match syntax.token_text() {
+      Some(text) => self.expect(&text).map(|_| ()),
+      // Metavariables like `n` and constructions like `f[x]` only make sense in templates.
+      None => {
+        Err(ParseError::Template{
+          operator: c.name.clone(),
+          message : format!("{} cannot appear in a syntax", syntax),
+          span    : self.peek().span
+        })
+      }
+    }
+  }
+
+  /// A run of a repeated token, e.g. `%%%` for `("%")+`, is lexed as a longer operator token if the
+  /// run or a part of it is itself a token, e.g. `%%`. If the next token is such a run of the token
+  /// of `syntax`, splits off its first occurrence so that each occurrence counts as a repetition.
+  fn split_repeated_token(&mut self, syntax: &Syntax) {
+    let text = match syntax.token_text() {
+      Some(text) if !text.is_empty() => text,
+      _ => return
+    };
+    let token = self.peek().clone();
+    if token.kind != TokenKind::Operator || !is_run_of(&token.text, &text) {
+      return;
+    }
+
+    let split = token.span.start + text.len();
+    let first = Token{
+      kind        : TokenKind::Operator,
+      text        : text.clone(),
+      span        : Span::new(token.span.start, split),
+      space_before: token.space_before,
+      space_after : false
+    };
+    let rest = Token{
+      kind        : TokenKind::Operator,
+      text        : token.text[text.len()..].to_string(),
+      span        : Span::new(split, token.span.end),
+      space_before: false,
+      space_after : token.space_after
+    };
+    self.tokens.splice(self.cursor..self.cursor + 1, [first, rest]);
+  }
+
+  /// Can the next token begin the given part of a syntax?
+  fn can_start(&self, syntax: &Syntax) -> bool {
+    let token = self.peek();
+
+    match syntax {
+
+      | Syntax::Expr1
+      | Syntax::Expr2
+      | Syntax::Expr3
+      | Syntax::Expr4 => {
+        match token.kind {
+          TokenKind::Operator => self.null_command_lookup(token).is_some(),
+          TokenKind::EOF => false,
+          _ => true
+        }
+      }
+
+      Syntax::Symbol => token.kind == TokenKind::Symbol,
+
+      | Syntax::Optional(inner)
+      | Syntax::OnePlus(inner)
+      | Syntax::ZeroPlus(inner)
+      | Syntax::OnePlusDelim(inner, _)
+      | Syntax::ZeroPlusDelim(inner, _) => self.can_start(inner),
+
+      | Syntax::Sequence(list)
+      | Syntax::SequenceDelim(list) => {
+        list.iter()
+            .find(|e| e.consumes_input())
+            .is_some_and(|e| self.can_start(e))
+      }
+
+      Syntax::Alternative(list) => list.iter().any(|e| self.can_start(e)),
+
+      other => other.token_text().is_some_and(|text| token.is_operator(&text)),
+
+    }
+  }
+
+  fn left_command_lookup(&self, token: &Token) -> Option<Rc<Operator>> {
+    match token.kind {
+      TokenKind::Operator => self.left_commands.get(&token.text).cloned(),
+      _ => None
+    }
+  }
+
+  /// Looks up the operator that starts with `token`. A run of a repeated N token that was lexed as
+  /// a longer token selects the operator of the repeated token, or of the longest one if it is a
+  /// run of several. See `split_repeated_token`.
+  fn null_command_lookup(&self, token: &Token) -> Option<Rc<Operator>> {
+    if token.kind != TokenKind::Operator {
+      return None;
+    }
+    if let Some(c) = self.null_commands.get(&token.text) {
+      return Some(c.clone());
+    }
+    self.null_tokens
+        .iter()
+        .find(|text| is_run_of(&token.text, text))
+        .map(|text| self.null_commands[text].clone())
+  }
+
+  /// The next token, which is not consumed. The last token is always `EOF`.
+  fn peek(&self) -> &Token {
+    &self.tokens[self.cursor.min(self.tokens.len() - 1)]
+  }
+
+  fn consume(&mut self) -> Token {
+    let token = self.peek().clone();
+    if token.kind != TokenKind::EOF {
+      self.cursor  += 1;
+      self.last_end = token.span.end;
+    }
+    token
+  }
 
-    let c = null_command_lookup(token);
-    let tree = c.null_denotation(terminal); // = c(terminal)
-    let mut r: u32 = INF;
+  fn expect(&mut self, text: &str) -> Result<Token, ParseError> {
+    if self.peek().is_operator(text) {
+      self.emit_token();
+      Ok(self.consume())
+    } else {
+      Err(unexpected(&format!("{:?}", text), self.peek()))
+    }
+  }
 
-    loop {
-      token = self.get_current_token();
-      // Look up the node class of the expression we are currently parsing
-      // based on the value of token. The node class knows it's left and
-      // next binding power.
-      c = self.left_command_lookup(token.type);
-      if (p > c.left_binding_power ) || (c.left_binding_power() > r) {
-        break;
+  /// Does one step of parsing, pushing the steps that remain onto `machine`. With `Stack::Native`,
+  /// parsing an operand recurses through this method, so each kind of step is a method of its own
+  /// to keep the frame small.
+  fn step(&mut self, step: Step, machine: &mut Machine) -> Result<(), ParseError> {
+    match step {
+      Step::Expression(p) => self.step_expression(p, machine),
+      Step::Continue{ p, r, lefts } => self.step_continue(p, r, lefts, machine),
+      Step::Null => self.step_null(machine),
+      Step::Finish{ c, start } => self.step_finish(&c, start, machine),
+      Step::FinishLeft{ c, start, elements } => self.step_finish_left(c, start, elements, machine),
+      Step::Sequence{ c, elements, i, trailing, constraint } => {
+        self.step_sequence(c, elements, i, trailing, constraint, machine)
+      }
+      Step::Match{ c, syntax, trailing } => match syntax {
+        | Syntax::Expr1
+        | Syntax::Expr2
+        | Syntax::Expr3
+        | Syntax::Expr4 => self.step_operand(&c, syntax, trailing, machine),
+        _ => self.step_match(c, syntax, trailing, machine)
+      },
+      Step::Bind(metavariable) => {
+        let operand = machine.result.take().unwrap();
+        machine.bindings.last_mut().unwrap().bind(&metavariable, operand);
+        Ok(())
+      }
+      Step::Repeat{ c, syntax, trailing, iterations, matching, cursor } => {
+        self.step_repeat(c, syntax, trailing, iterations, matching, cursor, machine)
       }
+      Step::OpenBindings => {
+        machine.bindings.push(Bindings::new());
+        Ok(())
+      }
+      Step::DropBindings => {
+        machine.bindings.pop();
+        Ok(())
+      }
+    }
+  }
 
-      self.consume();
-      let terminal = TerminalNodeImpl(token);
+  fn step_expression(&mut self, p: i32, machine: &mut Machine) -> Result<(), ParseError> {
+    self.enter(p)?;
+    machine.steps.push(Step::Continue{ p, r: INF, lefts: 0 });
+    machine.steps.push(Step::Null);
+    Ok(())
+  }
 
-      // Make the node
-      tree = c.left_denotation(self, tree, terminal); // = c(tree, terminal)
-      r = c.next_binding_power();
+  /// Look up the operator selected by the next token. The operator knows its left and next binding
+  /// powers.
+  fn step_continue(&mut self, p: i32, r: i32, lefts: usize, machine: &mut Machine)
+    -> Result<(), ParseError>
+  {
+    let tree = machine.result.take().unwrap();
+    match self.next_left_command(p, r) {
+      None => {
+        self.depth -= 1;
+        self.lefts -= lefts;
+        machine.result = Some(tree);
+      }
+      Some(c) => {
+        // The tree so far becomes a left operand, one level deeper.
+        self.check_depth()?;
+        self.lefts += 1;
+        let elements: Rc<[Syntax]> = left_elements(&c)[1..].iter().map(|e| (*e).clone()).collect();
+        let (start, bindings) = self.begin_left(&c, tree)?;
+        machine.bindings.push(bindings);
+        machine.steps.push(Step::Continue{ p, r: c.nbp(), lefts: lefts + 1 });
+        machine.steps.push(Step::FinishLeft{ c: c.clone(), start, elements: elements.clone() });
+        machine.steps.push(Step::Sequence{ c, elements, i: 0, trailing: true, constraint: None });
+      }
     }
+    Ok(())
+  }
 
-    return tree;
+  fn step_null(&mut self, machine: &mut Machine) -> Result<(), ParseError> {
+    match self.begin_null()? {
+      Null::Atom(node) => machine.result = Some(node),
+      Null::Operator(c, start) => {
+        let elements: Rc<[Syntax]> = c.syntax.elements().into_iter().cloned().collect();
+        machine.bindings.push(Bindings::new());
+        machine.steps.push(Step::Finish{ c: c.clone(), start });
+        machine.steps.push(Step::Sequence{ c, elements, i: 0, trailing: true, constraint: None });
+      }
+    }
+    Ok(())
+  }
 
+  fn step_finish(&mut self, c: &Operator, start: usize, machine: &mut Machine) -> Result<(), ParseError> {
+    let bindings = machine.bindings.pop().unwrap();
+    machine.result = Some(self.make_node(c, &bindings, start)?);
+    Ok(())
   }
 
-  fn left_command_lookup(&self, ttype: u32) -> ASTNode {
-    // Returns the node class for token type ttype.
-    self.left_command_table.get(ttype, DefaultLeftCommand)
+  /// Adjacent fully associative operators collapse into a single node. Each repetition binds the
+  /// operator's right operand again.
+  fn step_finish_left(&mut self, c: Rc<Operator>, start: usize, elements: Rc<[Syntax]>,
+                      machine: &mut Machine) -> Result<(), ParseError>
+  {
+    if self.repeats(&c) {
+      machine.steps.push(Step::FinishLeft{ c: c.clone(), start, elements: elements.clone() });
+      machine.steps.push(Step::Sequence{ c, elements, i: 0, trailing: true, constraint: None });
+      Ok(())
+    } else {
+      self.step_finish(&c, start, machine)
+    }
   }
 
-  fn null_command_lookup(&self, ttype: u32) -> ASTNode {
-    self.null_command_table.get(ttype, DefaultNullCommand)
+  fn step_sequence(&mut self, c: Rc<Operator>, elements: Rc<[Syntax]>, i: usize, trailing: bool,
+                   constraint: Option<Syntax>, machine: &mut Machine) -> Result<(), ParseError>
+  {
+    let element = match elements.get(i) {
+      Some(element) => element.clone(),
+      None => return Ok(())
+    };
+    if element.is_spacing() {
+      machine.steps.push(Step::Sequence{ c, elements, i: i + 1, trailing, constraint: Some(element) });
+      return Ok(());
+    }
+    let matched = self.satisfies(constraint.as_ref(), &element)?;
+    let last = elements.iter().rposition(|e| e.consumes_input());
+    machine.steps.push(Step::Sequence{ c: c.clone(), elements, i: i + 1, trailing, constraint: None });
+    if matched {
+      machine.steps.push(Step::Match{ c, syntax: element, trailing: trailing && Some(i) == last });
+    }
+    Ok(())
   }
 
-  /// The lexer.
-  fn next_token(&self) -> ASTNode {
-    ASTNode::Terminal("thisisaterminal")
+  /// Parses an operand and binds it to the metavariable `syntax`.
+  fn step_operand(&mut self, c: &Operator, syntax: Syntax, trailing: bool, machine: &mut Machine)
+    -> Result<(), ParseError>
+  {
+    // Operands that are not trailing are closed by a token, so they may be any expression.
+    let p = if trailing { c.rbp() } else { 0 };
+    match self.stack {
+      Stack::Native => {
+        let operand = self.E(p)?;
+        machine.bindings.last_mut().unwrap().bind(&syntax, operand);
+      }
+      Stack::Explicit => {
+        machine.steps.push(Step::Bind(syntax));
+        machine.steps.push(Step::Expression(p));
+      }
+    }
+    Ok(())
   }
 
-  /// NBP stands for the "next binding power". It gives the highest precedence of the operator that
-  /// this operator can be a left operand of.
-  pub fn next_binding_power(&self) -> u32 {
-    return -1;
+  fn step_match(&mut self, c: Rc<Operator>, syntax: Syntax, trailing: bool, machine: &mut Machine)
+    -> Result<(), ParseError>
+  {
+    match &syntax {
+
+      Syntax::Symbol => self.match_symbol(&syntax, machine.bindings.last_mut().unwrap())?,
+
+      Syntax::Optional(inner) => {
+        if self.can_start(inner) {
+          machine.steps.push(Step::Match{ c, syntax: (**inner).clone(), trailing });
+        }
+      }
+
+      | Syntax::OnePlus(_)
+      | Syntax::ZeroPlus(_)
+      | Syntax::OnePlusDelim(..)
+      | Syntax::ZeroPlusDelim(..) => {
+        machine.steps.push(Step::Repeat{
+          c, syntax, trailing, iterations: Vec::new(), matching: false, cursor: None
+        });
+      }
+
+      | Syntax::Sequence(list)
+      | Syntax::SequenceDelim(list) => {
+        let elements: Rc<[Syntax]> = list.iter().cloned().collect();
+        machine.steps.push(Step::Sequence{ c, elements, i: 0, trailing, constraint: None });
+      }
+
+      Syntax::Alternative(list) => {
+        let alternative = self.choose_alternative(list)?.clone();
+        machine.steps.push(Step::Match{ c, syntax: alternative, trailing });
+      }
+
+      | Syntax::NoSpace
+      | Syntax::Space
+      | Syntax::Newline
+      | Syntax::Empty => {}
+
+      other => self.match_token(&c, other)?,
+
+    }
+    Ok(())
   }
 
-  pub fn right_binding_power(&self) -> u32 {
-    return -1;
+  #[allow(clippy::too_many_arguments)]
+  fn step_repeat(&mut self, c: Rc<Operator>, syntax: Syntax, trailing: bool,
+                 mut iterations: Vec<Bindings>, matching: bool, cursor: Option<usize>,
+                 machine: &mut Machine) -> Result<(), ParseError>
+  {
+    if matching {
+      iterations.push(machine.bindings.pop().unwrap());
+    }
+
+    // The next iteration, if any: the delimiter preceding it and, if the group ends when an
+    // iteration consumes nothing, the cursor where it begins.
+    let next =
+      match (&syntax, iterations.is_empty()) {
+        _ if matching && cursor == Some(self.cursor) => None,
+        (Syntax::OnePlus(inner), true) => {
+          self.split_repeated_token(inner);
+          Some((inner, None, None))
+        }
+        (Syntax::OnePlus(inner) | Syntax::ZeroPlus(inner), _) => {
+          self.split_repeated_token(inner);
+          Some((inner, None, Some(self.cursor))).filter(|_| self.can_start(inner))
+        }
+        (Syntax::OnePlusDelim(inner, _), true) => Some((inner, None, None)),
+        (Syntax::ZeroPlusDelim(inner, _), true) => {
+          Some((inner, None, None)).filter(|_| self.can_start(inner))
+        }
+        (Syntax::OnePlusDelim(inner, delimiter) | Syntax::ZeroPlusDelim(inner, delimiter), false) => {
+          Some((inner, Some(delimiter), None)).filter(|_| self.can_start(delimiter))
+        }
+        _ => unreachable!("only repetition groups repeat")
+      };
+
+    match next {
+      None => machine.bindings.last_mut().unwrap().push_group(iterations),
+      Some((inner, delimiter, cursor)) => {
+        let inner = (**inner).clone();
+        let delimiter = delimiter.map(|delimiter| (**delimiter).clone());
+        machine.steps.push(Step::Repeat{
+          c: c.clone(), syntax, trailing, iterations, matching: true, cursor
+        });
+        machine.steps.push(Step::Match{ c: c.clone(), syntax: inner, trailing });
+        machine.steps.push(Step::OpenBindings);
+        if let Some(delimiter) = delimiter {
+          machine.steps.push(Step::DropBindings);
+          machine.steps.push(Step::Match{ c, syntax: delimiter, trailing: false });
+          machine.steps.push(Step::OpenBindings);
+        }
+      }
+    }
+    Ok(())
   }
 
-  /// LBP stands for "left binding power". It replaces the prec table. L
-  /// tokens will have a nonnegative LBP, while all others have an LBP of
-  ///  -1.
-  pub fn left_binding_power(&self) -> u32 {
-    return -1;
+}
+
+/// What begins an expression: an atom, or an operator with an N token and the start of its span.
+enum Null {
+  Atom(RcASTNode),
+  Operator(Rc<Operator>, usize),
+}
+
+/// A step of `E`. Each step that matches part of an operator's syntax pushes the steps that match
+/// the rest, so the stack of steps is the continuation of the parse. A step that completes a node
+/// leaves it in `Machine::result`, and the bindings of the operator being matched are the innermost
+/// of `Machine::bindings`.
+enum Step {
+  /// Begins an expression with binding power `p`.
+  Expression(i32),
+  /// Continues the expression with binding power `p` with the tree so far in `result`, whose last
+  /// operator had next binding power `r` and which is the left operand of `lefts` operators.
+  Continue{ p: i32, r: i32, lefts: usize },
+  /// `begin_null`
+  Null,
+  /// Builds the node of an operator that began with its N token.
+  Finish{ c: Rc<Operator>, start: usize },
+  /// Builds the node of an operator with a left operand, unless a fully associative operator
+  /// repeats.
+  FinishLeft{ c: Rc<Operator>, start: usize, elements: Rc<[Syntax]> },
+  /// Matches the elements of a sequence from the `i`th. The last element is `trailing` if the
+  /// sequence is, in which case an operand there is parsed with the operator's right binding
+  /// power. A whitespace `constraint` applies to the token beginning the element that follows it.
+  Sequence{ c: Rc<Operator>, elements: Rc<[Syntax]>, i: usize, trailing: bool, constraint: Option<Syntax> },
+  /// Matches one element of an operator's syntax.
+  Match{ c: Rc<Operator>, syntax: Syntax, trailing: bool },
+  /// Binds `result` to the metavariable.
+  Bind(Syntax),
+  /// A repetition group with the iterations matched so far. While `matching` an iteration, its
+  /// bindings are innermost. The group ends if the iteration began at `cursor` and consumed
+  /// nothing.
+  Repeat{
+    c         : Rc<Operator>,
+    syntax    : Syntax,
+    trailing  : bool,
+    iterations: Vec<Bindings>,
+    matching  : bool,
+    cursor    : Option<usize>
+  },
+  OpenBindings,
+  DropBindings,
+}
+
+#[derive(Default)]
+struct Machine {
+  steps   : Vec<Step>,
+  bindings: Vec<Bindings>,
+  result  : Option<RcASTNode>,
+}
+
+/// A copy of `operator` with the named characters in its tokens replaced with the characters they
+/// name.
+pub(crate) fn normalize_operator(operator: &Operator, named_chars: &NamedCharacters) -> Operator {
+  let normalize = |token: &Option<String>| token.as_ref().map(|t| named_chars.normalize(t));
+  Operator{
+    l_token: normalize(&operator.l_token),
+    n_token: normalize(&operator.n_token),
+    o_token: normalize(&operator.o_token),
+    syntax : named_chars.normalize_syntax(&operator.syntax),
+    ..operator.clone()
+  }
+}
+
+/// The elements of the syntax of an operator with a left operand that the parser matches: those
+/// that consume input and the whitespace constraints between them. The first is the left operand.
+pub(crate) fn left_elements(c: &Operator) -> Vec<&Syntax> {
+  c.syntax.elements().into_iter().filter(|e| e.consumes_input() || e.is_spacing()).collect()
+}
+
+/// Is `text` two or more repetitions of `token`?
+fn is_run_of(text: &str, token: &str) -> bool {
+  !token.is_empty() && text.len() > token.len() && text.trim_start_matches(token).is_empty()
+}
+
+pub(crate) fn unexpected(expected: &str, token: &Token) -> ParseError {
+  match token.kind {
+    TokenKind::EOF => ParseError::UnexpectedEnd{
+      expected: expected.to_string(),
+      span    : token.span
+    },
+    _ => ParseError::UnexpectedToken{
+      expected: expected.to_string(),
+      found   : token.text.clone(),
+      span    : token.span
+    }
   }
+}
 
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::grammar::syntax::{self, OpRecord};
+  use crate::grammar::compile_record;
+
+  fn literal(text: &str) -> Syntax {
+    Syntax::Literal(text.to_string())
+  }
+
+  fn word(text: &str) -> Box<Syntax> {
+    Box::new(Syntax::Word(text.to_string()))
+  }
+
+  fn operator(name: &str, precedence: u32, associativity: syntax::Associativity, syntax: Vec<Syntax>,
+              parse: Syntax) -> Operator
+  {
+    let record = OpRecord{
+      name: name.to_string(),
+      associativity,
+      precedence,
+      meaningful: true,
+      syntax: Syntax::Sequence(syntax),
+      parse,
+      fullform: Syntax::Empty,
+      latex: Syntax::Empty,
+      mathml: Syntax::Empty,
+      provenance: None
+    };
+    compile_record(&record).unwrap()
+  }
+
+  fn infix(name: &str, precedence: u32, associativity: syntax::Associativity, token: &str) -> Operator {
+    operator(name, precedence, associativity, vec![Syntax::Expr1, literal(token), Syntax::Expr2], Syntax::Empty)
+  }
+
+  fn arithmetic_parser() -> Parser {
+    let operators = vec![
+      infix("Power", 10, syntax::Associativity::Right, "^"),
+      infix("Times", 20, syntax::Associativity::Full, "*"),
+      infix("Divide", 20, syntax::Associativity::Left, "/"),
+      infix("Plus", 30, syntax::Associativity::Full, "+"),
+      infix("Less", 40, syntax::Associativity::Non, "<"),
+      operator(
+        "Minus", 15, syntax::Associativity::Right, vec![literal("-"), Syntax::Expr1], Syntax::Empty
+      ),
+      operator(
+        "Parentheses", 0, syntax::Associativity::Non,
+        vec![literal("("), Syntax::Expr1, literal(")")], Syntax::Expr1
+      ),
+      operator(
+        "Part", 1, syntax::Associativity::Left,
+        vec![
+          Syntax::Expr1,
+          literal("[["),
+          Syntax::ZeroPlusDelim(Box::new(Syntax::Expr2), Box::new(Syntax::Comma)),
+          literal("]]")
+        ],
+        Syntax::Cons(
+          word("Part"),
+          vec![Syntax::Expr1, Syntax::ZeroPlusDelim(Box::new(Syntax::Expr2), Box::new(Syntax::Comma))]
+        )
+      ),
+      operator(
+        "Construct", 1, syntax::Associativity::Left,
+        vec![
+          Syntax::Expr1,
+          literal("["),
+          Syntax::ZeroPlusDelim(Box::new(Syntax::Expr2), Box::new(Syntax::Comma)),
+          literal("]")
+        ],
+        Syntax::Cons(
+          Box::new(Syntax::Expr1),
+          vec![Syntax::ZeroPlusDelim(Box::new(Syntax::Expr2), Box::new(Syntax::Comma))]
+        )
+      ),
+    ];
+
+    Parser::new(operators.into_iter().map(|op| (op.name.clone(), op)).collect())
+  }
+
+  fn symbol(name: &str) -> RcASTNode {
+    Rc::new(ASTNode::symbol(name))
+  }
+
+  fn number(value: f64) -> RcASTNode {
+    Rc::new(ASTNode::number(value))
+  }
+
+  fn function(name: &str, children: Vec<RcASTNode>) -> RcASTNode {
+    Rc::new(ASTNode::function(name, children))
+  }
+
+  /// Compares trees, ignoring spans.
+  fn same_tree(a: &ASTNode, b: &ASTNode) -> bool {
+    a.evaluator() == b.evaluator()
+    && a.is_atom() == b.is_atom()
+    && match (a.head(), b.head()) {
+         (Some(x), Some(y)) => same_tree(x, y),
+         (None, None) => true,
+         _ => false
+       }
+    && a.children().len() == b.children().len()
+    && a.children().iter().zip(b.children().iter()).all(|(x, y)| same_tree(x, y))
+  }
+
+  fn assert_parses(parser: &mut Parser, text: &str, expected: RcASTNode) {
+    let tree = parser.parse(text).unwrap();
+    assert!(same_tree(&tree, &expected), "{} parsed as {}", text, tree);
+  }
+
+  #[test]
+  fn parse_precedence_and_associativity() {
+    let mut parser = arithmetic_parser();
+
+    assert_parses(
+      &mut parser,
+      "1 + 2 * x ^ y ^ 3",
+      function("Plus", vec![
+        number(1.0),
+        function("Times", vec![
+          number(2.0),
+          function("Power", vec![symbol("x"), function("Power", vec![symbol("y"), number(3.0)])])
+        ])
+      ])
+    );
+    assert_parses(
+      &mut parser,
+      "a / b / c",
+      function("Divide", vec![function("Divide", vec![symbol("a"), symbol("b")]), symbol("c")])
+    );
+    assert_parses(
+      &mut parser,
+      "a + b + c * d * e",
+      function("Plus", vec![
+        symbol("a"),
+        symbol("b"),
+        function("Times", vec![symbol("c"), symbol("d"), symbol("e")])
+      ])
+    );
+    assert_parses(
+      &mut parser,
+      "(a + b) + -c ^ 2",
+      function("Plus", vec![
+        function("Plus", vec![symbol("a"), symbol("b")]),
+        function("Minus", vec![function("Power", vec![symbol("c"), number(2.0)])])
+      ])
+    );
+  }
+
+  #[test]
+  fn parse_templates() {
+    let mut parser = arithmetic_parser();
+
+    assert_parses(
+      &mut parser,
+      "m[[i, j + 1]]",
+      function("Part", vec![
+        symbol("m"),
+        symbol("i"),
+        function("Plus", vec![symbol("j"), number(1.0)])
+      ])
+    );
+    assert_parses(
+      &mut parser,
+      "f[x][]",
+      Rc::new(ASTNode::compound(function("f", vec![symbol("x")]), vec![]))
+    );
+  }
+
+  #[test]
+  fn parse_counted_repetition() {
+    // syntax: ("%")+    parse: Out[Minus[n]]    fullform: Out[-n]
+    let mut out = operator(
+      "Out", 0, syntax::Associativity::Non,
+      vec![Syntax::OnePlus(Box::new(literal("%")))],
+      Syntax::Cons(word("Out"), vec![Syntax::Cons(word("Minus"), vec![Syntax::N])])
+    );
+    out.fullform = Syntax::Cons(word("Out"), vec![Syntax::NegN]);
+    // `%%` is a token of its own, so `%%%` lexes as `%%` `%`.
+    let percent_percent = operator(
+      "PercentPercent", 50, syntax::Associativity::Left,
+      vec![Syntax::Expr1, literal("%%"), Syntax::Expr2], Syntax::Empty
+    );
+    let mut parser = arithmetic_parser();
+    parser.add_operator(out);
+    parser.add_operator(percent_percent);
+
+    assert_parses(&mut parser, "%%%", function("Out", vec![function("Minus", vec![number(3.0)])]));
+    assert_parses(
+      &mut parser,
+      "% + 1",
+      function("Plus", vec![function("Out", vec![function("Minus", vec![number(1.0)])]), number(1.0)])
+    );
+
+    parser.form = Form::FullForm;
+    assert_parses(&mut parser, "%%%", function("Out", vec![number(-3.0)]));
+    assert_parses(
+      &mut parser,
+      "a %% b",
+      function("PercentPercent", vec![symbol("a"), symbol("b")])
+    );
+  }
+
+  #[test]
+  fn parse_overlapping_runs() {
+    // `%%%%` is a token of its own, and a run of both `%` and `%%`, whose operators repeat them.
+    // The longer wins, whatever order the command tables hold them in.
+    for _ in 0..8 {
+      let mut parser = arithmetic_parser();
+      parser.add_operator(operator(
+        "Out", 0, syntax::Associativity::Non,
+        vec![Syntax::OnePlus(Box::new(literal("%")))],
+        Syntax::Cons(word("Out"), vec![Syntax::N])
+      ));
+      parser.add_operator(operator(
+        "Pairs", 0, syntax::Associativity::Non,
+        vec![Syntax::OnePlus(Box::new(literal("%%")))],
+        Syntax::Cons(word("Pairs"), vec![Syntax::N])
+      ));
+      parser.add_operator(operator(
+        "Quadruple", 50, syntax::Associativity::Left,
+        vec![Syntax::Expr1, literal("%%%%"), Syntax::Expr2], Syntax::Empty
+      ));
+
+      assert_parses(&mut parser, "%%%%", function("Pairs", vec![number(2.0)]));
+    }
+  }
+
+  #[test]
+  fn parse_whitespace_constraints() {
+    let mut parser = arithmetic_parser();
+    // syntax: symb NoSpace "___" NoSpace (expr1)?
+    parser.add_operator(operator(
+      "NamedBlankNullSequence", 0, syntax::Associativity::Left,
+      vec![
+        Syntax::Symbol, Syntax::NoSpace, literal("___"), Syntax::NoSpace,
+        Syntax::Optional(Box::new(Syntax::Expr1))
+      ],
+      Syntax::Cons(
+        word("Pattern"),
+        vec![
+          Syntax::Symbol,
+          Syntax::Cons(word("BlankNullSequence"), vec![Syntax::Optional(Box::new(Syntax::Expr1))])
+        ]
+      )
+    ));
+    // syntax: expr1 Space "|" Space expr2
+    parser.add_operator(operator(
+      "Alternatives", 160, syntax::Associativity::Left,
+      vec![Syntax::Expr1, Syntax::Space, literal("|"), Syntax::Space, Syntax::Expr2],
+      Syntax::Empty
+    ));
+
+    assert_parses(
+      &mut parser,
+      "x___",
+      function("Pattern", vec![symbol("x"), function("BlankNullSequence", vec![])])
+    );
+    assert_parses(
+      &mut parser,
+      "x___h + 1",
+      function("Plus", vec![
+        function("Pattern", vec![symbol("x"), function("BlankNullSequence", vec![symbol("h")])]),
+        number(1.0)
+      ])
+    );
+    assert_parses(
+      &mut parser,
+      "x___ + 1",
+      function("Plus", vec![
+        function("Pattern", vec![symbol("x"), function("BlankNullSequence", vec![])]),
+        number(1.0)
+      ])
+    );
+    assert_eq!(
+      parser.parse("x ___"),
+      Err(ParseError::UnexpectedToken{
+        expected: "an operator or the end of the input".to_string(),
+        found   : "___".to_string(),
+        span    : Span::new(2, 5)
+      })
+    );
+    assert!(parser.parse("x___ h").is_err());
+
+    assert_parses(&mut parser, "a | b", function("Alternatives", vec![symbol("a"), symbol("b")]));
+    assert_eq!(
+      parser.parse("a | b|c"),
+      Err(ParseError::UnexpectedToken{
+        expected: "an operator or the end of the input".to_string(),
+        found   : "|".to_string(),
+        span    : Span::new(5, 6)
+      })
+    );
+    assert_eq!(
+      parser.parse("a |b"),
+      Err(ParseError::MissingSpace{ before: "b".to_string(), span: Span::new(3, 4) })
+    );
+  }
+
+  #[test]
+  fn parse_named_characters() {
+    let mut parser = arithmetic_parser();
+    // syntax: expr1 \[Element] expr2
+    parser.add_operator(operator(
+      "Element", 250, syntax::Associativity::Non,
+      vec![Syntax::Expr1, Syntax::NamedChar("Element".to_string()), Syntax::Expr2], Syntax::Empty
+    ));
+    // syntax: expr1 "\[Heart]" expr2
+    parser.add_operator(infix("Love", 50, syntax::Associativity::Left, "\\[Heart]"));
+
+    let expected = function("Element", vec![symbol("α"), symbol("β")]);
+    assert_parses(&mut parser, "\\[Alpha] \\[Element] \\[Beta]", expected.clone());
+    assert_parses(&mut parser, "α ∈ β", expected);
+    assert!(parser.parse("a \\[Heart] b").is_err());
+
+    parser.add_named_character("Heart", '♥');
+    let expected = function("Love", vec![symbol("a"), symbol("b")]);
+    assert_parses(&mut parser, "a \\[Heart] b", expected.clone());
+    assert_parses(&mut parser, "a ♥ b", expected);
+  }
+
+  #[test]
+  fn parse_spans() {
+    let mut parser = arithmetic_parser();
+    let tree = parser.parse("f[x] + 10").unwrap();
+
+    assert_eq!(tree.span(), Some(Span::new(0, 9)));
+    assert_eq!(tree.children()[0].span(), Some(Span::new(0, 4)));
+    assert_eq!(tree.children()[1].span(), Some(Span::new(7, 9)));
+  }
+
+  #[test]
+  fn parse_errors() {
+    let mut parser = arithmetic_parser();
+
+    assert_eq!(
+      parser.parse("a < b < c"),
+      Err(ParseError::UnexpectedToken{
+        expected: "an operator or the end of the input".to_string(),
+        found   : "<".to_string(),
+        span    : Span::new(6, 7)
+      })
+    );
+    assert_eq!(
+      parser.parse("f[x, y"),
+      Err(ParseError::UnexpectedEnd{ expected: "\"]\"".to_string(), span: Span::new(6, 6) })
+    );
+    assert!(parser.parse("* 2").is_err());
+    assert!(parser.parse("").is_err());
+  }
+
+  fn nested(depth: usize) -> String {
+    format!("{}a{}", "(".repeat(depth), ")".repeat(depth))
+  }
+
+  #[test]
+  fn parse_depth_limit() {
+    let mut parser = arithmetic_parser();
+
+    // The whole expression is the first level.
+    assert!(parser.parse(&nested(DEFAULT_MAX_DEPTH - 1)).is_ok());
+    assert_eq!(
+      parser.parse(&nested(100_000)),
+      Err(ParseError::TooDeep{ limit: DEFAULT_MAX_DEPTH, span: Span::new(DEFAULT_MAX_DEPTH, DEFAULT_MAX_DEPTH + 1) })
+    );
+    assert!(matches!(parser.parse(&vec!["a"; 100_000].join("^")), Err(ParseError::TooDeep{ .. })));
+    assert!(matches!(parser.parse(&"f[".repeat(100_000)), Err(ParseError::TooDeep{ .. })));
+    // The left operands of a left associative chain nest the tree as deeply, each counting as a
+    // level on top of the first.
+    assert!(parser.parse(&vec!["a"; DEFAULT_MAX_DEPTH - 1].join("/")).is_ok());
+    assert!(matches!(parser.parse(&vec!["a"; DEFAULT_MAX_DEPTH].join("/")), Err(ParseError::TooDeep{ .. })));
+    assert!(matches!(parser.parse(&vec!["a"; 100_000].join("/")), Err(ParseError::TooDeep{ .. })));
+    // Fully associative operators collapse into a single node rather than nest.
+    assert!(parser.parse(&vec!["a"; 100_000].join("+")).is_ok());
+
+    parser.max_depth = 3;
+    assert!(parser.parse("((a))").is_ok());
+    assert!(matches!(parser.parse("(((a)))"), Err(ParseError::TooDeep{ limit: 3, .. })));
+  }
+
+  #[test]
+  fn parse_with_explicit_stack() {
+    let mut recursive = arithmetic_parser();
+    let mut explicit = arithmetic_parser();
+    explicit.stack = Stack::Explicit;
+
+    let inputs = [
+      "a + b * c", "a ^ b ^ c", "a * b * c / d", "-a ^ b", "(a + b) * c", "f[x, y][z]", "a[[1, 2]]",
+      "f[]", "a < b < c", "f[x, y", "* 2", "", "(((a)))"
+    ];
+    for input in inputs {
+      assert_eq!(explicit.parse(input), recursive.parse(input), "{}", input);
+    }
+    explicit.max_depth = 3;
+    assert!(matches!(explicit.parse("(((a)))"), Err(ParseError::TooDeep{ limit: 3, .. })));
+
+    // Without the limit, nesting is bounded by memory rather than the stack.
+    let deep = std::thread::Builder::new().stack_size(64 * 1024).spawn(|| {
+      let mut parser = arithmetic_parser();
+      parser.stack = Stack::Explicit;
+      parser.max_depth = usize::MAX;
+      [nested(100_000), vec!["a"; 100_000].join("^"), format!("{}{}", "f[".repeat(100_000), "] ".repeat(100_000))]
+        .iter()
+        .all(|input| parser.parse(input).is_ok())
+    });
+    assert!(deep.unwrap().join().unwrap());
+  }
 }
@@ -0,0 +1,843 @@
+/*!
+
+Prints expression trees back to source text with as few parentheses as possible.
+
+`Printer::print` writes each compound expression `Name[operands]` with the syntax of the operator
+named `Name`, and parenthesizes an operand only where the parser would otherwise read it
+differently, so that parsing the printed text gives back the same tree. Whether an operand needs
+parentheses depends on where it appears in its operator's syntax and on the binding powers along
+the edge of the operand it sits against:
+
+  * A left operand must not absorb the operator that follows it: every operator on the left
+    operand's right edge must have a right binding power greater than the operator's left binding
+    power. If the left operand is itself an operator with an L token, the operator must also bind
+    no tighter than the left operand's next binding power allows.
+  * A trailing operand, parsed with the operator's right binding power, must take in every
+    operator on its own left edge. Here an operator whose left operand must be a symbol, like
+    `symb "_"`, counts as having a left operand, as the parser continues an expression with it
+    just the same.
+  * Adjacent applications of a fully associative operator are a single application with more
+    operands, so `Plus[a, b, c]` prints as `a + b + c` and `Plus[Plus[a, b], c]` as `(a + b) + c`.
+  * An operand delimited by tokens on both sides, like the `x` of `f[x]`, never needs them.
+
+Parentheses are written with the table's grouping operator, one whose syntax is
+`"(" expr1 ")"`, with any pair of tokens, and whose parse template is `expr1`, so that it leaves no
+trace in the tree. A tree that needs parentheses cannot be printed without one.
+
+Only operators with the default parse template, `name[operands]`, and a syntax without optional,
+repeated, or alternative parts can be printed, as only for these does the tree determine the
+source text. Operators whose token is shadowed by another operator's are not used, as the parser
+never selects them.
+
+By default a space separates every two tokens and operands. `Printer::with_spacing` chooses
+conventional spacing instead, `a + f[x, y]`, or the fewest spaces that still lex as the same tokens,
+`a+f[x,y]`; either way the printer keeps a space where an operator's syntax requires one and omits
+it where the syntax forbids one. `Printer::with_width` breaks fully associative chains that would
+run past the given column before their operator tokens, lining each continuation line up with the
+chain's first operand:
+
+```text
+alpha + beta + gamma
+  + delta
+```
+
+*/
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::ast::{ASTNode, is_symbol_name, quote};
+use crate::evaluator::Evaluator;
+use crate::grammar::syntax::Syntax;
+use crate::lexer::is_symbol_char;
+use crate::operator::{Associativity, Operator, OperatorTable};
+use crate::parser::left_elements;
+use crate::serialize::{MAX_DEPTH, too_deep};
+
+
+/// An expression laid out as source text.
+#[derive(Clone, Debug)]
+pub(crate) enum Term {
+  Atom(String),
+  Apply{ operator: Rc<Operator>, parts: Vec<Part> },
+}
+
+/// The parts of an operator application in the order they appear in the source text.
+#[derive(Clone, Debug)]
+pub(crate) enum Part {
+  Token(String),
+  Operand(Term),
+  /// The neighboring parts are not separated by whitespace.
+  NoSpace,
+  /// The neighboring parts are separated by whitespace.
+  Space,
+}
+
+impl Term {
+  /// The left operand, if the term is an operator application that takes one.
+  fn left(&self) -> Option<&Term> {
+    match self {
+      Term::Apply{ operator, parts } if takes_left_operand(operator) => match parts.first() {
+        Some(Part::Operand(term)) => Some(term),
+        _ => None
+      },
+      _ => None
+    }
+  }
+
+  /// The trailing operand, if the term ends with one that is parsed with the operator's right
+  /// binding power rather than closed by a token.
+  fn trailing(&self) -> Option<&Term> {
+    match self {
+      Term::Apply{ operator, parts } if ends_with_operand(&operator.syntax) => {
+        match parts.iter().rev().find(|p| !p.is_spacing()) {
+          Some(Part::Operand(term)) if parts.len() > 1 => Some(term),
+          _ => None
+        }
+      }
+      _ => None
+    }
+  }
+
+  fn operator(&self) -> Option<&Rc<Operator>> {
+    match self {
+      Term::Apply{ operator, .. } => Some(operator),
+      Term::Atom(_) => None
+    }
+  }
+
+  /// Writes the term as source text, wrapping every operator application in parentheses if
+  /// `parenthesize` is set.
+  pub(crate) fn write(&self, out: &mut String, parenthesize: bool) {
+    let mut writer = Writer::new(parenthesize, Spacing::Everywhere, None, Wrap::Fill, &[]);
+    writer.term(self);
+    out.push_str(&writer.out);
+  }
+
+  /// The text the term begins with.
+  fn first_text(&self, parenthesize: bool) -> &str {
+    match self {
+      Term::Atom(text) => text,
+      Term::Apply{ .. } if parenthesize => "(",
+      Term::Apply{ parts, .. } => parts.iter().map(|p| p.first_text(parenthesize)).find(|t| !t.is_empty()).unwrap_or(""),
+    }
+  }
+}
+
+impl Part {
+  fn first_text(&self, parenthesize: bool) -> &str {
+    match self {
+      Part::Token(text) => text,
+      Part::Operand(term) => term.first_text(parenthesize),
+      Part::NoSpace | Part::Space => "",
+    }
+  }
+
+  fn is_spacing(&self) -> bool {
+    matches!(self, Part::NoSpace | Part::Space)
+  }
+}
+
+/// Does the operator take a left operand that may be any expression, as opposed to a symbol?
+pub(crate) fn takes_left_operand(operator: &Operator) -> bool {
+  operator.l_token.is_some()
+    && left_elements(operator).first().is_some_and(|e| e.is_operand() && **e != Syntax::Symbol)
+}
+
+pub(crate) fn ends_with_operand(syntax: &Syntax) -> bool {
+  match syntax.elements().into_iter().rev().find(|e| e.consumes_input()) {
+    Some(Syntax::Optional(inner)) => ends_with_operand(inner),
+    Some(last) => matches!(last, Syntax::Expr1 | Syntax::Expr2 | Syntax::Expr3 | Syntax::Expr4),
+    None => false
+  }
+}
+
+/// The terms along the right edge of `term`: the term, its trailing operand, that operand's
+/// trailing operand, and so on.
+fn right_edge(term: &Term) -> Vec<&Term> {
+  let mut edge = vec![term];
+  while let Some(next) = edge.last().unwrap().trailing() {
+    edge.push(next);
+  }
+  edge
+}
+
+/// The terms along the left edge of `term`: the term, its left operand, and so on.
+fn left_edge(term: &Term) -> Vec<&Term> {
+  let mut edge = vec![term];
+  while let Some(next) = edge.last().unwrap().left() {
+    edge.push(next);
+  }
+  edge
+}
+
+/// Where an operand appears in its operator's syntax.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub(crate) enum Position {
+  Left,
+  Trailing,
+  /// Delimited by tokens on both sides.
+  Closed,
+}
+
+/// Would an operator on the right edge of `term` take `operator`, were it to follow the term, into
+/// its trailing operand?
+pub(crate) fn absorbs(term: &Term, operator: &Operator) -> bool {
+  right_edge(term)
+    .iter()
+    .filter(|t| t.trailing().is_some())
+    .any(|t| operator.lbp() >= t.operator().unwrap().rbp())
+}
+
+/// Would the parser read `operand` back as the operand of `operator` at `position`?
+pub(crate) fn fits(operator: &Operator, operand: &Term, position: Position) -> bool {
+  match position {
+
+    Position::Left => {
+      let continues =
+        match operand.operator() {
+          Some(left) if left.l_token.is_some() => {
+            operator.lbp() <= left.nbp()
+              && !(left.name == operator.name && operator.associativity == Associativity::Full)
+          }
+          _ => true
+        };
+      !absorbs(operand, operator) && continues
+    }
+
+    Position::Trailing => {
+      left_edge(operand)
+        .iter()
+        .filter_map(|t| t.operator())
+        .filter(|o| o.l_token.is_some())
+        .all(|o| o.lbp() >= operator.rbp())
+    }
+
+    Position::Closed => true
+
+  }
+}
+
+/// The operators of the table that the parser can select, in name order. As in the parser's command
+/// tables, of two operators with the same token, the one with the later name is selected.
+pub(crate) fn selectable_operators(table: &OperatorTable) -> Vec<Rc<Operator>> {
+  let mut names: Vec<&String> = table.keys().collect();
+  names.sort();
+  let mut selected: HashMap<(bool, &str), &str> = HashMap::new();
+  for name in &names {
+    let operator = &table[*name];
+    if let Some(token) = &operator.l_token {
+      selected.insert((true, token.as_str()), name.as_str());
+    }
+    if let Some(token) = &operator.n_token {
+      selected.insert((false, token.as_str()), name.as_str());
+    }
+  }
+
+  names.iter()
+       .map(|name| &table[*name])
+       .filter(|o| {
+         let token = o.l_token.as_deref().map(|t| (true, t)).or(o.n_token.as_deref().map(|t| (false, t)));
+         token.is_some_and(|token| selected.get(&token) == Some(&o.name.as_str()))
+       })
+       .map(|o| Rc::new(o.clone()))
+       .collect()
+}
+
+/// Is the operator a grouping operator, `"(" expr1 ")"` with the parse template `expr1`?
+fn is_grouping(operator: &Operator) -> bool {
+  let elements: Vec<&Syntax> =
+    operator.syntax.elements().into_iter().filter(|e| e.consumes_input()).collect();
+  operator.n_token.is_some()
+    && operator.parse == Syntax::Expr1
+    && matches!(
+      elements[..],
+      [open, Syntax::Expr1, close] if open.token_text().is_some() && close.token_text().is_some()
+    )
+}
+
+/// Can the tree determine the source text of the operator?
+pub(crate) fn is_printable(operator: &Operator) -> bool {
+  operator.parse == Syntax::Empty
+    && operator.syntax.elements().iter().all(|e| {
+      (e.is_operand() && !matches!(e, Syntax::Optional(_))) || e.token_text().is_some() || !e.consumes_input()
+    })
+}
+
+
+/// How the printer separates the tokens and operands of an operator application.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub enum Spacing {
+  /// A space between every two parts, as in `( a + b ) * f [ x , y ]`.
+  #[default]
+  Everywhere,
+  /// Spaces around infix operators and after delimiters, as in `(a + b) * f[x, y]`.
+  Conventional,
+  /// Only the spaces the lexer needs to tell the tokens apart, as in `(a+b)*f[x,y]`.
+  Minimal,
+}
+
+/// Where the printer breaks a fully associative chain that does not fit on a line.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub enum Wrap {
+  /// Before an operator token of the chain only when the token and its operand would not fit on
+  /// the line.
+  #[default]
+  Fill,
+  /// Before every operator token of the chain.
+  Each,
+}
+
+/// Writes terms as source text.
+struct Writer<'t> {
+  out         : String,
+  parenthesize: bool,
+  spacing     : Spacing,
+  width       : Option<usize>,
+  wrap        : Wrap,
+  /// The tokens of the table. The lexer reads the longest token it can, so two tokens written
+  /// without a space between them may read as a third.
+  tokens      : &'t [String],
+}
+
+impl<'t> Writer<'t> {
+  fn new(parenthesize: bool, spacing: Spacing, width: Option<usize>, wrap: Wrap, tokens: &'t [String]) -> Writer<'t> {
+    Writer{ out: String::new(), parenthesize, spacing, width, wrap, tokens }
+  }
+
+  fn column(&self) -> usize {
+    self.out.chars().rev().take_while(|c| *c != '\n').count()
+  }
+
+  /// The width of `term` written on a single line.
+  fn flat_width(&self, term: &Term) -> usize {
+    let mut flat = Writer{ out: String::new(), width: None, ..*self };
+    flat.term(term);
+    flat.out.chars().count()
+  }
+
+  fn term(&mut self, term: &Term) {
+    let (operator, parts) =
+      match term {
+        Term::Atom(text) => return self.out.push_str(text),
+        Term::Apply{ operator, parts } => (operator, parts),
+      };
+
+    // A fully associative chain too long for the line continues on the next, lined up with its
+    // first operand.
+    let wrapping =
+      operator.associativity == Associativity::Full
+        && takes_left_operand(operator)
+        && self.width.is_some_and(|width| self.column() + self.flat_width(term) > width);
+    if self.parenthesize {
+      self.out.push('(');
+    }
+    let indent = self.column();
+
+    let mut previous = None;
+    let mut constraint = None;
+    for (i, part) in parts.iter().enumerate() {
+      if part.is_spacing() {
+        constraint = Some(part);
+        continue;
+      }
+      if let Some(previous) = previous {
+        match constraint {
+          Some(Part::NoSpace) => {}
+          _ if wrapping && self.breaks_before(operator, parts, i) => {
+            self.out.push('\n');
+            self.out.extend(std::iter::repeat_n(' ', indent));
+          }
+          Some(Part::Space) => self.out.push(' '),
+          _ if self.separates(operator, parts, previous, i) => self.out.push(' '),
+          _ => {}
+        }
+      }
+      match part {
+        Part::Token(text) => self.out.push_str(text),
+        Part::Operand(term) => self.term(term),
+        Part::NoSpace | Part::Space => unreachable!(),
+      }
+      previous = Some(i);
+      constraint = None;
+    }
+
+    if self.parenthesize {
+      self.out.push(')');
+    }
+  }
+
+  /// Does a wrapped chain continue on a new line before `parts[i]`?
+  fn breaks_before(&self, operator: &Operator, parts: &[Part], i: usize) -> bool {
+    let is_chain_token = |part: &Part| matches!(part, Part::Token(text) if operator.l_token.as_ref() == Some(text));
+    if !is_chain_token(&parts[i]) {
+      return false;
+    }
+    match self.wrap {
+      Wrap::Each => true,
+      Wrap::Fill => {
+        let end = parts[i + 1..].iter().position(is_chain_token).map_or(parts.len(), |k| i + 1 + k);
+        let width: usize =
+          parts[i..end].iter()
+                       .map(|part| match part {
+                         Part::Token(text) => text.chars().count() + 1,
+                         Part::Operand(term) => self.flat_width(term) + 1,
+                         Part::NoSpace | Part::Space => 0,
+                       })
+                       .sum();
+        self.width.is_some_and(|limit| self.column() + width > limit)
+      }
+    }
+  }
+
+  /// Is `parts[next]` separated by a space from `parts[previous]`, the part written before it?
+  fn separates(&self, operator: &Operator, parts: &[Part], previous: usize, next: usize) -> bool {
+    // Juxtaposed operands always are, lest the second be read as continuing the first.
+    let operands = matches!((&parts[previous], &parts[next]), (Part::Operand(_), Part::Operand(_)));
+    match self.spacing {
+      Spacing::Everywhere => true,
+      Spacing::Minimal => operands || self.glues(&parts[next]),
+      Spacing::Conventional => {
+        let spaced =
+          match (&parts[previous], &parts[next]) {
+            (Part::Token(_), Part::Token(_)) => {
+              token_spacing(operator, parts, previous).1 && token_spacing(operator, parts, next).0
+            }
+            (Part::Token(_), _) => token_spacing(operator, parts, previous).1,
+            (_, Part::Token(_)) => token_spacing(operator, parts, next).0,
+            _ => true
+          };
+        operands || spaced || self.glues(&parts[next])
+      }
+    }
+  }
+
+  /// Would `part`, written right after the text so far, run together with it into a different
+  /// token?
+  fn glues(&self, part: &Part) -> bool {
+    let right = part.first_text(self.parenthesize);
+    let (Some(l), Some(r)) = (self.out.chars().last(), right.chars().next()) else {
+      return false;
+    };
+    (is_symbol_char(l) && is_symbol_char(r))
+      || (l.is_ascii_digit() && r == '.')
+      || (l == '.' && r.is_ascii_digit())
+      || self.tokens.iter().any(|token| {
+           token.char_indices().skip(1).any(|(k, _)| {
+             let rest = &token[k..];
+             self.out.ends_with(&token[..k]) && (right.starts_with(rest) || rest.starts_with(right))
+           })
+         })
+  }
+}
+
+/// Is the token `parts[i]` written with a space before it and after it in conventional spacing?
+fn token_spacing(operator: &Operator, parts: &[Part], i: usize) -> (bool, bool) {
+  let is_l_token = |k: usize| matches!(&parts[k], Part::Token(text) if k > 0 && operator.l_token.as_ref() == Some(text));
+  let following = |k: usize| parts[k + 1..].iter().position(|p| !p.is_spacing()).map(|n| k + 1 + n);
+  // Is the token followed by the trailing operand, which in a fully associative chain the next L
+  // token follows?
+  let next = following(i);
+  let trailing = ends_with_operand(&operator.syntax)
+    && next.is_some_and(|k| matches!(parts[k], Part::Operand(_)) && following(k).is_none_or(is_l_token));
+  match next {
+    // A prefix operator or an opening bracket
+    _ if i == 0 => (false, false),
+    // An infix operator, or a postfix operator or call bracket
+    _ if is_l_token(i) => (trailing, trailing),
+    // A closing bracket
+    None => (false, false),
+    // The second token of a ternary operator, or a delimiter
+    Some(_) => (trailing, true),
+  }
+}
+
+/// Prints expression trees as source text for an operator table.
+pub struct Printer {
+  operators: HashMap<String, Rc<Operator>>,
+  grouping : Option<Rc<Operator>>,
+  tokens   : Vec<String>,
+  spacing  : Spacing,
+  width    : Option<usize>,
+  wrap     : Wrap,
+}
+
+impl Printer {
+  pub fn new(table: &OperatorTable) -> Printer {
+    let selectable = selectable_operators(table);
+    Printer{
+      grouping : selectable.iter().find(|o| is_grouping(o)).cloned(),
+      operators: selectable.into_iter().filter(|o| is_printable(o)).map(|o| (o.name.clone(), o)).collect(),
+      tokens   : table.values().flat_map(|o| o.tokens()).collect(),
+      spacing  : Spacing::Everywhere,
+      width    : None,
+      wrap     : Wrap::Fill,
+    }
+  }
+
+  /// Sets how the printer separates tokens and operands. The default puts a space between any two.
+  pub fn with_spacing(self, spacing: Spacing) -> Printer {
+    Printer{ spacing, ..self }
+  }
+
+  /// Breaks fully associative chains, like `a + b + c`, that would run past column `width` over
+  /// several lines. Other operators are never broken, so a line may still be longer.
+  pub fn with_width(self, width: usize) -> Printer {
+    Printer{ width: Some(width), ..self }
+  }
+
+  /// Sets where a chain that does not fit on a line is broken. The default is `Wrap::Fill`.
+  pub fn with_wrap(self, wrap: Wrap) -> Printer {
+    Printer{ wrap, ..self }
+  }
+
+  /// Prints `node` with as few parentheses as possible. The printer recurses, so it takes trees only
+  /// as deep as the serializers do: a tree nested deeper than `serialize::MAX_DEPTH` is an error.
+  pub fn print(&self, node: &ASTNode) -> Result<String, String> {
+    let mut writer = Writer::new(false, self.spacing, self.width, self.wrap, &self.tokens);
+    writer.term(&self.term(node, 0)?);
+    Ok(writer.out)
+  }
+
+  /// Prints `node` with every operator application in parentheses, e.g. `((a + b) * c)`.
+  pub fn print_parenthesized(&self, node: &ASTNode) -> Result<String, String> {
+    let mut writer = Writer::new(true, self.spacing, self.width, self.wrap, &self.tokens);
+    writer.term(&ungroup(self.term(node, 0)?));
+    Ok(writer.out)
+  }
+
+  /// Lays out `node`, found `depth` nodes below the root. The terms built are at most twice as
+  /// deep, an operand and the parentheses around it, which bounds the recursion of the writer too.
+  pub(crate) fn term(&self, node: &ASTNode, depth: usize) -> Result<Term, String> {
+    if depth >= MAX_DEPTH {
+      return Err(too_deep());
+    }
+    if node.is_atom() {
+      return self.atom(node).map(Term::Atom);
+    }
+
+    let operator =
+      match node.head() {
+        Some(head) if head.is_atom() => node.name().and_then(|name| self.operators.get(name)),
+        _ => None
+      }
+      .ok_or_else(|| format!("No operator prints {}", describe(node)))?;
+
+    let elements = operator.syntax.elements();
+    let operand_count = elements.iter().filter(|e| e.is_operand()).count();
+    let children = node.children();
+    // Each repetition of a fully associative operator adds all of its operands but the first.
+    let repeats = operator.associativity == Associativity::Full && takes_left_operand(operator) && operand_count > 1;
+    let repeated = repeats && children.len() > operand_count && (children.len() - 1).is_multiple_of(operand_count - 1);
+    if children.len() != operand_count && !repeated {
+      return Err(format!(
+        "{} takes {} operands, but {} has {}",
+        operator.name, operand_count, describe(node), children.len()
+      ));
+    }
+
+    let last = elements.iter().rposition(|e| e.consumes_input());
+    let mut children = children.iter();
+    let mut parts = Vec::new();
+    let mut positions = Vec::new();
+    for (i, element) in elements.iter().enumerate() {
+      let position =
+        match i {
+          0 if takes_left_operand(operator) => Position::Left,
+          _ if Some(i) == last && ends_with_operand(&operator.syntax) => Position::Trailing,
+          _ => Position::Closed
+        };
+      match element {
+        Syntax::NoSpace => parts.push(Part::NoSpace),
+        Syntax::Space => parts.push(Part::Space),
+        Syntax::Symbol => {
+          let child = children.next().unwrap();
+          match child.is_atom() && child.name().is_some() {
+            true => parts.push(Part::Operand(self.term(child, depth + 1)?)),
+            false => return Err(format!("{} requires a symbol, not {}", operator.name, describe(child)))
+          }
+        }
+        e if e.is_operand() => {
+          let operand = self.operand(operator, children.next().unwrap(), position, depth + 1)?;
+          parts.push(Part::Operand(operand));
+          positions.push(position);
+        }
+        e => parts.extend(e.token_text().map(Part::Token)),
+      }
+    }
+
+    // The remaining operands of a fully associative operator repeat everything after its first
+    // operand, once for each group of operands.
+    let tail: Vec<Part> = parts[1..].to_vec();
+    let rest: Vec<_> = children.collect();
+    for group in rest.chunks(operand_count.max(2) - 1) {
+      self.close_operand(operator, &mut parts)?;
+      let mut operands = group.iter().zip(&positions[1..]);
+      for part in &tail {
+        match part {
+          Part::Operand(_) => {
+            let (child, position) = operands.next().unwrap();
+            parts.push(Part::Operand(self.operand(operator, child, *position, depth + 1)?));
+          }
+          part => parts.push(part.clone())
+        }
+      }
+    }
+
+    Ok(Term::Apply{ operator: operator.clone(), parts })
+  }
+
+  /// Lays out `node` as the operand of `operator` at `position`, in parentheses if it needs them.
+  fn operand(&self, operator: &Operator, node: &ASTNode, position: Position, depth: usize) -> Result<Term, String> {
+    let term = self.term(node, depth)?;
+    match fits(operator, &term, position) {
+      true => Ok(term),
+      false => self.group(term)
+    }
+  }
+
+  /// Parenthesizes the last operand in `parts` if it would take in the operator's token that is
+  /// about to follow it.
+  fn close_operand(&self, operator: &Operator, parts: &mut [Part]) -> Result<(), String> {
+    if let Some(Part::Operand(last)) = parts.last_mut() {
+      if absorbs(last, operator) {
+        *last = self.group(last.clone())?;
+      }
+    }
+    Ok(())
+  }
+
+  fn group(&self, term: Term) -> Result<Term, String> {
+    let grouping =
+      self.grouping
+          .as_ref()
+          .ok_or_else(|| "An operand needs parentheses, but the table has no grouping operator".to_string())?;
+    let tokens = grouping.syntax.tokens();
+    Ok(Term::Apply{
+      operator: grouping.clone(),
+      parts   : vec![Part::Token(tokens[0].clone()), Part::Operand(term), Part::Token(tokens[1].clone())],
+    })
+  }
+
+  fn atom(&self, node: &ASTNode) -> Result<String, String> {
+    match node.evaluator() {
+
+      // A symbol whose name is a word token is read as the symbol between backquotes.
+      Evaluator::Symbol(name) if is_symbol_name(name) && self.tokens.contains(name) => {
+        Ok(format!("`{}`", name))
+      }
+
+      // FullForm writes a symbol that is not an identifier and a number that is negative or not
+      // finite between backquotes, which the lexer reads back as the atom.
+      Evaluator::Symbol(_) | Evaluator::Number(_) => Ok(node.to_string()),
+
+      Evaluator::String(text) => Ok(quote(text)),
+
+      _ => Err(format!("{} has no input form", describe(node)))
+
+    }
+  }
+}
+
+/// The term without the parentheses `Printer::group` added to it.
+fn ungroup(term: Term) -> Term {
+  match term {
+    Term::Apply{ operator, mut parts } if is_grouping(&operator) => match parts.swap_remove(1) {
+      Part::Operand(inner) => ungroup(inner),
+      _ => unreachable!()
+    },
+    Term::Apply{ operator, parts } => {
+      let parts = parts.into_iter()
+                       .map(|part| match part {
+                         Part::Operand(term) => Part::Operand(ungroup(term)),
+                         part => part
+                       })
+                       .collect();
+      Term::Apply{ operator, parts }
+    }
+    atom => atom
+  }
+}
+
+/// Names a node in an error message.
+fn describe(node: &ASTNode) -> String {
+  match node.name() {
+    Some(name) if node.is_atom() => format!("the symbol {}", name),
+    Some(name) => format!("an expression with head {}", name),
+    None if node.is_atom() => "an atom".to_string(),
+    None => "an expression with a compound head".to_string(),
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use std::rc::Rc;
+
+  use super::*;
+  use crate::grammar::{compile_grammar_file, compile_records, parse_grammar};
+  use crate::parser::Parser;
+
+  fn node(name: &str, children: Vec<ASTNode>) -> ASTNode {
+    ASTNode::function(name, children.into_iter().map(Rc::new).collect())
+  }
+
+  fn symbol(name: &str) -> ASTNode {
+    ASTNode::symbol(name)
+  }
+
+  #[test]
+  fn minimal_parentheses() {
+    let printer = Printer::new(&compile_grammar_file("resources/operators.grammar").unwrap());
+    let print = |node: ASTNode| printer.print(&node).unwrap();
+
+    let (a, b, c) = (symbol("a"), symbol("b"), symbol("c"));
+    assert_eq!(print(node("Plus", vec![a.clone(), node("Times", vec![b.clone(), c.clone()])])), "a + b * c");
+    assert_eq!(print(node("Times", vec![node("Plus", vec![a.clone(), b.clone()]), c.clone()])), "( a + b ) * c");
+    assert_eq!(print(node("Plus", vec![a.clone(), b.clone(), c.clone()])), "a + b + c");
+    assert_eq!(print(node("Plus", vec![node("Plus", vec![a.clone(), b.clone()]), c.clone()])), "( a + b ) + c");
+    assert_eq!(print(node("Power", vec![a.clone(), node("Power", vec![b.clone(), c.clone()])])), "a ^ b ^ c");
+    assert_eq!(print(node("Power", vec![node("Power", vec![a.clone(), b.clone()]), c.clone()])), "( a ^ b ) ^ c");
+    assert_eq!(print(node("Divide", vec![node("Divide", vec![a.clone(), b.clone()]), c.clone()])), "a / b / c");
+    assert_eq!(print(node("Base", vec![node("Base", vec![a.clone(), b.clone()]), c.clone()])), "( a √ b ) √ c");
+    assert_eq!(print(ASTNode::string("say \"hi\"")), r#""say \"hi\"""#);
+  }
+
+  #[test]
+  fn printed_trees_parse_back() {
+    let table = compile_grammar_file("resources/operators.grammar").unwrap();
+    let printer = Printer::new(&table);
+    let mut parser = Parser::new(table);
+
+    for source in ["a - (b - c)", "(a = b) = c", "a = b := c", "(a + b) * (c + d)", "2 ^ (3 √ 4)"] {
+      let tree = parser.parse(source).unwrap();
+      let printed = printer.print(&tree).unwrap();
+      let reparsed = parser.parse(&printed).unwrap();
+      assert_eq!(printer.print(&reparsed).unwrap(), printed, "{}", source);
+    }
+  }
+
+  #[test]
+  fn unprintable_trees() {
+    let printer = Printer::new(&compile_grammar_file("resources/operators.grammar").unwrap());
+
+    assert!(printer.print(&node("Unknown", vec![symbol("a")])).is_err());
+    assert!(printer.print(&node("Times", vec![symbol("a")])).is_err());
+  }
+
+  #[test]
+  fn print_quoted_atoms() {
+    let table = compile_grammar_file("resources/operators.grammar").unwrap();
+    let printer = Printer::new(&table);
+    let mut parser = Parser::new(table);
+
+    let tree = node("Plus", vec![ASTNode::number(-1.0), symbol("a b"), ASTNode::number(f64::INFINITY)]);
+    let printed = printer.print(&tree).unwrap();
+    assert_eq!(printed, "`-1` + `a b` + `+inf.0`");
+    assert!(parser.parse(&printed).unwrap().same_expression(&tree));
+  }
+
+  #[test]
+  fn print_depth_limit() {
+    let printer = Printer::new(&compile_grammar_file("resources/operators.grammar").unwrap());
+    // Each `Power` takes the next as its left operand, in parentheses.
+    let nested = |depth: usize| (0..depth).fold(symbol("a"), |left, _| node("Power", vec![left, symbol("b")]));
+
+    let printed = printer.print(&nested(MAX_DEPTH - 1)).unwrap();
+    assert!(printed.starts_with(&"( ".repeat(MAX_DEPTH - 2)));
+    assert!(printer.print_parenthesized(&nested(MAX_DEPTH - 1)).is_ok());
+    assert_eq!(printer.print(&nested(MAX_DEPTH)), Err(too_deep()));
+    assert_eq!(printer.print_parenthesized(&nested(100_000)), Err(too_deep()));
+  }
+
+  /// A table with operators of each shape, and two tokens, `-` and `!`, that run together into
+  /// others.
+  const SHAPES: &str = "\
+defaults:
+associativity: left
+meaningful: true
+
+name: Call
+precedence: 1
+syntax: expr1 \"[\" expr2 \",\" expr3 \"]\"
+
+name: Power
+associativity: right
+precedence: 10
+syntax: expr1 \"^\" expr2
+
+name: Factorial
+precedence: 12
+syntax: expr1 \"!\"
+
+name: Decrement
+precedence: 12
+syntax: expr1 \"--\"
+
+name: Minus
+precedence: 15
+syntax: \"-\" expr1
+
+name: Times
+associativity: full
+precedence: 20
+syntax: expr1 \"*\" expr2
+
+name: Plus
+associativity: full
+precedence: 30
+syntax: expr1 \"+\" expr2
+
+name: Unequal
+associativity: non
+precedence: 40
+syntax: expr1 \"!=\" expr2
+
+name: Set
+associativity: right
+precedence: 50
+syntax: expr1 \"=\" expr2
+
+name: Parentheses
+associativity: non
+precedence: 100
+syntax: \"(\" expr1 \")\"
+parse: expr1
+";
+
+  #[test]
+  fn spacing() {
+    let table = compile_records(&parse_grammar(SHAPES).unwrap()).unwrap();
+    let mut parser = Parser::new(table.clone());
+
+    let cases = [
+      ("(a + b) * f[x, -y] ^ 2.5 != c!", ["( a + b ) * f [ x , - y ] ^ 2.5 != c !", "(a + b) * f[x, -y] ^ 2.5 != c!", "(a+b)*f[x,-y]^2.5!=c!"]),
+      ("- - a -- = b! != c",              ["- - a -- = b ! != c",                  "- -a-- = b! != c",               "- -a--=b!!=c"]),
+    ];
+    for (source, expected) in cases {
+      let tree = parser.parse(source).unwrap();
+      for (spacing, expected) in [Spacing::Everywhere, Spacing::Conventional, Spacing::Minimal].into_iter().zip(expected) {
+        let printed = Printer::new(&table).with_spacing(spacing).print(&tree).unwrap();
+        assert_eq!(printed, expected);
+        assert_eq!(parser.parse(&printed).unwrap().to_string(), tree.to_string(), "{}", printed);
+      }
+    }
+  }
+
+  #[test]
+  fn wrapping() {
+    let table = compile_records(&parse_grammar(SHAPES).unwrap()).unwrap();
+    let mut parser = Parser::new(table.clone());
+    let tree = parser.parse("x = alpha + beta + gamma * delta + epsilon + zeta").unwrap();
+    let printer = Printer::new(&table).with_spacing(Spacing::Conventional).with_width(24);
+
+    let filled = printer.print(&tree).unwrap();
+    assert_eq!(filled, "x = alpha + beta\n    + gamma * delta\n    + epsilon + zeta");
+    let each = printer.with_wrap(Wrap::Each).print(&tree).unwrap();
+    assert_eq!(each, "x = alpha\n    + beta\n    + gamma * delta\n    + epsilon\n    + zeta");
+
+    for printed in [filled, each] {
+      assert_eq!(parser.parse(&printed).unwrap().to_string(), tree.to_string(), "{}", printed);
+    }
+  }
+}
@@ -0,0 +1,300 @@
+/*!
+
+Property-based tests of the parser over random operator tables.
+
+Each seed gives a random operator table, compiled from grammar records, with infix operators of
+every associativity, prefix, postfix, matchfix, and ternary operators at a few precedences, some
+prefix operators sharing their token with an infix operator, and a grouping operator. For each
+expression the `corpus` module generates from the table:
+
+  1. The expression parses to the same tree as its fully parenthesized form.
+  2. Printing the tree with minimal parentheses and parsing the result gives back the same tree.
+  3. The reference parsers of the `reference` module parse it, and a near miss of it, the same way
+     the Pratt parser does.
+  4. The parser gives the same result, tree or error, with `Stack::Explicit` as it does recursing.
+
+A failure of the round trip is shrunk before it is reported: the tree is replaced by smaller trees
+(a subtree, an operand replaced by an atom, an operand of a fully associative operator dropped) and
+the table by one without an operator the tree does not use, for as long as the property still
+fails. The report gives the seed, the minimal table, and the minimal tree.
+
+*/
+
+use std::rc::Rc;
+
+use crate::ast::ASTNode;
+use crate::corpus::{Generator, Rng};
+use crate::grammar::{compile_grammar_file, compile_records};
+use crate::grammar::format::format_syntax;
+use crate::grammar::syntax::{Associativity, OpRecord, Syntax};
+use crate::operator::OperatorTable;
+use crate::parser::{Parser, Stack};
+use crate::printer::Printer;
+use crate::reference::differences;
+
+const GROUPING: &str = "Group";
+
+fn describe_table(table: &OperatorTable) -> String {
+  let mut names: Vec<&String> = table.keys().collect();
+  names.sort();
+  names.iter()
+       .map(|name| {
+         let operator = &table[*name];
+         format!(
+           "  {}: {}, {}, {:?}\n",
+           name, operator.precedence, format_syntax(&operator.syntax), operator.associativity
+         )
+       })
+       .collect()
+}
+
+fn literal(token: &str) -> Syntax {
+  Syntax::Literal(token.to_string())
+}
+
+fn take(rng: &mut Rng, tokens: &mut Vec<&'static str>) -> Option<&'static str> {
+  match tokens.is_empty() {
+    true => None,
+    false => Some(tokens.remove(rng.below(tokens.len())))
+  }
+}
+
+/// A random operator table.
+fn random_table(rng: &mut Rng) -> OperatorTable {
+  let mut singles: Vec<&'static str> = vec!["+", "-", "*", "/", "^", "!", "~", "@", "&", "#", "<", ">", "?", "|", ";", "="];
+  let mut pairs: Vec<(&str, &str)> = vec![("[", "]"), ("{", "}")];
+  let mut infix_tokens: Vec<&'static str> = Vec::new();
+  let mut records = vec![OpRecord{
+    name: GROUPING.to_string(),
+    associativity: Associativity::Non,
+    precedence: 0,
+    syntax: Syntax::Sequence(vec![literal("("), Syntax::Expr1, literal(")")]),
+    parse: Syntax::Expr1,
+    ..OpRecord::default()
+  }];
+
+  for i in 0..2 + rng.below(7) {
+    let precedence = 10 * (1 + rng.below(5) as u32);
+    let associativity = *rng.choose(&[Associativity::Left, Associativity::Right, Associativity::Non, Associativity::Full]);
+    let syntax =
+      match rng.below(6) {
+        0 | 1 => match take(rng, &mut singles) {
+          Some(token) => {
+            infix_tokens.push(token);
+            Syntax::Sequence(vec![Syntax::Expr1, literal(token), Syntax::Expr2])
+          }
+          None => continue
+        },
+        // A prefix operator, sometimes sharing its token with an infix operator.
+        2 => match (infix_tokens.is_empty() || rng.one_in(2), take(rng, &mut singles)) {
+          (true, Some(token)) => Syntax::Sequence(vec![literal(token), Syntax::Expr1]),
+          (false, _) => {
+            let token = infix_tokens[rng.below(infix_tokens.len())];
+            Syntax::Sequence(vec![literal(token), Syntax::Expr1])
+          }
+          _ => continue
+        },
+        3 => match take(rng, &mut singles) {
+          Some(token) => Syntax::Sequence(vec![Syntax::Expr1, literal(token)]),
+          None => continue
+        },
+        4 => match pairs.pop() {
+          Some((open, close)) => Syntax::Sequence(vec![literal(open), Syntax::Expr1, literal(close)]),
+          None => continue
+        },
+        _ => match (take(rng, &mut singles), take(rng, &mut singles)) {
+          (Some(first), Some(second)) => Syntax::Sequence(vec![
+            Syntax::Expr1, literal(first), Syntax::Expr2, literal(second), Syntax::Expr3
+          ]),
+          _ => continue
+        },
+      };
+    records.push(OpRecord{
+      name: format!("Op{}", i),
+      associativity,
+      precedence,
+      syntax,
+      ..OpRecord::default()
+    });
+  }
+
+  compile_records(&records).unwrap()
+}
+
+/// Printing `tree` and parsing the result gives back `tree`.
+fn round_trip(table: &OperatorTable, tree: &ASTNode) -> Result<(), String> {
+  let printed = Printer::new(table).print(tree)?;
+  let reparsed =
+    Parser::new(table.clone())
+      .parse(&printed)
+      .map_err(|e| format!("{} does not parse: {}", printed, e))?;
+  match tree.same_expression(&reparsed) {
+    true => Ok(()),
+    false => Err(format!("{} parses as {}", printed, reparsed))
+  }
+}
+
+/// Smaller trees to try in place of `node`, simplest first.
+fn simpler(node: &ASTNode) -> Vec<ASTNode> {
+  let atom = ASTNode::symbol("a");
+  if node.is_atom() {
+    return match node.name() {
+      Some("a") => vec![],
+      _ => vec![atom]
+    };
+  }
+
+  let head = node.head().unwrap();
+  let children = node.children();
+  let mut candidates = vec![atom];
+  candidates.extend(children.iter().filter(|c| !c.is_atom()).map(|c| (**c).clone()));
+  if children.len() > 2 {
+    for i in 0..children.len() {
+      let mut fewer = children.clone();
+      fewer.remove(i);
+      candidates.push(ASTNode::compound(head.clone(), fewer));
+    }
+  }
+  for (i, child) in children.iter().enumerate() {
+    for smaller in simpler(child) {
+      let mut replaced = children.clone();
+      replaced[i] = Rc::new(smaller);
+      candidates.push(ASTNode::compound(head.clone(), replaced));
+    }
+  }
+  candidates
+}
+
+fn uses(node: &ASTNode, name: &str) -> bool {
+  node.name() == Some(name) || node.children().iter().any(|c| uses(c, name))
+}
+
+/// Shrinks a counterexample to `property` to one from which neither the tree nor the table can
+/// lose anything without the property holding.
+fn shrink<P>(mut table: OperatorTable, mut tree: ASTNode, property: P) -> (OperatorTable, ASTNode, String)
+  where P: Fn(&OperatorTable, &ASTNode) -> Result<(), String>
+{
+  let mut failure = property(&table, &tree).expect_err("shrink requires a counterexample");
+
+  'shrinking: loop {
+    for candidate in simpler(&tree) {
+      if let Err(message) = property(&table, &candidate) {
+        tree = candidate;
+        failure = message;
+        continue 'shrinking;
+      }
+    }
+
+    let mut names: Vec<String> = table.keys().cloned().collect();
+    names.sort();
+    for name in names.iter().filter(|n| *n != GROUPING && !uses(&tree, n)) {
+      let mut smaller = table.clone();
+      smaller.remove(name);
+      if let Err(message) = property(&smaller, &tree) {
+        table = smaller;
+        failure = message;
+        continue 'shrinking;
+      }
+    }
+
+    return (table, tree, failure);
+  }
+}
+
+#[test]
+fn parse_and_round_trip_random_tables() {
+  for seed in 0..100 {
+    let mut rng = Rng::new(seed);
+    let table = random_table(&mut rng);
+    let mut parser = Parser::new(table.clone());
+    let mut generator = Generator::new(&table, seed);
+
+    for sample in generator.corpus(30) {
+      let tree =
+        parser.parse(&sample.input)
+              .unwrap_or_else(|e| panic!("seed {}: {} does not parse: {}\n{}", seed, sample.input, e, describe_table(&table)));
+      let expected = parser.parse(&sample.parenthesized).unwrap();
+      assert!(
+        tree.same_expression(&expected),
+        "seed {}: {} parses as {}, not as {}\n{}",
+        seed, sample.input, tree, sample.parenthesized, describe_table(&table)
+      );
+
+      if round_trip(&table, &tree).is_err() {
+        let (table, tree, failure) = shrink(table.clone(), (*tree).clone(), round_trip);
+        panic!(
+          "seed {}: the round trip of {} fails: {}\nminimal table:\n{}",
+          seed, tree, failure, describe_table(&table)
+        );
+      }
+    }
+  }
+}
+
+#[test]
+fn reference_parsers_agree_on_random_tables() {
+  for seed in 0..100 {
+    let table = random_table(&mut Rng::new(seed));
+    let mut generator = Generator::new(&table, seed);
+    let mut inputs = Vec::new();
+    for sample in generator.corpus(30) {
+      inputs.extend(generator.near_miss(&sample));
+      inputs.push(sample.input);
+    }
+
+    let differences = differences(&table, &inputs).unwrap();
+    assert!(
+      differences.is_empty(),
+      "seed {}:\n{}\n{}",
+      seed, differences[0], describe_table(&table)
+    );
+  }
+}
+
+#[test]
+fn explicit_stack_agrees_with_recursion() {
+  // The grammar files add repetition groups and whitespace constraints to the random tables.
+  let mut tables: Vec<(String, OperatorTable)> =
+    (0..50).map(|seed| (format!("seed {}", seed), random_table(&mut Rng::new(seed)))).collect();
+  for file in ["resources/operators.grammar", "examples/Grammar.txt"] {
+    tables.push((file.to_string(), compile_grammar_file(file).unwrap()));
+  }
+
+  for (name, table) in tables {
+    let mut recursive = Parser::new(table.clone());
+    let mut explicit = Parser::new(table.clone());
+    explicit.stack = Stack::Explicit;
+    let mut generator = Generator::new(&table, 0);
+    for sample in generator.corpus(30) {
+      for input in generator.near_miss(&sample).into_iter().chain([sample.input]) {
+        assert_eq!(explicit.parse(&input), recursive.parse(&input), "{}: {}", name, input);
+      }
+    }
+  }
+}
+
+#[test]
+fn shrinking_finds_minimal_counterexamples() {
+  let table = random_table(&mut Rng::new(3));
+  let names: Vec<String> = table.keys().filter(|n| *n != GROUPING).cloned().collect();
+  let mut parser = Parser::new(table.clone());
+  let mut generator = Generator::new(&table, 3).with_max_depth(5);
+
+  // A property that fails for any tree containing the operator `target`.
+  let target = &names[0];
+  let property = |_: &OperatorTable, tree: &ASTNode| match uses(tree, target) {
+    true => Err(format!("uses {}", target)),
+    false => Ok(())
+  };
+
+  let sample =
+    generator.corpus(200)
+             .into_iter()
+             .find(|s| s.input.len() > 20 && property(&table, &parser.parse(&s.input).unwrap()).is_err());
+  let tree = parser.parse(&sample.expect("a counterexample").input).unwrap();
+  let (table, tree, _) = shrink(table, (*tree).clone(), property);
+
+  assert_eq!(tree.name(), Some(target.as_str()));
+  assert!(tree.children().iter().all(|c| c.name() == Some("a")));
+  assert_eq!(table.len(), 2);
+}
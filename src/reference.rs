@@ -0,0 +1,686 @@
+/*!
+
+Reference parsers for differential testing of the Pratt parser in the `parser` module.
+
+`PrecedenceClimbing` and `ShuntingYard` parse expressions with the same `OperatorTable` as
+`Parser`, but with the two classic algorithms: precedence climbing recurses once per operator,
+parsing each operand with a bound on the precedence of the operators it may contain, and Dijkstra's
+shunting-yard algorithm keeps the operators still waiting for an operand on a stack, applying them
+once an incoming operator binds more loosely. Both work from the precedence and associativity of
+the operators directly rather than from the binding powers of `Operator`, so if `Parser` disagrees
+with them about an input, the bug is likely to be in `lbp`, `rbp`, or `nbp`.
+
+The reference parsers understand the shapes of operator found in textbooks, each with the default
+parse template, `name[operands]`:
+
+  * infix, `expr1 "+" expr2`, with any associativity,
+  * prefix, `"-" expr1`, and postfix, `expr1 "!"`,
+  * ternary, `expr1 "?" expr2 ":" expr3`, whose middle operand is delimited by tokens, and
+  * matchfix, `"[" expr1 "]"`, which may instead have the parse template `expr1`, as parentheses do.
+
+Where the textbooks leave a choice, they follow `Parser`:
+
+  * Of two adjacent operators of the same precedence, the one on the left decides how they group:
+    `a ^ b * c` is `a ^ (b * c)` if `^` is right associative, whatever the associativity of `*`.
+  * A non-associative operator cannot be the left operand of another operator of the same
+    precedence, so `a < b < c` does not parse, but a non-associative operator in the operand of a
+    looser operator does not prevent that operator from continuing, so `-a < b < c` parses as
+    `(-(a < b)) < c` if `-` binds more loosely than `<`.
+  * Adjacent applications of a fully associative operator with a left operand collapse into one
+    application with all of their operands.
+
+`differences` parses inputs with all three parsers and reports those they disagree about.
+
+*/
+
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
+
+use crate::ast::{ASTNode, RcASTNode};
+use crate::errors::ParseError;
+use crate::grammar::format_syntax;
+use crate::grammar::syntax::Syntax;
+use crate::lexer::{Lexer, Token, TokenKind};
+use crate::named_characters::NamedCharacters;
+use crate::operator::{Associativity, Operator, OperatorTable};
+use crate::parser::{normalize_operator, unexpected, Parser};
+use crate::printer::Printer;
+
+/// A precedence bound looser than any operator's.
+const LOOSEST: i64 = i64::MAX;
+/// A precedence bound tighter than any operator's.
+const TIGHTEST: i64 = i64::MIN;
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+enum Shape {
+  Infix,
+  /// With the token that ends the middle operand.
+  Ternary(String),
+  Postfix,
+  Prefix,
+  /// With the closing token.
+  Matchfix(String),
+}
+
+/// An operator as the reference parsers see it.
+#[derive(Debug)]
+struct Entry {
+  name         : String,
+  /// The token that selects the operator: its L token if it takes a left operand, otherwise its
+  /// N token.
+  token        : String,
+  shape        : Shape,
+  precedence   : i64,
+  associativity: Associativity,
+  /// The parse template is `expr1`, so the operator leaves no node of its own.
+  transparent  : bool,
+}
+
+impl Entry {
+  fn new(operator: &Operator) -> Result<Entry, String> {
+    let unsupported = || {
+      format!(
+        "The reference parsers do not support {}, with syntax {}",
+        operator.name, format_syntax(&operator.syntax)
+      )
+    };
+    let elements = operator.syntax.elements();
+    if elements.iter().any(|e| e.is_spacing()) {
+      return Err(unsupported());
+    }
+
+    let elements: Vec<&Syntax> = elements.into_iter().filter(|e| e.consumes_input()).collect();
+    let operand = |e: &Syntax| matches!(e, Syntax::Expr1 | Syntax::Expr2 | Syntax::Expr3 | Syntax::Expr4);
+    let tokens: Vec<String> = elements.iter().filter_map(|e| e.token_text()).collect();
+    let pattern: Vec<bool> = elements.iter().map(|e| operand(e)).collect();
+    if tokens.len() + pattern.iter().filter(|o| **o).count() != elements.len() {
+      return Err(unsupported());
+    }
+
+    let shape =
+      match pattern[..] {
+        [true, false, true] => Shape::Infix,
+        [true, false, true, false, true] => Shape::Ternary(tokens[1].clone()),
+        [true, false] => Shape::Postfix,
+        [false, true] => Shape::Prefix,
+        [false, true, false] => Shape::Matchfix(tokens[1].clone()),
+        _ => return Err(unsupported())
+      };
+    let transparent =
+      match &operator.parse {
+        Syntax::Empty => false,
+        Syntax::Expr1 if matches!(shape, Shape::Matchfix(_)) => true,
+        _ => return Err(unsupported())
+      };
+
+    Ok(Entry{
+      name         : operator.name.clone(),
+      token        : tokens[0].clone(),
+      shape,
+      precedence   : operator.precedence as i64,
+      associativity: operator.associativity,
+      transparent,
+    })
+  }
+
+  fn takes_left_operand(&self) -> bool {
+    matches!(self.shape, Shape::Infix | Shape::Ternary(_) | Shape::Postfix)
+  }
+
+  /// Do adjacent applications of the operator collapse into one?
+  fn collapses(&self) -> bool {
+    self.associativity == Associativity::Full && self.takes_left_operand()
+  }
+
+  /// The loosest precedence of an operator that the operator's last operand, if it is not closed by
+  /// a token, may continue with. An operator of the same precedence continues it only if the
+  /// operator is right associative.
+  fn right_bound(&self) -> i64 {
+    match (&self.shape, self.associativity) {
+      (Shape::Prefix, _) => self.precedence,
+      (Shape::Infix | Shape::Ternary(_), Associativity::Right) => self.precedence,
+      (Shape::Infix | Shape::Ternary(_), _) => self.precedence - 1,
+      _ => LOOSEST
+    }
+  }
+
+  /// The tightest precedence of an operator that may take an application of this operator as its
+  /// left operand. Only a looser operator may follow a non-associative one.
+  fn left_bound(&self) -> i64 {
+    match (&self.shape, self.associativity) {
+      (Shape::Infix | Shape::Ternary(_), Associativity::Non) => self.precedence + 1,
+      (Shape::Infix | Shape::Ternary(_) | Shape::Postfix, _) => self.precedence,
+      _ => TIGHTEST
+    }
+  }
+
+  fn apply(&self, mut operands: Vec<RcASTNode>) -> RcASTNode {
+    match self.transparent {
+      true => operands.remove(0),
+      false => Rc::new(ASTNode::function(&self.name, operands))
+    }
+  }
+}
+
+
+/// The operators of a table, looked up by the token that selects them.
+struct Operators {
+  left  : HashMap<String, Rc<Entry>>,
+  null  : HashMap<String, Rc<Entry>>,
+  sigils: Vec<String>,
+}
+
+impl Operators {
+  /// As in `Parser`, of two operators with the same token, the one with the later name is selected.
+  fn new(table: &OperatorTable) -> Result<Operators, String> {
+    let named_chars = NamedCharacters::default();
+    let mut names: Vec<&String> = table.keys().collect();
+    names.sort();
+
+    let mut operators = Operators{ left: HashMap::new(), null: HashMap::new(), sigils: Vec::new() };
+    for name in names {
+      let operator = normalize_operator(&table[name], &named_chars);
+      let entry = Rc::new(Entry::new(&operator)?);
+      match entry.takes_left_operand() {
+        true => operators.left.insert(entry.token.clone(), entry.clone()),
+        false => operators.null.insert(entry.token.clone(), entry.clone())
+      };
+      operators.sigils.extend(operator.tokens());
+    }
+    Ok(operators)
+  }
+
+  fn tokenize(&self, text: &str) -> Result<Vec<Token>, ParseError> {
+    Lexer::new(text, self.sigils.clone()).tokenize()
+  }
+
+  fn left(&self, token: &Token) -> Option<Rc<Entry>> {
+    match token.kind {
+      TokenKind::Operator => self.left.get(&token.text).cloned(),
+      _ => None
+    }
+  }
+
+  fn null(&self, token: &Token) -> Option<Rc<Entry>> {
+    match token.kind {
+      TokenKind::Operator => self.null.get(&token.text).cloned(),
+      _ => None
+    }
+  }
+}
+
+/// The operators of `table` that the reference parsers support.
+pub fn supported_operators(table: &OperatorTable) -> OperatorTable {
+  table.iter()
+       .filter(|(_, operator)| Entry::new(operator).is_ok())
+       .map(|(name, operator)| (name.clone(), operator.clone()))
+       .collect()
+}
+
+/// The node for an atom token, if the token is one.
+fn atom(token: &Token) -> Option<RcASTNode> {
+  let node =
+    match token.kind {
+      TokenKind::Symbol => ASTNode::symbol(&token.text),
+      TokenKind::String => ASTNode::string(&token.text),
+      TokenKind::Number => ASTNode::number(token.text.parse::<f64>().unwrap_or(f64::NAN)),
+      _ => return None
+    };
+  Some(Rc::new(node))
+}
+
+struct Tokens {
+  tokens: Vec<Token>,
+  cursor: usize,
+}
+
+impl Tokens {
+  /// The next token. The last token is always `EOF`.
+  fn peek(&self) -> &Token {
+    &self.tokens[self.cursor.min(self.tokens.len() - 1)]
+  }
+
+  fn consume(&mut self) {
+    self.cursor += 1;
+  }
+
+  fn expect(&mut self, text: &str) -> Result<(), ParseError> {
+    match self.peek().is_operator(text) {
+      true => {
+        self.consume();
+        Ok(())
+      }
+      false => Err(unexpected(&format!("{:?}", text), self.peek()))
+    }
+  }
+
+  fn expect_end(&self) -> Result<(), ParseError> {
+    match self.peek().kind {
+      TokenKind::EOF => Ok(()),
+      _ => Err(unexpected("an operator or the end of the input", self.peek()))
+    }
+  }
+}
+
+
+/// Parses expressions by precedence climbing.
+pub struct PrecedenceClimbing {
+  operators: Operators,
+}
+
+impl PrecedenceClimbing {
+  /// Fails if the table has an operator the reference parsers do not support.
+  pub fn new(table: &OperatorTable) -> Result<PrecedenceClimbing, String> {
+    Ok(PrecedenceClimbing{ operators: Operators::new(table)? })
+  }
+
+  pub fn parse(&self, text: &str) -> Result<RcASTNode, ParseError> {
+    let mut tokens = Tokens{ tokens: self.operators.tokenize(text)?, cursor: 0 };
+    let tree = self.expression(&mut tokens, LOOSEST)?;
+    tokens.expect_end()?;
+    Ok(tree)
+  }
+
+  /// Parses an expression whose operators, outside of delimited operands, have a precedence of at
+  /// most `bound`.
+  fn expression(&self, tokens: &mut Tokens, bound: i64) -> Result<RcASTNode, ParseError> {
+    let mut tree = self.primary(tokens)?;
+    // The tightest precedence of an operator that may take `tree` as its left operand.
+    let mut floor = TIGHTEST;
+
+    while let Some(entry) = self.operators.left(tokens.peek()) {
+      if entry.precedence > bound || entry.precedence < floor {
+        break;
+      }
+      tokens.consume();
+
+      let mut operands = vec![tree];
+      loop {
+        match &entry.shape {
+          Shape::Infix => operands.push(self.expression(tokens, entry.right_bound())?),
+          Shape::Ternary(second) => {
+            operands.push(self.expression(tokens, LOOSEST)?);
+            tokens.expect(second)?;
+            operands.push(self.expression(tokens, entry.right_bound())?);
+          }
+          _ => {}
+        }
+        if !(entry.collapses() && tokens.peek().is_operator(&entry.token)) {
+          break;
+        }
+        tokens.consume();
+      }
+
+      tree = entry.apply(operands);
+      floor = entry.left_bound();
+    }
+
+    Ok(tree)
+  }
+
+  /// Parses an atom or an operator without a left operand.
+  fn primary(&self, tokens: &mut Tokens) -> Result<RcASTNode, ParseError> {
+    let token = tokens.peek().clone();
+    if let Some(node) = atom(&token) {
+      tokens.consume();
+      return Ok(node);
+    }
+
+    let entry = self.operators.null(&token).ok_or_else(|| unexpected("an expression", &token))?;
+    tokens.consume();
+    let operand =
+      match &entry.shape {
+        Shape::Matchfix(close) => {
+          let operand = self.expression(tokens, LOOSEST)?;
+          tokens.expect(close)?;
+          operand
+        }
+        _ => self.expression(tokens, entry.right_bound())?
+      };
+    Ok(entry.apply(vec![operand]))
+  }
+}
+
+
+/// An operator on the shunting-yard stack, with the number of its operands already on the operand
+/// stack.
+enum Pending {
+  /// Waiting for its last operand, which is not closed by a token.
+  Trailing(Rc<Entry>, usize),
+  /// Waiting for the token that closes its current operand.
+  Delimited(Rc<Entry>, usize),
+}
+
+/// An expression on the operand stack, with the operator last applied to make it, if that operator
+/// takes a left operand.
+struct Operand {
+  node: RcASTNode,
+  last: Option<Rc<Entry>>,
+}
+
+/// What to do with the token following an operand.
+enum Action {
+  /// Apply the operator the token selects to the operand.
+  Apply(Rc<Entry>),
+  /// Add another operand to the fully associative operator on top of the stack.
+  Repeat,
+  /// Close the delimited operand on top of the stack.
+  Close,
+  End,
+}
+
+/// Parses expressions with Dijkstra's shunting-yard algorithm.
+pub struct ShuntingYard {
+  operators: Operators,
+}
+
+impl ShuntingYard {
+  /// Fails if the table has an operator the reference parsers do not support.
+  pub fn new(table: &OperatorTable) -> Result<ShuntingYard, String> {
+    Ok(ShuntingYard{ operators: Operators::new(table)? })
+  }
+
+  pub fn parse(&self, text: &str) -> Result<RcASTNode, ParseError> {
+    let mut tokens = Tokens{ tokens: self.operators.tokenize(text)?, cursor: 0 };
+    let mut operands: Vec<Operand> = Vec::new();
+    let mut pending: Vec<Pending> = Vec::new();
+
+    loop {
+      // An operand: any number of prefix operators and opening tokens, then an atom.
+      loop {
+        let token = tokens.peek().clone();
+        if let Some(node) = atom(&token) {
+          tokens.consume();
+          operands.push(Operand{ node, last: None });
+          break;
+        }
+        let entry = self.operators.null(&token).ok_or_else(|| unexpected("an expression", &token))?;
+        tokens.consume();
+        match entry.shape {
+          Shape::Matchfix(_) => pending.push(Pending::Delimited(entry, 0)),
+          _ => pending.push(Pending::Trailing(entry, 0))
+        }
+      }
+
+      // Operators following the operand, until one that needs another operand.
+      loop {
+        let token = tokens.peek().clone();
+        let entry = self.operators.left(&token);
+
+        let action =
+          loop {
+            let follows = |entry: &Rc<Entry>| {
+              operands.last()
+                      .and_then(|operand| operand.last.as_ref())
+                      .is_none_or(|last| entry.precedence >= last.left_bound())
+            };
+
+            match (pending.last(), &entry) {
+
+              (Some(Pending::Trailing(top, _)), Some(entry)) if Rc::ptr_eq(top, entry) && top.collapses() => {
+                break Action::Repeat;
+              }
+
+              (Some(Pending::Trailing(top, _)), Some(entry))
+                if entry.precedence <= top.right_bound() && follows(entry) => break Action::Apply(entry.clone()),
+
+              (Some(Pending::Trailing(..)), _) => self.reduce(&mut pending, &mut operands),
+
+              (Some(Pending::Delimited(..)) | None, Some(entry)) if follows(entry) => {
+                break Action::Apply(entry.clone());
+              }
+
+              (Some(Pending::Delimited(top, _)), _) => {
+                let close = match &top.shape {
+                  Shape::Ternary(close) | Shape::Matchfix(close) => close,
+                  _ => unreachable!()
+                };
+                match token.is_operator(close) {
+                  true => break Action::Close,
+                  false => return Err(unexpected(&format!("{:?}", close), &token))
+                }
+              }
+
+              (None, _) => {
+                tokens.expect_end()?;
+                break Action::End;
+              }
+
+            }
+          };
+
+        match action {
+
+          Action::Apply(entry) => {
+            tokens.consume();
+            match &entry.shape {
+              Shape::Postfix => {
+                while entry.collapses() && tokens.peek().is_operator(&entry.token) {
+                  tokens.consume();
+                }
+                let operand = operands.pop().unwrap();
+                operands.push(Operand{ node: entry.apply(vec![operand.node]), last: Some(entry) });
+              }
+              Shape::Ternary(_) => {
+                pending.push(Pending::Delimited(entry, 1));
+                break;
+              }
+              _ => {
+                pending.push(Pending::Trailing(entry, 1));
+                break;
+              }
+            }
+          }
+
+          Action::Repeat => {
+            tokens.consume();
+            match pending.pop() {
+              Some(Pending::Trailing(top, count)) => match &top.shape {
+                Shape::Ternary(_) => pending.push(Pending::Delimited(top, count + 1)),
+                _ => pending.push(Pending::Trailing(top, count + 1))
+              },
+              _ => unreachable!()
+            }
+            break;
+          }
+
+          Action::Close => {
+            tokens.consume();
+            match pending.pop() {
+              Some(Pending::Delimited(top, count)) => match &top.shape {
+                Shape::Ternary(_) => {
+                  pending.push(Pending::Trailing(top, count + 1));
+                  break;
+                }
+                _ => {
+                  let operand = operands.pop().unwrap();
+                  operands.push(Operand{ node: top.apply(vec![operand.node]), last: None });
+                }
+              },
+              _ => unreachable!()
+            }
+          }
+
+          Action::End => return Ok(operands.pop().unwrap().node)
+
+        }
+      }
+    }
+  }
+
+  /// Applies the operator on top of the stack to its operands.
+  fn reduce(&self, pending: &mut Vec<Pending>, operands: &mut Vec<Operand>) {
+    let (entry, count) =
+      match pending.pop() {
+        Some(Pending::Trailing(entry, count)) => (entry, count + 1),
+        _ => unreachable!()
+      };
+    let nodes = operands.split_off(operands.len() - count).into_iter().map(|o| o.node).collect();
+    let last = entry.takes_left_operand().then(|| entry.clone());
+    operands.push(Operand{ node: entry.apply(nodes), last });
+  }
+}
+
+
+/// An input that the parsers do not all parse the same way. Each result is either the expression,
+/// fully parenthesized, or the parse error.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Difference {
+  pub input              : String,
+  pub pratt              : String,
+  pub precedence_climbing: String,
+  pub shunting_yard      : String,
+}
+
+impl Display for Difference {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(
+      f,
+      "{}\n  Pratt parser:        {}\n  precedence climbing: {}\n  shunting yard:       {}",
+      self.input, self.pratt, self.precedence_climbing, self.shunting_yard
+    )
+  }
+}
+
+/// Parses each input with `Parser` and both reference parsers, returning the inputs they disagree
+/// about: those that one parses to a different expression than another, or that one rejects and
+/// another does not. Fails if the table has an operator the reference parsers do not support.
+pub fn differences(table: &OperatorTable, inputs: &[String]) -> Result<Vec<Difference>, String> {
+  let climbing = PrecedenceClimbing::new(table)?;
+  let shunting = ShuntingYard::new(table)?;
+  let mut parser = Parser::new(table.clone());
+  let printer = Printer::new(table);
+
+  let describe = |result: &Result<RcASTNode, ParseError>| match result {
+    Ok(node) => printer.print_parenthesized(node).unwrap_or_else(|_| node.to_string()),
+    Err(e) => format!("error: {}", e)
+  };
+  let agree = |a: &Result<RcASTNode, ParseError>, b: &Result<RcASTNode, ParseError>| match (a, b) {
+    (Ok(a), Ok(b)) => a.same_expression(b),
+    (Err(_), Err(_)) => true,
+    _ => false
+  };
+
+  let mut differences = Vec::new();
+  for input in inputs {
+    let pratt = parser.parse(input);
+    let precedence_climbing = climbing.parse(input);
+    let shunting_yard = shunting.parse(input);
+    if !agree(&pratt, &precedence_climbing) || !agree(&pratt, &shunting_yard) {
+      differences.push(Difference{
+        input              : input.clone(),
+        pratt              : describe(&pratt),
+        precedence_climbing: describe(&precedence_climbing),
+        shunting_yard      : describe(&shunting_yard),
+      });
+    }
+  }
+  Ok(differences)
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::grammar::{compile_grammar_file, compile_records};
+  use crate::grammar::syntax::{self, OpRecord};
+
+  fn literal(token: &str) -> Syntax {
+    Syntax::Literal(token.to_string())
+  }
+
+  fn record(name: &str, precedence: u32, associativity: syntax::Associativity, syntax: Vec<Syntax>) -> OpRecord {
+    OpRecord{
+      name: name.to_string(),
+      associativity,
+      precedence,
+      syntax: Syntax::Sequence(syntax),
+      ..OpRecord::default()
+    }
+  }
+
+  fn infix(name: &str, precedence: u32, associativity: syntax::Associativity, token: &str) -> OpRecord {
+    record(name, precedence, associativity, vec![Syntax::Expr1, literal(token), Syntax::Expr2])
+  }
+
+  fn table() -> OperatorTable {
+    compile_records(&[
+      infix("Power", 10, syntax::Associativity::Right, "^"),
+      infix("Times", 20, syntax::Associativity::Full, "*"),
+      infix("Divide", 20, syntax::Associativity::Left, "/"),
+      infix("Plus", 30, syntax::Associativity::Full, "+"),
+      infix("Less", 40, syntax::Associativity::Non, "<"),
+      record("Minus", 15, syntax::Associativity::Right, vec![literal("-"), Syntax::Expr1]),
+      record("Not", 45, syntax::Associativity::Right, vec![literal("~"), Syntax::Expr1]),
+      record("Factorial", 5, syntax::Associativity::Left, vec![Syntax::Expr1, literal("!")]),
+      record(
+        "If", 50, syntax::Associativity::Right,
+        vec![Syntax::Expr1, literal("?"), Syntax::Expr2, literal(":"), Syntax::Expr3]
+      ),
+      record("List", 0, syntax::Associativity::Non, vec![literal("{"), Syntax::Expr1, literal("}")]),
+      OpRecord{
+        parse: Syntax::Expr1,
+        ..record("Parentheses", 0, syntax::Associativity::Non, vec![literal("("), Syntax::Expr1, literal(")")])
+      },
+    ]).unwrap()
+  }
+
+  fn inputs(texts: &[&str]) -> Vec<String> {
+    texts.iter().map(|t| t.to_string()).collect()
+  }
+
+  #[test]
+  fn reference_parsers_agree_with_the_pratt_parser() {
+    let table = table();
+    let texts = inputs(&[
+      "a + b * c ^ d ^ e", "a * b / c * d", "a + b + c * d * e", "(a + b) + c", "- a ^ b !",
+      "- a * b", "~ a < b < c", "a < b < c", "a < b + c", "a + b < c", "a ? b ? c : d : e ? f : g",
+      "{ a ? b : c } !", "a ! !", "~ { a } < b", "a + * b", "( a", "a b", "a ?", "a : b",
+    ]);
+    assert_eq!(differences(&table, &texts).unwrap(), vec![]);
+
+    let climbing = PrecedenceClimbing::new(&table).unwrap();
+    let shunting = ShuntingYard::new(&table).unwrap();
+    let printer = Printer::new(&table);
+    for (text, expected) in [
+      ("a ^ b ^ c / d / e", "(((a ^ (b ^ c)) / d) / e)"),
+      ("~ a < b < c", "((~ (a < b)) < c)"),
+      ("a ? b : c ? d : e", "(a ? b : (c ? d : e))"),
+      ("a * b * c + d", "((a * b * c) + d)"),
+    ] {
+      for tree in [climbing.parse(text).unwrap(), shunting.parse(text).unwrap()] {
+        assert_eq!(printer.print_parenthesized(&tree).unwrap(), expected);
+      }
+    }
+  }
+
+  #[test]
+  fn differences_are_reported() {
+    // `Parser` treats precedences beyond `MAX_PRECEDENCE` as `MAX_PRECEDENCE`, so it sees `Times`
+    // and `Plus` at the same precedence, while the reference parsers still tell them apart.
+    let mut table = table();
+    table.get_mut("Times").unwrap().precedence = 20_000;
+    table.get_mut("Plus").unwrap().precedence = 30_000;
+
+    assert_eq!(
+      differences(&table, &inputs(&["a + b * c", "a * b + c"])).unwrap(),
+      vec![Difference{
+        input              : "a + b * c".to_string(),
+        pratt              : "((a + b) * c)".to_string(),
+        precedence_climbing: "(a + (b * c))".to_string(),
+        shunting_yard      : "(a + (b * c))".to_string(),
+      }]
+    );
+  }
+
+  #[test]
+  fn unsupported_operators() {
+    let table = compile_grammar_file("resources/operators.grammar").unwrap();
+    assert!(PrecedenceClimbing::new(&table).is_err());
+
+    let supported = supported_operators(&table);
+    assert!(!supported.is_empty() && supported.len() < table.len());
+    assert!(ShuntingYard::new(&supported).is_ok());
+  }
+}
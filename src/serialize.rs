@@ -0,0 +1,820 @@
+/*!
+
+Writes expression trees as S-expressions and as JSON, and reads them back, for handing trees to
+programs in other languages. Both forms are lossless: reading a tree back gives the same tree,
+spans included. A built-in function has no serialized form, as it is a pointer into this program.
+
+# S-expressions
+
+An atom is written as in FullForm: a symbol by name, a number as a decimal, and a string in double
+quotes, with `"`, `\`, newlines, and tabs escaped. A symbol whose name the lexer would not read as a
+symbol is written between bars, `|a b|`, with `|` and `\` escaped. Infinities and NaN are written
+`+inf.0`, `-inf.0`, and `+nan.0`. A compound expression is a list of its head and its children, so
+that `Plus[a, Times[b, c]]` is `(Plus a (Times b c))`, `f[]` is `(f)`, and `f[x][y]` is
+`((f x) y)`. A node with a span is followed by `@start:end`, the byte offsets of the source text it
+was parsed from:
+
+```text
+(Plus a@0:1 (Times b@4:5 c@8:9)@4:9)@0:9
+```
+
+# JSON
+
+A node is an object with exactly one of the members below that say what it is, and an optional
+`span`:
+
+```text
+{"symbol": "a"}                          a symbol
+{"number": 2.5}                          a number; "Infinity", "-Infinity", or "NaN" if not finite
+{"string": "text"}                       a string
+{"name": "Plus", "children": [...]}      a compound expression whose head is a symbol without a span
+{"head": {...}, "children": [...]}       any other compound expression
+"span": [start, end]                     the byte offsets of the source text the node was parsed from
+```
+
+`to_json` writes no whitespace and the members in the order above; `from_json` reads any JSON that
+follows the schema, and rejects unknown members.
+
+*/
+
+use std::fmt::Write;
+use std::rc::Rc;
+
+use crate::ast::{ASTNode, Children, is_symbol_name, quote};
+use crate::evaluator::Evaluator;
+use crate::lexer::Span;
+use crate::parser::DEFAULT_MAX_DEPTH;
+use crate::trace::json_string;
+
+
+/// The deepest nesting of nodes the writers and readers accept, so that whatever one writes the
+/// other reads back. The parser's `DEFAULT_MAX_DEPTH` bounds the subexpressions and left operands
+/// in progress at once, not the depth of the finished tree: a left-associative chain is built on
+/// top of its first operand, which may itself be a chain in parentheses, and a parse template may
+/// build more than one node for a subexpression. Twice the parser's limit covers the usual cases,
+/// but not every tree the parser accepts. Neither side recurses, so a deeper tree is an error
+/// rather than a stack overflow.
+pub const MAX_DEPTH: usize = 2 * DEFAULT_MAX_DEPTH;
+
+pub(crate) fn too_deep() -> String {
+  format!("Nodes are nested more than {} deep", MAX_DEPTH)
+}
+
+/// What remains to be written of a tree, last first. A compound node is begun when its `Node` is
+/// written and ended by its `Close`.
+enum Pending<'n> {
+  Node(&'n ASTNode, usize),
+  Text(&'static str),
+  Close(&'n ASTNode),
+}
+
+/// Writes `node` as an S-expression.
+pub fn to_sexpr(node: &ASTNode) -> Result<String, String> {
+  let mut out = String::new();
+  write_sexpr(node, &mut out)?;
+  Ok(out)
+}
+
+fn write_sexpr(node: &ASTNode, out: &mut String) -> Result<(), String> {
+  let mut pending = vec![Pending::Node(node, 0)];
+  while let Some(next) = pending.pop() {
+    let (node, depth) =
+      match next {
+        Pending::Node(node, depth) => (node, depth),
+        Pending::Text(text) => { out.push_str(text); continue; }
+        Pending::Close(node) => {
+          out.push(')');
+          write_sexpr_span(node, out);
+          continue;
+        }
+      };
+    if depth >= MAX_DEPTH {
+      return Err(too_deep());
+    }
+
+    match (node.head(), node.evaluator()) {
+
+      (Some(head), _) => {
+        out.push('(');
+        pending.push(Pending::Close(node));
+        for child in node.children().iter().rev() {
+          pending.push(Pending::Node(child, depth + 1));
+          pending.push(Pending::Text(" "));
+        }
+        pending.push(Pending::Node(head, depth + 1));
+        continue;
+      }
+
+      (None, Evaluator::Symbol(name)) if is_symbol_name(name) => out.push_str(name),
+
+      (None, Evaluator::Symbol(name)) => {
+        out.push('|');
+        for c in name.chars() {
+          match c {
+            '|' | '\\' => { out.push('\\'); out.push(c); }
+            c => out.push(c)
+          }
+        }
+        out.push('|');
+      }
+
+      (None, Evaluator::Number(value)) if value.is_nan() => out.push_str("+nan.0"),
+
+      (None, Evaluator::Number(value)) if value.is_infinite() => {
+        out.push_str(if *value > 0.0 { "+inf.0" } else { "-inf.0" })
+      }
+
+      (None, Evaluator::Number(value)) => write!(out, "{}", value).unwrap(),
+
+      (None, Evaluator::String(text)) => out.push_str(&quote(text)),
+
+      (None, Evaluator::BuiltIns(_)) => return Err("A built-in function cannot be serialized".to_string()),
+
+    }
+    write_sexpr_span(node, out);
+  }
+  Ok(())
+}
+
+fn write_sexpr_span(node: &ASTNode, out: &mut String) {
+  if let Some(span) = node.span() {
+    write!(out, "@{}:{}", span.start, span.end).unwrap();
+  }
+}
+
+/// Reads a tree written by `to_sexpr`. Whitespace may separate any two tokens, except a node and its
+/// span.
+pub fn from_sexpr(text: &str) -> Result<ASTNode, String> {
+  let mut reader = Reader::new(text);
+  let node = reader.sexpr()?;
+  reader.end()?;
+  Ok(node)
+}
+
+/// Writes `node` as JSON.
+pub fn to_json(node: &ASTNode) -> Result<String, String> {
+  let mut out = String::new();
+  write_json(node, &mut out)?;
+  Ok(out)
+}
+
+fn write_json(node: &ASTNode, out: &mut String) -> Result<(), String> {
+  let mut pending = vec![Pending::Node(node, 0)];
+  while let Some(next) = pending.pop() {
+    let (node, depth) =
+      match next {
+        Pending::Node(node, depth) => (node, depth),
+        Pending::Text(text) => { out.push_str(text); continue; }
+        Pending::Close(node) => {
+          out.push(']');
+          write_json_span(node, out);
+          continue;
+        }
+      };
+    if depth >= MAX_DEPTH {
+      return Err(too_deep());
+    }
+
+    match (node.head(), node.evaluator()) {
+
+      (Some(head), _) => {
+        pending.push(Pending::Close(node));
+        for (i, child) in node.children().iter().enumerate().rev() {
+          pending.push(Pending::Node(child, depth + 1));
+          if i > 0 {
+            pending.push(Pending::Text(","));
+          }
+        }
+        pending.push(Pending::Text(",\"children\":["));
+        match (head.name(), head.is_atom() && head.span().is_none()) {
+          (Some(name), true) => write!(out, "{{\"name\":{}", json_string(name)).unwrap(),
+          _ => {
+            out.push_str("{\"head\":");
+            pending.push(Pending::Node(head, depth + 1));
+          }
+        }
+        continue;
+      }
+
+      (None, Evaluator::Symbol(name)) => write!(out, "{{\"symbol\":{}", json_string(name)).unwrap(),
+
+      (None, Evaluator::Number(value)) if value.is_nan() => out.push_str("{\"number\":\"NaN\""),
+
+      (None, Evaluator::Number(value)) if value.is_infinite() => {
+        out.push_str(if *value > 0.0 { "{\"number\":\"Infinity\"" } else { "{\"number\":\"-Infinity\"" })
+      }
+
+      (None, Evaluator::Number(value)) => write!(out, "{{\"number\":{}", value).unwrap(),
+
+      (None, Evaluator::String(text)) => write!(out, "{{\"string\":{}", json_string(text)).unwrap(),
+
+      (None, Evaluator::BuiltIns(_)) => return Err("A built-in function cannot be serialized".to_string()),
+
+    }
+    write_json_span(node, out);
+  }
+  Ok(())
+}
+
+/// Writes the span of a node, if it has one, and ends the node's object.
+fn write_json_span(node: &ASTNode, out: &mut String) {
+  if let Some(span) = node.span() {
+    write!(out, ",\"span\":[{},{}]", span.start, span.end).unwrap();
+  }
+  out.push('}');
+}
+
+/// Reads a tree in the JSON schema `to_json` writes.
+pub fn from_json(text: &str) -> Result<ASTNode, String> {
+  let mut reader = Reader::new(text);
+  let value = reader.json()?;
+  reader.end()?;
+  node_from_json(&value)
+}
+
+
+/// A JSON value. Object members keep their order.
+enum Json {
+  Null,
+  Bool,
+  Number(f64),
+  String(String),
+  Array(Vec<Json>),
+  Object(Vec<(String, Json)>),
+}
+
+/// An array or object being read, with its items so far. An object has the name of the member
+/// whose value is being read.
+enum Container {
+  Array(Vec<Json>),
+  Object(Vec<(String, Json)>, String),
+}
+
+impl Json {
+  fn describe(&self) -> &'static str {
+    match self {
+      Json::Null => "null",
+      Json::Bool => "a boolean",
+      Json::Number(_) => "a number",
+      Json::String(_) => "a string",
+      Json::Array(_) => "an array",
+      Json::Object(_) => "an object",
+    }
+  }
+}
+
+fn node_from_json(value: &Json) -> Result<ASTNode, String> {
+  // The compound nodes being built, innermost last: each node's object, its parts still to be
+  // built, last first, and the head and children built so far.
+  let mut open: Vec<(&Json, Vec<&Json>, Children)> = Vec::new();
+  let mut next = value;
+  loop {
+    if open.len() >= MAX_DEPTH {
+      return Err(too_deep());
+    }
+    let mut node =
+      match node_parts(next)? {
+        NodeParts::Atom(node) => Some(with_json_span(node, next)?),
+        NodeParts::Compound(head, children) => {
+          let mut parts: Vec<&Json> = children.iter().rev().collect();
+          let mut built = Children::new();
+          match head {
+            Head::Name(name) => built.push(Rc::new(ASTNode::symbol(name))),
+            Head::Node(head) => parts.push(head),
+          }
+          open.push((next, parts, built));
+          None
+        }
+      };
+
+    // Adds the node built to its parent and builds each parent with no parts left, until a part
+    // remains to be built next.
+    loop {
+      let Some((_, parts, built)) = open.last_mut() else {
+        return Ok(node.expect("a node is built when none are open"));
+      };
+      if let Some(node) = node.take() {
+        built.push(Rc::new(node));
+      }
+      if let Some(part) = parts.pop() {
+        next = part;
+        break;
+      }
+      let (object, _, mut built) = open.pop().unwrap();
+      let head = built.remove(0);
+      node = Some(with_json_span(ASTNode::compound(head, built), object)?);
+    }
+  }
+}
+
+/// What a node object describes: an atom, or a compound expression yet to be built.
+enum NodeParts<'j> {
+  Atom(ASTNode),
+  Compound(Head<'j>, &'j [Json]),
+}
+
+enum Head<'j> {
+  Name(&'j str),
+  Node(&'j Json),
+}
+
+fn node_parts(value: &Json) -> Result<NodeParts<'_>, String> {
+  let members =
+    match value {
+      Json::Object(members) => members,
+      other => return Err(format!("Expected a node object, found {}", other.describe()))
+    };
+  let member = |name: &str| members.iter().find(|(key, _)| key == name).map(|(_, value)| value);
+  if let Some((key, _)) = members.iter().find(|(key, _)| {
+    !["symbol", "number", "string", "name", "head", "children", "span"].contains(&key.as_str())
+  }) {
+    return Err(format!("Unknown member {} in a node", json_string(key)));
+  }
+
+  let node =
+    match (member("symbol"), member("number"), member("string"), member("name"), member("head"), member("children")) {
+
+      (Some(Json::String(name)), None, None, None, None, None) => ASTNode::symbol(name),
+
+      (None, Some(number), None, None, None, None) => {
+        let value =
+          match number {
+            Json::Number(value) => *value,
+            Json::String(text) if text == "Infinity" => f64::INFINITY,
+            Json::String(text) if text == "-Infinity" => f64::NEG_INFINITY,
+            Json::String(text) if text == "NaN" => f64::NAN,
+            other => return Err(format!("Expected a number, found {}", other.describe()))
+          };
+        ASTNode::number(value)
+      }
+
+      (None, None, Some(Json::String(text)), None, None, None) => ASTNode::string(text),
+
+      (None, None, None, name, head, Some(Json::Array(children))) if name.is_some() != head.is_some() => {
+        let head =
+          match (name, head) {
+            (Some(Json::String(name)), _) => Head::Name(name),
+            (Some(other), _) => return Err(format!("Expected a name, found {}", other.describe())),
+            (_, Some(head)) => Head::Node(head),
+            _ => unreachable!()
+          };
+        return Ok(NodeParts::Compound(head, children));
+      }
+
+      _ => {
+        return Err(
+          "A node must have a string \"symbol\", a \"number\", a string \"string\", or \"children\" with \
+           either \"name\" or \"head\"".to_string()
+        )
+      }
+
+    };
+  Ok(NodeParts::Atom(node))
+}
+
+/// Gives `node` the span of the node object `value`, if it has one.
+fn with_json_span(node: ASTNode, value: &Json) -> Result<ASTNode, String> {
+  let span =
+    match value {
+      Json::Object(members) => members.iter().find(|(key, _)| key == "span").map(|(_, value)| value),
+      _ => None
+    };
+  match span {
+    None => Ok(node),
+    Some(Json::Array(bounds)) => match bounds[..] {
+      [Json::Number(start), Json::Number(end)] if is_offset(start) && is_offset(end) && start <= end => {
+        Ok(node.with_span(Span::new(start as usize, end as usize)))
+      }
+      _ => Err("A span must be two offsets [start, end] with start <= end".to_string())
+    },
+    Some(other) => Err(format!("Expected a span, found {}", other.describe()))
+  }
+}
+
+fn is_offset(value: f64) -> bool {
+  value >= 0.0 && value.fract() == 0.0 && value <= u32::MAX as f64
+}
+
+
+/// Reads S-expressions and JSON values from text.
+struct Reader<'t> {
+  text    : &'t str,
+  position: usize,
+}
+
+impl<'t> Reader<'t> {
+  fn new(text: &'t str) -> Reader<'t> {
+    Reader{ text, position: 0 }
+  }
+
+  fn rest(&self) -> &'t str {
+    &self.text[self.position..]
+  }
+
+  fn peek(&self) -> Option<char> {
+    self.rest().chars().next()
+  }
+
+  fn bump(&mut self) -> Option<char> {
+    let c = self.peek()?;
+    self.position += c.len_utf8();
+    Some(c)
+  }
+
+  fn skip_whitespace(&mut self) {
+    let rest = self.rest();
+    self.position += rest.len() - rest.trim_start().len();
+  }
+
+  fn error(&self, expected: &str) -> String {
+    match self.peek() {
+      Some(c) => format!("Expected {} at offset {}, found {:?}", expected, self.position, c),
+      None => format!("Expected {} at offset {}, found the end of the input", expected, self.position),
+    }
+  }
+
+  fn expect(&mut self, c: char) -> Result<(), String> {
+    match self.peek() == Some(c) {
+      true => { self.bump(); Ok(()) }
+      false => Err(self.error(&format!("{:?}", c)))
+    }
+  }
+
+  fn end(&mut self) -> Result<(), String> {
+    self.skip_whitespace();
+    match self.peek() {
+      None => Ok(()),
+      Some(_) => Err(self.error("the end of the input"))
+    }
+  }
+
+  /// Takes the longest prefix of the rest of the text whose characters satisfy `accept`.
+  fn take_while(&mut self, accept: impl Fn(char) -> bool) -> &'t str {
+    let rest = self.rest();
+    let length = rest.find(|c: char| !accept(c)).unwrap_or(rest.len());
+    self.position += length;
+    &rest[..length]
+  }
+
+  /// Reads an S-expression. The compound nodes being read are kept on a stack rather than read
+  /// recursively.
+  fn sexpr(&mut self) -> Result<ASTNode, String> {
+    // The head and children read so far of each compound node being read, innermost last.
+    let mut open: Vec<Children> = Vec::new();
+    loop {
+      self.skip_whitespace();
+      if open.len() >= MAX_DEPTH && self.peek() != Some(')') {
+        return Err(too_deep());
+      }
+      let node =
+        match self.peek() {
+
+          Some('(') => {
+            self.bump();
+            self.skip_whitespace();
+            if self.peek() == Some(')') {
+              return Err(self.error("a head"));
+            }
+            open.push(Children::new());
+            continue;
+          }
+
+          Some(')') if !open.is_empty() => {
+            self.bump();
+            let mut children = open.pop().unwrap();
+            let head = children.remove(0);
+            ASTNode::compound(head, children)
+          }
+
+          Some('"') => ASTNode::string(&self.quoted('"')?),
+
+          Some('|') => ASTNode::symbol(&self.quoted('|')?),
+
+          Some(c) if c.is_ascii_digit() || c == '-' || c == '+' => {
+            let text = self.take_while(|c| c.is_ascii_alphanumeric() || ".+-".contains(c));
+            let value =
+              match text {
+                "+inf.0" => f64::INFINITY,
+                "-inf.0" => f64::NEG_INFINITY,
+                "+nan.0" => f64::NAN,
+                _ if text.trim_start_matches(['-', '+']).starts_with(|c: char| c.is_ascii_digit()) => {
+                  text.parse().map_err(|_| format!("{:?} is not a number", text))?
+                }
+                _ => return Err(format!("{:?} is not a number", text))
+              };
+            ASTNode::number(value)
+          }
+
+          Some(c) if c.is_alphabetic() || c == '$' => {
+            ASTNode::symbol(self.take_while(|c| c.is_alphanumeric() || c == '$'))
+          }
+
+          _ => return Err(self.error("an S-expression"))
+
+        };
+
+      let node = self.sexpr_span(node)?;
+      match open.last_mut() {
+        Some(children) => children.push(Rc::new(node)),
+        None => return Ok(node)
+      }
+    }
+  }
+
+  /// Reads the span following a node, if there is one.
+  fn sexpr_span(&mut self, node: ASTNode) -> Result<ASTNode, String> {
+    match self.peek() {
+      Some('@') => {
+        self.bump();
+        let start = self.offset()?;
+        self.expect(':')?;
+        let end = self.offset()?;
+        match start <= end {
+          true => Ok(node.with_span(Span::new(start, end))),
+          false => Err(format!("The span {}:{} ends before it starts", start, end))
+        }
+      }
+      _ => Ok(node)
+    }
+  }
+
+  fn offset(&mut self) -> Result<usize, String> {
+    let digits = self.take_while(|c| c.is_ascii_digit());
+    digits.parse().map_err(|_| self.error("an offset"))
+  }
+
+  /// Reads text between `delimiter`s, with `\` escaping the next character. In a string, `\n` and
+  /// `\t` stand for a newline and a tab.
+  fn quoted(&mut self, delimiter: char) -> Result<String, String> {
+    self.bump();
+    let mut text = String::new();
+    loop {
+      match self.bump() {
+        Some(c) if c == delimiter => return Ok(text),
+        Some('\\') => match self.bump() {
+          Some('n') if delimiter == '"' => text.push('\n'),
+          Some('t') if delimiter == '"' => text.push('\t'),
+          Some(c) => text.push(c),
+          None => break,
+        },
+        Some(c) => text.push(c),
+        None => break,
+      }
+    }
+    Err(format!("Unterminated {}", if delimiter == '"' { "string" } else { "symbol" }))
+  }
+
+  /// Reads a JSON value. The arrays and objects being read are kept on a stack rather than read
+  /// recursively.
+  fn json(&mut self) -> Result<Json, String> {
+    let mut open: Vec<Container> = Vec::new();
+    loop {
+      // Each level of nodes is two levels of JSON, a node object and its array of children.
+      if open.len() > 2 * MAX_DEPTH {
+        return Err(too_deep());
+      }
+      self.skip_whitespace();
+      let mut value =
+        match self.peek() {
+
+          Some('{') => {
+            self.bump();
+            self.skip_whitespace();
+            if self.peek() == Some('}') {
+              self.bump();
+              Json::Object(Vec::new())
+            } else {
+              let key = self.member_name(&[])?;
+              open.push(Container::Object(Vec::new(), key));
+              continue;
+            }
+          }
+
+          Some('[') => {
+            self.bump();
+            self.skip_whitespace();
+            if self.peek() == Some(']') {
+              self.bump();
+              Json::Array(Vec::new())
+            } else {
+              open.push(Container::Array(Vec::new()));
+              continue;
+            }
+          }
+
+          Some('"') => Json::String(self.json_string()?),
+
+          Some(c) if c == '-' || c.is_ascii_digit() => {
+            let text = self.take_while(|c| c.is_ascii_digit() || "+-.eE".contains(c));
+            Json::Number(text.parse().map_err(|_| format!("{:?} is not a number", text))?)
+          }
+
+          Some(c) if c.is_ascii_alphabetic() => match self.take_while(|c| c.is_ascii_alphabetic()) {
+            "null" => Json::Null,
+            "true" | "false" => Json::Bool,
+            word => return Err(format!("Unexpected {:?}", word))
+          },
+
+          _ => return Err(self.error("a JSON value"))
+
+        };
+
+      // Adds the value to the innermost container, and each container it ends to the next, until
+      // one continues with another value.
+      loop {
+        self.skip_whitespace();
+        match open.pop() {
+          None => return Ok(value),
+          Some(Container::Array(mut items)) => {
+            items.push(value);
+            match self.bump() {
+              Some(',') => {
+                open.push(Container::Array(items));
+                break;
+              }
+              Some(']') => value = Json::Array(items),
+              _ => return Err(format!("Expected ',' or ']' before offset {}", self.position))
+            }
+          }
+          Some(Container::Object(mut members, key)) => {
+            members.push((key, value));
+            match self.bump() {
+              Some(',') => {
+                let key = self.member_name(&members)?;
+                open.push(Container::Object(members, key));
+                break;
+              }
+              Some('}') => value = Json::Object(members),
+              _ => return Err(format!("Expected ',' or '}}' before offset {}", self.position))
+            }
+          }
+        }
+      }
+    }
+  }
+
+  /// Reads the name of a member of an object with the given `members` and the `:` after it.
+  fn member_name(&mut self, members: &[(String, Json)]) -> Result<String, String> {
+    self.skip_whitespace();
+    if self.peek() != Some('"') {
+      return Err(self.error("a member name"));
+    }
+    let key = self.json_string()?;
+    if members.iter().any(|(name, _)| *name == key) {
+      return Err(format!("The member {} appears twice", json_string(&key)));
+    }
+    self.skip_whitespace();
+    self.expect(':')?;
+    Ok(key)
+  }
+
+  fn json_string(&mut self) -> Result<String, String> {
+    self.bump();
+    let mut text = String::new();
+    loop {
+      match self.bump() {
+        Some('"') => return Ok(text),
+        Some('\\') => match self.bump() {
+          Some('"') => text.push('"'),
+          Some('\\') => text.push('\\'),
+          Some('/') => text.push('/'),
+          Some('b') => text.push('\u{8}'),
+          Some('f') => text.push('\u{c}'),
+          Some('n') => text.push('\n'),
+          Some('r') => text.push('\r'),
+          Some('t') => text.push('\t'),
+          Some('u') => {
+            let unit = self.code_unit()?;
+            // A character outside the basic multilingual plane is escaped as a surrogate pair.
+            let c =
+              match unit {
+                0xD800..=0xDBFF if self.rest().starts_with("\\u") => {
+                  self.position += 2;
+                  let low = self.code_unit()?;
+                  char::decode_utf16([unit, low]).next().and_then(Result::ok)
+                }
+                unit => char::from_u32(unit as u32),
+              };
+            text.push(c.ok_or_else(|| format!("Invalid \\u escape before offset {}", self.position))?);
+          }
+          _ => return Err(format!("Invalid escape before offset {}", self.position))
+        },
+        Some(c) if (c as u32) < 0x20 => return Err(format!("Unescaped control character before offset {}", self.position)),
+        Some(c) => text.push(c),
+        None => return Err("Unterminated string".to_string())
+      }
+    }
+  }
+
+  fn code_unit(&mut self) -> Result<u16, String> {
+    let digits = self.rest().get(..4).filter(|d| d.chars().all(|c| c.is_ascii_hexdigit()));
+    match digits {
+      Some(digits) => {
+        self.position += 4;
+        Ok(u16::from_str_radix(digits, 16).unwrap())
+      }
+      None => Err(self.error("four hexadecimal digits"))
+    }
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::grammar::compile_grammar_file;
+  use crate::parser::Parser;
+
+  fn node(name: &str, children: Vec<ASTNode>) -> ASTNode {
+    ASTNode::function(name, children.into_iter().map(Rc::new).collect())
+  }
+
+  #[test]
+  fn serializes_parsed_trees() {
+    let mut parser = Parser::new(compile_grammar_file("resources/operators.grammar").unwrap());
+    let tree = parser.parse("a + b * \"c\"").unwrap();
+
+    let sexpr = to_sexpr(&tree).unwrap();
+    assert_eq!(sexpr, r#"(Plus a@0:1 (Times b@4:5 "c"@8:11)@4:11)@0:11"#);
+    assert_eq!(from_sexpr(&sexpr).unwrap(), *tree);
+
+    let json = to_json(&tree).unwrap();
+    assert_eq!(
+      json,
+      r#"{"name":"Plus","children":[{"symbol":"a","span":[0,1]},{"name":"Times","children":[{"symbol":"b","span":[4,5]},{"string":"c","span":[8,11]}],"span":[4,11]}],"span":[0,11]}"#
+    );
+    assert_eq!(from_json(&json).unwrap(), *tree);
+  }
+
+  #[test]
+  fn serializes_any_tree() {
+    let curried = ASTNode::compound(
+      Rc::new(node("f", vec![ASTNode::symbol("x")])),
+      vec![Rc::new(ASTNode::symbol("a b|c")), Rc::new(ASTNode::string("say \"hi\"\n"))]
+    );
+    let numbers = node("List", vec![
+      ASTNode::number(-0.5), ASTNode::number(1e300), ASTNode::number(f64::INFINITY), ASTNode::number(f64::NAN)
+    ]);
+    let empty = node("f", vec![]);
+
+    assert_eq!(to_sexpr(&curried).unwrap(), r#"((f x) |a b\|c| "say \"hi\"\n")"#);
+    assert_eq!(to_sexpr(&empty).unwrap(), "(f)");
+    assert_eq!(
+      to_json(&curried).unwrap(),
+      r#"{"head":{"name":"f","children":[{"symbol":"x"}]},"children":[{"symbol":"a b|c"},{"string":"say \"hi\"\n"}]}"#
+    );
+
+    for tree in [curried, numbers, empty] {
+      // NaN is not equal to itself, so the trees are compared by their serialized forms.
+      let sexpr = to_sexpr(&tree).unwrap();
+      assert_eq!(to_sexpr(&from_sexpr(&sexpr).unwrap()).unwrap(), sexpr);
+      let json = to_json(&tree).unwrap();
+      assert_eq!(to_json(&from_json(&json).unwrap()).unwrap(), json);
+    }
+  }
+
+  #[test]
+  fn reads_hand_written_json() {
+    let json = r#" { "span" : [0, 3], "children" : [ {"number": 1.5e1}, {"symbol": "é😀"} ], "name" : "g" } "#;
+    let tree = from_json(json).unwrap();
+    assert_eq!(tree, node("g", vec![ASTNode::number(15.0), ASTNode::symbol("é😀")]).with_span(Span::new(0, 3)));
+  }
+
+  #[test]
+  fn rejects_malformed_input() {
+    for text in ["", "()", "(f", "f@1", "f@2:1", "\"a", "|a", "-x", "f g", "(f))", "#"] {
+      assert!(from_sexpr(text).is_err(), "{}", text);
+    }
+    for text in [
+      "", "{}", "[]", r#"{"symbol":1}"#, r#"{"symbol":"a","string":"b"}"#, r#"{"name":"f"}"#,
+      r#"{"name":"f","head":{"symbol":"g"},"children":[]}"#, r#"{"symbol":"a","span":[2,1]}"#,
+      r#"{"symbol":"a","extra":1}"#, r#"{"symbol":"a","symbol":"b"}"#, r#"{"symbol":"a"} x"#,
+    ] {
+      assert!(from_json(text).is_err(), "{}", text);
+    }
+
+    for depth in [MAX_DEPTH + 1, 100_000] {
+      let deep = "(f ".repeat(depth) + &")".repeat(depth);
+      assert_eq!(from_sexpr(&deep), Err(too_deep()));
+      let deep = r#"{"name":"f","children":["#.repeat(depth) + &"]}".repeat(depth);
+      assert_eq!(from_json(&deep), Err(too_deep()));
+    }
+  }
+
+  #[test]
+  fn round_trips_at_the_depth_limit() {
+    // `f[f[…f[a]…]]` with `a` nested `depth` deep.
+    let chain = |depth: usize| (0..depth).fold(ASTNode::symbol("a"), |tree, _| node("f", vec![tree]));
+
+    let deepest = chain(MAX_DEPTH - 1);
+    assert_eq!(from_sexpr(&to_sexpr(&deepest).unwrap()).unwrap(), deepest);
+    assert_eq!(from_json(&to_json(&deepest).unwrap()).unwrap(), deepest);
+
+    // What the readers would reject is not written, however deep, as with `Stack::Explicit`.
+    for depth in [MAX_DEPTH, 100_000] {
+      let tree = chain(depth);
+      assert_eq!(to_sexpr(&tree), Err(too_deep()));
+      assert_eq!(to_json(&tree), Err(too_deep()));
+    }
+  }
+}
@@ -9,13 +9,25 @@ use crate::ast::RcASTNode;
 
 
 pub struct SymbolRecord{
-  name: String,
+  pub name: String,
 
 }
 
 
 
-pub struct SymbolTable<'a>(HashMap<String, RcASTNode<'a>>);
+#[derive(Default)]
+pub struct SymbolTable(HashMap<String, RcASTNode>);
+
+impl SymbolTable {
+  /// Binds `name` to `node`, replacing any previous binding.
+  pub fn register(&mut self, name: &str, node: RcASTNode) {
+    self.0.insert(name.to_string(), node);
+  }
+
+  pub fn lookup(&self, name: &str) -> Option<&RcASTNode> {
+    self.0.get(name)
+  }
+}
 
 
 
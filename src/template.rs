@@ -0,0 +1,377 @@
+/*!
+
+A parse template describes the expression an operator builds from the operands it matched. Templates
+are written in the grammar DSL using the same notation as the expressions they describe:
+
+```text
+syntax: expr1 "[[" (expr2, )* "]]"
+parse:  Part[expr1, (expr2,)*]
+```
+
+While matching its syntax, the parser records the operand bound to each metavariable in a set of
+`Bindings`. Instantiating the template replaces each metavariable with its operand:
+
+  * `expr1`..`expr4` and `symb` are replaced with the operands they matched. A metavariable that
+    matched more than once, as the right operand of a fully associative operator does, is replaced
+    with all of its matches.
+  * A repetition group, e.g. `(expr2,)*`, is instantiated once per repetition of the group in the
+    syntax that bound the same metavariables, and the results are spliced into the surrounding
+    argument list.
+  * `n` is the number of repetitions of the first repetition group, and `-n` is its negation.
+  * The word `name` is the name of the operator. Any other word is a symbol, a quoted literal is a
+    string, and a number is a number.
+  * An optional part, e.g. `(expr1)?`, is omitted when its metavariables are unbound.
+
+The empty template stands for the default template `name[operands]`, where operands are all matched
+operands in the order they were matched.
+
+*/
+#![allow(dead_code)]
+
+use std::rc::Rc;
+
+use crate::ast::{ASTNode, RcASTNode, Children};
+use crate::grammar::syntax::Syntax;
+
+enum Capture {
+  /// An operand matched by a metavariable.
+  Value(Syntax, RcASTNode),
+  /// The iterations of a repetition group.
+  Group(Vec<Bindings>),
+}
+
+/// The operands matched by an operator's syntax, in the order they were matched.
+#[derive(Default)]
+pub struct Bindings {
+  captures: Vec<Capture>,
+}
+
+impl Bindings {
+
+  pub fn new() -> Bindings {
+    Bindings::default()
+  }
+
+  pub fn bind(&mut self, metavariable: &Syntax, node: RcASTNode) {
+    self.captures.push(Capture::Value(metavariable.clone(), node));
+  }
+
+  pub fn push_group(&mut self, iterations: Vec<Bindings>) {
+    self.captures.push(Capture::Group(iterations));
+  }
+
+  /// The operands bound directly to `metavariable`, not counting those bound within groups.
+  fn values_of(&self, metavariable: &Syntax) -> Children {
+    self.captures
+        .iter()
+        .filter_map(|c| match c {
+          Capture::Value(m, node) if m == metavariable => Some(node.clone()),
+          _ => None
+        })
+        .collect()
+  }
+
+  /// The operands bound to `metavariable`, including those bound within groups.
+  fn all_values_of(&self, metavariable: &Syntax) -> Children {
+    let mut values = Children::new();
+    for capture in &self.captures {
+      match capture {
+        Capture::Value(m, node) => {
+          if m == metavariable {
+            values.push(node.clone());
+          }
+        }
+        Capture::Group(iterations) => {
+          for iteration in iterations {
+            values.extend(iteration.all_values_of(metavariable));
+          }
+        }
+      }
+    }
+    values
+  }
+
+  fn groups(&self) -> impl Iterator<Item=&Vec<Bindings>> {
+    self.captures.iter().filter_map(|c| match c {
+      Capture::Group(iterations) => Some(iterations),
+      _ => None
+    })
+  }
+
+  /// Every matched operand in the order it was matched.
+  pub fn operands(&self) -> Children {
+    let mut operands = Children::new();
+    for capture in &self.captures {
+      match capture {
+        Capture::Value(_, node) => operands.push(node.clone()),
+        Capture::Group(iterations) => {
+          for iteration in iterations {
+            operands.extend(iteration.operands());
+          }
+        }
+      }
+    }
+    operands
+  }
+
+  /// The number of repetitions of the first repetition group, the value of `n`.
+  pub fn count(&self) -> Option<usize> {
+    self.groups().next().map(|iterations| iterations.len())
+  }
+
+}
+
+/// Instantiates `template` for the operator named `name`. The template must produce exactly one
+/// expression.
+pub fn instantiate(template: &Syntax, name: &str, bindings: &Bindings) -> Result<ASTNode, String> {
+  if *template == Syntax::Empty {
+    return Ok(ASTNode::function(name, bindings.operands()));
+  }
+
+  let mut nodes = expand(template, name, &[bindings])?;
+  if nodes.len() != 1 {
+    return Err(format!("the template produced {} expressions instead of one", nodes.len()));
+  }
+
+  let node = nodes.pop().unwrap();
+  Ok(Rc::try_unwrap(node).unwrap_or_else(|node| (*node).clone()))
+}
+
+/// Looks up a metavariable, innermost scope first. A metavariable bound only within repetition
+/// groups stands for all of its bindings.
+fn lookup(metavariable: &Syntax, scopes: &[&Bindings]) -> Children {
+  for scope in scopes.iter().rev() {
+    let values = scope.values_of(metavariable);
+    if !values.is_empty() {
+      return values;
+    }
+  }
+  match scopes.last() {
+    Some(scope) => scope.all_values_of(metavariable),
+    None => Children::new()
+  }
+}
+
+fn count(scopes: &[&Bindings]) -> usize {
+  scopes.iter().rev().find_map(|scope| scope.count()).unwrap_or(0)
+}
+
+/// Finds the repetition group that bound any of the metavariables of a template group.
+fn find_group<'b>(operands: &[Syntax], scopes: &[&'b Bindings]) -> Option<&'b Vec<Bindings>> {
+  for scope in scopes.iter().rev() {
+    for iterations in scope.groups() {
+      let binds_operand =
+        operands.is_empty()
+        || iterations.iter().any(
+             |iteration| operands.iter().any(|m| !iteration.all_values_of(m).is_empty())
+           );
+      if binds_operand {
+        return Some(iterations);
+      }
+    }
+  }
+  None
+}
+
+/// Instantiates a part of a template, producing the list of expressions it stands for.
+fn expand(template: &Syntax, name: &str, scopes: &[&Bindings]) -> Result<Children, String> {
+  let nodes: Children =
+    match template {
+
+      | Syntax::Expr1
+      | Syntax::Expr2
+      | Syntax::Expr3
+      | Syntax::Expr4
+      | Syntax::Symbol => lookup(template, scopes),
+
+      Syntax::N => vec![Rc::new(ASTNode::number(count(scopes) as f64))],
+
+      Syntax::NegN => vec![Rc::new(ASTNode::number(-(count(scopes) as f64)))],
+
+      Syntax::Number(value) => vec![Rc::new(ASTNode::number(*value as f64))],
+
+      Syntax::Word(word) => {
+        let symbol = if word == "name" { name } else { word.as_str() };
+        vec![Rc::new(ASTNode::symbol(symbol))]
+      }
+
+      Syntax::Literal(text) => vec![Rc::new(ASTNode::string(text))],
+
+      Syntax::NamedChar(char_name) => vec![Rc::new(ASTNode::string(&format!("\\[{}]", char_name)))],
+
+      Syntax::Cons(head, arguments) => {
+        let mut heads = expand(head, name, scopes)?;
+        if heads.len() != 1 {
+          return Err(format!("the head {} produced {} expressions instead of one", head, heads.len()));
+        }
+        let mut children = Children::new();
+        for argument in arguments {
+          children.extend(expand(argument, name, scopes)?);
+        }
+        vec![Rc::new(ASTNode::compound(heads.pop().unwrap(), children))]
+      }
+
+      | Syntax::Sequence(list)
+      | Syntax::SequenceDelim(list) => {
+        let mut nodes = Children::new();
+        for item in list {
+          nodes.extend(expand(item, name, scopes)?);
+        }
+        nodes
+      }
+
+      Syntax::Alternative(list) => {
+        let mut nodes = Children::new();
+        for item in list {
+          nodes = expand(item, name, scopes)?;
+          if !nodes.is_empty() {
+            break;
+          }
+        }
+        nodes
+      }
+
+      Syntax::Optional(inner) => {
+        let bound = inner.operands().iter().all(|m| !lookup(m, scopes).is_empty());
+        if bound { expand(inner, name, scopes)? } else { Children::new() }
+      }
+
+      | Syntax::OnePlus(inner)
+      | Syntax::ZeroPlus(inner)
+      | Syntax::OnePlusDelim(inner, _)
+      | Syntax::ZeroPlusDelim(inner, _) => {
+        match find_group(&inner.operands(), scopes) {
+
+          Some(iterations) => {
+            let mut nodes = Children::new();
+            for iteration in iterations {
+              let mut inner_scopes = scopes.to_vec();
+              inner_scopes.push(iteration);
+              nodes.extend(expand(inner, name, &inner_scopes)?);
+            }
+            nodes
+          }
+
+          None => expand(inner, name, scopes)?
+
+        }
+      }
+
+      | Syntax::Comma
+      | Syntax::NoSpace
+      | Syntax::Space
+      | Syntax::Newline
+      | Syntax::Empty => Children::new(),
+
+    };
+
+  Ok(nodes)
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn symbol(name: &str) -> RcASTNode {
+    Rc::new(ASTNode::symbol(name))
+  }
+
+  fn word(word: &str) -> Box<Syntax> {
+    Box::new(Syntax::Word(word.to_string()))
+  }
+
+  #[test]
+  fn instantiate_default_template() {
+    let mut bindings = Bindings::new();
+    bindings.bind(&Syntax::Expr1, symbol("a"));
+    bindings.bind(&Syntax::Expr2, symbol("b"));
+
+    let node = instantiate(&Syntax::Empty, "Plus", &bindings).unwrap();
+
+    assert_eq!(node, ASTNode::function("Plus", vec![symbol("a"), symbol("b")]));
+  }
+
+  #[test]
+  fn instantiate_repetition_group() {
+    // Part[expr1, (expr2,)*]
+    let template = Syntax::Cons(
+      word("Part"),
+      vec![
+        Syntax::Expr1,
+        Syntax::ZeroPlusDelim(Box::new(Syntax::Expr2), Box::new(Syntax::Comma))
+      ]
+    );
+    let mut bindings = Bindings::new();
+    bindings.bind(&Syntax::Expr1, symbol("m"));
+    let iterations = ["i", "j"].iter().map(|s| {
+      let mut iteration = Bindings::new();
+      iteration.bind(&Syntax::Expr2, symbol(s));
+      iteration
+    }).collect();
+    bindings.push_group(iterations);
+
+    let node = instantiate(&template, "Part", &bindings).unwrap();
+
+    assert_eq!(node, ASTNode::function("Part", vec![symbol("m"), symbol("i"), symbol("j")]));
+  }
+
+  #[test]
+  fn instantiate_expression_head_and_optional() {
+    // expr1[(expr2,)*] with no repetitions
+    let template = Syntax::Cons(
+      Box::new(Syntax::Expr1),
+      vec![Syntax::ZeroPlusDelim(Box::new(Syntax::Expr2), Box::new(Syntax::Comma))]
+    );
+    let mut bindings = Bindings::new();
+    bindings.bind(&Syntax::Expr1, Rc::new(ASTNode::function("f", vec![symbol("x")])));
+    bindings.push_group(vec![]);
+
+    let node = instantiate(&template, "Construct", &bindings).unwrap();
+
+    let f_x = Rc::new(ASTNode::function("f", vec![symbol("x")]));
+    assert_eq!(node, ASTNode::compound(f_x, vec![]));
+    assert!(!node.is_atom());
+
+    // Pattern[symb, BlankNullSequence[(expr1)?]] with expr1 unbound
+    let template = Syntax::Cons(
+      word("Pattern"),
+      vec![
+        Syntax::Symbol,
+        Syntax::Cons(word("BlankNullSequence"), vec![Syntax::Optional(Box::new(Syntax::Expr1))])
+      ]
+    );
+    let mut bindings = Bindings::new();
+    bindings.bind(&Syntax::Symbol, symbol("x"));
+
+    let node = instantiate(&template, "NamedBlankNullSequence", &bindings).unwrap();
+
+    assert_eq!(
+      node,
+      ASTNode::function(
+        "Pattern",
+        vec![symbol("x"), Rc::new(ASTNode::function("BlankNullSequence", vec![]))]
+      )
+    );
+  }
+
+  #[test]
+  fn instantiate_count() {
+    // Out[-n] for three repetitions
+    let template = Syntax::Cons(word("Out"), vec![Syntax::NegN]);
+    let mut bindings = Bindings::new();
+    bindings.push_group(vec![Bindings::new(), Bindings::new(), Bindings::new()]);
+
+    let node = instantiate(&template, "Out", &bindings).unwrap();
+
+    assert_eq!(node, ASTNode::function("Out", vec![Rc::new(ASTNode::number(-3.0))]));
+  }
+
+  #[test]
+  fn instantiate_errors() {
+    let bindings = Bindings::new();
+    let template = Syntax::Sequence(vec![Syntax::Word("a".to_string()), Syntax::Word("b".to_string())]);
+
+    assert!(instantiate(&template, "Op", &bindings).is_err());
+  }
+}
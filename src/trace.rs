@@ -0,0 +1,371 @@
+/*!
+
+A trace of the parser's decisions, for finding out why a change to the operator table changes how
+an expression parses.
+
+With a `Sink` in `Parser::trace`, the parser reports each step of the `E` procedure as an `Event`:
+the atom or null command that begins an expression, and for each token that follows, the left
+command it selects and the comparison of the operator's left binding power `lbp` with the
+expression's `p` and `r` that decides whether the operator continues the expression or ends it.
+
+```text
+E(p = 0) at "a"
+atom "a"
+left "+" selects Plus: p 0 <= lbp 9970 <= r inf, so r = 9970
+  E(p = 9971) at "b"
+  atom "b"
+  left "*" selects Times: p 9971 <= lbp 9980 <= r inf, so r = 9980
+    E(p = 9981) at "c"
+    atom "c"
+    break at the end of the input: no left command
+  break at the end of the input: no left command
+break at the end of the input: no left command
+```
+
+Three sinks are provided: `Indented`, which writes the events as above, indented by depth, e.g.
+to standard error; `Vec<Event>`, which collects them; and `JsonLines`, which writes one JSON
+object per event. A sink shared as `Rc<RefCell<_>>` can be read after the parse.
+
+*/
+
+use std::cell::RefCell;
+use std::fmt::{Display, Formatter};
+use std::io::{self, Write};
+use std::rc::Rc;
+
+use crate::lexer::Span;
+
+/// A step of the `E` procedure. `depth` is the number of calls of `E` in progress, the first
+/// being 1. `token` is the text of the token the step concerns, empty at the end of the input.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum Event {
+  /// `E(p)` begins an expression.
+  Enter{ depth: usize, p: i32, token: String, span: Span },
+  /// The expression begins with an atom, or with the null command `operator`.
+  Null{ depth: usize, token: String, operator: Option<String>, span: Span },
+  /// The token selects the left command `operator`, which continues the expression because
+  /// `p <= lbp <= r`. The expression continues with `r = nbp`.
+  Left{ depth: usize, token: String, operator: String, p: i32, r: i32, lbp: i32, nbp: i32, span: Span },
+  /// The expression ends before the token.
+  Break{ depth: usize, token: String, p: i32, r: i32, reason: Reason, span: Span },
+  /// The token is matched by an operator's syntax other than as its L or N token, e.g. a closing
+  /// bracket.
+  Token{ depth: usize, token: String, span: Span },
+}
+
+/// Why an expression ends before a token.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum Reason {
+  /// The token does not select a left command.
+  NoLeftCommand,
+  /// `lbp < p`: the operator binds less tightly than the operator whose operand is ending.
+  Looser{ operator: String, lbp: i32 },
+  /// `lbp > r`: the operator binds more tightly than the previous operator of the expression
+  /// allows, e.g. a second non-associative operator.
+  Tighter{ operator: String, lbp: i32 },
+  /// The token breaks the operator's whitespace constraint.
+  Spacing{ operator: String },
+}
+
+impl Event {
+  pub fn depth(&self) -> usize {
+    match self {
+      | Event::Enter{ depth, .. }
+      | Event::Null{ depth, .. }
+      | Event::Left{ depth, .. }
+      | Event::Break{ depth, .. }
+      | Event::Token{ depth, .. } => *depth
+    }
+  }
+
+  /// The event as a JSON object on one line, e.g.
+  /// `{"event":"left","depth":1,"token":"+","operator":"Plus","p":0,"r":2147483647,...}`.
+  pub fn to_json(&self) -> String {
+    let mut fields = vec![("event", json_string(self.name())), ("depth", self.depth().to_string())];
+    match self {
+
+      Event::Enter{ p, token, span, .. } => {
+        fields.extend([("p", p.to_string()), ("token", json_string(token)), span_field(span)]);
+      }
+
+      Event::Null{ token, operator, span, .. } => {
+        let operator = operator.as_deref().map_or("null".to_string(), json_string);
+        fields.extend([("token", json_string(token)), ("operator", operator), span_field(span)]);
+      }
+
+      Event::Left{ token, operator, p, r, lbp, nbp, span, .. } => {
+        fields.extend([
+          ("token", json_string(token)),
+          ("operator", json_string(operator)),
+          ("p", p.to_string()),
+          ("r", r.to_string()),
+          ("lbp", lbp.to_string()),
+          ("nbp", nbp.to_string()),
+          span_field(span)
+        ]);
+      }
+
+      Event::Break{ token, p, r, reason, span, .. } => {
+        fields.extend([("token", json_string(token)), ("p", p.to_string()), ("r", r.to_string())]);
+        let (name, operator, lbp) =
+          match reason {
+            Reason::NoLeftCommand => ("no left command", None, None),
+            Reason::Looser{ operator, lbp } => ("looser", Some(operator), Some(lbp)),
+            Reason::Tighter{ operator, lbp } => ("tighter", Some(operator), Some(lbp)),
+            Reason::Spacing{ operator } => ("spacing", Some(operator), None),
+          };
+        fields.push(("reason", json_string(name)));
+        fields.extend(operator.map(|operator| ("operator", json_string(operator))));
+        fields.extend(lbp.map(|lbp| ("lbp", lbp.to_string())));
+        fields.push(span_field(span));
+      }
+
+      Event::Token{ token, span, .. } => {
+        fields.extend([("token", json_string(token)), span_field(span)]);
+      }
+
+    }
+
+    let fields: Vec<String> = fields.iter().map(|(name, value)| format!("\"{}\":{}", name, value)).collect();
+    format!("{{{}}}", fields.join(","))
+  }
+
+  fn name(&self) -> &'static str {
+    match self {
+      Event::Enter{ .. } => "enter",
+      Event::Null{ .. } => "null",
+      Event::Left{ .. } => "left",
+      Event::Break{ .. } => "break",
+      Event::Token{ .. } => "token",
+    }
+  }
+}
+
+/// The event on one line, without indentation.
+impl Display for Event {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+
+      Event::Enter{ p, token, .. } => write!(f, "E(p = {}) at {}", power(*p), describe(token)),
+
+      Event::Null{ token, operator: None, .. } => write!(f, "atom {:?}", token),
+
+      Event::Null{ token, operator: Some(operator), .. } => write!(f, "null {:?} selects {}", token, operator),
+
+      Event::Left{ token, operator, p, r, lbp, nbp, .. } => {
+        write!(
+          f, "left {:?} selects {}: p {} <= lbp {} <= r {}, so r = {}",
+          token, operator, power(*p), power(*lbp), power(*r), power(*nbp)
+        )
+      }
+
+      Event::Break{ token, p, r, reason, .. } => {
+        write!(f, "break at {}: ", describe(token))?;
+        match reason {
+          Reason::NoLeftCommand => write!(f, "no left command"),
+          Reason::Looser{ operator, lbp } => {
+            write!(f, "{} has lbp {} < p {}", operator, power(*lbp), power(*p))
+          }
+          Reason::Tighter{ operator, lbp } => {
+            write!(f, "{} has lbp {} > r {}", operator, power(*lbp), power(*r))
+          }
+          Reason::Spacing{ operator } => write!(f, "the whitespace before it does not suit {}", operator),
+        }
+      }
+
+      Event::Token{ token, .. } => write!(f, "token {:?}", token),
+
+    }
+  }
+}
+
+/// A binding power, with the infinite `r` of an expression no operator has continued yet as `inf`.
+fn power(bp: i32) -> String {
+  match bp {
+    i32::MAX => "inf".to_string(),
+    _ => bp.to_string()
+  }
+}
+
+fn describe(token: &str) -> String {
+  match token.is_empty() {
+    true => "the end of the input".to_string(),
+    false => format!("{:?}", token)
+  }
+}
+
+fn span_field(span: &Span) -> (&'static str, String) {
+  ("span", format!("[{},{}]", span.start, span.end))
+}
+
+/// `text` as a JSON string literal.
+pub(crate) fn json_string(text: &str) -> String {
+  let mut quoted = String::with_capacity(text.len() + 2);
+  quoted.push('"');
+  for c in text.chars() {
+    match c {
+      '"' => quoted.push_str("\\\""),
+      '\\' => quoted.push_str("\\\\"),
+      '\n' => quoted.push_str("\\n"),
+      '\r' => quoted.push_str("\\r"),
+      '\t' => quoted.push_str("\\t"),
+      c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+      c => quoted.push(c)
+    }
+  }
+  quoted.push('"');
+  quoted
+}
+
+
+/// Receives the events of a traced parse.
+pub trait Sink {
+  fn event(&mut self, event: &Event);
+}
+
+impl Sink for Vec<Event> {
+  fn event(&mut self, event: &Event) {
+    self.push(event.clone());
+  }
+}
+
+impl<S: Sink> Sink for Rc<RefCell<S>> {
+  fn event(&mut self, event: &Event) {
+    self.borrow_mut().event(event);
+  }
+}
+
+/// Writes each event on its own line, indented two spaces for each level of nesting. Errors
+/// writing the trace are ignored.
+pub struct Indented<W: Write> {
+  writer: W,
+}
+
+impl<W: Write> Indented<W> {
+  pub fn new(writer: W) -> Indented<W> {
+    Indented{ writer }
+  }
+}
+
+impl Indented<io::Stderr> {
+  pub fn stderr() -> Indented<io::Stderr> {
+    Indented::new(io::stderr())
+  }
+}
+
+impl<W: Write> Sink for Indented<W> {
+  fn event(&mut self, event: &Event) {
+    // `Enter` begins the level it reports, and the other events at a depth belong to it.
+    let indent = "  ".repeat(event.depth().saturating_sub(1));
+    let _ = writeln!(self.writer, "{}{}", indent, event);
+  }
+}
+
+/// Writes each event as a JSON object on its own line. See `Event::to_json`. Errors writing the
+/// trace are ignored.
+pub struct JsonLines<W: Write> {
+  writer: W,
+}
+
+impl<W: Write> JsonLines<W> {
+  pub fn new(writer: W) -> JsonLines<W> {
+    JsonLines{ writer }
+  }
+}
+
+impl<W: Write> Sink for JsonLines<W> {
+  fn event(&mut self, event: &Event) {
+    let _ = writeln!(self.writer, "{}", event.to_json());
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::grammar::compile_grammar_file;
+  use crate::parser::{Parser, Stack};
+
+  fn trace(parser: &mut Parser, text: &str) -> Vec<Event> {
+    let events = Rc::new(RefCell::new(Vec::new()));
+    parser.trace = Some(Box::new(events.clone()));
+    let _ = parser.parse(text);
+    parser.trace = None;
+    events.take()
+  }
+
+  fn indented(events: &[Event]) -> String {
+    let mut writer = Indented::new(Vec::new());
+    for event in events {
+      writer.event(event);
+    }
+    String::from_utf8(writer.writer).unwrap()
+  }
+
+  #[test]
+  fn trace_binding_power_decisions() {
+    let mut parser = Parser::new(compile_grammar_file("resources/operators.grammar").unwrap());
+
+    assert_eq!(
+      indented(&trace(&mut parser, "a / b / (c)")),
+      "\
+E(p = 0) at \"a\"
+atom \"a\"
+left \"/\" selects Divide: p 0 <= lbp 9980 <= r inf, so r = 9980
+  E(p = 9981) at \"b\"
+  atom \"b\"
+  break at \"/\": Divide has lbp 9980 < p 9981
+left \"/\" selects Divide: p 0 <= lbp 9980 <= r 9980, so r = 9980
+  E(p = 9981) at \"(\"
+  null \"(\" selects Parentheses
+    E(p = 0) at \"c\"
+    atom \"c\"
+    break at \")\": no left command
+  token \")\"
+  break at the end of the input: no left command
+break at the end of the input: no left command
+"
+    );
+
+    // A non-associative operator cannot follow another of its precedence.
+    let events = trace(&mut parser, "a √ b √ c");
+    assert_eq!(
+      events.iter().rfind(|e| matches!(e, Event::Break{ .. })).unwrap().to_string(),
+      "break at \"√\": Base has lbp 9995 > r 9994"
+    );
+  }
+
+  #[test]
+  fn trace_the_same_steps_with_either_stack() {
+    let mut recursive = Parser::new(compile_grammar_file("resources/operators.grammar").unwrap());
+    let mut explicit = Parser::new(compile_grammar_file("resources/operators.grammar").unwrap());
+    explicit.stack = Stack::Explicit;
+
+    for text in ["a + b * c - d", "f[x, g[y]][z]", "a := b = (c ^ d ^ e)", "a √ b √ c", "a + [b"] {
+      assert_eq!(trace(&mut explicit, text), trace(&mut recursive, text), "{}", text);
+    }
+  }
+
+  #[test]
+  fn trace_json_lines() {
+    let mut parser = Parser::new(compile_grammar_file("resources/operators.grammar").unwrap());
+    let mut writer = JsonLines::new(Vec::new());
+    for event in trace(&mut parser, "a + b") {
+      writer.event(&event);
+    }
+    let output = String::from_utf8(writer.writer).unwrap();
+    let lines: Vec<&str> = output.lines().collect();
+
+    assert_eq!(lines.len(), 7);
+    assert_eq!(lines[0], r#"{"event":"enter","depth":1,"p":0,"token":"a","span":[0,1]}"#);
+    assert_eq!(
+      lines[2],
+      r#"{"event":"left","depth":1,"token":"+","operator":"Plus","p":0,"r":2147483647,"lbp":9970,"nbp":9970,"span":[2,3]}"#
+    );
+    assert_eq!(
+      lines[6],
+      r#"{"event":"break","depth":1,"token":"","p":0,"r":9970,"reason":"no left command","span":[5,5]}"#
+    );
+    assert_eq!(json_string("\"a\\b\"\n\u{1}"), r#""\"a\\b\"\n\u0001""#);
+  }
+}
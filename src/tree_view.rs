@@ -0,0 +1,201 @@
+/*!
+
+Draws expression trees, for seeing how an expression parsed. `tree_form` draws a tree as text, one
+node to a line below its parent, like Mathematica's `TreeForm`:
+
+```text
+Plus  0..9
+├── a  0..1
+└── Times  4..9
+    ├── b  4..5
+    └── c  8..9
+```
+
+and `dot` writes it as a Graphviz graph, to be drawn with e.g. `dot -Tsvg`.
+
+Each node is labeled with the FullForm of its head, usually an operator name, or for an atom with
+its own FullForm, so that a string reads `"a b"` and the symbol `a` reads `a`; then, if the node was
+parsed, with its span in the source text.
+
+*/
+
+use std::fmt::Write;
+
+use crate::ast::ASTNode;
+use crate::trace::json_string;
+
+
+/// The characters `tree_form` draws the branches of a tree with.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub enum Lines {
+  /// Box-drawing characters, `├──`, `└──`, and `│`.
+  #[default]
+  Box,
+  /// ASCII characters only, `|--`, `` `-- ``, and `|`.
+  Ascii,
+}
+
+impl Lines {
+  /// The branch to a child, the branch to the last child, and what continues a branch past the
+  /// child's own children.
+  fn strokes(self) -> (&'static str, &'static str, &'static str) {
+    match self {
+      Lines::Box => ("├── ", "└── ", "│   "),
+      Lines::Ascii => ("|-- ", "`-- ", "|   "),
+    }
+  }
+}
+
+/// The label of a node: the FullForm of its head, or of the atom, and its span.
+fn label(node: &ASTNode) -> String {
+  let mut label =
+    match node.head() {
+      Some(head) => head.to_string(),
+      None => node.to_string(),
+    };
+  if let Some(span) = node.span() {
+    write!(label, "  {}..{}", span.start, span.end).unwrap();
+  }
+  label
+}
+
+/// Draws `node` as an indented tree, one node to a line.
+pub fn tree_form(node: &ASTNode, lines: Lines) -> String {
+  let mut out = label(node);
+  out.push('\n');
+  draw_descendants(node, lines, &mut out);
+  out
+}
+
+/// Draws the descendants of `node` below it, with an explicit stack rather than recursively, so
+/// that a deep tree cannot overflow the call stack. Each line begins with a prefix, the branches of
+/// the ancestors that have children yet to be drawn.
+fn draw_descendants(node: &ASTNode, lines: Lines, out: &mut String) {
+  let (branch, last_branch, continued) = lines.strokes();
+  let mut prefix = String::new();
+  // The nodes yet to be drawn, last first, each with the length of the prefix its line begins with
+  // and whether it is the last of its siblings.
+  let mut pending: Vec<(&ASTNode, usize, bool)> = Vec::new();
+  push_children(node, 0, &mut pending);
+  while let Some((node, length, last)) = pending.pop() {
+    prefix.truncate(length);
+    out.push_str(&prefix);
+    out.push_str(if last { last_branch } else { branch });
+    out.push_str(&label(node));
+    out.push('\n');
+
+    prefix.push_str(if last { "    " } else { continued });
+    push_children(node, prefix.len(), &mut pending);
+  }
+}
+
+fn push_children<'n>(node: &'n ASTNode, length: usize, pending: &mut Vec<(&'n ASTNode, usize, bool)>) {
+  let count = node.children().len();
+  for (i, child) in node.children().iter().enumerate().rev() {
+    pending.push((child, length, i + 1 == count));
+  }
+}
+
+/// Writes `node` as a Graphviz `digraph` with an edge from each node to each of its children, in
+/// order. Compound expressions are drawn as boxes and atoms as ellipses.
+pub fn dot(node: &ASTNode) -> String {
+  let mut out = String::from("digraph AST {\n  node [fontname=\"monospace\"];\n  ordering=out;\n");
+  dot_nodes(node, &mut out);
+  out.push_str("}\n");
+  out
+}
+
+/// What remains to be written of a graph, last first.
+enum Pending<'n> {
+  /// A node and the number of its parent.
+  Node(&'n ASTNode, Option<usize>),
+  /// The edge from a parent to a child, written after the child's subtree.
+  Edge(usize, usize),
+}
+
+/// Writes the tree's nodes, numbered in preorder, and its edges, with an explicit stack rather than
+/// recursively, so that a deep tree cannot overflow the call stack.
+fn dot_nodes(node: &ASTNode, out: &mut String) {
+  let mut count = 0;
+  let mut pending = vec![Pending::Node(node, None)];
+  while let Some(next) = pending.pop() {
+    let (node, parent) =
+      match next {
+        Pending::Node(node, parent) => (node, parent),
+        Pending::Edge(parent, child) => {
+          writeln!(out, "  n{} -> n{};", parent, child).unwrap();
+          continue;
+        }
+      };
+    let id = count;
+    count += 1;
+    let shape = if node.is_atom() { "ellipse" } else { "box" };
+    // DOT strings escape quotes and backslashes as JSON strings do.
+    writeln!(out, "  n{} [label={}, shape={}];", id, json_string(&label(node)), shape).unwrap();
+    if let Some(parent) = parent {
+      pending.push(Pending::Edge(parent, id));
+    }
+    for child in node.children().iter().rev() {
+      pending.push(Pending::Node(child, Some(id)));
+    }
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::rc::Rc;
+  use crate::grammar::compile_grammar_file;
+  use crate::parser::Parser;
+
+  #[test]
+  fn draws_trees() {
+    let mut parser = Parser::new(compile_grammar_file("resources/operators.grammar").unwrap());
+    let tree = parser.parse("a + b * \"c\" + d").unwrap();
+
+    assert_eq!(
+      tree_form(&tree, Lines::Box),
+      "Plus  0..15\n\
+       ├── a  0..1\n\
+       ├── Times  4..11\n\
+       │   ├── b  4..5\n\
+       │   └── \"c\"  8..11\n\
+       └── d  14..15\n"
+    );
+    assert_eq!(
+      tree_form(&tree, Lines::Ascii),
+      "Plus  0..15\n\
+       |-- a  0..1\n\
+       |-- Times  4..11\n\
+       |   |-- b  4..5\n\
+       |   `-- \"c\"  8..11\n\
+       `-- d  14..15\n"
+    );
+
+    let graph = dot(&tree);
+    assert!(graph.starts_with("digraph AST {\n"));
+    assert!(graph.contains("  n0 [label=\"Plus  0..15\", shape=box];\n"));
+    assert!(graph.contains("  n4 [label=\"\\\"c\\\"  8..11\", shape=ellipse];\n"));
+    assert!(graph.contains("  n0 -> n1;\n  n2 [label=\"Times  4..11\""));
+    assert!(graph.ends_with("  n0 -> n5;\n}\n"));
+  }
+
+  #[test]
+  fn draws_deep_trees() {
+    // A drawn tree is indented as deep as it is nested, so the tree is kept small and the stack
+    // smaller.
+    let drawn = std::thread::Builder::new().stack_size(64 * 1024).spawn(|| {
+      let depth = 2_000;
+      let tree =
+        (0..depth).fold(ASTNode::symbol("x"), |node, _| ASTNode::function("f", vec![Rc::new(node)]));
+      let drawn = tree_form(&tree, Lines::Ascii);
+      let graph = dot(&tree);
+      drawn.lines().count() == depth + 1
+        && drawn.ends_with(&format!("{}`-- x\n", "    ".repeat(depth - 1)))
+        && graph.contains(&format!("  n{} [label=\"x\", shape=ellipse];\n", depth))
+        && graph.ends_with("  n0 -> n1;\n}\n")
+    });
+    assert!(drawn.unwrap().join().unwrap());
+  }
+}
@@ -0,0 +1,436 @@
+/*!
+
+Typesets expression trees as LaTeX and as presentation MathML, for showing them as mathematics.
+
+An operator's typeset form is given by the `latex` and `mathml` templates of its grammar entry,
+written in the notation of parse templates: quoted text is written as it is, and `expr1`..`expr4`
+stand for the node's first to fourth children, themselves typeset. An operand in parentheses,
+`(expr1)`, is put in parentheses when its own operator binds more loosely, as the base of a power
+must be; a bare operand is written as it is, for places that already set it apart, like the
+numerator of a fraction:
+
+```text
+name: Power
+associativity: right
+precedence: 10
+syntax: expr1 "^" expr2
+latex: (expr1) "^{" expr2 "}"
+mathml: "<msup>" (expr1) expr2 "</msup>"
+```
+
+A node with more children than its template has operands, as a fully associative operator's may,
+repeats the template after its first operand for each further group of children, so that
+`expr1 " + " expr2` typesets `Plus[a, b, c]` as `a + b + c`.
+
+An operator without a template is typeset from its syntax: its tokens as operators, with common
+ones like `<=` and `->` as their mathematical symbols, and its operands in order. An operand at the
+start or end of the syntax is put in parentheses when its operator binds more loosely than the
+operator, or as tightly but on the side the operator's associativity does not group, as in
+`a - (b - c)`. An operand in parentheses in a template counts as being at the start of the syntax
+if it comes first in the template, and at the end otherwise. A compound expression whose head is
+not an operator, or whose operator's syntax has optional, repeated, or alternative parts, is
+typeset as a function application, `f(x, y)`.
+
+In MathML every operand is a single element, so templates may use operands as the arguments of
+elements like `msup`: an atom is an `mi`, `mn`, or `ms` element, and anything else an `mrow`.
+
+*/
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::ast::ASTNode;
+use crate::evaluator::Evaluator;
+use crate::grammar::syntax::Syntax;
+use crate::operator::{Affix, Associativity, Operator, OperatorTable};
+use crate::printer::{Position, ends_with_operand, is_printable, takes_left_operand};
+
+
+/// Tokens with a mathematical symbol of their own, in LaTeX and in MathML.
+const SYMBOLS: &[(&str, &str, &str)] = &[
+  ("*", "\\cdot", "\u{22C5}"),
+  ("<=", "\\le", "\u{2264}"),
+  (">=", "\\ge", "\u{2265}"),
+  ("!=", "\\ne", "\u{2260}"),
+  ("==", "=", "="),
+  ("->", "\\to", "\u{2192}"),
+  ("=>", "\\Rightarrow", "\u{21D2}"),
+  ("&&", "\\land", "\u{2227}"),
+  ("||", "\\lor", "\u{2228}"),
+  ("√", "\\surd", "\u{221A}"),
+];
+
+/// Typesets expression trees for an operator table.
+pub struct Typesetter {
+  operators: HashMap<String, Rc<Operator>>,
+}
+
+impl Typesetter {
+  pub fn new(table: &OperatorTable) -> Typesetter {
+    Typesetter{
+      operators: table.values().map(|o| (o.name.clone(), Rc::new(o.clone()))).collect(),
+    }
+  }
+
+  /// Typesets `node` as LaTeX, to be set in math mode.
+  pub fn latex(&self, node: &ASTNode) -> String {
+    self.typeset(node, Notation::Latex)
+  }
+
+  /// Typesets `node` as a MathML `math` element.
+  pub fn mathml(&self, node: &ASTNode) -> String {
+    format!(
+      "<math xmlns=\"http://www.w3.org/1998/Math/MathML\">{}</math>",
+      self.typeset(node, Notation::MathML)
+    )
+  }
+
+  fn typeset(&self, node: &ASTNode, notation: Notation) -> String {
+    if node.is_atom() {
+      return notation.atom(node);
+    }
+    if let Some(operator) = self.operator(node) {
+      let typeset =
+        match notation.template(operator) {
+          Syntax::Empty => self.render_syntax(operator, node, notation),
+          template => self.render_template(operator, template, node, notation),
+        };
+      if let Some(typeset) = typeset {
+        return typeset;
+      }
+    }
+
+    let head = self.typeset(node.head().unwrap(), notation);
+    let arguments = node.children().iter().map(|child| self.typeset(child, notation)).collect();
+    notation.apply(head, arguments)
+  }
+
+  /// The operator of a compound expression whose head is the operator's name.
+  fn operator(&self, node: &ASTNode) -> Option<&Rc<Operator>> {
+    match node.head() {
+      Some(head) if head.is_atom() => node.name().and_then(|name| self.operators.get(name)),
+      _ => None
+    }
+  }
+
+  /// Typesets `node` with its operator's template, or returns `None` if the node's children do not
+  /// fit the template.
+  fn render_template(&self, operator: &Operator, template: &Syntax, node: &ASTNode, notation: Notation)
+    -> Option<String>
+  {
+    let elements = template.elements();
+    let operand_count = elements.iter().filter_map(|e| template_operand(e)).map(|(i, _)| i + 1).max().unwrap_or(0);
+    let repeats = template_operand(elements[0]).is_some_and(|(i, _)| i == 0);
+    let groups = repetitions(node.children().len(), operand_count, repeats)?;
+
+    let mut out = String::new();
+    for group in 0..=groups {
+      // Each group after the first repeats the template after its first operand, and takes the
+      // next `operand_count - 1` children.
+      let skip = if group == 0 { 0 } else { 1 };
+      let offset = if group == 0 { 0 } else { operand_count + (group - 1) * (operand_count - 1) - 1 };
+      for (i, element) in elements.iter().enumerate().skip(skip) {
+        match (template_operand(element), element) {
+          (Some((index, parenthesized)), _) => {
+            let child = &node.children()[if index == 0 { 0 } else { offset + index }];
+            let position = if i == 0 { Position::Left } else { Position::Trailing };
+            out.push_str(&match parenthesized {
+              true => self.operand(operator, child, position, notation),
+              false => self.typeset(child, notation),
+            });
+          }
+          (None, Syntax::Space) => out.push(' '),
+          (None, Syntax::Number(n)) => out.push_str(&n.to_string()),
+          (None, e) => match e.token_text() {
+            Some(text) => out.push_str(&text),
+            None if !e.consumes_input() => {}
+            None => return None
+          }
+        }
+      }
+    }
+
+    Some(notation.group(out))
+  }
+
+  /// Typesets `node` from the syntax of its operator, or returns `None` if its syntax cannot be
+  /// typeset or its children do not fit it.
+  fn render_syntax(&self, operator: &Operator, node: &ASTNode, notation: Notation) -> Option<String> {
+    if !is_printable(operator) {
+      return None;
+    }
+    let elements: Vec<&Syntax> = operator.syntax.elements().into_iter().filter(|e| e.consumes_input()).collect();
+    let operand_count = elements.iter().filter(|e| e.is_operand()).count();
+    let repeats = operator.associativity == Associativity::Full && takes_left_operand(operator);
+    let groups = repetitions(node.children().len(), operand_count, repeats)?;
+
+    let mut children = node.children().iter();
+    let mut parts = Vec::new();
+    for group in 0..=groups {
+      let skip = if group == 0 { 0 } else { 1 };
+      for (i, element) in elements.iter().enumerate().skip(skip) {
+        match element.token_text() {
+          Some(text) => parts.push(notation.token(&text)),
+          None => {
+            let position =
+              match i {
+                0 if takes_left_operand(operator) => Position::Left,
+                _ if i + 1 == elements.len() && ends_with_operand(&operator.syntax) => Position::Trailing,
+                _ => Position::Closed
+              };
+            parts.push(self.operand(operator, children.next().unwrap(), position, notation));
+          }
+        }
+      }
+    }
+
+    Some(notation.row(parts))
+  }
+
+  /// Typesets `node` as the operand of `operator` at `position`, in parentheses if it needs them.
+  fn operand(&self, operator: &Operator, node: &ASTNode, position: Position, notation: Notation) -> String {
+    let typeset = self.typeset(node, notation);
+    match self.needs_parentheses(operator, node, position) {
+      true => notation.parenthesize(typeset),
+      false => typeset
+    }
+  }
+
+  fn needs_parentheses(&self, operator: &Operator, node: &ASTNode, position: Position) -> bool {
+    let inner =
+      match self.operator(node) {
+        // Operators that are delimited on both sides, like brackets, never need parentheses.
+        Some(inner) if !matches!(inner.affix, Affix::Matchfix | Affix::Null) => inner,
+        _ => return false
+      };
+    match (inner.precedence.cmp(&operator.precedence), position) {
+      (_, Position::Closed) => false,
+      (std::cmp::Ordering::Less, _) => false,
+      (std::cmp::Ordering::Greater, _) => true,
+      (std::cmp::Ordering::Equal, Position::Left) => operator.associativity != Associativity::Left,
+      (std::cmp::Ordering::Equal, Position::Trailing) => operator.associativity != Associativity::Right,
+    }
+  }
+}
+
+/// The index of the child an operand of a template stands for, and whether the operand is in
+/// parentheses.
+fn template_operand(element: &Syntax) -> Option<(usize, bool)> {
+  match element {
+    Syntax::Expr1 => Some((0, false)),
+    Syntax::Expr2 => Some((1, false)),
+    Syntax::Expr3 => Some((2, false)),
+    Syntax::Expr4 => Some((3, false)),
+    Syntax::Alternative(list) if list.len() == 1 => template_operand(&list[0]).map(|(i, _)| (i, true)),
+    _ => None
+  }
+}
+
+/// How many times a syntax or template with `operand_count` operands repeats after its first
+/// operand to take `children` children, if it can repeat, or `None` if it cannot take them.
+fn repetitions(children: usize, operand_count: usize, repeats: bool) -> Option<usize> {
+  match children == operand_count {
+    true => Some(0),
+    false if repeats && operand_count > 1 && children > operand_count => {
+      let extra = children - operand_count;
+      extra.is_multiple_of(operand_count - 1).then_some(extra / (operand_count - 1))
+    }
+    false => None
+  }
+}
+
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum Notation {
+  Latex,
+  MathML,
+}
+
+impl Notation {
+  fn template(self, operator: &Operator) -> &Syntax {
+    match self {
+      Notation::Latex => &operator.latex,
+      Notation::MathML => &operator.mathml,
+    }
+  }
+
+  fn atom(self, node: &ASTNode) -> String {
+    match (self, node.evaluator()) {
+
+      (Notation::Latex, Evaluator::Symbol(name)) if name.chars().count() == 1 => latex_escape(name, false),
+      (Notation::Latex, Evaluator::Symbol(name)) => format!("\\mathrm{{{}}}", latex_escape(name, false)),
+      (Notation::Latex, Evaluator::Number(value)) => number_text(*value),
+      (Notation::Latex, Evaluator::String(text)) => format!("\\text{{{}}}", latex_escape(text, true)),
+
+      (Notation::MathML, Evaluator::Symbol(name)) => format!("<mi>{}</mi>", xml_escape(name)),
+      (Notation::MathML, Evaluator::Number(value)) => format!("<mn>{}</mn>", number_text(*value)),
+      (Notation::MathML, Evaluator::String(text)) => format!("<ms>{}</ms>", xml_escape(text)),
+
+      // A built-in function is shown by its FullForm.
+      (Notation::Latex, Evaluator::BuiltIns(_)) => format!("\\mathrm{{{}}}", latex_escape(&node.to_string(), false)),
+      (Notation::MathML, Evaluator::BuiltIns(_)) => format!("<mi>{}</mi>", xml_escape(&node.to_string())),
+
+    }
+  }
+
+  fn token(self, text: &str) -> String {
+    let symbol = SYMBOLS.iter().find(|(token, _, _)| *token == text);
+    match (self, symbol) {
+      (Notation::Latex, Some((_, latex, _))) => latex.to_string(),
+      (Notation::Latex, None) if text.chars().all(char::is_alphabetic) => format!("\\mathrm{{{}}}", text),
+      (Notation::Latex, None) => latex_escape(text, false),
+      (Notation::MathML, Some((_, _, mathml))) => format!("<mo>{}</mo>", mathml),
+      (Notation::MathML, None) => format!("<mo>{}</mo>", xml_escape(text)),
+    }
+  }
+
+  /// Joins the parts of an operator application.
+  fn row(self, parts: Vec<String>) -> String {
+    match self {
+      Notation::Latex => parts.join(" "),
+      Notation::MathML => format!("<mrow>{}</mrow>", parts.concat()),
+    }
+  }
+
+  /// Makes an instantiated template a single element.
+  fn group(self, typeset: String) -> String {
+    match self {
+      Notation::Latex => typeset,
+      Notation::MathML => format!("<mrow>{}</mrow>", typeset),
+    }
+  }
+
+  fn parenthesize(self, typeset: String) -> String {
+    match self {
+      Notation::Latex => format!("\\left({}\\right)", typeset),
+      Notation::MathML => format!("<mrow><mo>(</mo>{}<mo>)</mo></mrow>", typeset),
+    }
+  }
+
+  fn apply(self, head: String, arguments: Vec<String>) -> String {
+    match self {
+      Notation::Latex => format!("{}\\left({}\\right)", head, arguments.join(", ")),
+      Notation::MathML => format!(
+        "<mrow>{}<mo>\u{2061}</mo><mrow><mo>(</mo>{}<mo>)</mo></mrow></mrow>",
+        head,
+        arguments.join("<mo>,</mo>")
+      ),
+    }
+  }
+}
+
+/// A number as a decimal, unlike FullForm, which puts a negative number between backquotes.
+fn number_text(value: f64) -> String {
+  // Zero and negative zero are the same number.
+  match value == 0.0 {
+    true => "0".to_string(),
+    false => value.to_string()
+  }
+}
+
+/// Escapes the characters LaTeX gives a meaning to, for math mode or, if `text` is set, for text in
+/// `\text{…}`.
+fn latex_escape(source: &str, text: bool) -> String {
+  let mut escaped = String::with_capacity(source.len());
+  for c in source.chars() {
+    match c {
+      '{' | '}' | '$' | '%' | '&' | '#' | '_' => { escaped.push('\\'); escaped.push(c); }
+      '\\' if text => escaped.push_str("\\textbackslash{}"),
+      '^' if text => escaped.push_str("\\textasciicircum{}"),
+      '~' if text => escaped.push_str("\\textasciitilde{}"),
+      '\\' => escaped.push_str("\\backslash{}"),
+      '^' => escaped.push_str("\\hat{}"),
+      '~' => escaped.push_str("\\sim{}"),
+      c => escaped.push(c),
+    }
+  }
+  escaped
+}
+
+fn xml_escape(source: &str) -> String {
+  let mut escaped = String::with_capacity(source.len());
+  for c in source.chars() {
+    match c {
+      '&' => escaped.push_str("&amp;"),
+      '<' => escaped.push_str("&lt;"),
+      '>' => escaped.push_str("&gt;"),
+      '"' => escaped.push_str("&quot;"),
+      c => escaped.push(c),
+    }
+  }
+  escaped
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::grammar::compile_grammar_file;
+  use crate::parser::Parser;
+
+  #[test]
+  fn latex() {
+    let table = compile_grammar_file("resources/operators.grammar").unwrap();
+    let typesetter = Typesetter::new(&table);
+    let mut parser = Parser::new(table);
+    let mut latex = |source: &str| typesetter.latex(&parser.parse(source).unwrap());
+
+    assert_eq!(latex("(a + b) * c"), "\\left(a + b\\right) \\cdot c");
+    assert_eq!(latex("a - (b - c) - d"), "a - \\left(b - c\\right) - d");
+    assert_eq!(latex("x = (a + b) / 2"), "x = \\frac{a + b}{2}");
+    assert_eq!(latex("(a ^ b) ^ c ^ 2"), "\\left(a^{b}\\right)^{c^{2}}");
+    assert_eq!(latex("(a + b) ^ (1/2)"), "\\left(a + b\\right)^{\\frac{1}{2}}");
+    assert_eq!(latex("f[x1, \"50%\"]"), "f\\left(\\mathrm{x1}, \\text{50\\%}\\right)");
+  }
+
+  #[test]
+  fn repeated_templates() {
+    let mut table = compile_grammar_file("resources/operators.grammar").unwrap();
+    let plus = table.get_mut("Plus").unwrap();
+    plus.latex = Syntax::Sequence(vec![Syntax::Expr1, Syntax::Literal(" + ".to_string()), Syntax::Expr2]);
+    let times = table.get_mut("Times").unwrap();
+    times.latex = Syntax::Sequence(vec![
+      Syntax::Alternative(vec![Syntax::Expr1]), Syntax::Literal(" ".to_string()), Syntax::Alternative(vec![Syntax::Expr2])
+    ]);
+    let typesetter = Typesetter::new(&table);
+    let mut parser = Parser::new(table);
+    let mut latex = |source: &str| typesetter.latex(&parser.parse(source).unwrap());
+
+    assert_eq!(latex("a + b * c * d + e"), "a + b c d + e");
+    assert_eq!(latex("(a + b) * c * (d * e)"), "\\left(a + b\\right) c \\left(d e\\right)");
+  }
+
+  #[test]
+  fn mathml() {
+    let table = compile_grammar_file("resources/operators.grammar").unwrap();
+    let typesetter = Typesetter::new(&table);
+    let mut parser = Parser::new(table);
+
+    assert_eq!(
+      typesetter.mathml(&parser.parse("(a + 1) ^ 2 / b").unwrap()),
+      "<math xmlns=\"http://www.w3.org/1998/Math/MathML\"><mrow><mfrac><mrow><msup><mrow><mo>(</mo>\
+       <mrow><mi>a</mi><mo>+</mo><mn>1</mn></mrow><mo>)</mo></mrow><mn>2</mn></msup></mrow><mi>b</mi>\
+       </mfrac></mrow></math>"
+    );
+    assert_eq!(
+      typesetter.mathml(&parser.parse("f[x = \"<\"]").unwrap()),
+      "<math xmlns=\"http://www.w3.org/1998/Math/MathML\"><mrow><mi>f</mi><mo>\u{2061}</mo><mrow><mo>(</mo>\
+       <mrow><mi>x</mi><mo>=</mo><ms>&lt;</ms></mrow><mo>)</mo></mrow></mrow></math>"
+    );
+  }
+
+  #[test]
+  fn box_templates() {
+    let table = compile_grammar_file("examples/Grammar.txt").unwrap();
+    let typesetter = Typesetter::new(&table);
+    let mut parser = Parser::new(table);
+    let tree = parser.parse("x \\^ i \\% n").unwrap();
+
+    assert_eq!(typesetter.latex(&tree), "x_{i}^{n}");
+    assert_eq!(
+      typesetter.mathml(&tree),
+      "<math xmlns=\"http://www.w3.org/1998/Math/MathML\"><mrow><msubsup><mi>x</mi><mi>i</mi><mi>n</mi>\
+       </msubsup></mrow></math>"
+    );
+  }
+}